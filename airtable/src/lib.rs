@@ -36,7 +36,7 @@
  * ```
  */
 #![allow(clippy::field_reassign_with_default)]
-use std::{env, fmt, fmt::Debug, marker::PhantomData};
+use std::{env, fmt, fmt::Debug, marker::PhantomData, sync::Arc};
 
 use anyhow::{bail, Result};
 use chrono::{offset::Utc, DateTime};
@@ -71,6 +71,23 @@ impl Airtable {
     /// You can leave the Enterprise Account ID empty if you are not using the
     /// Enterprise API features.
     pub fn new<K, B, E>(key: K, base_id: B, enterprise_account_id: E) -> Self
+    where
+        K: ToString,
+        B: ToString,
+        E: ToString,
+    {
+        Airtable::new_with_middleware(key, base_id, enterprise_account_id, vec![])
+    }
+
+    /// Create a new Airtable client struct that runs additional middleware. The
+    /// middleware is run for every attempt at a request, after the tracing and
+    /// retry middleware of the client.
+    pub fn new_with_middleware<K, B, E>(
+        key: K,
+        base_id: B,
+        enterprise_account_id: E,
+        middleware: Vec<Arc<dyn reqwest_middleware::Middleware>>,
+    ) -> Self
     where
         K: ToString,
         B: ToString,
@@ -80,12 +97,17 @@ impl Airtable {
         match http {
             Ok(c) => {
                 let retry_policy = reqwest_retry::policies::ExponentialBackoff::builder().build_with_max_retries(3);
-                let client = reqwest_middleware::ClientBuilder::new(c)
+                let mut builder = reqwest_middleware::ClientBuilder::new(c)
                     // Trace HTTP requests. See the tracing crate to make use of these traces.
                     .with(reqwest_tracing::TracingMiddleware::default())
                     // Retry failed requests.
-                    .with(reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy))
-                    .build();
+                    .with(reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy));
+
+                for middleware in middleware {
+                    builder = builder.with_arc(middleware);
+                }
+
+                let client = builder.build();
 
                 Self {
                     key: key.to_string(),
@@ -0,0 +1,2 @@
+ALTER TABLE rfds DROP COLUMN pdf_link_s3;
+ALTER TABLE rfds DROP COLUMN pdf_link_local;
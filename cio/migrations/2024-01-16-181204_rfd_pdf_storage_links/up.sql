@@ -0,0 +1,2 @@
+ALTER TABLE rfds ADD COLUMN pdf_link_local VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE rfds ADD COLUMN pdf_link_s3 VARCHAR NOT NULL DEFAULT '';
@@ -0,0 +1,9 @@
+CREATE TABLE rfd_references (
+    id SERIAL PRIMARY KEY,
+    rfd_number INTEGER NOT NULL,
+    referenced_rfd_number INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (rfd_number, referenced_rfd_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_rfd_references_referenced ON rfd_references(referenced_rfd_number);
@@ -0,0 +1,15 @@
+CREATE TABLE rfd_state_changes (
+    id SERIAL PRIMARY KEY,
+    rfd_number INTEGER NOT NULL,
+    from_state VARCHAR NOT NULL DEFAULT '',
+    to_state VARCHAR NOT NULL,
+    changed_by VARCHAR NOT NULL DEFAULT '',
+    sha VARCHAR NOT NULL DEFAULT '',
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_rfd_state_changes_rfd ON rfd_state_changes(rfd_number, changed_at);
+
+-- Seed the history with the current state of every RFD so that existing RFDs have a starting point
+INSERT INTO rfd_state_changes (rfd_number, to_state, sha, changed_at)
+SELECT number, state, sha, commit_date FROM rfds WHERE state <> '';
@@ -0,0 +1,8 @@
+CREATE TABLE rfd_snoozes (
+    id SERIAL PRIMARY KEY,
+    rfd_number INTEGER NOT NULL UNIQUE,
+    snoozed_until TIMESTAMPTZ NOT NULL,
+    snoozed_by VARCHAR NOT NULL DEFAULT '',
+    reason VARCHAR NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1 @@
+DROP TABLE function_steps;
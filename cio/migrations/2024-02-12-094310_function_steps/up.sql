@@ -0,0 +1,13 @@
+CREATE TABLE function_steps (
+    id SERIAL PRIMARY KEY,
+    saga_id VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    status VARCHAR NOT NULL,
+    conclusion VARCHAR NOT NULL DEFAULT '',
+    output TEXT NOT NULL DEFAULT '',
+    error TEXT NOT NULL DEFAULT '',
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    undone_at TIMESTAMPTZ,
+    UNIQUE (saga_id, name)
+);
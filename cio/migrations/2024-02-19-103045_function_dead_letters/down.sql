@@ -0,0 +1 @@
+DROP TABLE function_dead_letters;
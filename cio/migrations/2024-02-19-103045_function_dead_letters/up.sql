@@ -0,0 +1,12 @@
+CREATE TABLE function_dead_letters (
+    id SERIAL PRIMARY KEY,
+    saga_id VARCHAR NOT NULL UNIQUE,
+    name VARCHAR NOT NULL,
+    cio_company_id INTEGER NOT NULL,
+    attempts INTEGER NOT NULL,
+    error_class VARCHAR NOT NULL,
+    last_error TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    retried_saga_id VARCHAR,
+    retried_at TIMESTAMPTZ
+);
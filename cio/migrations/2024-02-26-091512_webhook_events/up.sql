@@ -0,0 +1,18 @@
+CREATE TABLE webhook_events (
+    id SERIAL PRIMARY KEY,
+    source VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    headers JSONB NOT NULL DEFAULT '{}',
+    body TEXT NOT NULL,
+    verified BOOLEAN NOT NULL,
+    status VARCHAR NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NOT NULL DEFAULT '',
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    processed_at TIMESTAMPTZ,
+    replayed_at TIMESTAMPTZ
+);
+
+CREATE INDEX webhook_events_status_next_attempt_at ON webhook_events (status, next_attempt_at);
+CREATE INDEX webhook_events_received_at ON webhook_events (received_at);
@@ -0,0 +1,8 @@
+CREATE TABLE drift_reports (
+    id SERIAL PRIMARY KEY,
+    cio_company_id INTEGER NOT NULL,
+    report JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX drift_reports_cio_company_id_created_at ON drift_reports (cio_company_id, created_at);
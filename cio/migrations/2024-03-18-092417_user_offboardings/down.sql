@@ -0,0 +1,2 @@
+DROP TABLE user_offboarding_steps;
+DROP TABLE user_offboardings;
@@ -0,0 +1,26 @@
+CREATE TABLE user_offboardings (
+    id SERIAL PRIMARY KEY,
+    cio_company_id INTEGER NOT NULL,
+    username VARCHAR NOT NULL,
+    email VARCHAR NOT NULL,
+    manager_email VARCHAR NOT NULL DEFAULT '',
+    status VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    returned_at TIMESTAMPTZ
+);
+
+CREATE INDEX user_offboardings_cio_company_id_username ON user_offboardings (cio_company_id, username);
+
+CREATE TABLE user_offboarding_steps (
+    id SERIAL PRIMARY KEY,
+    offboarding_id INTEGER NOT NULL REFERENCES user_offboardings (id) ON DELETE CASCADE,
+    step VARCHAR NOT NULL,
+    status VARCHAR NOT NULL,
+    detail TEXT NOT NULL DEFAULT '',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NOT NULL DEFAULT '',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    UNIQUE (offboarding_id, step)
+);
@@ -0,0 +1,18 @@
+CREATE TABLE provisioning_steps (
+    id SERIAL PRIMARY KEY,
+    cio_company_id INTEGER NOT NULL,
+    username VARCHAR NOT NULL,
+    step VARCHAR NOT NULL,
+    start_date DATE NOT NULL,
+    due_on DATE NOT NULL,
+    status VARCHAR NOT NULL,
+    detail TEXT NOT NULL DEFAULT '',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX provisioning_steps_cio_company_id_status_due_on ON provisioning_steps (cio_company_id, status, due_on);
+CREATE INDEX provisioning_steps_cio_company_id_username ON provisioning_steps (cio_company_id, username);
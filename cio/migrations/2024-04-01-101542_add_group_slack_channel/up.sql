@@ -0,0 +1 @@
+ALTER TABLE groups ADD COLUMN slack_channel VARCHAR NOT NULL DEFAULT '';
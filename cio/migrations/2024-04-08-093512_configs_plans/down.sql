@@ -0,0 +1 @@
+DROP TABLE configs_plans;
@@ -0,0 +1,9 @@
+CREATE TABLE configs_plans (
+    id SERIAL PRIMARY KEY,
+    cio_company_id INTEGER NOT NULL,
+    sha VARCHAR NOT NULL,
+    plan JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX configs_plans_cio_company_id_sha ON configs_plans (cio_company_id, sha, created_at);
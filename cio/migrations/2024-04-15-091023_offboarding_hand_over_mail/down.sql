@@ -0,0 +1,2 @@
+-- The removed forwarding steps are not restored, as they are not run any more.
+SELECT 1;
@@ -0,0 +1,3 @@
+-- Mail is now handed over to the manager after the user is suspended, instead of being forwarded
+-- before. Forwarding steps that have not completed are no longer run.
+DELETE FROM user_offboarding_steps WHERE step = 'forward-mail' AND status <> 'completed';
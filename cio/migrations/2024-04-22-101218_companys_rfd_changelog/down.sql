@@ -0,0 +1,3 @@
+ALTER TABLE companys DROP COLUMN rfd_changelog_window_days;
+ALTER TABLE companys DROP COLUMN rfd_changelog_from;
+ALTER TABLE companys DROP COLUMN rfd_changelog_recipients;
@@ -0,0 +1,3 @@
+ALTER TABLE companys ADD COLUMN rfd_changelog_recipients TEXT[] NOT NULL DEFAULT ARRAY[]::TEXT[];
+ALTER TABLE companys ADD COLUMN rfd_changelog_from VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE companys ADD COLUMN rfd_changelog_window_days INTEGER NOT NULL DEFAULT 0;
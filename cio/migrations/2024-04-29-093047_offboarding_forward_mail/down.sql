@@ -0,0 +1 @@
+UPDATE user_offboarding_steps SET step = 'suspend' WHERE step = 'lock-out';
@@ -0,0 +1,6 @@
+-- Users are now locked out of Google instead of suspended, so that their mail can be forwarded to
+-- their manager until their account is deleted, instead of their address being moved to the
+-- manager. Users that were already suspended are not locked out again, and steps of the old kinds
+-- that have not completed are no longer run.
+UPDATE user_offboarding_steps SET step = 'lock-out' WHERE step = 'suspend' AND status = 'completed';
+DELETE FROM user_offboarding_steps WHERE step IN ('suspend', 'hand-over-mail') AND status <> 'completed';
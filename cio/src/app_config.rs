@@ -1,8 +1,9 @@
+use chrono_tz::Tz;
 use docusign::Envelope;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::{applicants::Applicant, companies::Company, configs::User};
+use crate::{applicants::Applicant, companies::Company, configs::User, features::Features, rfd::RFDState};
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct DocuSignConfig {
@@ -159,12 +160,286 @@ pub struct FinanceConfig {
     pub vendor_aliases: HashMap<String, String>,
 }
 
+/// Where the reports of drift between the configs repo and the external services are sent
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct DriftConfig {
+    /// The Slack channel to post a summary of each report to. Reports are not posted when empty
+    #[serde(default)]
+    pub slack_channel: String,
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct GitHubConfig {
     #[serde(default)]
     pub ignored_repos: Vec<String>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RFDStaleConfig {
+    /// The number of days that an RFD may go without changes while in a given state before it is
+    /// considered stale. RFDs in states without a threshold are never considered stale
+    #[serde(default = "RFDStaleConfig::default_thresholds")]
+    pub thresholds: HashMap<String, i64>,
+    /// Send a Slack direct message to the authors of stale RFDs
+    #[serde(default)]
+    pub slack: bool,
+    /// Send an email to the authors of stale RFDs
+    #[serde(default)]
+    pub email: bool,
+    /// Address to send emails from. Defaults to the rfds@ address of the company when empty
+    #[serde(default)]
+    pub from: String,
+}
+
+impl RFDStaleConfig {
+    fn default_thresholds() -> HashMap<String, i64> {
+        [
+            (RFDState::Prediscussion, 90),
+            (RFDState::Ideation, 90),
+            (RFDState::Discussion, 60),
+        ]
+        .into_iter()
+        .map(|(state, days)| (state.to_string(), days))
+        .collect()
+    }
+
+    pub fn threshold_days(&self, state: &RFDState) -> Option<i64> {
+        self.thresholds.get(state.as_str()).copied()
+    }
+
+    pub fn from(&self, company: &Company) -> String {
+        if self.from.is_empty() {
+            format!("rfds@{}", company.gsuite_domain)
+        } else {
+            self.from.clone()
+        }
+    }
+}
+
+impl Default for RFDStaleConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: Self::default_thresholds(),
+            slack: false,
+            email: false,
+            from: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct RFDPdfStorageConfig {
+    /// Commit rendered PDFs back to the RFD repository. Also enabled by the RFD_PDFS_IN_GITHUB flag
+    #[serde(default)]
+    pub github: bool,
+    /// Write rendered PDFs to the shared Google Drive. Also enabled by the RFD_PDFS_IN_GOOGLE_DRIVE flag
+    #[serde(default)]
+    pub google_drive: bool,
+    #[serde(default)]
+    pub local: Option<RFDLocalPdfStorageConfig>,
+    #[serde(default)]
+    pub s3: Option<RFDS3PdfStorageConfig>,
+}
+
+impl RFDPdfStorageConfig {
+    pub fn github_enabled(&self) -> bool {
+        self.github || Features::is_enabled("RFD_PDFS_IN_GITHUB")
+    }
+
+    pub fn google_drive_enabled(&self) -> bool {
+        self.google_drive || Features::is_enabled("RFD_PDFS_IN_GOOGLE_DRIVE")
+    }
+
+    pub fn any_enabled(&self) -> bool {
+        self.github_enabled() || self.google_drive_enabled() || self.local.is_some() || self.s3.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RFDLocalPdfStorageConfig {
+    /// Directory that PDFs are written to. It is created if it does not exist
+    pub path: String,
+    /// Base url that the directory is served from. When absent a file:// url is recorded instead
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RFDS3PdfStorageConfig {
+    /// Endpoint of the S3 compatible service, i.e. https://s3.us-east-1.amazonaws.com or http://localhost:9000
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "RFDS3PdfStorageConfig::default_region")]
+    pub region: String,
+    /// Key prefix to write PDFs under
+    #[serde(default)]
+    pub prefix: String,
+    /// Public base url for the bucket. When absent the url of the object on the endpoint is recorded
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+impl RFDS3PdfStorageConfig {
+    fn default_region() -> String {
+        "us-east-1".to_string()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RFDConfig {
+    /// Host of the site that rendered RFDs are served from
+    #[serde(default = "RFDConfig::default_rendered_host")]
+    pub rendered_host: String,
+    #[serde(default)]
+    pub pdfs: RFDPdfStorageConfig,
+    #[serde(default)]
+    pub stale: RFDStaleConfig,
+}
+
+impl RFDConfig {
+    fn default_rendered_host() -> String {
+        "rfd.shared.oxide.computer".to_string()
+    }
+}
+
+impl Default for RFDConfig {
+    fn default() -> Self {
+        Self {
+            rendered_host: Self::default_rendered_host(),
+            pdfs: Default::default(),
+            stale: Default::default(),
+        }
+    }
+}
+
+/// The schedule that jobs run on unless it is overridden, as (job, cron expression, enabled). Jobs
+/// whose interval does not evenly divide a day run on an `@every` interval so that their cadence does
+/// not restart at midnight
+const DEFAULT_JOB_SCHEDULE: &[(&str, &str, bool)] = &[
+    ("check-stale-rfds", "0 9 * * Tue", true),
+    ("offboard-users", "*/30 * * * *", true),
+    ("provision-users", "0 * * * *", true),
+    ("report-drift", "0 9 * * Mon", true),
+    ("send-rfd-changelog", "0 8 * * Mon", true),
+    ("sync-analytics", "0 0 * * *", false),
+    ("sync-api-tokens", "@every 23h", true),
+    ("sync-applications", "@every 7h", true),
+    ("sync-asset-inventory", "0 */2 * * *", false),
+    ("sync-companies", "0 */12 * * *", true),
+    ("sync-configs", "0 * * * *", true),
+    ("sync-finance", "0 */6 * * *", false),
+    ("sync-functions", "0 */12 * * *", false),
+    ("sync-huddles", "0 * * * *", true),
+    ("sync-interviews", "0 */4 * * *", true),
+    ("sync-journal-clubs", "0 */12 * * *", false),
+    ("sync-mailing-lists", "0 */3 * * *", true),
+    ("sync-other", "@every 18h", false),
+    ("sync-recorded-meetings", "0 */3 * * *", false),
+    ("sync-repos", "@every 16h", true),
+    ("sync-rfds", "@every 14h", false),
+    ("sync-salesforce", "*/30 * * * *", true),
+    ("sync-shipments", "0 */2 * * *", true),
+    ("sync-shorturls", "0 */3 * * *", true),
+    ("sync-swag-inventory", "@every 9h", false),
+    ("sync-travel", "@every 5h", false),
+];
+
+/// When the background jobs of the server run. The built in schedule is used for any job that is
+/// not listed in `jobs`, and for any setting that a listed job leaves out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScheduleConfig {
+    /// The timezone that cron expressions are evaluated in, unless a job sets its own
+    #[serde(default = "ScheduleConfig::default_timezone")]
+    pub timezone: Tz,
+    /// Overrides of the built in schedule, keyed by job name
+    #[serde(default)]
+    pub jobs: BTreeMap<String, JobScheduleConfig>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            timezone: Self::default_timezone(),
+            jobs: BTreeMap::new(),
+        }
+    }
+}
+
+impl ScheduleConfig {
+    fn default_timezone() -> Tz {
+        chrono_tz::US::Pacific
+    }
+
+    /// Resolve the schedule of every job, merging the overrides on to the built in schedule
+    pub fn jobs(&self) -> Vec<JobSchedule> {
+        let mut jobs = DEFAULT_JOB_SCHEDULE
+            .iter()
+            .map(|(name, cron, enabled)| {
+                (
+                    name.to_string(),
+                    JobSchedule {
+                        name: name.to_string(),
+                        cron: cron.to_string(),
+                        enabled: *enabled,
+                        jitter_seconds: 0,
+                        timezone: self.timezone,
+                    },
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        for (name, config) in &self.jobs {
+            let job = jobs.entry(name.to_string()).or_insert_with(|| JobSchedule {
+                name: name.to_string(),
+                cron: String::new(),
+                enabled: true,
+                jitter_seconds: 0,
+                timezone: self.timezone,
+            });
+
+            if let Some(cron) = &config.cron {
+                job.cron = cron.to_string();
+            }
+
+            if let Some(enabled) = config.enabled {
+                job.enabled = enabled;
+            }
+
+            if let Some(jitter_seconds) = config.jitter_seconds {
+                job.jitter_seconds = jitter_seconds;
+            }
+
+            if let Some(timezone) = config.timezone {
+                job.timezone = timezone;
+            }
+        }
+
+        jobs.into_values().collect()
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct JobScheduleConfig {
+    /// A cron expression, either in the five field crontab format or with a leading seconds field, or
+    /// an interval such as `@every 7h` that is counted from when the server starts
+    pub cron: Option<String>,
+    pub enabled: Option<bool>,
+    /// Delay each run by a random number of seconds, up to this many, to spread out jobs that are
+    /// scheduled for the same time
+    pub jitter_seconds: Option<u64>,
+    pub timezone: Option<Tz>,
+}
+
+/// The resolved schedule of a single job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobSchedule {
+    pub name: String,
+    pub cron: String,
+    pub enabled: bool,
+    pub jitter_seconds: u64,
+    pub timezone: Tz,
+}
+
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct AppConfig {
     pub envelopes: DocuSignConfig,
@@ -172,12 +447,19 @@ pub struct AppConfig {
     pub apply: ApplyConfig,
     pub finance: FinanceConfig,
     #[serde(default)]
+    pub drift: DriftConfig,
+    #[serde(default)]
     pub github: GitHubConfig,
+    #[serde(default)]
+    pub rfd: RFDConfig,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ApplyConfig, DocuSignConfig, GitHubConfig, OnboardingConfig};
+    use super::{ApplyConfig, DocuSignConfig, GitHubConfig, OnboardingConfig, RFDConfig, ScheduleConfig};
+    use crate::rfd::RFDState;
     use crate::{applicants::tests::mock_applicant, companies::tests::mock_company, configs::tests::mock_user};
 
     fn mock_docusign_toml(label: &str) -> String {
@@ -377,4 +659,119 @@ ignored_repos = [
         .unwrap();
         assert_eq!(vec!["12345".to_string(), "67890".to_string(),], config.ignored_repos);
     }
+
+    #[test]
+    fn test_missing_rfd_rendered_host_config() {
+        let config: RFDConfig = toml::from_str("").unwrap();
+
+        assert_eq!("rfd.shared.oxide.computer", config.rendered_host);
+    }
+
+    #[test]
+    fn test_rfd_rendered_host_config() {
+        let config: RFDConfig = toml::from_str(
+            r#"
+rendered_host = "rfd.testemaildomain.com"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!("rfd.testemaildomain.com", config.rendered_host);
+    }
+
+    #[test]
+    fn test_rfd_pdf_storage_config() {
+        let config: RFDConfig = toml::from_str(
+            r#"
+[pdfs.local]
+path = "/tmp/rfd-pdfs"
+
+[pdfs.s3]
+endpoint = "http://localhost:9000"
+bucket = "rfd-pdfs"
+"#,
+        )
+        .unwrap();
+
+        assert!(!config.pdfs.github);
+        assert!(!config.pdfs.google_drive);
+        assert_eq!("/tmp/rfd-pdfs", config.pdfs.local.as_ref().unwrap().path);
+        assert_eq!(None, config.pdfs.local.as_ref().unwrap().base_url);
+
+        let s3 = config.pdfs.s3.unwrap();
+        assert_eq!("rfd-pdfs", s3.bucket);
+        assert_eq!("us-east-1", s3.region);
+        assert_eq!("", s3.prefix);
+    }
+
+    #[test]
+    fn test_rfd_stale_config() {
+        let config: RFDConfig = toml::from_str("").unwrap();
+
+        assert_eq!(Some(60), config.stale.threshold_days(&RFDState::Discussion));
+        assert_eq!(None, config.stale.threshold_days(&RFDState::Published));
+        assert!(!config.stale.slack);
+        assert!(!config.stale.email);
+
+        let config: RFDConfig = toml::from_str(
+            r#"
+[stale]
+slack = true
+
+[stale.thresholds]
+discussion = 30
+published = 365
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(30), config.stale.threshold_days(&RFDState::Discussion));
+        assert_eq!(Some(365), config.stale.threshold_days(&RFDState::Published));
+        assert_eq!(None, config.stale.threshold_days(&RFDState::Ideation));
+        assert!(config.stale.slack);
+    }
+
+    #[test]
+    fn test_schedule_config() {
+        let config: ScheduleConfig = toml::from_str("").unwrap();
+        let jobs = config.jobs();
+
+        let configs = jobs.iter().find(|job| job.name == "sync-configs").unwrap();
+        assert_eq!("0 * * * *", configs.cron);
+        assert!(configs.enabled);
+        assert_eq!(chrono_tz::US::Pacific, configs.timezone);
+
+        let config: ScheduleConfig = toml::from_str(
+            r#"
+timezone = "UTC"
+
+[jobs.sync-configs]
+jitter_seconds = 120
+
+[jobs.sync-rfds]
+enabled = true
+timezone = "Europe/London"
+
+[jobs.sync-something-new]
+cron = "*/5 * * * *"
+"#,
+        )
+        .unwrap();
+        let jobs = config.jobs();
+
+        // Overrides only replace the settings that they include
+        let configs = jobs.iter().find(|job| job.name == "sync-configs").unwrap();
+        assert_eq!("0 * * * *", configs.cron);
+        assert!(configs.enabled);
+        assert_eq!(120, configs.jitter_seconds);
+        assert_eq!(chrono_tz::UTC, configs.timezone);
+
+        let rfds = jobs.iter().find(|job| job.name == "sync-rfds").unwrap();
+        assert!(rfds.enabled);
+        assert_eq!(chrono_tz::Europe::London, rfds.timezone);
+
+        let new = jobs.iter().find(|job| job.name == "sync-something-new").unwrap();
+        assert_eq!("*/5 * * * *", new.cron);
+        assert!(new.enabled);
+    }
 }
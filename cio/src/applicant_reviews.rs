@@ -180,7 +180,7 @@ pub async fn refresh_reviews(db: &Database, company: &Company) -> Result<()> {
             for record in chunk {
                 let db = db.clone();
 
-                tasks.push(tokio::spawn(async move {
+                tasks.push(crate::sandbox::spawn(async move {
                     let new_review: NewApplicantReview = record.fields.into();
 
                     let mut review = new_review.upsert_in_db(&db).await?;
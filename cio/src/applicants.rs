@@ -16,7 +16,6 @@ use log::{info, warn};
 use macros::db;
 use regex::Regex;
 use schemars::JsonSchema;
-use sendgrid_api::{traits::MailOps, Client as SendGrid};
 use serde::{Deserialize, Serialize};
 use slack_chat_api::{
     FormattedMessage, MessageAttachment, MessageBlock, MessageBlockText, MessageBlockType, MessageType,
@@ -35,7 +34,7 @@ use crate::{
     enclose,
     interviews::ApplicantInterview,
     schema::{applicant_interviews, applicant_reviewers, applicants, users},
-    utils::{check_if_github_issue_exists, truncate},
+    utils::{check_if_github_issue_exists, send_plain_text_email, truncate},
 };
 
 // The line breaks that get parsed are weird thats why we have the random asterisks here.
@@ -801,22 +800,17 @@ impl Applicant {
 
     /// Send an email to a scorer that they are assigned to an applicant.
     pub async fn send_email_to_scorer(&self, scorer: &str, company: &Company) {
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
-
         // Send the message.
-        sendgrid_client
-            .mail_send()
-            .send_plain_text(
-                &format!("[applicants] Reviewing applicant {}", self.name),
-                &self.as_scorer_email(),
-                &[scorer.to_string()],
-                &[],
-                &[],
-                &format!("careers@{}", company.gsuite_domain),
-            )
-            .await
-            .unwrap();
+        send_plain_text_email(
+            &format!("[applicants] Reviewing applicant {}", self.name),
+            &self.as_scorer_email(),
+            &[scorer.to_string()],
+            &[],
+            &[],
+            &format!("careers@{}", company.gsuite_domain),
+        )
+        .await
+        .unwrap();
     }
 
     /// Get the applicant's information in the form of the body of an email for a
@@ -1494,21 +1488,16 @@ impl Applicant {
                 status
             );
 
-            // Initialize the SendGrid client.
-            let sendgrid_client = SendGrid::new_from_env();
-
             // Send the message.
-            sendgrid_client
-                .mail_send()
-                .send_plain_text(
-                    &letter.subject,
-                    &letter.body,
-                    &[self.email.to_string()],
-                    &letter.cc,
-                    &letter.bcc,
-                    &letter.from,
-                )
-                .await?;
+            send_plain_text_email(
+                &letter.subject,
+                &letter.body,
+                &[self.email.to_string()],
+                &letter.cc,
+                &letter.bcc,
+                &letter.from,
+            )
+            .await?;
 
             log::info!(
                 "Applicant {} in {:?} has been sent a rejection letter.",
@@ -1666,42 +1655,33 @@ The applicants Airtable \
     /// Send an email internally that we have a new application.
     async fn send_email_internally(&self, db: &Database) -> Result<()> {
         let company = self.company(db).await?;
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
 
         // Send the message.
-        sendgrid_client
-            .mail_send()
-            .send_plain_text(
-                &format!("New {} Application: {}", self.role, self.name),
-                &self.as_company_notification_email(),
-                &[format!("applications@{}", company.gsuite_domain)],
-                &[],
-                &[],
-                &format!("applications@{}", company.gsuite_domain),
-            )
-            .await?;
+        send_plain_text_email(
+            &format!("New {} Application: {}", self.role, self.name),
+            &self.as_company_notification_email(),
+            &[format!("applications@{}", company.gsuite_domain)],
+            &[],
+            &[],
+            &format!("applications@{}", company.gsuite_domain),
+        )
+        .await?;
 
         Ok(())
     }
 
     /// Send an email to the applicant that we recieved their application.
     async fn send_email_recieved_application_to_applicant(&self, letter: &Letter) -> Result<()> {
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
-
         // Send the message.
-        sendgrid_client
-            .mail_send()
-            .send_plain_text(
-                &letter.subject,
-                &letter.body,
-                &[self.email.to_string()],
-                &letter.cc,
-                &letter.bcc,
-                &letter.from,
-            )
-            .await?;
+        send_plain_text_email(
+            &letter.subject,
+            &letter.body,
+            &[self.email.to_string()],
+            &letter.cc,
+            &letter.bcc,
+            &letter.from,
+        )
+        .await?;
 
         Ok(())
     }
@@ -2456,7 +2436,7 @@ pub async fn refresh_new_applicants_and_reviews(
                 let tasks: Vec<_> = applicant_chunk
                     .into_iter()
                     .map(|mut applicant| {
-                        tokio::spawn(
+                        crate::sandbox::spawn(
                             enclose! { (db, company, github, configs_issues, app_config) async move {
                                 applicant.refresh(&db, &company, &github, &configs_issues, app_config).await
                             }},
@@ -108,7 +108,7 @@ impl ApplicationForm {
         // It can take too long to expand and update an application. Instead perform that work
         // asynchronously
         let db = db.clone();
-        tokio::spawn(async move {
+        crate::sandbox::spawn(async move {
             // Expand the application.
             applicant.expand(&db, &drive_client, &config.apply).await?;
 
@@ -1,4 +1,4 @@
-use std::{convert::TryInto, env};
+use std::{convert::TryInto, env, sync::Arc};
 
 use airtable_api::Airtable;
 use anyhow::{anyhow, bail, Result};
@@ -59,6 +59,8 @@ use crate::{
     core::UpdateAirtableRecord,
     db::Database,
     dns_proxy::DnsProviderProxy,
+    metrics::VendorMetricsMiddleware,
+    sandbox::{Sandbox, SandboxMiddleware, SandboxService},
     schema::{api_tokens, companys},
 };
 
@@ -161,6 +163,18 @@ pub struct NewCompany {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub nginx_ip: String,
 
+    /// Addresses to send the RFD changelog to. Defaults to the all@ address of the company when
+    /// empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rfd_changelog_recipients: Vec<String>,
+    /// Address to send the RFD changelog from. Defaults to the rfds@ address of the company when
+    /// empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub rfd_changelog_from: String,
+    /// The number of days that the RFD changelog covers. Defaults to a week when not set
+    #[serde(default)]
+    pub rfd_changelog_window_days: i32,
+
     /// The CIO company ID.
     #[serde(default)]
     pub cio_company_id: i32,
@@ -174,7 +188,47 @@ impl UpdateAirtableRecord<Company> for Company {
     }
 }
 
+impl Companys {
+    /// Get the companies that jobs can be run for. Jobs read the configuration of a company from
+    /// its configs repo, so only companies with a GitHub app installation are included.
+    pub async fn get_active(db: &Database, cio_company_id: i32) -> Result<Vec<Company>> {
+        Ok(Self::get_from_db(db, cio_company_id)
+            .await?
+            .0
+            .into_iter()
+            .filter(|company| company.github_app_installation_id != 0)
+            .collect())
+    }
+}
+
 impl Company {
+    /// The addresses to send the RFD changelog to
+    pub fn changelog_recipients(&self) -> Vec<String> {
+        if self.rfd_changelog_recipients.is_empty() {
+            vec![format!("all@{}", self.gsuite_domain)]
+        } else {
+            self.rfd_changelog_recipients.clone()
+        }
+    }
+
+    /// The address to send the RFD changelog from
+    pub fn changelog_sender(&self) -> String {
+        if self.rfd_changelog_from.is_empty() {
+            format!("rfds@{}", self.gsuite_domain)
+        } else {
+            self.rfd_changelog_from.to_string()
+        }
+    }
+
+    /// The number of days that the RFD changelog covers
+    pub fn changelog_window_days(&self) -> i64 {
+        if self.rfd_changelog_window_days > 0 {
+            self.rfd_changelog_window_days.into()
+        } else {
+            7
+        }
+    }
+
     /// Returns the shippo data structure for the address at the office
     /// for the company.
     pub async fn hq_shipping_address(&self, db: &Database) -> Result<shippo::Address> {
@@ -203,6 +257,10 @@ impl Company {
     }
 
     pub async fn post_to_slack_channel(&self, db: &Database, msg: &slack_chat_api::FormattedMessage) -> Result<()> {
+        if Sandbox::intercept(SandboxService::Slack, format!("post message to `{}`", msg.channel)) {
+            return Ok(());
+        }
+
         // Create the Slack client.
         let r = self.authenticate_slack(db).await;
         if let Err(e) = r {
@@ -350,7 +408,12 @@ impl Company {
 
     /// Authenticate with Airtable.
     pub fn authenticate_airtable(&self, base_id: &str) -> Airtable {
-        Airtable::new(&self.airtable_api_key, base_id, &self.airtable_enterprise_account_id)
+        Airtable::new_with_middleware(
+            &self.airtable_api_key,
+            base_id,
+            &self.airtable_enterprise_account_id,
+            vec![Arc::new(VendorMetricsMiddleware { vendor: "airtable" })],
+        )
     }
 
     /// Authenticate with ShipBob.
@@ -475,7 +538,11 @@ impl Company {
                     bot_token.company_id.to_string(),
                     bot_token.access_token,
                     user_token.access_token,
-                );
+                )
+                // Keep writes from being made while sandboxed.
+                .write_filter(|request| {
+                    !Sandbox::intercept(SandboxService::Slack, format!("{} {}", request.method(), request.url()))
+                });
                 // Slack does not give you refresh tokens.
                 // So we don't need to do any song and dance to refresh.
 
@@ -497,6 +564,8 @@ impl Company {
                 "departments:read".to_string(),
                 "transactions:read".to_string(),
                 "reimbursements:read".to_string(),
+                "cards:read".to_string(),
+                "cards:write".to_string(),
             ],
         ))
     }
@@ -894,6 +963,24 @@ impl Company {
     }
 
     async fn get_google_service_account_token(&self, as_user: &str) -> Result<String> {
+        self.google_service_account_token(
+            as_user,
+            &[
+                "https://www.googleapis.com/auth/admin.directory.group",
+                "https://www.googleapis.com/auth/admin.directory.resource.calendar",
+                "https://www.googleapis.com/auth/admin.directory.user",
+                "https://www.googleapis.com/auth/calendar",
+                "https://www.googleapis.com/auth/apps.groups.settings",
+                "https://www.googleapis.com/auth/spreadsheets",
+                "https://www.googleapis.com/auth/drive",
+            ],
+        )
+        .await
+    }
+
+    /// Get a token for the service account acting as the given user, or as the GSuite subject if
+    /// no user is given, that is limited to the given scopes.
+    pub(crate) async fn google_service_account_token(&self, as_user: &str, scopes: &[&str]) -> Result<String> {
         if self.google_service_account.is_empty() {
             bail!("no service account");
         }
@@ -910,17 +997,7 @@ impl Company {
             .build()
             .await?;
 
-        let token = auth
-            .token(&[
-                "https://www.googleapis.com/auth/admin.directory.group",
-                "https://www.googleapis.com/auth/admin.directory.resource.calendar",
-                "https://www.googleapis.com/auth/admin.directory.user",
-                "https://www.googleapis.com/auth/calendar",
-                "https://www.googleapis.com/auth/apps.groups.settings",
-                "https://www.googleapis.com/auth/spreadsheets",
-                "https://www.googleapis.com/auth/drive",
-            ])
-            .await?;
+        let token = auth.token(scopes).await?;
 
         let token_string = token
             .token()
@@ -1033,6 +1110,12 @@ impl Company {
             .with(reqwest_tracing::TracingMiddleware::default())
             // Retry failed requests.
             .with(reqwest_retry::RetryTransientMiddleware::new_with_policy(retry_policy))
+            // Keep writes from being made while sandboxed.
+            .with(SandboxMiddleware {
+                service: SandboxService::GitHub,
+            })
+            // Count the requests that are sent, including retries.
+            .with(VendorMetricsMiddleware { vendor: "github" })
             .build();
 
         Ok(octorust::Client::custom(
@@ -1289,8 +1372,34 @@ pub mod tests {
             slack_channel_debug: String::default(),
             google_service_account: String::default(),
             nginx_ip: String::default(),
+            rfd_changelog_recipients: vec![],
+            rfd_changelog_from: String::default(),
+            rfd_changelog_window_days: 0,
             cio_company_id: 0,
             airtable_record_id: String::default(),
         }
     }
+
+    #[test]
+    fn test_rfd_changelog_defaults() {
+        let mut company = mock_company();
+        company.gsuite_domain = "testemaildomain.com".to_string();
+
+        assert_eq!(
+            vec!["all@testemaildomain.com".to_string()],
+            company.changelog_recipients()
+        );
+        assert_eq!("rfds@testemaildomain.com", company.changelog_sender());
+        assert_eq!(7, company.changelog_window_days());
+
+        company.rfd_changelog_recipients = vec!["rfd-watchers@testemaildomain.com".to_string()];
+        company.rfd_changelog_window_days = 14;
+
+        assert_eq!(
+            vec!["rfd-watchers@testemaildomain.com".to_string()],
+            company.changelog_recipients()
+        );
+        assert_eq!("rfds@testemaildomain.com", company.changelog_sender());
+        assert_eq!(14, company.changelog_window_days());
+    }
 }
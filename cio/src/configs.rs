@@ -8,7 +8,7 @@ use std::{
 use anyhow::{bail, Result};
 use async_bb8_diesel::AsyncRunQueryDsl;
 use async_trait::async_trait;
-use chrono::naive::NaiveDate;
+use chrono::{naive::NaiveDate, Utc};
 use diesel::{
     deserialize::{self, FromSql},
     pg::{Pg, PgValue},
@@ -24,7 +24,6 @@ use gusto_api::Client as Gusto;
 use log::{info, warn};
 use macros::db;
 use schemars::JsonSchema;
-use sendgrid_api::{traits::MailOps, Client as SendGrid};
 use serde::{Deserialize, Serialize};
 use zoom_api::Client as Zoom;
 
@@ -37,14 +36,17 @@ use crate::{
     applicants::Applicant,
     certs::{Certificate, Certificates, GitHubBackend, NewCertificate},
     companies::Company,
+    configs_plan::{ChangeAction, ConfigsPlan, PlanDrift, PlanScope},
     core::UpdateAirtableRecord,
     db::Database,
     features::Features,
     gsuite::{update_gsuite_building, update_gsuite_calendar_resource},
-    providers::{ProviderReadOps, ProviderWriteOps},
+    offboarding::UserOffboarding,
+    providers::{ProviderReadOps, ProviderWriteOps, SlackWriter},
+    provisioning,
     schema::{applicants, buildings, groups, links, resources, users},
     shipments::NewOutboundShipment,
-    utils::{get_file_content_from_repo, get_github_user_public_ssh_keys},
+    utils::{get_file_content_from_repo, get_github_user_public_ssh_keys, send_plain_text_email},
 };
 
 /// The data type for our configuration files.
@@ -83,6 +85,7 @@ pub enum ExternalServices {
     Google,
     Okta,
     Ramp,
+    Slack,
     Zoom,
 }
 
@@ -94,6 +97,7 @@ impl ExternalServices {
             ExternalServices::Google => "google",
             ExternalServices::Okta => "okta",
             ExternalServices::Ramp => "ramp",
+            ExternalServices::Slack => "slack",
             ExternalServices::Zoom => "zoom",
         }
     }
@@ -114,6 +118,7 @@ impl ExternalServices {
                     .ok_or_else(|| anyhow::anyhow!("Failed to instantiate Okta client"))?,
             ),
             ExternalServices::Ramp => Box::new(company.authenticate_ramp()?),
+            ExternalServices::Slack => Box::new(SlackWriter::new(company.authenticate_slack(db).await?)),
             ExternalServices::Zoom => Box::new(company.authenticate_zoom(db).await?),
         })
     }
@@ -127,6 +132,7 @@ impl fmt::Display for ExternalServices {
             ExternalServices::Google => write!(f, "Google"),
             ExternalServices::Okta => write!(f, "Okta"),
             ExternalServices::Ramp => write!(f, "Ramp"),
+            ExternalServices::Slack => write!(f, "Slack"),
             ExternalServices::Zoom => write!(f, "Zoom"),
         }
     }
@@ -146,6 +152,7 @@ impl FromSql<VarChar, Pg> for ExternalServices {
             b"google" => Ok(ExternalServices::Google),
             b"okta" => Ok(ExternalServices::Okta),
             b"ramp" => Ok(ExternalServices::Ramp),
+            b"slack" => Ok(ExternalServices::Slack),
             b"zoom" => Ok(ExternalServices::Zoom),
             unknown_service => Err(format!(
                 "Encountered unknown external service value {:?} in database. Unable to deserialize.",
@@ -369,14 +376,13 @@ pub mod null_date_format {
 }
 
 impl UserConfig {
-    /// Sync a user from the config file with the services.
+    /// Sync a user from the config file with the database. Changes to the external services are
+    /// planned and applied separately, see [`crate::configs_plan::ConfigsPlan`].
     #[allow(clippy::too_many_arguments)]
     pub async fn sync(
         &mut self,
         db: &Database,
         company: &Company,
-        config: &AppConfig,
-        github: &octorust::Client,
         gsuite_users_map: &BTreeMap<String, GSuiteUser>,
         okta_users: &HashMap<String, okta::types::User>,
         ramp_users: &HashMap<String, ramp_minimal_api::User>,
@@ -385,25 +391,9 @@ impl UserConfig {
         gusto_users: &HashMap<String, gusto_api::types::Employee>,
         gusto_users_by_id: &HashMap<String, gusto_api::types::Employee>,
     ) -> Result<()> {
-        // Get everything we need to authenticate with GSuite.
-        // Initialize the GSuite client.
-        let gsuite = company.authenticate_google_admin(db).await?;
-
-        // We don't need a base id here since we are only using the enterprise api features.
-        let airtable_auth = company.authenticate_airtable("");
-
         // Initialize the Gusto client.
         let gusto_auth = company.authenticate_gusto(db).await;
 
-        // Initialize the Okta client.
-        let okta_auth = company.authenticate_okta();
-
-        // Initialize the Ramp client.
-        let ramp = company.authenticate_ramp()?;
-
-        // Initialize the Zoom client.
-        let zoom_auth = company.authenticate_zoom(db).await;
-
         // Set the user's email.
         self.email = format!("{}@{}", self.username, company.gsuite_domain);
 
@@ -506,111 +496,7 @@ impl UserConfig {
         // Expand the user.
         self.expand(db, company).await?;
 
-        let mut new_user = self.upsert(db).await?;
-
-        // Attempt to provision this user with our known external services
-
-        if let Some(ref okta) = okta_auth {
-            // ONLY DO THIS IF WE USE OKTA FOR CONFIGURATION,
-            // OTHERWISE THE GSUITE CODE WILL SEND ITS OWN EMAIL.
-            // Ensure the okta user.
-            let okta_id = okta.ensure_user(db, company, &new_user, config).await?;
-            // Set the GSuite ID for the user.
-            new_user.okta_id = okta_id.to_string();
-            // Update the user in the database.
-            new_user = new_user.update(db).await?;
-        } else {
-            // Update the user in GSuite.
-            // ONLY DO THIS IF THE COMPANY DOES NOT USE OKTA.
-            let gsuite_id = gsuite.ensure_user(db, company, &new_user, config).await?;
-            // Set the GSuite ID for the user.
-            new_user.google_id = gsuite_id.to_string();
-            // Update the user in the database.
-            new_user = new_user.update(db).await?;
-
-            // Create a zoom account for the user, if we have zoom credentials and
-            // we cannot find the zoom user.
-            // Otherwise update the zoom user.
-            // We only do this if not managed by Okta.
-            if let Ok(ref zoom) = zoom_auth {
-                match zoom.ensure_user(db, company, &new_user, config).await {
-                    Ok(zoom_id) => {
-                        // Set the Zoom ID for the user.
-                        new_user.zoom_id = zoom_id.to_string();
-                        // Update the user in the database.
-                        new_user = new_user.update(db).await?;
-                    }
-                    Err(e) => {
-                        warn!("Failed to ensure zoom user `{}`: {}", new_user.id, e);
-                    }
-                }
-            }
-        }
-
-        // Add the user to their GitHub teams and the org.
-        if !new_user.github.is_empty() {
-            // Add them to the org and any teams they need to be added to.
-            // We don't return an id here.
-            match github.ensure_user(db, company, &new_user, config).await {
-                Ok(id) => Ok(id),
-                Err(err) => {
-                    warn!("Failed to ensure GitHub user `{}`: {}", new_user.id, err);
-                    Err(err)
-                }
-            }?;
-        }
-
-        match ramp.ensure_user(db, company, &new_user, config).await {
-            Ok(ramp_id) => {
-                // Set the Ramp ID for the user.
-                new_user.ramp_id = ramp_id.to_string();
-                // Update the user in the database.
-                new_user = new_user.update(db).await?;
-            }
-            Err(e) => {
-                warn!("Failed to ensure ramp user `{}`: {}", new_user.id, e);
-            }
-        }
-
-        // Get the Airtable information for the user.
-        match airtable_auth.ensure_user(db, company, &new_user, config).await {
-            Ok(airtable_id) => {
-                new_user.airtable_id = airtable_id;
-
-                // Update the user in the database.
-                new_user = new_user.update(db).await?;
-            }
-            Err(e) => {
-                warn!("Failed to ensure airtable user `{}`: {}", new_user.id, e);
-            }
-        }
-
-        // Deprovision this user explicitly from any service they should not have access to
-        for denied_service in &new_user.denied_services {
-            match denied_service.get_provider_writer(db, company).await {
-                Ok(denied_service_provider) => {
-                    info!(
-                        "Removing user {} from {} as they are denied access in their config",
-                        new_user.id, denied_service
-                    );
-
-                    match denied_service_provider.delete_user(db, company, &new_user).await {
-                        Ok(_) => info!("Removed user {} from {}", new_user.id, denied_service),
-                        Err(err) => warn!(
-                            "Failed to remove user {} from {}. err: {:?}",
-                            new_user.id, denied_service, err
-                        ),
-                    }
-                }
-                Err(err) => warn!(
-                    "Failed to create provider client for {} when handling denied services for user {}. err: {}",
-                    denied_service, new_user.id, err
-                ),
-            }
-        }
-
-        // Update with any other changes we made to the user.
-        new_user.update(db).await?;
+        self.upsert(db).await?;
 
         Ok(())
     }
@@ -988,19 +874,14 @@ impl User {
     pub async fn send_email_new_consultant(&self, db: &Database) -> Result<()> {
         let company = self.company(db).await?;
 
-        // Initialize the SendGrid client.
-        let sendgrid = SendGrid::new_from_env();
-
         // Get the user's aliases if they have one.
         let aliases = self.aliases.join(", ");
 
         // Send the message.
-        sendgrid
-            .mail_send()
-            .send_plain_text(
-                &format!("Your New Email Account: {}", self.email),
-                &format!(
-                    "Yoyoyo {},
+        send_plain_text_email(
+            &format!("Your New Email Account: {}", self.email),
+            &format!(
+                "Yoyoyo {},
 
 You should have an email from Okta about setting up your account with them.
 We use Okta to authenticate to a number of different apps -- including
@@ -1024,14 +905,14 @@ jess@{}.
 
 xoxo,
   The Onboarding Bot",
-                    self.first_name, company.domain, company.domain, self.email, aliases, company.gsuite_domain,
-                ),
-                &[self.recovery_email.to_string()],
-                &[self.email.to_string(), format!("jess@{}", company.gsuite_domain)],
-                &[],
-                &format!("admin@{}", company.gsuite_domain),
-            )
-            .await?;
+                self.first_name, company.domain, company.domain, self.email, aliases, company.gsuite_domain,
+            ),
+            &[self.recovery_email.to_string()],
+            &[self.email.to_string(), format!("jess@{}", company.gsuite_domain)],
+            &[],
+            &format!("admin@{}", company.gsuite_domain),
+        )
+        .await?;
 
         Ok(())
     }
@@ -1045,22 +926,17 @@ xoxo,
     ) -> Result<()> {
         let company = self.company(db).await?;
 
-        // Initialize the SendGrid client.
-        let sendgrid = SendGrid::new_from_env();
-
         let letter = config.create_welcome_letter(&company, self, password);
 
-        sendgrid
-            .mail_send()
-            .send_plain_text(
-                &letter.subject,
-                &letter.body,
-                &[self.recovery_email.to_string()],
-                &letter.cc,
-                &letter.bcc,
-                &letter.from,
-            )
-            .await?;
+        send_plain_text_email(
+            &letter.subject,
+            &letter.body,
+            &[self.recovery_email.to_string()],
+            &letter.cc,
+            &letter.bcc,
+            &letter.from,
+        )
+        .await?;
 
         Ok(())
     }
@@ -1068,8 +944,6 @@ xoxo,
     /// Send an email to the new user about their account.
     pub async fn send_email_new_user(&self, db: &Database) -> Result<()> {
         let company = self.company(db).await?;
-        // Initialize the SendGrid client.
-        let sendgrid = SendGrid::new_from_env();
 
         // Get the user's aliases if they have one.
         let aliases = self.aliases.join(", ");
@@ -1096,12 +970,10 @@ let jess@{} know what your GitHub handle is.",
         }
 
         // Send the message.
-        sendgrid
-            .mail_send()
-            .send_plain_text(
-                &format!("Your New Email Account: {}", self.email),
-                &format!(
-                    "Yoyoyo {},
+        send_plain_text_email(
+            &format!("Your New Email Account: {}", self.email),
+            &format!(
+                "Yoyoyo {},
 
 You should have an email from Okta about setting up your account with them.
 We use Okta to authenticate to a number of different apps -- including
@@ -1156,23 +1028,23 @@ Lastly, be sure to order yourself some swag: https://swag.oxide.computer
 
 xoxo,
   The Onboarding Bot",
-                    self.first_name,
-                    company.domain,
-                    company.domain,
-                    self.email,
-                    aliases,
-                    github_copy,
-                    company.gsuite_domain,
-                    company.github_org,
-                    company.github_org,
-                    company.github_org,
-                ),
-                &[self.recovery_email.to_string()],
-                &[self.email.to_string(), format!("jess@{}", company.gsuite_domain)],
-                &[],
-                &format!("admin@{}", company.gsuite_domain),
-            )
-            .await?;
+                self.first_name,
+                company.domain,
+                company.domain,
+                self.email,
+                aliases,
+                github_copy,
+                company.gsuite_domain,
+                company.github_org,
+                company.github_org,
+                company.github_org,
+            ),
+            &[self.recovery_email.to_string()],
+            &[self.email.to_string(), format!("jess@{}", company.gsuite_domain)],
+            &[],
+            &format!("admin@{}", company.gsuite_domain),
+        )
+        .await?;
 
         Ok(())
     }
@@ -1332,6 +1204,11 @@ pub struct GroupConfig {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub repos: Vec<String>,
 
+    /// The Slack channel that members of this group are added to. If this is left empty then
+    /// members are instead added to a Slack user group with the same handle as the group name
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub slack_channel: String,
+
     /// allow_external_members: Identifies whether members external to your
     /// organization can join the group. Possible values are:
     /// - true: G Suite users external to your organization can become
@@ -1724,18 +1601,19 @@ impl HuddleConfig {
 }
 /// Get the configs from the GitHub repository and parse them.
 pub async fn get_configs_from_repo(github: &octorust::Client, company: &Company) -> Result<Config> {
+    // Leaving the revision blank gives us the default branch
+    get_configs_from_repo_at(github, company, "").await
+}
+
+/// Get the configs as they were at a revision of the configs repo.
+pub async fn get_configs_from_repo_at(github: &octorust::Client, company: &Company, revision: &str) -> Result<Config> {
     let owner = &company.github_org;
     let repo = "configs";
 
-    log::info!("Getting configs from GitHub");
+    log::info!("Getting configs from GitHub at revision `{}`", revision);
     let files = github
         .repos()
-        .get_content_vec_entries(
-            owner,
-            repo,
-            "/configs/",
-            "", // leaving the branch blank gives us the default branch
-        )
+        .get_content_vec_entries(owner, repo, "/configs/", revision)
         .await?
         .body;
 
@@ -1743,11 +1621,7 @@ pub async fn get_configs_from_repo(github: &octorust::Client, company: &Company)
     for file in files {
         info!("decoding {}", file.name);
         // Get the contents of the file.
-        let (contents, _) = get_file_content_from_repo(
-            github, owner, repo, "", // leaving the branch blank gives us the default branch
-            &file.path,
-        )
-        .await?;
+        let (contents, _) = get_file_content_from_repo(github, owner, repo, revision, &file.path).await?;
 
         let decoded = from_utf8(&contents)?.trim().to_string();
 
@@ -1761,22 +1635,33 @@ pub async fn get_configs_from_repo(github: &octorust::Client, company: &Company)
     Ok(config)
 }
 
-/// Sync our users with our database and then update Airtable from the database.
+/// Get the sha of the latest commit to the default branch of the configs repo.
+pub async fn get_configs_revision(github: &octorust::Client, company: &Company) -> Result<String> {
+    let owner = &company.github_org;
+    let repo = "configs";
+
+    let default_branch = github.repos().get(owner, repo).await?.body.default_branch;
+    let branch = github.repos().get_branch(owner, repo, &default_branch).await?.body;
+
+    Ok(branch.commit.sha)
+}
+
+/// Sync our users with our database and then update Airtable from the database. Only the changes
+/// in the reviewed plan are applied to the external services, and the differences to the plan at
+/// the time it is applied are returned.
 pub async fn sync_users(
     db: &Database,
     github: &octorust::Client,
     users: BTreeMap<String, UserConfig>,
     company: &Company,
     config: &AppConfig,
-) -> Result<()> {
+    reviewed: &ConfigsPlan,
+) -> Result<PlanDrift> {
     // Get everything we need to authenticate with GSuite.
     // Initialize the GSuite client.
     let gsuite = company.authenticate_google_admin(db).await?;
     let gcal = company.authenticate_google_calendar(db).await?;
 
-    // We don't need a base id here since we are only using the enterprise api features.
-    let airtable_auth = company.authenticate_airtable("");
-
     // Initialize the Gusto client.
     let mut gusto_users: HashMap<String, gusto_api::types::Employee> = HashMap::new();
     let mut gusto_users_by_id: HashMap<String, gusto_api::types::Employee> = HashMap::new();
@@ -1910,12 +1795,10 @@ pub async fn sync_users(
             .skip(skip)
             .take(take)
             .map(|(_, mut user)| {
-                tokio::spawn(crate::enclose! { (db, company, config, github, gsuite_users_map, okta_users, ramp_users, zoom_users, zoom_users_pending, gusto_users, gusto_users_by_id) async move {
+                crate::sandbox::spawn(crate::enclose! { (db, company, gsuite_users_map, okta_users, ramp_users, zoom_users, zoom_users_pending, gusto_users, gusto_users_by_id) async move {
                 user.sync(
                     &db,
                     &company,
-                    &config,
-                    &github,
                     &gsuite_users_map,
                     &okta_users,
                     &ramp_users,
@@ -1947,11 +1830,50 @@ pub async fn sync_users(
         skip += take;
     }
 
-    for (_, user) in users {
+    let today = Utc::now().date_naive();
+    for user in users.values() {
         // Remove the user from the BTreeMap.
         user_map.remove(&user.username);
+
+        // A user that is in the configs is no longer being offboarded.
+        if let Err(err) = UserOffboarding::returned(db, company.id, &user.username).await {
+            warn!("Failed to record return of user {}. err: {:?}", user.username, err);
+        }
+
+        // New hires are provisioned on a schedule relative to their start date.
+        if let Some(db_user) = User::get_from_db(db, company.id, user.username.to_string()).await {
+            if let Err(err) = provisioning::schedule(db, company.id, &db_user, today).await {
+                warn!(
+                    "Failed to schedule provisioning of user {}. err: {:?}",
+                    user.username, err
+                );
+            }
+        }
     }
 
+    // Plan the changes to the external services now that the database is up to date, and apply
+    // exactly those changes. Users that have been removed from the configs are offboarded step by
+    // step rather than deleted from every service at once, so their deletes are left out. Changes
+    // that were not reviewed are left out as well.
+    //
+    // Accounts that are created by a pending provisioning step are left to that step.
+    let held = provisioning::held_services(db, company).await?;
+    let mut plan = ConfigsPlan::for_users(db, github, company, &users).await?;
+    let drift = plan.retain_reviewed(reviewed, PlanScope::Users);
+    if !drift.is_empty() {
+        warn!("{}", drift);
+    }
+    plan.changes
+        .retain(|change| change.action != ChangeAction::Delete || !user_map.contains_key(&change.user));
+    plan.changes.retain(|change| {
+        change.action == ChangeAction::Delete
+            || !held
+                .iter()
+                .any(|(username, service)| username == &change.user && service == &change.service)
+    });
+    info!("Applying configs users plan:\n{}", plan.render());
+    let failed = plan.apply(db, company, config).await?;
+
     info!(
         "Remaining users that would be removed during sync: {:?}",
         user_map.keys()
@@ -1959,17 +1881,28 @@ pub async fn sync_users(
 
     // Remove any users that should no longer be in the database.
     // This is found by the remaining users that are in the map since we removed
-    // the existing repos from the map above.
+    // the existing repos from the map above. Their accounts in other services are removed
+    // by their offboarding.
     for (username, user) in user_map {
+        // A user that has not started yet is no longer going to.
+        if let Err(err) = provisioning::cancel_user(db, company.id, &username).await {
+            warn!("Failed to cancel provisioning of user {}. err: {:?}", username, err);
+        }
+
         if !Features::is_enabled("REMOTE_USER_DELETES") {
             info!(
                 "User {} meets criteria for removal, but removals are currently disabled",
                 user.id
             );
         } else {
-            info!("deleting user `{}` from the database and other services", user.id);
+            info!("offboarding user `{}` from the database and other services", user.id);
+
+            let mut has_failures = failed.iter().any(|change| change.user == username);
 
-            let mut has_failures = false;
+            if let Err(err) = UserOffboarding::start(db, company, &user).await {
+                warn!("Failed to start offboarding user {}. err: {:?}", username, err);
+                has_failures = true;
+            }
 
             if !user.google_anniversary_event_id.is_empty() {
                 // First delete the recurring event for their anniversary.
@@ -2018,80 +1951,6 @@ pub async fn sync_users(
                 }
             }
 
-            // Supend the user from okta.
-            if let Some(ref okta) = okta_auth {
-                match okta.delete_user(db, company, &user).await {
-                    Ok(_) => {
-                        info!("Deleted user {} from okta", username);
-                    }
-                    Err(err) => {
-                        warn!("Failed to delete user {} from okta. err: {:?}", username, err);
-
-                        has_failures = true;
-                    }
-                }
-            }
-
-            if company.okta_domain.is_empty() {
-                // Delete the user from GSuite and other apps.
-                // ONLY DO THIS IF THE COMPANY DOES NOT USE OKTA.
-                // Suspend the user from GSuite so we can transfer their data.
-                match gsuite.delete_user(db, company, &user).await {
-                    Ok(_) => {
-                        info!("Deactivated user {} in GSuite", username);
-                    }
-                    Err(err) => {
-                        warn!("Failed to deactivate user {} in GSuite. err: {:?}", username, err);
-
-                        has_failures = true;
-                    }
-                }
-            }
-
-            // Remove the user from the github org.
-            match github.delete_user(db, company, &user).await {
-                Ok(_) => {
-                    info!("Deleted user {} from GitHub", username);
-                }
-                Err(err) => {
-                    warn!("Failed to delete user {} from GitHub. err: {:?}", username, err);
-                }
-            }
-
-            // TODO: Deactivate the user from Ramp.
-            // We only want to lock the cards from more purchases. Removing GSuite/Okta
-            // will disallow them from logging in. And we want their purchase history so
-            // we don't want to delete them.
-
-            // TODO: Delete the user from Slack.
-            // Removing SSO (GSuite/Okta) will disallow them from logging in.
-
-            // Delete the user from Zoom.
-            if let Ok(ref zoom) = zoom_auth {
-                match zoom.delete_user(db, company, &user).await {
-                    Ok(_) => {
-                        info!("Deleted user {} from Zoom", username);
-                    }
-                    Err(err) => {
-                        warn!("Failed to delete user {} from Zoom. err: {:?}", username, err);
-
-                        has_failures = true;
-                    }
-                }
-            }
-
-            // Delete the user from Airtable.
-            // Okta should take care of this if we are using Okta.
-            // But let's do it anyway.
-            match airtable_auth.delete_user(db, company, &user).await {
-                Ok(_) => {
-                    info!("Deleted user {} from Airtable", username);
-                }
-                Err(err) => {
-                    warn!("Failed to delete user {} from Airtable. err: {:?}", username, err);
-                }
-            }
-
             // User deletes are currently disabled. We no longer want to allow the behavior of removing
             // user records from our system. Instead they should be only marked as deleted so that we
             // can restore them in the future if needed.
@@ -2126,7 +1985,7 @@ pub async fn sync_users(
     // Update users in airtable.
     Users::get_from_db(db, company.id).await?.update_airtable(db).await?;
 
-    Ok(())
+    Ok(drift)
 }
 
 /// Sync our buildings with our database and then update Airtable from the database.
@@ -2374,16 +2233,18 @@ pub async fn sync_resources(
     Ok(())
 }
 
-/// Sync our groups with our database and then update Airtable from the database.
-pub async fn sync_groups(db: &Database, groups: BTreeMap<String, GroupConfig>, company: &Company) -> Result<()> {
-    // Get everything we need to authenticate with GSuite.
-    // Initialize the GSuite client.
-    let gsuite = company.authenticate_google_admin(db).await?;
-
+/// Sync our groups with our database and then update Airtable from the database. Only the changes
+/// in the reviewed plan are applied to the external services, and the differences to the plan at
+/// the time it is applied are returned.
+pub async fn sync_groups(
+    db: &Database,
+    groups: BTreeMap<String, GroupConfig>,
+    company: &Company,
+    config: &AppConfig,
+    reviewed: &ConfigsPlan,
+) -> Result<PlanDrift> {
     let github = company.authenticate_github()?;
 
-    let okta_auth = company.authenticate_okta();
-
     // Get all the groups.
     let db_groups = Groups::get_from_db(db, company.id).await?;
     // Create a BTreeMap
@@ -2393,7 +2254,7 @@ pub async fn sync_groups(db: &Database, groups: BTreeMap<String, GroupConfig>, c
     }
 
     // Sync groups.
-    for (_, mut group) in groups {
+    for (_, mut group) in groups.clone() {
         group.expand(company);
 
         group.upsert(db).await?;
@@ -2414,33 +2275,23 @@ pub async fn sync_groups(db: &Database, groups: BTreeMap<String, GroupConfig>, c
 
     info!("updated configs groups in the database");
 
-    // Update the groups in GitHub and GSuite.
-    // Get all the groups.
-    let db_groups = Groups::get_from_db(db, company.id).await?;
-    // Iterate over all the groups in our database.
+    // Plan the changes to the groups in GitHub, GSuite and Okta, and apply exactly the changes
+    // that were reviewed.
     // TODO: delete any groups that are not in the database for each vendor.
-    for g in db_groups {
-        // No more group syncing
-
-        // if g.supports_provisioning_in(&ExternalServices::GitHub) {
-        //     github.ensure_group(db, company, &g).await?;
-        // }
-
-        // if g.supports_provisioning_in(&ExternalServices::Google) {
-        //     gsuite.ensure_group(db, company, &g).await?;
-        // }
-
-        // if let Some(ref okta) = okta_auth {
-        //     if g.supports_provisioning_in(&ExternalServices::Okta) {
-        //         okta.ensure_group(db, company, &g).await?;
-        //     }
-        // }
+    let mut plan = ConfigsPlan::for_groups(db, &github, company, &groups).await?;
+    let drift = plan.retain_reviewed(reviewed, PlanScope::Groups);
+    if !drift.is_empty() {
+        warn!("{}", drift);
+    }
+    if !plan.is_empty() {
+        info!("Applying configs groups plan:\n{}", plan.render());
+        plan.apply(db, company, config).await?;
     }
 
     // Update groups in airtable.
     Groups::get_from_db(db, company.id).await?.update_airtable(db).await?;
 
-    Ok(())
+    Ok(drift)
 }
 
 /// Sync our links with our database and then update Airtable from the database.
@@ -2585,10 +2436,113 @@ pub async fn sync_certificates(
     Ok(())
 }
 
+/// The individual steps of a configs sync, in the order that they need to be run. Buildings must
+/// be synced before resources, and groups before users, as the later steps refer to the earlier
+/// ones. The links, certificates and anniversaries steps are independent of each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigsSyncStep {
+    Buildings,
+    Resources,
+    Groups,
+    Users,
+    Links,
+    Certificates,
+    Anniversaries,
+}
+
+impl ConfigsSyncStep {
+    pub const ALL: [ConfigsSyncStep; 7] = [
+        ConfigsSyncStep::Buildings,
+        ConfigsSyncStep::Resources,
+        ConfigsSyncStep::Groups,
+        ConfigsSyncStep::Users,
+        ConfigsSyncStep::Links,
+        ConfigsSyncStep::Certificates,
+        ConfigsSyncStep::Anniversaries,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigsSyncStep::Buildings => "buildings",
+            ConfigsSyncStep::Resources => "resources",
+            ConfigsSyncStep::Groups => "groups",
+            ConfigsSyncStep::Users => "users",
+            ConfigsSyncStep::Links => "links",
+            ConfigsSyncStep::Certificates => "certificates",
+            ConfigsSyncStep::Anniversaries => "anniversaries",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.name() == name)
+    }
+}
+
+/// Run a single step of a configs sync against the configs as they were at a revision of the
+/// configs repo.
+pub async fn sync_configs_step(
+    db: &Database,
+    github: &octorust::Client,
+    company: &Company,
+    config: &AppConfig,
+    step: ConfigsSyncStep,
+    revision: &str,
+) -> Result<()> {
+    // Anniversaries are built from the users in the database, not from the configs.
+    if step == ConfigsSyncStep::Anniversaries {
+        return refresh_anniversary_events(db, company).await;
+    }
+
+    let configs = get_configs_from_repo_at(github, company, revision).await?;
+
+    match step {
+        ConfigsSyncStep::Buildings => sync_buildings(db, configs.buildings, company).await,
+        ConfigsSyncStep::Resources => sync_resources(db, configs.resources, company).await,
+        ConfigsSyncStep::Groups => {
+            let reviewed = reviewed_plan(db, github, company, revision).await?;
+            sync_groups(db, configs.groups, company, config, &reviewed)
+                .await
+                .map(|_| ())
+        }
+        ConfigsSyncStep::Users => {
+            let reviewed = reviewed_plan(db, github, company, revision).await?;
+            sync_users(db, github, configs.users, company, config, &reviewed)
+                .await
+                .map(|_| ())
+        }
+        ConfigsSyncStep::Links => sync_links(db, configs.links, configs.huddles, company).await,
+        ConfigsSyncStep::Certificates => sync_certificates(db, github, configs.certificates, company).await,
+        ConfigsSyncStep::Anniversaries => refresh_anniversary_events(db, company).await,
+    }
+}
+
+/// The plan that was reviewed for a revision of the configs repo. Changes to users and groups are
+/// only applied to the external services from a reviewed plan, so a revision that was not
+/// reviewed has an empty plan and is only synced to the database.
+async fn reviewed_plan(
+    db: &Database,
+    github: &octorust::Client,
+    company: &Company,
+    revision: &str,
+) -> Result<ConfigsPlan> {
+    match ConfigsPlan::reviewed_for_revision(db, github, company, revision).await? {
+        Some(reviewed) => Ok(reviewed),
+        None => {
+            warn!(
+                "No plan was reviewed for configs revision {}, so no changes are applied to users and groups",
+                revision
+            );
+            Ok(ConfigsPlan::default())
+        }
+    }
+}
+
 pub async fn refresh_db_configs_and_airtable(db: &Database, company: &Company, config: &AppConfig) -> Result<()> {
     let github = company.authenticate_github()?;
 
-    let configs = get_configs_from_repo(&github, company).await?;
+    let revision = get_configs_revision(&github, company).await?;
+    let configs = get_configs_from_repo_at(&github, company, &revision).await?;
+    let reviewed = reviewed_plan(db, &github, company, &revision).await?;
 
     // Sync buildings.
     // Syncing buildings must happen before we sync resource.
@@ -2599,10 +2553,10 @@ pub async fn refresh_db_configs_and_airtable(db: &Database, company: &Company, c
 
     // Sync groups.
     // Syncing groups must happen before we sync the users.
-    sync_groups(db, configs.groups, company).await?;
+    sync_groups(db, configs.groups, company, config, &reviewed).await?;
 
     // Sync users.
-    sync_users(db, &github, configs.users, company, config).await?;
+    sync_users(db, &github, configs.users, company, config, &reviewed).await?;
 
     // Sync links.
     let (links, certs, ann) = tokio::join!(
@@ -2766,7 +2720,7 @@ pub mod tests {
     use serde::{Deserialize, Serialize};
     use serde_json;
 
-    use super::{ExternalServices, User, UserConfig};
+    use super::{ConfigsSyncStep, ExternalServices, User, UserConfig};
 
     pub fn mock_user() -> User {
         User {
@@ -2865,6 +2819,12 @@ pub mod tests {
             },
             serde_json::from_str::<ServiceWrapper>("{\"service\": \"ramp\"}").unwrap()
         );
+        assert_eq!(
+            ServiceWrapper {
+                service: ExternalServices::Slack
+            },
+            serde_json::from_str::<ServiceWrapper>("{\"service\": \"slack\"}").unwrap()
+        );
         assert_eq!(
             ServiceWrapper {
                 service: ExternalServices::Zoom
@@ -2912,6 +2872,14 @@ pub mod tests {
             .unwrap()
             .as_str()
         );
+        assert_eq!(
+            "{\"service\":\"slack\"}",
+            serde_json::to_string(&ServiceWrapper {
+                service: ExternalServices::Slack
+            })
+            .unwrap()
+            .as_str()
+        );
         assert_eq!(
             "{\"service\":\"zoom\"}",
             serde_json::to_string(&ServiceWrapper {
@@ -2944,6 +2912,7 @@ denied_services = [
     'google',
     'okta',
     'ramp',
+    'slack',
     'zoom'
 ]
 recovery_email = 'testuser@localhost'
@@ -2968,6 +2937,7 @@ manager = 'orb'
                 ExternalServices::Google,
                 ExternalServices::Okta,
                 ExternalServices::Ramp,
+                ExternalServices::Slack,
                 ExternalServices::Zoom
             ]
         );
@@ -3006,4 +2976,13 @@ manager = 'orb'
         assert_eq!(user.denied_services, vec![]);
         assert!(!user.gusto_pull_permission);
     }
+
+    #[test]
+    fn test_configs_sync_step_names() {
+        for step in ConfigsSyncStep::ALL {
+            assert_eq!(Some(step), ConfigsSyncStep::from_name(step.name()));
+        }
+
+        assert_eq!(None, ConfigsSyncStep::from_name("revision"));
+    }
 }
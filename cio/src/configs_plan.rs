@@ -0,0 +1,1583 @@
+//! Plan and apply the changes that syncing the configs repo makes to external services.
+//!
+//! A [`ConfigsPlan`] is computed by reading the current state of each provider and comparing it to
+//! the users and groups in the configs. Nothing is written while planning, so a plan can be
+//! rendered for review on a pull request to the configs repo before it is merged. Applying a plan
+//! executes exactly the changes it lists through each provider's [`ProviderWriteOps`], and leaves
+//! users and groups without any planned changes untouched.
+//!
+//! The plan that was reviewed for the head of a pull request is stored, so that once the pull
+//! request is merged only the reviewed changes are applied. Changes to users and groups are never
+//! applied without a reviewed plan. Any difference between the reviewed plan and the plan at the
+//! time it is applied is reported as a [`PlanDrift`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+
+use anyhow::{anyhow, bail, Result};
+use async_bb8_diesel::AsyncRunQueryDsl;
+use diesel::{ExpressionMethods, Insertable, QueryDsl};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    companies::Company,
+    configs::{ExternalServices, Group, GroupConfig, User, UserConfig, Users},
+    db::Database,
+    features::Features,
+    providers::{slack_group_name, ProviderReadOps, ProviderWriteOps},
+    schema::configs_plans,
+};
+
+/// The order that services are planned, rendered and applied in.
+const SERVICES: [ExternalServices; 7] = [
+    ExternalServices::Okta,
+    ExternalServices::Google,
+    ExternalServices::GitHub,
+    ExternalServices::Slack,
+    ExternalServices::Zoom,
+    ExternalServices::Ramp,
+    ExternalServices::Airtable,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+    AddMember,
+    RemoveMember,
+}
+
+impl ChangeAction {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ChangeAction::Create | ChangeAction::AddMember => "+",
+            ChangeAction::Update => "~",
+            ChangeAction::Delete | ChangeAction::RemoveMember => "-",
+        }
+    }
+}
+
+/// A single attribute of a user or group that a change will modify.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct AttributeChange {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// A single change to a user, a group or a group membership in an external service.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct PlannedChange {
+    pub service: ExternalServices,
+    pub action: ChangeAction,
+    /// The username of the user that is changed. Empty for changes to groups
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub user: String,
+    /// The group that is changed, or that the user is added to or removed from
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub group: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<AttributeChange>,
+}
+
+impl fmt::Display for PlannedChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.action {
+            ChangeAction::AddMember | ChangeAction::RemoveMember => write!(
+                f,
+                "{} membership \"{}\" in group \"{}\"",
+                self.action.symbol(),
+                self.user,
+                self.group
+            ),
+            _ if self.user.is_empty() => write!(f, "{} group \"{}\"", self.action.symbol(), self.group),
+            _ => write!(f, "{} user \"{}\"", self.action.symbol(), self.user),
+        }?;
+
+        for attribute in &self.attributes {
+            if self.action == ChangeAction::Create {
+                write!(f, "\n    {}: {:?}", attribute.name, attribute.to)?;
+            } else {
+                write!(
+                    f,
+                    "\n    {}: {:?} -> {:?}",
+                    attribute.name, attribute.from, attribute.to
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The changes that a sync of the configs would make to each external service.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigsPlan {
+    pub changes: Vec<PlannedChange>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = configs_plans)]
+struct NewConfigsPlan {
+    cio_company_id: i32,
+    sha: String,
+    plan: serde_json::Value,
+}
+
+/// The part of the configs that a plan was made for. Groups and users are planned and applied
+/// separately, while the plan that is reviewed covers both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanScope {
+    Groups,
+    Users,
+}
+
+impl PlanScope {
+    /// Whether a change belongs to this part of the configs. Changes to group memberships belong
+    /// to users, as they are applied along with them
+    pub fn contains(&self, change: &PlannedChange) -> bool {
+        match self {
+            PlanScope::Groups => change.user.is_empty(),
+            PlanScope::Users => !change.user.is_empty(),
+        }
+    }
+}
+
+/// How the plan at the time it is applied differs from the plan that was reviewed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanDrift {
+    /// Changes that are needed now, but were not reviewed and so are not applied
+    pub unreviewed: Vec<PlannedChange>,
+    /// Changes that were reviewed, but are no longer needed
+    pub outdated: Vec<PlannedChange>,
+}
+
+impl PlanDrift {
+    pub fn is_empty(&self) -> bool {
+        self.unreviewed.is_empty() && self.outdated.is_empty()
+    }
+}
+
+impl fmt::Display for PlanDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sections = [
+            ("Not applied, as they were not reviewed", &self.unreviewed),
+            ("Reviewed, but no longer needed", &self.outdated),
+        ];
+
+        write!(f, "The plan differs from the plan that was reviewed:")?;
+        for (title, changes) in sections {
+            if changes.is_empty() {
+                continue;
+            }
+
+            write!(f, "\n\n# {}", title)?;
+            for change in changes {
+                write!(f, "\n{} ({})", change, change.service)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The revisions that a plan may have been reviewed for before a revision of the configs repo was
+/// merged. These are the revision itself, which covers merge commits and rebases, and the heads of
+/// the pull requests that the revision belongs to, which covers squash merges.
+pub async fn reviewed_revisions(github: &octorust::Client, company: &Company, sha: &str) -> Vec<String> {
+    let mut shas = vec![sha.to_string()];
+
+    match github
+        .repos()
+        .list_all_pull_requests_associated_with_commit(&company.github_org, "configs", sha)
+        .await
+    {
+        Ok(response) => shas.extend(response.body.into_iter().map(|pull| pull.head.sha)),
+        Err(err) => warn!(
+            "Failed to find the pull requests of configs commit {}. err: {}",
+            sha, err
+        ),
+    }
+
+    shas
+}
+
+impl ConfigsPlan {
+    /// Plan the changes for both the groups and the users of the configs. Groups are planned
+    /// first, as they are synced before users.
+    pub async fn for_configs(
+        db: &Database,
+        github: &octorust::Client,
+        company: &Company,
+        groups: &BTreeMap<String, GroupConfig>,
+        users: &BTreeMap<String, UserConfig>,
+    ) -> Result<Self> {
+        let mut plan = Self::for_groups(db, github, company, groups).await?;
+        plan.changes
+            .extend(Self::for_users(db, github, company, users).await?.changes);
+
+        Ok(plan)
+    }
+
+    /// Plan the groups to create or update in each provider. Groups are only synced to providers
+    /// when the REMOTE_GROUP_SYNC feature is enabled, otherwise the plan is always empty.
+    pub async fn for_groups(
+        db: &Database,
+        github: &octorust::Client,
+        company: &Company,
+        groups: &BTreeMap<String, GroupConfig>,
+    ) -> Result<Self> {
+        if !Features::is_enabled("REMOTE_GROUP_SYNC") {
+            return Ok(Self::default());
+        }
+
+        let groups = groups.values().cloned().collect::<Vec<_>>();
+        let states = load_states(db, github, company, &[], &[], &groups).await?;
+
+        Ok(Self::from_states(&states))
+    }
+
+    /// Plan the users to create, update or remove, and the group memberships to change, in each
+    /// provider. Users that are in the database but no longer in the configs are only removed
+    /// when the REMOTE_USER_DELETES feature is enabled.
+    pub async fn for_users(
+        db: &Database,
+        github: &octorust::Client,
+        company: &Company,
+        users: &BTreeMap<String, UserConfig>,
+    ) -> Result<Self> {
+        let (desired, removed) = desired_users(db, company, users).await?;
+        let states = load_states(db, github, company, &desired, &removed, &[]).await?;
+
+        Ok(Self::from_states(&states))
+    }
+
+    fn from_states(states: &[ServiceState]) -> Self {
+        Self {
+            changes: states.iter().flat_map(|state| state.diff()).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Store the plan that was reviewed for a revision of the configs repo, so that it can be
+    /// applied once the revision is merged
+    pub async fn store(&self, db: &Database, company_id: i32, sha: &str) -> Result<()> {
+        diesel::insert_into(configs_plans::table)
+            .values(&NewConfigsPlan {
+                cio_company_id: company_id,
+                sha: sha.to_string(),
+                plan: serde_json::to_value(self)?,
+            })
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the plan that was most recently reviewed for any of the revisions of the configs repo
+    pub async fn reviewed(db: &Database, company_id: i32, shas: &[String]) -> Result<Option<Self>> {
+        let plan = configs_plans::dsl::configs_plans
+            .filter(configs_plans::dsl::cio_company_id.eq(company_id))
+            .filter(configs_plans::dsl::sha.eq_any(shas.to_vec()))
+            .order_by(configs_plans::dsl::created_at.desc())
+            .select(configs_plans::dsl::plan)
+            .limit(1)
+            .load_async::<serde_json::Value>(db.pool())
+            .await?
+            .pop();
+
+        Ok(plan.map(serde_json::from_value).transpose()?)
+    }
+
+    /// Get the plan that was most recently reviewed for a revision of the configs repo, either for
+    /// the revision itself or for the head of a pull request that it was merged from
+    pub async fn reviewed_for_revision(
+        db: &Database,
+        github: &octorust::Client,
+        company: &Company,
+        sha: &str,
+    ) -> Result<Option<Self>> {
+        Self::reviewed(db, company.id, &reviewed_revisions(github, company, sha).await).await
+    }
+
+    /// Drop the changes that are not in the reviewed plan, so that only reviewed changes are
+    /// applied. The plan is compared to the changes of the reviewed plan that are in `scope`.
+    /// Returns how the plan differed from the reviewed plan.
+    pub fn retain_reviewed(&mut self, reviewed: &ConfigsPlan, scope: PlanScope) -> PlanDrift {
+        let reviewed = reviewed
+            .changes
+            .iter()
+            .filter(|change| scope.contains(change))
+            .collect::<Vec<_>>();
+
+        let outdated = reviewed
+            .iter()
+            .filter(|change| !self.changes.contains(change))
+            .map(|change| (*change).clone())
+            .collect();
+        let (changes, unreviewed) = self.changes.drain(..).partition(|change| reviewed.contains(&change));
+        self.changes = changes;
+
+        PlanDrift { unreviewed, outdated }
+    }
+
+    /// Render the plan as a Terraform style diff, with a section for each service.
+    pub fn render(&self) -> String {
+        if self.is_empty() {
+            return "No changes. The external services match the configs.".to_string();
+        }
+
+        let mut out = String::new();
+
+        for service in SERVICES.iter() {
+            let changes = self
+                .changes
+                .iter()
+                .filter(|change| &change.service == service)
+                .collect::<Vec<_>>();
+
+            if changes.is_empty() {
+                continue;
+            }
+
+            out.push_str(&format!("# {}\n", service));
+            for change in changes {
+                out.push_str(&format!("{}\n", change));
+            }
+            out.push('\n');
+        }
+
+        let count = |actions: &[ChangeAction]| {
+            self.changes
+                .iter()
+                .filter(|change| actions.contains(&change.action))
+                .count()
+        };
+
+        out.push_str(&format!(
+            "Plan: {} to add, {} to change, {} to destroy.",
+            count(&[ChangeAction::Create, ChangeAction::AddMember]),
+            count(&[ChangeAction::Update]),
+            count(&[ChangeAction::Delete, ChangeAction::RemoveMember]),
+        ));
+
+        out
+    }
+
+    /// Apply each change in the plan in order. A change that fails is logged and the remaining
+    /// changes are still applied. Returns the changes that failed.
+    pub async fn apply(&self, db: &Database, company: &Company, config: &AppConfig) -> Result<Vec<PlannedChange>> {
+        let mut writers: Vec<(ExternalServices, Box<dyn ProviderWriteOps + Send + Sync>)> = vec![];
+        let mut failed = vec![];
+
+        for change in &self.changes {
+            if !writers.iter().any(|(service, _)| service == &change.service) {
+                match change.service.get_provider_writer(db, company).await {
+                    Ok(writer) => writers.push((change.service.clone(), writer)),
+                    Err(err) => {
+                        warn!(
+                            "Failed to create provider client for {}. err: {:?}",
+                            change.service, err
+                        );
+                        failed.push(change.clone());
+                        continue;
+                    }
+                }
+            }
+
+            // The writer was either found or added above
+            let (_, writer) = writers.iter().find(|(service, _)| service == &change.service).unwrap();
+
+            match apply_change(db, company, config, writer.as_ref(), change).await {
+                Ok(_) => info!(
+                    "Applied `{}` to {}",
+                    change.to_string().replace('\n', " "),
+                    change.service
+                ),
+                Err(err) => {
+                    warn!(
+                        "Failed to apply `{}` to {}. err: {:?}",
+                        change.to_string().replace('\n', " "),
+                        change.service,
+                        err
+                    );
+                    failed.push(change.clone());
+                }
+            }
+        }
+
+        Ok(failed)
+    }
+}
+
+async fn apply_change(
+    db: &Database,
+    company: &Company,
+    config: &AppConfig,
+    writer: &(dyn ProviderWriteOps + Send + Sync),
+    change: &PlannedChange,
+) -> Result<()> {
+    if change.user.is_empty() {
+        let group = Group::get_from_db(db, company.id, change.group.to_string())
+            .await
+            .ok_or_else(|| anyhow!("Group {} does not exist in the database", change.group))?;
+
+        return match change.action {
+            ChangeAction::Create | ChangeAction::Update => writer.ensure_group(db, company, &group).await,
+            _ => bail!("Unsupported change to group {}", change.group),
+        };
+    }
+
+    let mut user = User::get_from_db(db, company.id, change.user.to_string())
+        .await
+        .ok_or_else(|| anyhow!("User {} does not exist in the database", change.user))?;
+
+    match change.action {
+        ChangeAction::Create | ChangeAction::Update => {
+            ensure_account(db, company, config, writer, &change.service, &mut user).await
+        }
+        ChangeAction::Delete => writer.delete_user(db, company, &user).await,
+        ChangeAction::AddMember => writer.add_user_to_group(company, &user, &change.group).await,
+        ChangeAction::RemoveMember => writer.remove_user_from_group(company, &user, &change.group).await,
+    }
+}
+
+/// Create or update the account of a user in a service, and store the id of the account on the user.
+pub(crate) async fn ensure_account(
+    db: &Database,
+    company: &Company,
+    config: &AppConfig,
+    writer: &(dyn ProviderWriteOps + Send + Sync),
+    service: &ExternalServices,
+    user: &mut User,
+) -> Result<()> {
+    let id = writer.ensure_user(db, company, user, config).await?;
+
+    if !id.is_empty() {
+        match service {
+            ExternalServices::Airtable => user.airtable_id = id,
+            ExternalServices::Google => user.google_id = id,
+            ExternalServices::Okta => user.okta_id = id,
+            ExternalServices::Ramp => user.ramp_id = id,
+            ExternalServices::Zoom => user.zoom_id = id,
+            ExternalServices::GitHub | ExternalServices::Slack => (),
+        }
+
+        user.update(db).await?;
+    }
+
+    Ok(())
+}
+
+/// The parts of a user that are sourced from the configs repo, and so can be planned before a
+/// change to the repo has been merged and synced to the database.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DesiredUser {
+    pub username: String,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub recovery_email: String,
+    pub recovery_phone: String,
+    pub github: String,
+    pub chat: String,
+    pub aws_role: String,
+    pub department: String,
+    pub aliases: Vec<String>,
+    pub groups: Vec<String>,
+    pub is_group_admin: bool,
+    pub typev: String,
+    pub denied_services: Vec<ExternalServices>,
+}
+
+impl DesiredUser {
+    /// Expands the user in the same way as [`UserConfig::expand`], leaving out the parts that
+    /// are sourced from other services.
+    pub fn from_config(config: &UserConfig, company: &Company) -> Self {
+        let mut user = config.clone();
+
+        user.email = format!("{}@{}", user.username, company.gsuite_domain);
+        user.populate_type();
+        user.ensure_all_aliases();
+        user.ensure_all_groups();
+        user.department = titlecase::titlecase(&user.department);
+
+        Self {
+            username: user.username,
+            email: user.email,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            recovery_email: user.recovery_email,
+            recovery_phone: user.recovery_phone,
+            github: user.github,
+            chat: user.chat,
+            aws_role: user.aws_role,
+            department: user.department,
+            aliases: user.aliases,
+            groups: user.groups,
+            is_group_admin: user.is_group_admin,
+            typev: user.typev,
+            denied_services: user.denied_services,
+        }
+    }
+
+    fn is_full_time(&self) -> bool {
+        self.typev == "full-time"
+    }
+
+    pub(crate) fn is_denied(&self, service: &ExternalServices) -> bool {
+        self.denied_services.contains(service)
+    }
+}
+
+impl From<&User> for DesiredUser {
+    fn from(user: &User) -> Self {
+        Self {
+            username: user.username.to_string(),
+            email: user.email.to_string(),
+            first_name: user.first_name.to_string(),
+            last_name: user.last_name.to_string(),
+            recovery_email: user.recovery_email.to_string(),
+            recovery_phone: user.recovery_phone.to_string(),
+            github: user.github.to_string(),
+            chat: user.chat.to_string(),
+            aws_role: user.aws_role.to_string(),
+            department: user.department.to_string(),
+            aliases: user.aliases.clone(),
+            groups: user.groups.clone(),
+            is_group_admin: user.is_group_admin,
+            typev: user.typev.to_string(),
+            denied_services: user.denied_services.clone(),
+        }
+    }
+}
+
+/// The users in the configs, along with the users that are in the database but have since been
+/// removed from the configs.
+pub(crate) async fn desired_users(
+    db: &Database,
+    company: &Company,
+    users: &BTreeMap<String, UserConfig>,
+) -> Result<(Vec<DesiredUser>, Vec<DesiredUser>)> {
+    let desired = users
+        .values()
+        .map(|user| DesiredUser::from_config(user, company))
+        .collect::<Vec<_>>();
+
+    let removed = Users::get_from_db(db, company.id)
+        .await?
+        .0
+        .iter()
+        .filter(|user| !users.values().any(|config| config.username == user.username))
+        .map(DesiredUser::from)
+        .collect::<Vec<_>>();
+
+    Ok((desired, removed))
+}
+
+/// An account in a provider, either as it currently is or as the configs want it to be.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Account {
+    pub(crate) attributes: BTreeMap<String, String>,
+    pub(crate) groups: BTreeSet<String>,
+}
+
+impl Account {
+    fn with_attributes(attributes: &[(&str, &str)]) -> Self {
+        Self {
+            attributes: attributes
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            groups: Default::default(),
+        }
+    }
+}
+
+/// The accounts and groups of a single provider, both as they currently are and as the configs
+/// want them to be. Accounts are keyed by username. Accounts in the provider that do not belong
+/// to a known user are not managed by the configs, so they are only listed in `unmanaged`.
+#[derive(Debug, Clone)]
+pub(crate) struct ServiceState {
+    pub(crate) service: ExternalServices,
+    pub(crate) current: BTreeMap<String, Account>,
+    pub(crate) desired: BTreeMap<String, Account>,
+    /// Users that should not have an account in the provider
+    pub(crate) removed: BTreeSet<String>,
+    /// The description of each group, by name
+    pub(crate) current_groups: BTreeMap<String, String>,
+    pub(crate) desired_groups: BTreeMap<String, String>,
+    /// The email, or GitHub login, of each active account that does not belong to a known user
+    pub(crate) unmanaged: BTreeSet<String>,
+}
+
+impl ServiceState {
+    pub(crate) fn new(service: ExternalServices) -> Self {
+        Self {
+            service,
+            current: Default::default(),
+            desired: Default::default(),
+            removed: Default::default(),
+            current_groups: Default::default(),
+            desired_groups: Default::default(),
+            unmanaged: Default::default(),
+        }
+    }
+
+    fn change(&self, action: ChangeAction, user: &str, group: &str) -> PlannedChange {
+        PlannedChange {
+            service: self.service.clone(),
+            action,
+            user: user.to_string(),
+            group: group.to_string(),
+            attributes: vec![],
+        }
+    }
+
+    /// Compute the changes that bring the current state in line with the desired state. Only the
+    /// attributes of the desired account are compared, as those are the attributes the configs
+    /// manage.
+    pub(crate) fn diff(&self) -> Vec<PlannedChange> {
+        let mut changes = vec![];
+
+        for (name, description) in &self.desired_groups {
+            match self.current_groups.get(name) {
+                None => changes.push(self.change(ChangeAction::Create, "", name)),
+                Some(current) if current != description => {
+                    let mut change = self.change(ChangeAction::Update, "", name);
+                    change.attributes.push(AttributeChange {
+                        name: "description".to_string(),
+                        from: current.to_string(),
+                        to: description.to_string(),
+                    });
+                    changes.push(change);
+                }
+                Some(_) => (),
+            }
+        }
+
+        for (username, desired) in &self.desired {
+            let current = self.current.get(username);
+
+            let attributes = desired
+                .attributes
+                .iter()
+                .filter_map(|(name, to)| {
+                    let from = current
+                        .and_then(|account| account.attributes.get(name))
+                        .cloned()
+                        .unwrap_or_default();
+
+                    if &from == to || (current.is_none() && to.is_empty()) {
+                        None
+                    } else {
+                        Some(AttributeChange {
+                            name: name.to_string(),
+                            from,
+                            to: to.to_string(),
+                        })
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if current.is_none() || !attributes.is_empty() {
+                let action = if current.is_none() {
+                    ChangeAction::Create
+                } else {
+                    ChangeAction::Update
+                };
+
+                let mut change = self.change(action, username, "");
+                change.attributes = attributes;
+                changes.push(change);
+            }
+
+            let current_groups = current.map(|account| account.groups.clone()).unwrap_or_default();
+
+            for group in desired.groups.difference(&current_groups) {
+                changes.push(self.change(ChangeAction::AddMember, username, group));
+            }
+
+            for group in current_groups.difference(&desired.groups) {
+                changes.push(self.change(ChangeAction::RemoveMember, username, group));
+            }
+        }
+
+        for username in &self.removed {
+            if self.current.contains_key(username) && !self.desired.contains_key(username) {
+                changes.push(self.change(ChangeAction::Delete, username, ""));
+            }
+        }
+
+        changes
+    }
+}
+
+/// The users that should not have an account in the service. These are the users that are
+/// denied the service in their config, along with the users that have been removed from the
+/// configs if removals are enabled and are carried out for the service.
+fn removals(
+    service: &ExternalServices,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    removes_deleted_users: bool,
+) -> BTreeSet<String> {
+    let mut removals = users
+        .iter()
+        .filter(|user| user.is_denied(service))
+        .map(|user| user.username.to_string())
+        .collect::<BTreeSet<_>>();
+
+    if removes_deleted_users && Features::is_enabled("REMOTE_USER_DELETES") {
+        removals.extend(removed.iter().map(|user| user.username.to_string()));
+    }
+
+    removals
+}
+
+/// The groups from the configs that are provisioned in the service, by name.
+fn desired_groups(service: &ExternalServices, groups: &[GroupConfig]) -> BTreeMap<String, String> {
+    groups
+        .iter()
+        .filter(|group| group.supports_provisioning_in(service))
+        .map(|group| (group.name.to_string(), group.description.to_string()))
+        .collect()
+}
+
+/// Read the current state of each provider that the company uses. The users in the provider
+/// are matched to `users` and `removed` by email, or by GitHub handle for GitHub. Groups in the
+/// provider are only compared when `groups` is not empty.
+pub(crate) async fn load_states(
+    db: &Database,
+    github: &octorust::Client,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    groups: &[GroupConfig],
+) -> Result<Vec<ServiceState>> {
+    let all_users = users.iter().chain(removed.iter()).collect::<Vec<_>>();
+    let by_email = all_users
+        .iter()
+        .map(|user| (user.email.to_string(), user.username.to_string()))
+        .collect::<HashMap<_, _>>();
+
+    let okta = company.authenticate_okta();
+    let mut states = vec![];
+
+    // Okta manages the accounts in the other services when it is in use, in which case users are
+    // not provisioned in Google and Zoom directly.
+    if let Some(ref okta) = okta {
+        states.push(load_okta(okta, company, users, removed, groups, &by_email).await?);
+    }
+
+    let gsuite = company.authenticate_google_admin(db).await?;
+    states.push(load_google(&gsuite, company, users, removed, groups, &by_email, okta.is_none()).await?);
+
+    states.push(load_github(github, company, users, removed, groups).await?);
+
+    match company.authenticate_slack(db).await {
+        Ok(slack) => states.push(load_slack(&slack, company, users, removed, groups, &by_email).await?),
+        Err(err) => warn!("Skipping Slack when planning configs sync. err: {}", err),
+    }
+
+    match company.authenticate_zoom(db).await {
+        Ok(zoom) => states.push(load_zoom(&zoom, company, users, removed, &by_email, okta.is_none()).await?),
+        Err(err) => warn!("Skipping Zoom when planning configs sync. err: {}", err),
+    }
+
+    let ramp = company.authenticate_ramp()?;
+    states.push(load_ramp(&ramp, company, users, removed, &by_email).await?);
+
+    // We don't need a base id here since we are only using the enterprise api features.
+    let airtable = company.authenticate_airtable("");
+    states.push(load_airtable(&airtable, company, users, removed).await?);
+
+    Ok(states)
+}
+
+async fn load_okta(
+    okta: &okta::Client,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    groups: &[GroupConfig],
+    by_email: &HashMap<String, String>,
+) -> Result<ServiceState> {
+    let mut state = ServiceState::new(ExternalServices::Okta);
+    let mut usernames_by_id: HashMap<String, String> = HashMap::new();
+
+    for okta_user in okta.list_provider_users(company).await? {
+        if let Some(profile) = &okta_user.profile {
+            if let Some(username) = by_email.get(&profile.email) {
+                let mut aliases = profile.email_aliases.clone();
+                aliases.sort();
+
+                state.current.insert(
+                    username.to_string(),
+                    Account::with_attributes(&[
+                        ("first_name", &profile.first_name),
+                        ("last_name", &profile.last_name),
+                        ("department", &profile.department),
+                        ("second_email", &profile.second_email),
+                        ("mobile_phone", &profile.mobile_phone),
+                        ("github_username", &profile.github_username),
+                        ("matrix_username", &profile.matrix_username),
+                        ("aws_role", &profile.aws_role),
+                        ("email_aliases", &aliases.join(", ")),
+                    ]),
+                );
+                usernames_by_id.insert(okta_user.id.to_string(), username.to_string());
+            } else {
+                state.unmanaged.insert(profile.email.to_string());
+            }
+        }
+    }
+
+    // The Everyone group can not be modified.
+    let okta_groups = okta
+        .list_provider_groups(company)
+        .await?
+        .into_iter()
+        .filter_map(|group| group.profile.map(|profile| (group.id, profile)))
+        .filter(|(_, profile)| profile.name != "Everyone")
+        .collect::<Vec<_>>();
+
+    for (id, profile) in &okta_groups {
+        if !groups.is_empty() {
+            state
+                .current_groups
+                .insert(profile.name.to_string(), profile.description.to_string());
+        }
+
+        for member in okta.groups().list_all_users(id).await?.body {
+            if let Some(account) = usernames_by_id
+                .get(&member.id)
+                .and_then(|username| state.current.get_mut(username))
+            {
+                account.groups.insert(profile.name.to_string());
+            }
+        }
+    }
+
+    for user in users.iter().filter(|user| !user.is_denied(&state.service)) {
+        let mut aliases = user
+            .aliases
+            .iter()
+            .map(|alias| format!("{}@{}", alias, company.gsuite_domain))
+            .collect::<Vec<_>>();
+        aliases.sort();
+
+        let mut account = Account::with_attributes(&[
+            ("first_name", &user.first_name),
+            ("last_name", &user.last_name),
+            ("department", &user.department),
+            ("second_email", &user.recovery_email),
+            ("mobile_phone", &user.recovery_phone),
+            ("github_username", &user.github),
+            ("matrix_username", &user.chat),
+            ("aws_role", &user.aws_role),
+            ("email_aliases", &aliases.join(", ")),
+        ]);
+
+        // Users are only added to groups that exist in Okta.
+        account.groups = okta_groups
+            .iter()
+            .filter(|(_, profile)| user.groups.contains(&profile.name))
+            .map(|(_, profile)| profile.name.to_string())
+            .collect();
+
+        state.desired.insert(user.username.to_string(), account);
+    }
+
+    state.removed = removals(&state.service, users, removed, true);
+    state.desired_groups = desired_groups(&state.service, groups);
+
+    Ok(state)
+}
+
+async fn load_google(
+    gsuite: &gsuite_api::Client,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    groups: &[GroupConfig],
+    by_email: &HashMap<String, String>,
+    provisions_users: bool,
+) -> Result<ServiceState> {
+    let mut state = ServiceState::new(ExternalServices::Google);
+
+    for gsuite_user in gsuite.list_provider_users(company).await? {
+        if let Some(username) = by_email.get(&gsuite_user.primary_email) {
+            state.current.insert(username.to_string(), Account::default());
+        } else if !gsuite_user.suspended {
+            state.unmanaged.insert(gsuite_user.primary_email);
+        }
+    }
+
+    if !groups.is_empty() {
+        for group in gsuite.list_provider_groups(company).await? {
+            state.current_groups.insert(group.name, group.description);
+        }
+    }
+
+    // Existing GSuite users are not updated and group memberships are no longer synced, so
+    // users are only ever created.
+    if provisions_users {
+        for user in users.iter().filter(|user| !user.is_denied(&state.service)) {
+            state.desired.insert(user.username.to_string(), Account::default());
+        }
+    }
+
+    state.removed = removals(&state.service, users, removed, provisions_users);
+    state.desired_groups = desired_groups(&state.service, groups);
+
+    Ok(state)
+}
+
+async fn load_github(
+    github: &octorust::Client,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    groups: &[GroupConfig],
+) -> Result<ServiceState> {
+    let mut state = ServiceState::new(ExternalServices::GitHub);
+
+    // GitHub handles are case insensitive.
+    let by_handle = users
+        .iter()
+        .chain(removed.iter())
+        .filter(|user| !user.github.is_empty())
+        .map(|user| (user.github.to_lowercase(), user))
+        .collect::<HashMap<_, _>>();
+
+    let mut members = vec![];
+    for member in github.list_provider_users(company).await? {
+        match by_handle.get(&member.login.to_lowercase()) {
+            Some(user) => members.push(*user),
+            None => {
+                state.unmanaged.insert(member.login);
+            }
+        }
+    }
+
+    let teams = github.list_provider_groups(company).await?;
+
+    if !groups.is_empty() {
+        for team in &teams {
+            state
+                .current_groups
+                .insert(team.name.to_string(), team.description.to_string());
+        }
+    }
+
+    for user in members {
+        let membership = github
+            .orgs()
+            .get_membership_for_user(&company.github_org, &user.github)
+            .await
+            .map(|response| response.body)?;
+
+        let mut account = Account::with_attributes(&[("role", &membership.role.to_string())]);
+
+        // A user is only counted as a member of a team when they have the role in it that their
+        // config asks for, otherwise their membership needs to be updated.
+        let team_role = if user.is_group_admin {
+            octorust::types::TeamMembershipRole::Maintainer
+        } else {
+            octorust::types::TeamMembershipRole::Member
+        };
+
+        for team in &teams {
+            match github
+                .teams()
+                .get_membership_for_user_in_org(&company.github_org, &team.slug, &user.github)
+                .await
+                .map(|response| response.body)
+            {
+                Ok(membership) => {
+                    if membership.role == team_role {
+                        account.groups.insert(team.slug.to_string());
+                    }
+                }
+                Err(e) => {
+                    // If the error is Not Found they are not a member.
+                    if !e.to_string().contains("404") {
+                        bail!(
+                            "checking if user `{}` is a member of the github team `{}` failed: {}",
+                            user.github,
+                            team.slug,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        state.current.insert(user.username.to_string(), account);
+    }
+
+    for user in users
+        .iter()
+        .filter(|user| !user.github.is_empty() && !user.is_denied(&state.service))
+    {
+        let role = if user.is_group_admin {
+            octorust::types::OrgsSetMembershipUserRequestRole::Admin
+        } else {
+            octorust::types::OrgsSetMembershipUserRequestRole::Member
+        };
+
+        let mut account = Account::with_attributes(&[("role", &role.to_string())]);
+
+        // Users are only added to teams that exist in GitHub.
+        account.groups = teams
+            .iter()
+            .filter(|team| user.groups.contains(&team.name) || user.groups.contains(&team.slug))
+            .map(|team| team.slug.to_string())
+            .collect();
+
+        state.desired.insert(user.username.to_string(), account);
+    }
+
+    state.removed = removals(&state.service, users, removed, true);
+    state.desired_groups = desired_groups(&state.service, groups);
+
+    Ok(state)
+}
+
+async fn load_slack(
+    slack: &slack_chat_api::Slack,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    groups: &[GroupConfig],
+    by_email: &HashMap<String, String>,
+) -> Result<ServiceState> {
+    let mut state = ServiceState::new(ExternalServices::Slack);
+    let mut usernames_by_id: HashMap<String, String> = HashMap::new();
+
+    // Empty attributes are never written to Slack, so they are left out to avoid planning an
+    // update that can not be applied.
+    let profile = |first_name: &str, last_name: &str, title: &str| {
+        let attributes = [("first_name", first_name), ("last_name", last_name), ("title", title)]
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .collect::<Vec<_>>();
+
+        Account::with_attributes(&attributes)
+    };
+
+    for slack_user in slack.list_provider_users(company).await? {
+        if let Some(username) = by_email.get(&slack_user.profile.email) {
+            state.current.insert(
+                username.to_string(),
+                profile(
+                    &slack_user.profile.first_name,
+                    &slack_user.profile.last_name,
+                    &slack_user.profile.title,
+                ),
+            );
+            usernames_by_id.insert(slack_user.id, username.to_string());
+        } else if !slack_user.is_restricted && !slack_user.is_ultra_restricted {
+            // Guests are invited by hand to work with people outside of the company.
+            state.unmanaged.insert(slack_user.profile.email);
+        }
+    }
+
+    let slack_groups = groups
+        .iter()
+        .filter(|group| group.supports_provisioning_in(&state.service))
+        .collect::<Vec<_>>();
+
+    let usergroups = slack.list_provider_groups(company).await?;
+    let channels = if slack_groups.iter().any(|group| !group.slack_channel.is_empty()) {
+        slack.list_channels().await?
+    } else {
+        vec![]
+    };
+    let find_channel = |group: &GroupConfig| {
+        channels
+            .iter()
+            .find(|channel| channel.name == group.slack_channel.trim_start_matches('#'))
+    };
+
+    for group in &slack_groups {
+        let members = if group.slack_channel.is_empty() {
+            match usergroups.iter().find(|usergroup| usergroup.handle == group.name) {
+                Some(usergroup) => {
+                    state
+                        .current_groups
+                        .insert(group.name.to_string(), usergroup.description.to_string());
+                    usergroup.users.clone()
+                }
+                None => continue,
+            }
+        } else {
+            match find_channel(group) {
+                Some(channel) => {
+                    // Channels are only checked to exist, so their description is never changed.
+                    state
+                        .current_groups
+                        .insert(group.name.to_string(), group.description.to_string());
+                    slack.list_channel_members(&channel.id).await?
+                }
+                None => {
+                    warn!(
+                        "Slack channel `{}` for group `{}` does not exist",
+                        group.slack_channel, group.name
+                    );
+                    continue;
+                }
+            }
+        };
+
+        for id in members {
+            if let Some(account) = usernames_by_id
+                .get(&id)
+                .and_then(|username| state.current.get_mut(username))
+            {
+                account
+                    .groups
+                    .insert(slack_group_name(&group.name, &group.slack_channel));
+            }
+        }
+    }
+
+    for user in users.iter().filter(|user| !user.is_denied(&state.service)) {
+        let mut account = profile(&user.first_name, &user.last_name, &user.department);
+
+        // User groups are created by the plan, but users are only added to channels that exist.
+        account.groups = slack_groups
+            .iter()
+            .filter(|group| user.groups.contains(&group.name))
+            .filter(|group| group.slack_channel.is_empty() || find_channel(group).is_some())
+            .map(|group| slack_group_name(&group.name, &group.slack_channel))
+            .collect();
+
+        state.desired.insert(user.username.to_string(), account);
+    }
+
+    state.removed = removals(&state.service, users, removed, true);
+    state.desired_groups = desired_groups(&state.service, groups);
+
+    Ok(state)
+}
+
+async fn load_zoom(
+    zoom: &zoom_api::Client,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    by_email: &HashMap<String, String>,
+    provisions_users: bool,
+) -> Result<ServiceState> {
+    let mut state = ServiceState::new(ExternalServices::Zoom);
+
+    let mut zoom_users = zoom.list_provider_users(company).await?;
+
+    // Users that have been invited, but have not yet accepted, already have an account.
+    zoom_users.extend(
+        zoom.users()
+            .get_all(
+                zoom_api::types::UsersStatus::Pending,
+                "", // role id
+                zoom_api::types::UsersIncludeFields::Noop,
+            )
+            .await
+            .map(|response| response.body)?,
+    );
+
+    for zoom_user in zoom_users {
+        if let Some(username) = by_email.get(&zoom_user.email) {
+            state.current.insert(username.to_string(), Account::default());
+        } else {
+            state.unmanaged.insert(zoom_user.email);
+        }
+    }
+
+    if provisions_users {
+        for user in users
+            .iter()
+            .filter(|user| user.is_full_time() && !user.is_denied(&state.service))
+        {
+            state.desired.insert(user.username.to_string(), Account::default());
+        }
+    }
+
+    state.removed = removals(&state.service, users, removed, true);
+
+    Ok(state)
+}
+
+async fn load_ramp(
+    ramp: &ramp_minimal_api::RampClient,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+    by_email: &HashMap<String, String>,
+) -> Result<ServiceState> {
+    let mut state = ServiceState::new(ExternalServices::Ramp);
+
+    let departments = ramp
+        .departments()
+        .list()
+        .await?
+        .data
+        .into_iter()
+        .map(|department| (department.id, department.name))
+        .collect::<HashMap<_, _>>();
+
+    for ramp_user in ramp.list_provider_users(company).await? {
+        if let Some(username) = by_email.get(&ramp_user.email) {
+            let department = departments.get(&ramp_user.department_id).cloned().unwrap_or_default();
+            state.current.insert(
+                username.to_string(),
+                Account::with_attributes(&[("department", &department)]),
+            );
+        } else if matches!(
+            ramp_user.status,
+            ramp_minimal_api::UserStatus::Active | ramp_minimal_api::UserStatus::Onboarding
+        ) {
+            state.unmanaged.insert(ramp_user.email);
+        }
+    }
+
+    for user in users
+        .iter()
+        .filter(|user| user.is_full_time() && !user.recovery_phone.is_empty() && !user.is_denied(&state.service))
+    {
+        // Departments are not created in Ramp, so a user can only be placed in one that exists.
+        let account = if departments.values().any(|name| name == &user.department) {
+            Account::with_attributes(&[("department", &user.department)])
+        } else {
+            Account::default()
+        };
+
+        state.desired.insert(user.username.to_string(), account);
+    }
+
+    // Users that are removed from the configs keep their Ramp account, so that their purchase
+    // history is retained.
+    state.removed = removals(&state.service, users, removed, false);
+
+    Ok(state)
+}
+
+async fn load_airtable(
+    airtable: &airtable_api::Airtable,
+    company: &Company,
+    users: &[DesiredUser],
+    removed: &[DesiredUser],
+) -> Result<ServiceState> {
+    let mut state = ServiceState::new(ExternalServices::Airtable);
+
+    if company.airtable_enterprise_account_id.is_empty() {
+        // We don't have an enterprise account, so we can't manage Airtable users.
+        return Ok(state);
+    }
+
+    for user in users.iter().chain(removed.iter()) {
+        // Users that have not signed up to Airtable can not be given access, so users are never
+        // created, only granted access to the workspaces.
+        if let Ok(airtable_user) = airtable.get_enterprise_user(&user.email).await {
+            let has_access = |workspace_id: &str| {
+                airtable_user
+                    .collaborations
+                    .workspace_collaborations
+                    .iter()
+                    .any(|collab| collab.workspace_id == workspace_id)
+                    .to_string()
+            };
+
+            state.current.insert(
+                user.username.to_string(),
+                Account::with_attributes(&[
+                    ("workspace", &has_access(&company.airtable_workspace_id)),
+                    (
+                        "read_only_workspace",
+                        &has_access(&company.airtable_workspace_read_only_id),
+                    ),
+                ]),
+            );
+        }
+    }
+
+    for user in users.iter().filter(|user| {
+        user.is_full_time() && !user.is_denied(&state.service) && state.current.contains_key(&user.username)
+    }) {
+        state.desired.insert(
+            user.username.to_string(),
+            Account::with_attributes(&[("workspace", "true"), ("read_only_workspace", "true")]),
+        );
+    }
+
+    state.removed = removals(&state.service, users, removed, true);
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(attributes: &[(&str, &str)], groups: &[&str]) -> Account {
+        let mut account = Account::with_attributes(attributes);
+        account.groups = groups.iter().map(|group| group.to_string()).collect();
+        account
+    }
+
+    #[test]
+    fn test_diff_users() {
+        let mut state = ServiceState::new(ExternalServices::Okta);
+
+        state.current.insert(
+            "unchanged".to_string(),
+            account(&[("department", "Engineering"), ("city", "Emeryville")], &["eng"]),
+        );
+        state.current.insert(
+            "moved".to_string(),
+            account(&[("department", "Engineering")], &["eng", "ops"]),
+        );
+        state
+            .current
+            .insert("denied".to_string(), account(&[("department", "Sales")], &[]));
+
+        // Attributes that the configs do not manage are not compared.
+        state.desired.insert(
+            "unchanged".to_string(),
+            account(&[("department", "Engineering")], &["eng"]),
+        );
+        state.desired.insert(
+            "moved".to_string(),
+            account(&[("department", "Operations")], &["ops", "all"]),
+        );
+        state.desired.insert(
+            "new".to_string(),
+            account(&[("department", "Sales"), ("aws_role", "")], &["all"]),
+        );
+        state.removed.insert("denied".to_string());
+
+        // Users without an account are not removed.
+        state.removed.insert("missing".to_string());
+
+        let changes = state.diff();
+        let summary = changes
+            .iter()
+            .map(|change| (change.action, change.user.as_str(), change.group.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeAction::Update, "moved", ""),
+                (ChangeAction::AddMember, "moved", "all"),
+                (ChangeAction::RemoveMember, "moved", "eng"),
+                (ChangeAction::Create, "new", ""),
+                (ChangeAction::AddMember, "new", "all"),
+                (ChangeAction::Delete, "denied", ""),
+            ]
+        );
+
+        assert_eq!(
+            changes[0].attributes,
+            vec![AttributeChange {
+                name: "department".to_string(),
+                from: "Engineering".to_string(),
+                to: "Operations".to_string(),
+            }]
+        );
+
+        // Empty attributes are left out of new accounts.
+        assert_eq!(changes[3].attributes.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_groups() {
+        let mut state = ServiceState::new(ExternalServices::GitHub);
+
+        state
+            .current_groups
+            .insert("eng".to_string(), "Engineering".to_string());
+        state
+            .current_groups
+            .insert("unmanaged".to_string(), "Not in the configs".to_string());
+
+        state
+            .desired_groups
+            .insert("eng".to_string(), "The engineering team".to_string());
+        state.desired_groups.insert("ops".to_string(), "Operations".to_string());
+
+        let changes = state.diff();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].action, ChangeAction::Update);
+        assert_eq!(changes[0].group, "eng");
+        assert_eq!(changes[1].action, ChangeAction::Create);
+        assert_eq!(changes[1].group, "ops");
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            ConfigsPlan::default().render(),
+            "No changes. The external services match the configs."
+        );
+
+        let change = |service: ExternalServices, action: ChangeAction, user: &str, group: &str| PlannedChange {
+            service,
+            action,
+            user: user.to_string(),
+            group: group.to_string(),
+            attributes: vec![],
+        };
+
+        let mut update = change(ExternalServices::Okta, ChangeAction::Update, "jdoe", "");
+        update.attributes.push(AttributeChange {
+            name: "department".to_string(),
+            from: "Engineering".to_string(),
+            to: "Operations".to_string(),
+        });
+
+        let mut create = change(ExternalServices::Okta, ChangeAction::Create, "asmith", "");
+        create.attributes.push(AttributeChange {
+            name: "first_name".to_string(),
+            from: String::new(),
+            to: "Ada".to_string(),
+        });
+
+        let plan = ConfigsPlan {
+            changes: vec![
+                change(ExternalServices::GitHub, ChangeAction::RemoveMember, "jdoe", "eng"),
+                create,
+                update,
+                change(ExternalServices::Okta, ChangeAction::AddMember, "asmith", "all"),
+                change(ExternalServices::Okta, ChangeAction::Delete, "bob", ""),
+                change(ExternalServices::GitHub, ChangeAction::Create, "", "ops"),
+            ],
+        };
+
+        assert_eq!(
+            plan.render(),
+            r#"# Okta
++ user "asmith"
+    first_name: "Ada"
+~ user "jdoe"
+    department: "Engineering" -> "Operations"
++ membership "asmith" in group "all"
+- user "bob"
+
+# GitHub
+- membership "jdoe" in group "eng"
++ group "ops"
+
+Plan: 3 to add, 1 to change, 2 to destroy."#
+        );
+    }
+
+    #[test]
+    fn test_retain_reviewed() {
+        let change = |service: ExternalServices, action: ChangeAction, user: &str, group: &str| PlannedChange {
+            service,
+            action,
+            user: user.to_string(),
+            group: group.to_string(),
+            attributes: vec![],
+        };
+
+        let reviewed = ConfigsPlan {
+            changes: vec![
+                change(ExternalServices::Okta, ChangeAction::Create, "asmith", ""),
+                change(ExternalServices::Okta, ChangeAction::AddMember, "asmith", "all"),
+                change(ExternalServices::Zoom, ChangeAction::Create, "jdoe", ""),
+                change(ExternalServices::GitHub, ChangeAction::Create, "", "ops"),
+            ],
+        };
+
+        let mut plan = ConfigsPlan {
+            changes: vec![
+                change(ExternalServices::Okta, ChangeAction::Create, "asmith", ""),
+                change(ExternalServices::Okta, ChangeAction::AddMember, "asmith", "all"),
+                change(ExternalServices::Okta, ChangeAction::AddMember, "asmith", "eng"),
+            ],
+        };
+
+        let drift = plan.retain_reviewed(&reviewed, PlanScope::Users);
+
+        // Only the reviewed changes are left to be applied
+        assert_eq!(
+            plan.changes,
+            vec![
+                change(ExternalServices::Okta, ChangeAction::Create, "asmith", ""),
+                change(ExternalServices::Okta, ChangeAction::AddMember, "asmith", "all"),
+            ]
+        );
+
+        // Group changes are planned separately, so are not outdated
+        assert_eq!(
+            drift,
+            PlanDrift {
+                unreviewed: vec![change(ExternalServices::Okta, ChangeAction::AddMember, "asmith", "eng")],
+                outdated: vec![change(ExternalServices::Zoom, ChangeAction::Create, "jdoe", "")],
+            }
+        );
+        assert_eq!(
+            drift.to_string(),
+            r#"The plan differs from the plan that was reviewed:
+
+# Not applied, as they were not reviewed
++ membership "asmith" in group "eng" (Okta)
+
+# Reviewed, but no longer needed
++ user "jdoe" (Zoom)"#
+        );
+
+        // Group changes are compared to the reviewed group changes
+        let mut plan = ConfigsPlan {
+            changes: vec![
+                change(ExternalServices::GitHub, ChangeAction::Create, "", "ops"),
+                change(ExternalServices::Google, ChangeAction::Update, "", "eng"),
+            ],
+        };
+        let drift = plan.retain_reviewed(&reviewed, PlanScope::Groups);
+        assert_eq!(
+            plan.changes,
+            vec![change(ExternalServices::GitHub, ChangeAction::Create, "", "ops")]
+        );
+        assert_eq!(
+            drift,
+            PlanDrift {
+                unreviewed: vec![change(ExternalServices::Google, ChangeAction::Update, "", "eng")],
+                outdated: vec![],
+            }
+        );
+
+        let users = ConfigsPlan {
+            changes: reviewed
+                .changes
+                .iter()
+                .filter(|change| PlanScope::Users.contains(change))
+                .cloned()
+                .collect(),
+        };
+
+        let mut plan = users.clone();
+        assert!(plan.retain_reviewed(&reviewed, PlanScope::Users).is_empty());
+        assert_eq!(plan, users);
+
+        // Without a reviewed plan nothing is applied
+        let mut plan = users.clone();
+        let drift = plan.retain_reviewed(&ConfigsPlan::default(), PlanScope::Users);
+        assert!(plan.is_empty());
+        assert_eq!(drift.unreviewed, users.changes);
+    }
+}
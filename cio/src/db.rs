@@ -27,6 +27,12 @@ impl Database {
     pub async fn new() -> Self {
         let database_url = env::var("CIO_DATABASE_URL").expect("CIO_DATABASE_URL must be set");
 
+        Self::from_url(&database_url)
+    }
+
+    /// Create a pool for the database at the given url. Connections are only established once
+    /// the pool is used.
+    pub fn from_url(database_url: &str) -> Self {
         let manager = ConnectionManager::<DbConnection>::new(database_url);
         let pool = bb8::Builder::new().build_unchecked(manager);
 
@@ -5,6 +5,7 @@ use crate::{
     cloud_dns::CloudDnsClient,
     cloudflare::CloudFlareClient,
     dns_providers::{DNSProviderOps, DnsRecord, DnsUpdateMode},
+    sandbox::{Sandbox, SandboxService},
 };
 
 pub struct DnsProviderProxy {
@@ -22,6 +23,16 @@ impl DnsProviderProxy {
 impl DNSProviderOps for DnsProviderProxy {
     /// Ensure the record exists and has the correct information.
     async fn ensure_record(&self, record: DnsRecord, mode: DnsUpdateMode) -> Result<()> {
+        if Sandbox::intercept(
+            SandboxService::Dns,
+            format!(
+                "ensure {} record {} is {} ({:?})",
+                record.type_, record.name, record.content, mode
+            ),
+        ) {
+            return Ok(());
+        }
+
         // Do not exit on CF failures
         if let Err(err) = self.cloudflare.ensure_record(record.clone(), mode.clone()).await {
             log::info!("Failed to ensure dns record for {} in CloudFlare. This may be expected if the domain is not configured yet. :: {}", record.name, err);
@@ -34,6 +45,13 @@ impl DNSProviderOps for DnsProviderProxy {
 
     /// Delete the record if it exists.
     async fn delete_record(&self, record: DnsRecord) -> Result<()> {
+        if Sandbox::intercept(
+            SandboxService::Dns,
+            format!("delete {} record {}", record.type_, record.name),
+        ) {
+            return Ok(());
+        }
+
         // Do not exit on CF failures
         if let Err(err) = self.cloudflare.delete_record(record.clone()).await {
             log::info!("Failed to delete dns record for {} from CloudFlare. This may be expected if the domain is not configured yet. :: {}", record.name, err);
@@ -0,0 +1,448 @@
+//! Detect drift between the configs repo and the accounts in each provider.
+//!
+//! A [`DriftReport`] lists the accounts in a provider that do not belong to any user in the
+//! configs, the group memberships that differ from the configs, and the users that still have an
+//! account in a service that their config denies them. Building a report only reads from the
+//! providers. Bringing them back in line with the configs is left to the configs sync.
+
+use std::fmt;
+
+use anyhow::Result;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Utc};
+use diesel::{ExpressionMethods, Insertable, QueryDsl};
+use log::info;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use slack_chat_api::{FormattedMessage, MessageBlock, MessageBlockText, MessageBlockType, MessageType};
+
+use crate::{
+    app_config::DriftConfig,
+    companies::Company,
+    configs::{get_configs_from_repo, ExternalServices},
+    configs_plan::{desired_users, load_states, ChangeAction, DesiredUser, ServiceState},
+    db::Database,
+    schema::drift_reports,
+};
+
+/// Slack does not accept section blocks with more text than this.
+const SLACK_SECTION_LIMIT: usize = 3000;
+
+/// An account in a provider that does not belong to any user in the configs
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct OrphanedAccount {
+    pub service: ExternalServices,
+    /// The email of the account, or the login for GitHub
+    pub account: String,
+    /// The user that the account belonged to, if the user has been removed from the configs.
+    /// Empty if the account never belonged to a known user
+    pub user: String,
+}
+
+/// A group membership in a provider that differs from the configs
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct MembershipDrift {
+    pub service: ExternalServices,
+    pub user: String,
+    pub group: String,
+    /// Whether the configs have the user as a member of the group
+    pub in_configs: bool,
+}
+
+/// An account that a user still has in a service that their config denies them
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct DeniedAccess {
+    pub service: ExternalServices,
+    pub user: String,
+}
+
+/// The differences between the configs and the providers that were found during a single check
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct DriftReport {
+    pub generated_at: DateTime<Utc>,
+    pub orphaned_accounts: Vec<OrphanedAccount>,
+    pub membership_drift: Vec<MembershipDrift>,
+    pub denied_access: Vec<DeniedAccess>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = drift_reports)]
+struct NewDriftReport {
+    cio_company_id: i32,
+    report: serde_json::Value,
+}
+
+impl DriftReport {
+    /// Compare the configs on the default branch of the configs repo against every provider that
+    /// the company uses
+    pub async fn build(db: &Database, github: &octorust::Client, company: &Company) -> Result<Self> {
+        let configs = get_configs_from_repo(github, company).await?;
+        let (users, removed) = desired_users(db, company, &configs.users).await?;
+        let states = load_states(db, github, company, &users, &removed, &[]).await?;
+
+        Ok(Self::from_states(Utc::now(), &states, &users, &removed))
+    }
+
+    fn from_states(
+        generated_at: DateTime<Utc>,
+        states: &[ServiceState],
+        users: &[DesiredUser],
+        removed: &[DesiredUser],
+    ) -> Self {
+        let mut report = Self {
+            generated_at,
+            orphaned_accounts: vec![],
+            membership_drift: vec![],
+            denied_access: vec![],
+        };
+
+        for state in states {
+            report
+                .orphaned_accounts
+                .extend(state.unmanaged.iter().map(|account| OrphanedAccount {
+                    service: state.service.clone(),
+                    account: account.to_string(),
+                    user: String::new(),
+                }));
+
+            // Users that have been removed from the configs are still known from the database,
+            // so their accounts are matched to them rather than listed as unmanaged.
+            for user in removed.iter().filter(|user| state.current.contains_key(&user.username)) {
+                let account = if state.service == ExternalServices::GitHub {
+                    &user.github
+                } else {
+                    &user.email
+                };
+
+                report.orphaned_accounts.push(OrphanedAccount {
+                    service: state.service.clone(),
+                    account: account.to_string(),
+                    user: user.username.to_string(),
+                });
+            }
+
+            for change in state.diff() {
+                let in_configs = match change.action {
+                    ChangeAction::AddMember => true,
+                    ChangeAction::RemoveMember => false,
+                    _ => continue,
+                };
+
+                report.membership_drift.push(MembershipDrift {
+                    service: change.service,
+                    user: change.user,
+                    group: change.group,
+                    in_configs,
+                });
+            }
+
+            report.denied_access.extend(
+                users
+                    .iter()
+                    .filter(|user| user.is_denied(&state.service) && state.current.contains_key(&user.username))
+                    .map(|user| DeniedAccess {
+                        service: state.service.clone(),
+                        user: user.username.to_string(),
+                    }),
+            );
+        }
+
+        report
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orphaned_accounts.is_empty() && self.membership_drift.is_empty() && self.denied_access.is_empty()
+    }
+
+    /// Store the report, so that the latest report can be served without reading every provider
+    pub async fn store(&self, db: &Database, company_id: i32) -> Result<()> {
+        diesel::insert_into(drift_reports::table)
+            .values(&NewDriftReport {
+                cio_company_id: company_id,
+                report: serde_json::to_value(self)?,
+            })
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the most recently stored report of a company
+    pub async fn latest(db: &Database, company_id: i32) -> Result<Option<Self>> {
+        let report = drift_reports::dsl::drift_reports
+            .filter(drift_reports::dsl::cio_company_id.eq(company_id))
+            .order_by(drift_reports::dsl::created_at.desc())
+            .select(drift_reports::dsl::report)
+            .limit(1)
+            .load_async::<serde_json::Value>(db.pool())
+            .await?
+            .pop();
+
+        Ok(report.map(serde_json::from_value).transpose()?)
+    }
+
+    /// The title and a line for each finding of each kind of drift
+    fn sections(&self) -> [(&'static str, Vec<String>); 3] {
+        [
+            (
+                "Orphaned accounts",
+                self.orphaned_accounts.iter().map(orphan_line).collect(),
+            ),
+            (
+                "Group memberships",
+                self.membership_drift.iter().map(membership_line).collect(),
+            ),
+            (
+                "Access to denied services",
+                self.denied_access.iter().map(denied_line).collect(),
+            ),
+        ]
+    }
+
+    /// A summary of the report for posting to Slack, with a section for each kind of drift that
+    /// was found
+    pub fn slack_message(&self, channel: &str) -> FormattedMessage {
+        let mut blocks = vec![section(format!(
+            "*Access drift as of {}*\n{} orphaned account(s), {} group membership(s) that differ, {} user(s) with \
+             access to denied services",
+            self.generated_at.format("%m-%d-%Y"),
+            self.orphaned_accounts.len(),
+            self.membership_drift.len(),
+            self.denied_access.len()
+        ))];
+
+        for (title, lines) in self.sections() {
+            if !lines.is_empty() {
+                blocks.push(section(bulleted(title, &lines, SLACK_SECTION_LIMIT)));
+            }
+        }
+
+        FormattedMessage {
+            channel: channel.to_string(),
+            attachments: Default::default(),
+            blocks,
+        }
+    }
+}
+
+fn orphan_line(orphan: &OrphanedAccount) -> String {
+    if orphan.user.is_empty() {
+        format!("{}: {}", orphan.service, orphan.account)
+    } else {
+        format!("{}: {} (removed user {})", orphan.service, orphan.account, orphan.user)
+    }
+}
+
+fn membership_line(drift: &MembershipDrift) -> String {
+    if drift.in_configs {
+        format!("{}: {} is missing from {}", drift.service, drift.user, drift.group)
+    } else {
+        format!(
+            "{}: {} is in {} but not in the configs",
+            drift.service, drift.user, drift.group
+        )
+    }
+}
+
+fn denied_line(denied: &DeniedAccess) -> String {
+    format!("{}: {}", denied.service, denied.user)
+}
+
+/// A titled list of lines, cut short so that it fits within `limit` characters.
+fn bulleted(title: &str, lines: &[String], limit: usize) -> String {
+    let mut text = format!("*{}*", title);
+
+    for (i, line) in lines.iter().enumerate() {
+        let remaining = lines.len() - i;
+        let more = format!("\n…and {} more", remaining);
+        let line = format!("\n• {}", line);
+
+        // Leave room to say how many lines were left out, unless this is the last line.
+        let reserved = if remaining > 1 { more.chars().count() } else { 0 };
+        if text.chars().count() + line.chars().count() + reserved > limit {
+            text.push_str(&more);
+            break;
+        }
+
+        text.push_str(&line);
+    }
+
+    text
+}
+
+fn section(text: String) -> MessageBlock {
+    MessageBlock {
+        block_type: MessageBlockType::Section,
+        text: Some(MessageBlockText {
+            text_type: MessageType::Markdown,
+            text,
+        }),
+        elements: Default::default(),
+        accessory: Default::default(),
+        block_id: Default::default(),
+        fields: Default::default(),
+    }
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Access drift as of {}", self.generated_at.format("%m-%d-%Y"))?;
+
+        if self.is_empty() {
+            return writeln!(f, "\nThe providers match the configs");
+        }
+
+        for (title, lines) in self.sections() {
+            if !lines.is_empty() {
+                writeln!(f, "\n{} ({})", title, lines.len())?;
+
+                for line in lines {
+                    writeln!(f, "\t{}", line)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Build a drift report for the company, store it and post a summary of it to Slack
+pub async fn report_drift(
+    db: &Database,
+    company: &Company,
+    config: &DriftConfig,
+    dry_run: bool,
+) -> Result<DriftReport> {
+    let github = company.authenticate_github()?;
+    let report = DriftReport::build(db, &github, company).await?;
+
+    info!(
+        "Found {} orphaned accounts, {} differing group memberships and {} users with access to denied services",
+        report.orphaned_accounts.len(),
+        report.membership_drift.len(),
+        report.denied_access.len()
+    );
+
+    if !dry_run {
+        report.store(db, company.id).await?;
+
+        if !config.slack_channel.is_empty() {
+            company
+                .post_to_slack_channel(db, &report.slack_message(&config.slack_channel))
+                .await?;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::configs_plan::Account;
+
+    fn user(username: &str, github: &str, denied_services: &[ExternalServices]) -> DesiredUser {
+        DesiredUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            github: github.to_string(),
+            denied_services: denied_services.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn account(groups: &[&str]) -> Account {
+        Account {
+            attributes: Default::default(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_drift_from_states() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 11, 9, 0, 0).unwrap();
+
+        let users = vec![
+            user("member", "member-gh", &[]),
+            user("denied", "denied-gh", &[ExternalServices::GitHub]),
+        ];
+        let removed = vec![user("former", "former-gh", &[])];
+
+        let mut github = ServiceState::new(ExternalServices::GitHub);
+        github.current.insert("member".to_string(), account(&["eng", "ops"]));
+        github.current.insert("denied".to_string(), account(&[]));
+        github.current.insert("former".to_string(), account(&["eng"]));
+        github.desired.insert("member".to_string(), account(&["eng", "all"]));
+        github.unmanaged.insert("stranger".to_string());
+
+        let mut zoom = ServiceState::new(ExternalServices::Zoom);
+        zoom.current.insert("member".to_string(), account(&[]));
+        zoom.current.insert("former".to_string(), account(&[]));
+
+        let report = DriftReport::from_states(now, &[github, zoom], &users, &removed);
+
+        assert_eq!(
+            vec![
+                OrphanedAccount {
+                    service: ExternalServices::GitHub,
+                    account: "stranger".to_string(),
+                    user: "".to_string(),
+                },
+                OrphanedAccount {
+                    service: ExternalServices::GitHub,
+                    account: "former-gh".to_string(),
+                    user: "former".to_string(),
+                },
+                OrphanedAccount {
+                    service: ExternalServices::Zoom,
+                    account: "former@example.com".to_string(),
+                    user: "former".to_string(),
+                },
+            ],
+            report.orphaned_accounts
+        );
+
+        assert_eq!(
+            vec![
+                MembershipDrift {
+                    service: ExternalServices::GitHub,
+                    user: "member".to_string(),
+                    group: "all".to_string(),
+                    in_configs: true,
+                },
+                MembershipDrift {
+                    service: ExternalServices::GitHub,
+                    user: "member".to_string(),
+                    group: "ops".to_string(),
+                    in_configs: false,
+                },
+            ],
+            report.membership_drift
+        );
+
+        // Denied users are only reported for the services that they have an account in.
+        assert_eq!(
+            vec![DeniedAccess {
+                service: ExternalServices::GitHub,
+                user: "denied".to_string(),
+            }],
+            report.denied_access
+        );
+    }
+
+    #[test]
+    fn test_bulleted_fits_limit() {
+        let lines = (1..=5).map(|i| format!("line {}", i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            "*Title*\n• line 1\n• line 2\n• line 3\n• line 4\n• line 5",
+            bulleted("Title", &lines, 100)
+        );
+
+        let text = bulleted("Title", &lines, 40);
+        assert_eq!("*Title*\n• line 1\n• line 2\n…and 3 more", text);
+        assert!(text.chars().count() <= 40);
+    }
+}
@@ -1,9 +1,9 @@
-use std::{fmt, ops::Deref};
+use std::{collections::BTreeMap, fmt, ops::Deref};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_bb8_diesel::AsyncRunQueryDsl;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use chrono_humanize::HumanTime;
 use macros::db;
 use schemars::JsonSchema;
@@ -14,8 +14,12 @@ use slack_chat_api::{
 };
 
 use crate::{
-    airtable::AIRTABLE_FUNCTIONS_TABLE, companies::Company, core::UpdateAirtableRecord, db::Database,
-    schema::functions, utils::truncate,
+    airtable::AIRTABLE_FUNCTIONS_TABLE,
+    companies::Company,
+    core::UpdateAirtableRecord,
+    db::Database,
+    schema::{function_dead_letters, function_steps, functions},
+    utils::truncate,
 };
 
 #[db {
@@ -139,7 +143,8 @@ impl From<NewFunction> for FormattedMessage {
                     text: format!("Re-run {}", item.name),
                 }),
                 action_id: "function".to_string(),
-                value: item.name.to_string(),
+                // Re-runs are for the same company as the run that is being reported on.
+                value: format!("{}:{}", item.name, item.cio_company_id),
                 image_url: Default::default(),
                 alt_text: Default::default(),
             };
@@ -225,7 +230,451 @@ impl fmt::Display for FnOutput {
     }
 }
 
+/// Criteria for listing function runs. All criteria are optional and are combined together
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct FunctionFilter {
+    pub name: Option<String>,
+    /// Only include runs for this company
+    pub company_id: Option<i32>,
+    pub status: Option<String>,
+    pub conclusion: Option<String>,
+    /// Only include runs created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only include runs created before this time
+    pub created_before: Option<DateTime<Utc>>,
+    /// Maximum number of runs to return. Defaults to 100 and may not be more than 1000
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl FunctionFilter {
+    /// Matches the most recent run of a function for a company that is still in progress and that
+    /// was started within the given window
+    pub fn in_progress(name: &str, company_id: i32, window: Duration, now: DateTime<Utc>) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            company_id: Some(company_id),
+            status: Some(octorust::types::JobStatus::InProgress.to_string()),
+            created_after: Some(now - window),
+            limit: Some(1),
+            ..Default::default()
+        }
+    }
+
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(100).clamp(1, 1000)
+    }
+}
+
+/// A function run without its logs
+#[derive(Debug, Clone, PartialEq, JsonSchema, Serialize)]
+pub struct FunctionSummary {
+    pub saga_id: String,
+    pub name: String,
+    pub status: String,
+    pub conclusion: String,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// How long the run took, or has been running for if it has not completed
+    pub duration_seconds: i64,
+}
+
+impl FunctionSummary {
+    pub fn new(function: &Function, now: DateTime<Utc>) -> Self {
+        Self {
+            saga_id: function.saga_id.clone(),
+            name: function.name.clone(),
+            status: function.status.clone(),
+            conclusion: function.conclusion.clone(),
+            created_at: function.created_at,
+            completed_at: function.completed_at,
+            duration_seconds: (function.completed_at.unwrap_or(now) - function.created_at).num_seconds(),
+        }
+    }
+}
+
+/// A function run along with the logs it produced
+#[derive(Debug, Clone, PartialEq, JsonSchema, Serialize)]
+pub struct FunctionDetails {
+    #[serde(flatten)]
+    pub summary: FunctionSummary,
+    pub logs: String,
+    /// The steps of the run, for functions that are run as multiple steps
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<FunctionStep>,
+}
+
+impl From<Function> for FunctionDetails {
+    fn from(function: Function) -> Self {
+        Self {
+            summary: FunctionSummary::new(&function, Utc::now()),
+            logs: function.logs,
+            steps: vec![],
+        }
+    }
+}
+
+/// A single step of a function that is run as multiple steps
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct FunctionStep {
+    pub id: i32,
+    pub saga_id: String,
+    pub name: String,
+    pub status: String,
+    pub conclusion: String,
+    /// The value produced by the step for the steps that follow it
+    pub output: String,
+    pub error: String,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the step was reverted because a later step failed
+    pub undone_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = function_steps, treat_none_as_null = true)]
+struct NewFunctionStep {
+    saga_id: String,
+    name: String,
+    status: String,
+    conclusion: String,
+    output: String,
+    error: String,
+    started_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    undone_at: Option<DateTime<Utc>>,
+}
+
+impl NewFunctionStep {
+    /// Store the step, replacing any earlier record of the same step of the run
+    async fn upsert(self, db: &Database) -> Result<FunctionStep> {
+        Ok(diesel::insert_into(function_steps::table)
+            .values(&self)
+            .on_conflict((function_steps::dsl::saga_id, function_steps::dsl::name))
+            .do_update()
+            .set(&self)
+            .get_result_async::<FunctionStep>(db.pool())
+            .await?)
+    }
+}
+
+impl FunctionStep {
+    /// List the steps of a run in the order that they were started
+    pub async fn list(db: &Database, saga_id: &str) -> Result<Vec<Self>> {
+        Ok(function_steps::dsl::function_steps
+            .filter(function_steps::dsl::saga_id.eq(saga_id.to_string()))
+            .order_by(function_steps::dsl::id.asc())
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// Record that a step of a run has started
+    pub async fn start(db: &Database, saga_id: &uuid::Uuid, name: &str) -> Result<Self> {
+        NewFunctionStep {
+            saga_id: saga_id.to_string(),
+            name: name.to_string(),
+            status: octorust::types::JobStatus::InProgress.to_string(),
+            conclusion: octorust::types::Conclusion::Noop.to_string(),
+            output: String::new(),
+            error: String::new(),
+            started_at: Utc::now(),
+            completed_at: None,
+            undone_at: None,
+        }
+        .upsert(db)
+        .await
+    }
+
+    /// Record that a step of a run was not run again because it had already succeeded in the run
+    /// that is being resumed. The output of the earlier run is carried over for the steps that
+    /// follow it
+    pub async fn skip(db: &Database, saga_id: &uuid::Uuid, name: &str, output: &str) -> Result<Self> {
+        let now = Utc::now();
+
+        NewFunctionStep {
+            saga_id: saga_id.to_string(),
+            name: name.to_string(),
+            status: octorust::types::JobStatus::Completed.to_string(),
+            conclusion: octorust::types::Conclusion::Skipped.to_string(),
+            output: output.to_string(),
+            error: String::new(),
+            started_at: now,
+            completed_at: Some(now),
+            undone_at: None,
+        }
+        .upsert(db)
+        .await
+    }
+
+    /// Record the outcome of a step of a run
+    pub async fn complete(
+        db: &Database,
+        saga_id: &uuid::Uuid,
+        name: &str,
+        conclusion: &octorust::types::Conclusion,
+        output: &str,
+        error: &str,
+    ) -> Result<()> {
+        diesel::update(function_steps::dsl::function_steps)
+            .filter(function_steps::dsl::saga_id.eq(saga_id.to_string()))
+            .filter(function_steps::dsl::name.eq(name.to_string()))
+            .set((
+                function_steps::dsl::status.eq(octorust::types::JobStatus::Completed.to_string()),
+                function_steps::dsl::conclusion.eq(conclusion.to_string()),
+                function_steps::dsl::output.eq(output.to_string()),
+                function_steps::dsl::error.eq(error.to_string()),
+                function_steps::dsl::completed_at.eq(Some(Utc::now())),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record that a step of a run was reverted
+    pub async fn undo(db: &Database, saga_id: &uuid::Uuid, name: &str) -> Result<()> {
+        diesel::update(function_steps::dsl::function_steps)
+            .filter(function_steps::dsl::saga_id.eq(saga_id.to_string()))
+            .filter(function_steps::dsl::name.eq(name.to_string()))
+            .set(function_steps::dsl::undone_at.eq(Some(Utc::now())))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Whether the step succeeded, either in this run or in the run that this one resumed, and has
+    /// not since been reverted
+    pub fn is_resumable(&self) -> bool {
+        (self.conclusion == octorust::types::Conclusion::Success.to_string()
+            || self.conclusion == octorust::types::Conclusion::Skipped.to_string())
+            && self.undone_at.is_none()
+    }
+
+    /// Get the outputs of the steps of a run that do not need to be run again when the run is
+    /// resumed, keyed by step name
+    pub async fn resumable(db: &Database, saga_id: &str) -> Result<BTreeMap<String, String>> {
+        Ok(Self::list(db, saga_id)
+            .await?
+            .into_iter()
+            .filter(|step| step.is_resumable())
+            .map(|step| (step.name, step.output))
+            .collect())
+    }
+
+    /// Get the output that a step produced in the most recent successful run of a function for a
+    /// company
+    pub async fn last_successful_output(
+        db: &Database,
+        function: &str,
+        company_id: i32,
+        name: &str,
+    ) -> Result<Option<String>> {
+        Ok(function_steps::dsl::function_steps
+            .inner_join(functions::table.on(functions::dsl::saga_id.eq(function_steps::dsl::saga_id)))
+            .filter(functions::dsl::name.eq(function.to_string()))
+            .filter(functions::dsl::cio_company_id.eq(company_id))
+            .filter(functions::dsl::conclusion.eq(octorust::types::Conclusion::Success.to_string()))
+            .filter(function_steps::dsl::name.eq(name.to_string()))
+            .filter(function_steps::dsl::undone_at.is_null())
+            .order_by(functions::dsl::created_at.desc())
+            .select(function_steps::dsl::output)
+            .limit(1)
+            .load_async::<String>(db.pool())
+            .await?
+            .pop())
+    }
+}
+
+/// The output of a single step of a function that is run as multiple steps
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FnStepOutput {
+    pub step: String,
+    pub output: String,
+}
+
+/// A function run that failed and was not going to be attempted again, either because it failed
+/// with a permanent error or because it ran out of retries
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct FunctionDeadLetter {
+    pub id: i32,
+    pub saga_id: String,
+    pub name: String,
+    pub cio_company_id: i32,
+    pub attempts: i32,
+    /// Whether the last error was `transient` or `permanent`
+    pub error_class: String,
+    pub last_error: String,
+    pub created_at: DateTime<Utc>,
+    /// The run that was started to retry this one
+    pub retried_saga_id: Option<String>,
+    pub retried_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = function_dead_letters)]
+struct NewFunctionDeadLetter {
+    saga_id: String,
+    name: String,
+    cio_company_id: i32,
+    attempts: i32,
+    error_class: String,
+    last_error: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct DeadLetterFilter {
+    pub company_id: Option<i32>,
+    /// Include the runs that have already been retried
+    #[serde(default)]
+    pub include_retried: bool,
+}
+
+impl FunctionDeadLetter {
+    /// Record that a run failed for good
+    pub async fn record(
+        db: &Database,
+        function: &Function,
+        attempts: u32,
+        error_class: &str,
+        last_error: &str,
+    ) -> Result<Self> {
+        let letter = NewFunctionDeadLetter {
+            saga_id: function.saga_id.to_string(),
+            name: function.name.to_string(),
+            cio_company_id: function.cio_company_id,
+            attempts: attempts as i32,
+            error_class: error_class.to_string(),
+            last_error: last_error.to_string(),
+        };
+
+        Ok(diesel::insert_into(function_dead_letters::table)
+            .values(&letter)
+            .on_conflict(function_dead_letters::dsl::saga_id)
+            .do_update()
+            .set(&letter)
+            .get_result_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// List the runs that failed for good, most recent first
+    pub async fn list(db: &Database, filter: &DeadLetterFilter) -> Result<Vec<Self>> {
+        let mut query = function_dead_letters::dsl::function_dead_letters.into_boxed();
+
+        if let Some(company_id) = filter.company_id {
+            query = query.filter(function_dead_letters::dsl::cio_company_id.eq(company_id));
+        }
+
+        if !filter.include_retried {
+            query = query.filter(function_dead_letters::dsl::retried_at.is_null());
+        }
+
+        Ok(query
+            .order_by(function_dead_letters::dsl::created_at.desc())
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// Record that a run was retried by another run
+    pub async fn mark_retried(db: &Database, saga_id: &str, retried_saga_id: &uuid::Uuid) -> Result<()> {
+        diesel::update(function_dead_letters::dsl::function_dead_letters)
+            .filter(function_dead_letters::dsl::saga_id.eq(saga_id.to_string()))
+            .set((
+                function_dead_letters::dsl::retried_saga_id.eq(Some(retried_saga_id.to_string())),
+                function_dead_letters::dsl::retried_at.eq(Some(Utc::now())),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+}
+
 impl Function {
+    /// List function runs matching the filter, most recently created first
+    pub async fn list(db: &Database, filter: &FunctionFilter) -> Result<Vec<Self>> {
+        let mut query = functions::dsl::functions.into_boxed();
+
+        if let Some(name) = &filter.name {
+            query = query.filter(functions::dsl::name.eq(name.to_string()));
+        }
+
+        if let Some(company_id) = filter.company_id {
+            query = query.filter(functions::dsl::cio_company_id.eq(company_id));
+        }
+
+        if let Some(status) = &filter.status {
+            query = query.filter(functions::dsl::status.eq(status.to_string()));
+        }
+
+        if let Some(conclusion) = &filter.conclusion {
+            query = query.filter(functions::dsl::conclusion.eq(conclusion.to_string()));
+        }
+
+        if let Some(created_after) = filter.created_after {
+            query = query.filter(functions::dsl::created_at.ge(created_after));
+        }
+
+        if let Some(created_before) = filter.created_before {
+            query = query.filter(functions::dsl::created_at.lt(created_before));
+        }
+
+        Ok(query
+            .order_by(functions::dsl::created_at.desc())
+            .offset(filter.offset.unwrap_or(0).max(0))
+            .limit(filter.limit())
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// Find the most recent run of a function for a company that is still in progress and was
+    /// started within the window. Runs older than the window are assumed to have been orphaned by a
+    /// restart
+    pub async fn find_in_progress(
+        db: &Database,
+        name: &str,
+        company_id: i32,
+        window: Duration,
+    ) -> Result<Option<Self>> {
+        Ok(
+            Self::list(db, &FunctionFilter::in_progress(name, company_id, window, Utc::now()))
+                .await?
+                .into_iter()
+                .next(),
+        )
+    }
+
+    /// Record the company that a run is for. Runs are created before their saga starts, at which
+    /// point they are attributed to the company of the server
+    pub async fn set_company(db: &Database, saga_id: &uuid::Uuid, company_id: i32) -> Result<()> {
+        diesel::update(functions::dsl::functions)
+            .filter(functions::dsl::saga_id.eq(saga_id.to_string()))
+            .set(functions::dsl::cio_company_id.eq(company_id))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark an in-progress run as cancelled. This only records the cancellation, stopping the work
+    /// itself is left to the runner of the saga
+    pub async fn cancel(&mut self, db: &Database) -> Result<()> {
+        if self.status != octorust::types::JobStatus::InProgress.to_string() {
+            bail!("Function {} is not in progress and can not be cancelled", self.saga_id);
+        }
+
+        self.status = octorust::types::JobStatus::Completed.to_string();
+        self.conclusion = octorust::types::Conclusion::Cancelled.to_string();
+        self.completed_at = Some(Utc::now());
+
+        *self = self.update(db).await?;
+
+        let company = self.company(db).await?;
+        self.send_slack_notification(db, &company).await?;
+
+        Ok(())
+    }
+
     pub async fn send_slack_notification(&self, db: &Database, company: &Company) -> Result<()> {
         let n: NewFunction = self.into();
         n.send_slack_notification(db, company).await
@@ -246,6 +695,18 @@ impl Function {
         Ok(())
     }
 
+    /// Replace the logs of a running saga. Only the logs are written so that this can not overwrite
+    /// status changes that are made while the saga is running
+    pub async fn update_logs(db: &Database, saga_id: &uuid::Uuid, logs: &str) -> Result<()> {
+        diesel::update(functions::dsl::functions)
+            .filter(functions::dsl::saga_id.eq(saga_id.to_string()))
+            .set(functions::dsl::logs.eq(logs.to_string()))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
     /// Add logs with a conclusion saga.
     pub async fn add_logs_with_conclusion(
         db: &Database,
@@ -352,7 +813,15 @@ impl Function {
             steno::SagaNodeEventType::Succeeded(s) => {
                 // We only care if the conclusion is not null. That means we actually have logs.
                 let value: serde_json::Value = s.deref().clone();
-                if value != serde_json::Value::Null {
+                if value == serde_json::Value::Null {
+                    log::warn!(
+                        "Saga reach success state with a null value. It will be left incomplete. saga_id: {}",
+                        event.saga_id
+                    );
+                } else if serde_json::from_value::<FnStepOutput>(value.clone()).is_ok() {
+                    // The steps of a multi-step function are recorded as they run, and the
+                    // function is concluded once all of them have finished.
+                } else {
                     let string: String = serde_json::from_value(value).unwrap_or_default();
 
                     // Save the success output to the logs.
@@ -361,18 +830,17 @@ impl Function {
                     // Get the logs.
                     nf.logs = string.trim().to_string();
                     nf.completed_at = Some(Utc::now());
-                } else {
-                    log::warn!(
-                        "Saga reach success state with a null value. It will be left incomplete. saga_id: {}",
-                        event.saga_id
-                    );
                 }
             }
             steno::SagaNodeEventType::Failed(err) => {
                 // Save the error to the logs.
                 nf.logs = format!("{}\n\n{:?}", nf.logs, err).trim().to_string();
-                nf.conclusion = octorust::types::Conclusion::Failure.to_string();
-                nf.completed_at = Some(Utc::now());
+
+                // A cancelled run fails once it is stopped, but it should still be reported as cancelled
+                if nf.conclusion != octorust::types::Conclusion::Cancelled.to_string() {
+                    nf.conclusion = octorust::types::Conclusion::Failure.to_string();
+                    nf.completed_at = Some(Utc::now());
+                }
             }
             steno::SagaNodeEventType::UndoStarted => (),
             steno::SagaNodeEventType::UndoFinished => (),
@@ -433,3 +901,97 @@ pub async fn refresh_functions(db: &Database, company: &Company) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_in_progress_filter() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 12, 12, 0, 0).unwrap();
+        let filter = FunctionFilter::in_progress("sync-repos", 2, Duration::hours(1), now);
+
+        assert_eq!(Some("sync-repos".to_string()), filter.name);
+        assert_eq!(Some(2), filter.company_id);
+        assert_eq!(Some("in_progress".to_string()), filter.status);
+        assert_eq!(None, filter.conclusion);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2024, 2, 12, 11, 0, 0).unwrap()),
+            filter.created_after
+        );
+        assert_eq!(None, filter.created_before);
+        assert_eq!(1, filter.limit());
+    }
+
+    #[test]
+    fn test_filter_limit() {
+        assert_eq!(100, FunctionFilter::default().limit());
+
+        let filter = FunctionFilter {
+            limit: Some(5000),
+            ..Default::default()
+        };
+        assert_eq!(1000, filter.limit());
+
+        let filter = FunctionFilter {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(1, filter.limit());
+    }
+
+    #[test]
+    fn test_summary_duration() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 12, 12, 0, 0).unwrap();
+        let mut function = Function {
+            id: 1,
+            name: "sync-repos".to_string(),
+            status: "in_progress".to_string(),
+            conclusion: String::new(),
+            created_at: now - Duration::minutes(5),
+            completed_at: None,
+            logs: "Some logs".to_string(),
+            saga_id: "4bb0d9cf-4c4d-4f36-a7c1-9d3a3a6e6bd4".to_string(),
+            cio_company_id: 1,
+            airtable_record_id: String::new(),
+        };
+
+        assert_eq!(300, FunctionSummary::new(&function, now).duration_seconds);
+
+        function.completed_at = Some(now - Duration::minutes(4));
+        assert_eq!(60, FunctionSummary::new(&function, now).duration_seconds);
+    }
+
+    #[test]
+    fn test_resumable_steps() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 12, 12, 0, 0).unwrap();
+        let mut step = FunctionStep {
+            id: 1,
+            saga_id: "4bb0d9cf-4c4d-4f36-a7c1-9d3a3a6e6bd4".to_string(),
+            name: "groups".to_string(),
+            status: "completed".to_string(),
+            conclusion: "success".to_string(),
+            output: String::new(),
+            error: String::new(),
+            started_at: now - Duration::minutes(5),
+            completed_at: Some(now),
+            undone_at: None,
+        };
+
+        assert!(step.is_resumable());
+
+        // A step that was carried over from an earlier run does not need to be run again either
+        step.conclusion = "skipped".to_string();
+        assert!(step.is_resumable());
+
+        // But steps that were reverted do
+        step.undone_at = Some(now);
+        assert!(!step.is_resumable());
+
+        step.undone_at = None;
+        step.conclusion = "failure".to_string();
+        assert!(!step.is_resumable());
+    }
+}
@@ -6,7 +6,6 @@ use chrono::{Duration, NaiveDate, Utc};
 use google_calendar::types::Event;
 use handlebars::Handlebars;
 use log::{debug, info, warn};
-use sendgrid_api::{traits::MailOps, Client as SendGrid};
 
 use crate::{
     airtable::{AIRTABLE_DISCUSSION_TOPICS_TABLE, AIRTABLE_MEETING_SCHEDULE_TABLE},
@@ -14,7 +13,8 @@ use crate::{
     configs::{get_configs_from_repo, User},
     core::{DiscussionTopic, Meeting, MeetingReminderEmailData},
     db::Database,
-    utils::{create_or_update_file_in_github_repo, SliceExt},
+    sandbox::{Sandbox, SandboxService},
+    utils::{create_or_update_file_in_github_repo, send_plain_text_email, SliceExt},
 };
 
 /// Make sure if an event is moved in Google Calendar that Airtable is updated.
@@ -74,9 +74,14 @@ pub async fn sync_changes_to_google_events(db: &Database, company: &Company) ->
                 record.fields.week = "".to_string();
 
                 // Update the Airtable
-                airtable
-                    .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![record.clone()])
-                    .await?;
+                if !Sandbox::intercept(
+                    SandboxService::Airtable,
+                    format!("id={} table={} update", record.id, AIRTABLE_MEETING_SCHEDULE_TABLE),
+                ) {
+                    airtable
+                        .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![record.clone()])
+                        .await?;
+                }
 
                 // Get the discussion topics for the meeting.
                 let mut discussion_topics = String::new();
@@ -305,9 +310,14 @@ pub async fn send_huddle_reminders(db: &Database, company: &Company) -> Result<(
                         r.fields.name = "".to_string();
                         r.fields.week = "".to_string();
                         r.fields.cancelled = true;
-                        airtable
-                            .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![r.clone()])
-                            .await?;
+                        if !Sandbox::intercept(
+                            SandboxService::Airtable,
+                            format!("id={} table={} update", r.id, AIRTABLE_MEETING_SCHEDULE_TABLE),
+                        ) {
+                            airtable
+                                .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![r.clone()])
+                                .await?;
+                        }
 
                         // Continue through our loop.
                         continue;
@@ -351,20 +361,16 @@ pub async fn send_huddle_reminders(db: &Database, company: &Company) -> Result<(
                 let template = &handlebars.render_template(EMAIL_TEMPLATE, &email_data)?;
 
                 // Send the email.
-                // Initialize the SendGrid client.
-                let sendgrid = SendGrid::new_from_env();
                 // Send the email.
-                sendgrid
-                    .mail_send()
-                    .send_plain_text(
-                        &format!("Reminder {} huddle tomorrow", slug),
-                        template,
-                        &[format!("{}@{}", huddle.email, company.gsuite_domain)],
-                        &[],
-                        &[],
-                        &format!("huddle-reminders@{}", company.gsuite_domain),
-                    )
-                    .await?;
+                send_plain_text_email(
+                    &format!("Reminder {} huddle tomorrow", slug),
+                    template,
+                    &[format!("{}@{}", huddle.email, company.gsuite_domain)],
+                    &[],
+                    &[],
+                    &format!("huddle-reminders@{}", company.gsuite_domain),
+                )
+                .await?;
 
                 info!(
                     "successfully sent {} huddle reminder email to {}@{}",
@@ -378,9 +384,14 @@ pub async fn send_huddle_reminders(db: &Database, company: &Company) -> Result<(
                 // Clear out the fields that are functions since the API cannot take values for those.
                 r.fields.name = "".to_string();
                 r.fields.week = "".to_string();
-                airtable
-                    .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![r.clone()])
-                    .await?;
+                if !Sandbox::intercept(
+                    SandboxService::Airtable,
+                    format!("id={} table={} update", r.id, AIRTABLE_MEETING_SCHEDULE_TABLE),
+                ) {
+                    airtable
+                        .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![r.clone()])
+                        .await?;
+                }
 
                 info!(
                     "updated {} huddle meeting record to show the reminder email was sent",
@@ -642,12 +653,16 @@ pub async fn sync_huddles(db: &Database, company: &Company) -> Result<()> {
                     record.fields.attendees = attendees;
 
                     // Send the updated record to Airtable.
-                    match airtable
-                        .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![record.clone()])
-                        .await
-                    {
-                        Ok(_) => (),
-                        Err(err) => warn!("error updating record `{}`: {}", json!(record.fields).to_string(), err),
+                    if !Sandbox::intercept(
+                        SandboxService::Airtable,
+                        format!("id={} table={} update", record.id, AIRTABLE_MEETING_SCHEDULE_TABLE),
+                    ) {
+                        if let Err(err) = airtable
+                            .update_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![record.clone()])
+                            .await
+                        {
+                            warn!("error updating record `{}`: {}", json!(record.fields).to_string(), err);
+                        }
                     }
 
                     // Delete it from our hashmap.
@@ -691,9 +706,14 @@ pub async fn sync_huddles(db: &Database, company: &Company) -> Result<()> {
                     fields: meeting,
                     created_time: None,
                 };
-                airtable
-                    .create_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![record])
-                    .await?;
+                if !Sandbox::intercept(
+                    SandboxService::Airtable,
+                    format!("table={} create", AIRTABLE_MEETING_SCHEDULE_TABLE),
+                ) {
+                    airtable
+                        .create_records(AIRTABLE_MEETING_SCHEDULE_TABLE, vec![record])
+                        .await?;
+                }
             }
         }
     }
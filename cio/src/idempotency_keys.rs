@@ -0,0 +1,51 @@
+use anyhow::Result;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Duration, Utc};
+use diesel::{ExpressionMethods, Insertable};
+
+use crate::{db::Database, schema::idempotency_keys};
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = idempotency_keys)]
+struct NewIdempotencyKey {
+    key: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// The keys of webhook deliveries that have been received, so that redeliveries of the same event
+/// can be skipped
+pub struct IdempotencyKey;
+
+impl IdempotencyKey {
+    /// Record a key for the length of the ttl. Returns false if the key was already recorded and
+    /// has not yet expired. Expired keys are removed as part of recording
+    pub async fn record(db: &Database, key: &str, ttl: Duration) -> Result<bool> {
+        let now = Utc::now();
+
+        diesel::delete(idempotency_keys::dsl::idempotency_keys)
+            .filter(idempotency_keys::dsl::expires_at.le(now))
+            .execute_async(db.pool())
+            .await?;
+
+        let inserted = diesel::insert_into(idempotency_keys::table)
+            .values(&NewIdempotencyKey {
+                key: key.to_string(),
+                expires_at: now + ttl,
+            })
+            .on_conflict_do_nothing()
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(inserted == 1)
+    }
+
+    /// Remove a key, so that the next delivery with the key is treated as new
+    pub async fn forget(db: &Database, key: &str) -> Result<()> {
+        diesel::delete(idempotency_keys::dsl::idempotency_keys)
+            .filter(idempotency_keys::dsl::key.eq(key))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+}
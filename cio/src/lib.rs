@@ -19,11 +19,13 @@ pub mod cloudflare;
 pub mod colors;
 pub mod companies;
 pub mod configs;
+pub mod configs_plan;
 pub mod core;
 pub mod customers;
 pub mod db;
 pub mod dns_providers;
 pub mod dns_proxy;
+pub mod drift;
 #[macro_use]
 pub mod enclose;
 pub mod features;
@@ -34,17 +36,22 @@ pub mod github_prs;
 pub mod gsuite;
 pub mod health;
 pub mod huddles;
+pub mod idempotency_keys;
 pub mod interviews;
 pub mod journal_clubs;
 pub mod mailerlite;
 pub mod mailing_list;
+pub mod metrics;
 pub mod octorust_utils;
+pub mod offboarding;
 pub mod printer;
 pub mod providers;
+pub mod provisioning;
 pub mod rack_line;
 pub mod recorded_meetings;
 pub mod repos;
 pub mod rfd;
+pub mod sandbox;
 pub mod schema;
 pub mod sf;
 pub mod shipment_status;
@@ -57,6 +64,7 @@ pub mod tailscale;
 pub mod templates;
 pub mod travel;
 pub mod utils;
+pub mod webhook_events;
 pub mod zoho;
 
 #[macro_use]
@@ -9,15 +9,18 @@ use cio_api::{
     db::Database,
     journal_clubs::{JournalClubMeeting, JournalClubMeetings},
     mailing_list::{MailingListSubscriber, MailingListSubscribers},
+    metrics::{EndpointPaths, RequestMetricsDrain},
     repos::{GithubRepo, GithubRepos},
-    rfd::{RFDs, RFD},
+    rfd::{RFDEntry, RFDs, RFD},
     schema::resources,
 };
 use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
 use dropshot::{
     endpoint, ApiDescription, ConfigDropshot, ConfigLogging, ConfigLoggingLevel, HttpError, HttpResponseOk,
-    HttpServerStarter, RequestContext,
+    HttpServerStarter, Path, RequestContext,
 };
+use schemars::JsonSchema;
+use serde::Deserialize;
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
@@ -60,6 +63,8 @@ async fn main() -> Result<(), String> {
     api.register(api_get_journal_club_meetings).unwrap();
     api.register(api_get_links).unwrap();
     api.register(api_get_mailing_list_subscribers).unwrap();
+    api.register(api_get_metrics).unwrap();
+    api.register(api_get_rfd).unwrap();
     api.register(api_get_rfds).unwrap();
     api.register(api_get_users).unwrap();
 
@@ -74,6 +79,10 @@ async fn main() -> Result<(), String> {
     let mut buffer = File::create(api_file).unwrap();
     api_definition.write(&mut buffer).unwrap();
 
+    // Record the metrics of each request as dropshot logs its completion
+    let endpoints = EndpointPaths::from_openapi(&api_definition.json().unwrap());
+    let log = slog::Logger::root(RequestMetricsDrain::new("cio-server", endpoints, log), slog::o!());
+
     /*
      * The functions that implement our API endpoints will share this context.
      */
@@ -281,6 +290,20 @@ async fn api_get_mailing_list_subscribers(
     ))
 }
 
+/**
+ * Fetch the metrics of the server in the Prometheus text format.
+ */
+#[endpoint {
+    method = GET,
+    path = "/metrics",
+}]
+async fn api_get_metrics(rqctx: RequestContext<Context>) -> Result<http::Response<hyper::Body>, HttpError> {
+    let api_context = rqctx.context();
+    let db = &api_context.db;
+
+    cio_api::metrics::response(db).map_err(|e| HttpError::for_internal_error(format!("{:?}", e)))
+}
+
 /**
  * Fetch all RFDs.
  */
@@ -295,6 +318,35 @@ async fn api_get_rfds(rqctx: RequestContext<Context>) -> Result<HttpResponseOk<V
     Ok(HttpResponseOk(RFDs::get_from_db(db, 1).await.unwrap().0))
 }
 
+#[derive(Deserialize, JsonSchema)]
+struct RFDPathParams {
+    num: i32,
+}
+
+/**
+ * Fetch an RFD along with the RFDs that it references and is referenced by.
+ */
+#[endpoint {
+    method = GET,
+    path = "/rfds/{num}",
+}]
+async fn api_get_rfd(
+    rqctx: RequestContext<Context>,
+    path_params: Path<RFDPathParams>,
+) -> Result<HttpResponseOk<RFDEntry>, HttpError> {
+    let api_context = rqctx.context();
+    let db = &api_context.db;
+
+    match RFDEntry::get_from_db(db, path_params.into_inner().num).await {
+        Ok(Some(rfd)) => Ok(HttpResponseOk(rfd)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => {
+            log::error!("Failed to lookup rfd. err: {:?}", err);
+            Err(HttpError::for_internal_error("".to_string()))
+        }
+    }
+}
+
 /**
  * Fetch a list of employees.
  */
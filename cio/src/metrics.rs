@@ -0,0 +1,394 @@
+//! Prometheus metrics for the webhooky and cio API servers. Both servers render the metrics from
+//! their `/metrics` endpoint.
+//!
+//! Dropshot does not support middleware, so request metrics are read from the "request completed"
+//! records that dropshot logs for every request, see [`RequestMetricsDrain`].
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use task_local_extensions::Extensions;
+
+use crate::{db::Database, health::SelfMemory};
+
+/// Latency buckets for requests, in seconds
+const REQUEST_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Duration buckets for jobs, in seconds. Most jobs take minutes rather than milliseconds
+const JOB_BUCKETS: &[f64] = &[1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0];
+
+/// The endpoint label of requests that did not match any endpoint of the server
+const UNMATCHED_ENDPOINT: &str = "unmatched";
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new_custom(Some("cio".to_string()), None).unwrap();
+    static ref HTTP_REQUESTS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new(
+                "http_requests_total",
+                "Requests handled, by endpoint and response status"
+            ),
+            &["server", "method", "endpoint", "status"],
+        )
+        .unwrap()
+    );
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle requests, by endpoint"
+            )
+            .buckets(REQUEST_BUCKETS.to_vec()),
+            &["server", "method", "endpoint"],
+        )
+        .unwrap()
+    );
+    static ref WEBHOOK_VERIFICATION_FAILURES: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new(
+                "webhook_verification_failures_total",
+                "Webhooks that were received without a valid signature, by provider"
+            ),
+            &["provider"],
+        )
+        .unwrap()
+    );
+    static ref JOB_RUNS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new("job_runs_total", "Job runs that have finished, by job and outcome"),
+            &["job", "outcome"],
+        )
+        .unwrap()
+    );
+    static ref JOB_DURATION: HistogramVec = register(
+        HistogramVec::new(
+            HistogramOpts::new("job_duration_seconds", "Time taken by job runs, including retries")
+                .buckets(JOB_BUCKETS.to_vec()),
+            &["job"],
+        )
+        .unwrap()
+    );
+    static ref JOB_ATTEMPT_FAILURES: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new(
+                "job_attempt_failures_total",
+                "Attempts at running a job that failed, by job and class of error"
+            ),
+            &["job", "class"],
+        )
+        .unwrap()
+    );
+    static ref DB_POOL_CONNECTIONS: IntGaugeVec = register(
+        IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Connections held by the database pool, by state"),
+            &["state"],
+        )
+        .unwrap()
+    );
+    static ref VENDOR_API_CALLS: IntCounterVec = register(
+        IntCounterVec::new(
+            Opts::new(
+                "vendor_api_calls_total",
+                "Requests made to vendor APIs, by vendor and response status"
+            ),
+            &["vendor", "method", "status"],
+        )
+        .unwrap()
+    );
+    static ref MEMORY_RSS: IntGauge =
+        register(IntGauge::new("memory_rss_bytes", "Resident memory of the server process").unwrap());
+}
+
+fn register<M>(metric: M) -> M
+where
+    M: prometheus::core::Collector + Clone + 'static,
+{
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .expect("Metrics are only registered once");
+    metric
+}
+
+/// Record a request that was handled by one of our servers
+pub fn observe_request(server: &str, method: &str, endpoint: &str, status: &str, latency: Option<Duration>) {
+    HTTP_REQUESTS
+        .with_label_values(&[server, method, endpoint, status])
+        .inc();
+
+    if let Some(latency) = latency {
+        HTTP_REQUEST_DURATION
+            .with_label_values(&[server, method, endpoint])
+            .observe(latency.as_secs_f64());
+    }
+}
+
+/// Record a webhook that failed signature verification. Only webhooks that are received through
+/// an audit extractor need to be recorded here, as the webhooks that are rejected outright show up
+/// as unauthorized requests.
+pub fn record_webhook_verification_failure(provider: &str) {
+    WEBHOOK_VERIFICATION_FAILURES.with_label_values(&[provider]).inc();
+}
+
+/// Record a job run that has finished
+pub fn observe_job(job: &str, succeeded: bool, duration: Duration) {
+    let outcome = if succeeded { "success" } else { "failure" };
+
+    JOB_RUNS.with_label_values(&[job, outcome]).inc();
+    JOB_DURATION.with_label_values(&[job]).observe(duration.as_secs_f64());
+}
+
+/// Record a failed attempt at running a job, whether or not it is going to be attempted again
+pub fn record_job_attempt_failure<C>(job: &str, class: C)
+where
+    C: fmt::Display,
+{
+    JOB_ATTEMPT_FAILURES.with_label_values(&[job, &class.to_string()]).inc();
+}
+
+/// Record a request made to a vendor API. Requests that failed without a response are recorded
+/// with a status of "error".
+pub fn record_vendor_call(vendor: &str, method: &str, status: &str) {
+    VENDOR_API_CALLS.with_label_values(&[vendor, method, status]).inc();
+}
+
+/// Render the metrics in the Prometheus text format. The gauges of the database pool and of the
+/// memory of the process are read at the time of rendering.
+pub fn render(db: &Database) -> Result<String> {
+    let state = db.pool().state();
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["idle"])
+        .set(state.idle_connections as i64);
+    DB_POOL_CONNECTIONS
+        .with_label_values(&["in_use"])
+        .set(state.connections.saturating_sub(state.idle_connections) as i64);
+
+    if let Ok(memory) = SelfMemory::new() {
+        if let Some(rss) = memory.status_vmrss.or(memory.statm_rss) {
+            MEMORY_RSS.set(rss as i64);
+        }
+    }
+
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// A response for a `/metrics` endpoint
+pub fn response(db: &Database) -> Result<http::Response<hyper::Body>> {
+    Ok(http::Response::builder()
+        .status(http::StatusCode::OK)
+        .header(http::header::CONTENT_TYPE, TextEncoder::new().format_type())
+        .body(render(db)?.into())?)
+}
+
+/// The path templates of the endpoints of a server, used to label requests by endpoint rather than
+/// by the path that was requested
+#[derive(Debug, Clone, Default)]
+pub struct EndpointPaths {
+    paths: Vec<Vec<String>>,
+}
+
+impl EndpointPaths {
+    /// Read the paths of the endpoints from the OpenAPI spec of a server
+    pub fn from_openapi(spec: &serde_json::Value) -> Self {
+        let paths = spec
+            .get("paths")
+            .and_then(|paths| paths.as_object())
+            .map(|paths| paths.keys().map(|path| split_path(path)).collect())
+            .unwrap_or_default();
+
+        EndpointPaths { paths }
+    }
+
+    /// The path template of the endpoint that handles a uri
+    pub fn endpoint(&self, uri: &str) -> String {
+        let path = uri.split(['?', '#']).next().unwrap_or_default();
+        let segments = split_path(path);
+
+        self.paths
+            .iter()
+            .find(|template| {
+                template.len() == segments.len()
+                    && template
+                        .iter()
+                        .zip(&segments)
+                        .all(|(template, segment)| template.starts_with('{') || template == segment)
+            })
+            .map(|template| format!("/{}", template.join("/")))
+            .unwrap_or_else(|| UNMATCHED_ENDPOINT.to_string())
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.to_string())
+        .collect()
+}
+
+/// A log drain that records request metrics from the records that dropshot logs as it completes
+/// requests, and passes every record on to the wrapped drain
+pub struct RequestMetricsDrain<D> {
+    server: String,
+    endpoints: EndpointPaths,
+    drain: D,
+}
+
+impl<D> RequestMetricsDrain<D> {
+    pub fn new(server: &str, endpoints: EndpointPaths, drain: D) -> Self {
+        RequestMetricsDrain {
+            server: server.to_string(),
+            endpoints,
+            drain,
+        }
+    }
+}
+
+impl<D> slog::Drain for RequestMetricsDrain<D>
+where
+    D: slog::Drain,
+{
+    type Ok = D::Ok;
+    type Err = D::Err;
+
+    fn log(&self, record: &slog::Record, values: &slog::OwnedKVList) -> Result<Self::Ok, Self::Err> {
+        if record.msg().as_str() == Some("request completed") {
+            let mut fields = RecordFields::default();
+
+            // The method and uri are set on the logger of the request, the status and latency on
+            // the record itself
+            let serialized = slog::KV::serialize(values, record, &mut fields)
+                .and_then(|_| slog::KV::serialize(&record.kv(), record, &mut fields));
+
+            if serialized.is_ok() {
+                if let (Some(method), Some(uri), Some(status)) =
+                    (fields.get("method"), fields.get("uri"), fields.get("response_code"))
+                {
+                    let latency = fields
+                        .get("latency_us")
+                        .and_then(|latency| latency.parse::<u64>().ok())
+                        .map(Duration::from_micros);
+
+                    observe_request(&self.server, method, &self.endpoints.endpoint(uri), status, latency);
+                }
+            }
+        }
+
+        self.drain.log(record, values)
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecordFields(HashMap<String, String>);
+
+impl RecordFields {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|value| value.as_str())
+    }
+}
+
+impl slog::Serializer for RecordFields {
+    fn emit_arguments(&mut self, key: slog::Key, value: &fmt::Arguments) -> slog::Result {
+        self.0.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+}
+
+/// Client middleware that counts the requests made to a vendor API
+pub struct VendorMetricsMiddleware {
+    pub vendor: &'static str,
+}
+
+#[async_trait]
+impl reqwest_middleware::Middleware for VendorMetricsMiddleware {
+    async fn handle(
+        &self,
+        req: reqwest::Request,
+        extensions: &mut Extensions,
+        next: reqwest_middleware::Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        let method = req.method().to_string();
+        let response = next.run(req, extensions).await;
+
+        let status = match &response {
+            Ok(response) => response.status().as_u16().to_string(),
+            Err(_) => "error".to_string(),
+        };
+        record_vendor_call(self.vendor, &method, &status);
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use slog::Drain;
+
+    use super::*;
+
+    #[test]
+    fn test_endpoint_paths() {
+        let spec = serde_json::json!({
+            "paths": {
+                "/functions": {},
+                "/functions/{saga_id}": {},
+                "/functions/{saga_id}/retry": {},
+                "/rfd/{num}/snooze": {},
+            }
+        });
+        let endpoints = EndpointPaths::from_openapi(&spec);
+
+        assert_eq!("/functions", endpoints.endpoint("/functions?status=completed"));
+        assert_eq!("/functions/{saga_id}", endpoints.endpoint("/functions/8c5e9a1f"));
+        assert_eq!(
+            "/functions/{saga_id}/retry",
+            endpoints.endpoint("/functions/8c5e9a1f/retry")
+        );
+        assert_eq!("/rfd/{num}/snooze", endpoints.endpoint("/rfd/123/snooze/"));
+        assert_eq!(UNMATCHED_ENDPOINT, endpoints.endpoint("/rfd/123"));
+        assert_eq!(UNMATCHED_ENDPOINT, endpoints.endpoint("/"));
+    }
+
+    #[test]
+    fn test_request_metrics_drain() {
+        let spec = serde_json::json!({ "paths": { "/rfd/{num}": {} } });
+        let drain = RequestMetricsDrain::new("test-server", EndpointPaths::from_openapi(&spec), slog::Discard);
+        let logger = slog::Logger::root(drain.fuse(), slog::o!());
+
+        let request_log = logger.new(slog::o!("method" => "GET", "uri" => "/rfd/42?format=json"));
+        slog::info!(request_log, "request completed"; "response_code" => "200", "latency_us" => 1500u64);
+        slog::info!(request_log, "some other record"; "response_code" => "200");
+
+        assert_eq!(
+            1,
+            HTTP_REQUESTS
+                .with_label_values(&["test-server", "GET", "/rfd/{num}", "200"])
+                .get()
+        );
+        assert_eq!(
+            1,
+            HTTP_REQUEST_DURATION
+                .with_label_values(&["test-server", "GET", "/rfd/{num}"])
+                .get_sample_count()
+        );
+    }
+
+    #[test]
+    fn test_job_metrics_are_rendered() {
+        observe_job("sync-test", false, Duration::from_secs(90));
+        record_job_attempt_failure("sync-test", "transient");
+
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer).unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+
+        assert!(rendered.contains(r#"cio_job_runs_total{job="sync-test",outcome="failure"} 1"#));
+        assert!(rendered.contains(r#"cio_job_attempt_failures_total{class="transient",job="sync-test"} 1"#));
+    }
+}
@@ -0,0 +1,1221 @@
+//! Offboard the users that have been removed from the configs.
+//!
+//! Removing a user from the configs starts a [`UserOffboarding`], which works through the same
+//! sequence of [`OffboardingStep`]s across the providers for every departing user. They are locked
+//! out of their accounts before anything is removed, so that they lose access as early as possible,
+//! and their documents and mail are then handed over to their manager. Their Google account is
+//! deleted last, once their mail has been forwarded for [`MAIL_FORWARDING_DAYS`].
+//!
+//! The `offboard-users` job is run as a saga with a node for each step, and each node runs its step
+//! with [`run_offboarding_step`] for every offboarding that is in progress. A step only runs for an
+//! offboarding once the steps before it are done, so a step that fails for one user holds back the
+//! rest of that user's offboarding until it succeeds on a later run, without holding back anyone
+//! else's. None of the steps are undone when a later one fails, as a departed user must never get
+//! their access back.
+//!
+//! Each step is recorded along with when it was completed, or why it was skipped, so that HR can
+//! confirm that a user has been fully offboarded.
+
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use async_bb8_diesel::{AsyncConnection, AsyncRunQueryDsl};
+use chrono::{DateTime, Duration, Utc};
+use diesel::{ExpressionMethods, Insertable, QueryDsl, Queryable};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    companies::Company,
+    configs::{ExternalServices, User},
+    db::Database,
+    schema::{user_offboarding_steps, user_offboardings},
+    utils::generate_password,
+};
+
+/// How long the mail of a departed user is forwarded to their manager before their Google account
+/// is deleted
+pub const MAIL_FORWARDING_DAYS: i64 = 30;
+
+const GMAIL_SETTINGS_URL: &str = "https://gmail.googleapis.com/gmail/v1/users/me/settings";
+const GMAIL_SETTINGS_SCOPE: &str = "https://www.googleapis.com/auth/gmail.settings.sharing";
+
+const DATA_TRANSFER_URL: &str = "https://admin.googleapis.com/admin/datatransfer/v1";
+const DATA_TRANSFER_SCOPE: &str = "https://www.googleapis.com/auth/admin.datatransfer";
+
+/// The name of the Drive application in the Data Transfer API.
+const DRIVE_APPLICATION: &str = "Drive and Docs";
+
+/// The individual steps of an offboarding, in the order that they are run. Locking the user out
+/// comes first so that they lose access before anything else happens. Their Google account is
+/// deleted last, as their Drive files are transferred and their mail forwarded from it until then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffboardingStep {
+    LockOut,
+    TransferDrive,
+    ForwardMail,
+    RemoveGitHub,
+    RemoveOkta,
+    DeactivateRampCards,
+    RemoveZoom,
+    RemoveSlack,
+    RemoveAirtable,
+    DeprovisionTailscale,
+    DeleteGoogle,
+}
+
+impl OffboardingStep {
+    pub const ALL: [OffboardingStep; 11] = [
+        OffboardingStep::LockOut,
+        OffboardingStep::TransferDrive,
+        OffboardingStep::ForwardMail,
+        OffboardingStep::RemoveGitHub,
+        OffboardingStep::RemoveOkta,
+        OffboardingStep::DeactivateRampCards,
+        OffboardingStep::RemoveZoom,
+        OffboardingStep::RemoveSlack,
+        OffboardingStep::RemoveAirtable,
+        OffboardingStep::DeprovisionTailscale,
+        OffboardingStep::DeleteGoogle,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OffboardingStep::LockOut => "lock-out",
+            OffboardingStep::TransferDrive => "transfer-drive",
+            OffboardingStep::ForwardMail => "forward-mail",
+            OffboardingStep::RemoveGitHub => "remove-github",
+            OffboardingStep::RemoveOkta => "remove-okta",
+            OffboardingStep::DeactivateRampCards => "deactivate-ramp-cards",
+            OffboardingStep::RemoveZoom => "remove-zoom",
+            OffboardingStep::RemoveSlack => "remove-slack",
+            OffboardingStep::RemoveAirtable => "remove-airtable",
+            OffboardingStep::DeprovisionTailscale => "deprovision-tailscale",
+            OffboardingStep::DeleteGoogle => "delete-google",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.name() == name)
+    }
+}
+
+/// Where an offboarding is as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffboardingStatus {
+    InProgress,
+    Completed,
+    /// The user was added back to the configs before the offboarding completed
+    Cancelled,
+}
+
+impl fmt::Display for OffboardingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OffboardingStatus::InProgress => write!(f, "in_progress"),
+            OffboardingStatus::Completed => write!(f, "completed"),
+            OffboardingStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// Where a single step of an offboarding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OffboardingStepStatus {
+    Pending,
+    Completed,
+    /// The company does not use the service, or the user does not have an account in it
+    Skipped,
+    /// The last attempt failed. The step is attempted again on the next run
+    Failed,
+}
+
+impl fmt::Display for OffboardingStepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OffboardingStepStatus::Pending => write!(f, "pending"),
+            OffboardingStepStatus::Completed => write!(f, "completed"),
+            OffboardingStepStatus::Skipped => write!(f, "skipped"),
+            OffboardingStepStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// The offboarding of a user that was removed from the configs
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct UserOffboarding {
+    pub id: i32,
+    pub cio_company_id: i32,
+    pub username: String,
+    pub email: String,
+    /// The email of the user's manager when the offboarding started. Mail and documents are
+    /// handed over to them
+    pub manager_email: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the user was added back to the configs, if they have been
+    pub returned_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_offboardings)]
+struct NewUserOffboarding {
+    cio_company_id: i32,
+    username: String,
+    email: String,
+    manager_email: String,
+    status: String,
+}
+
+/// The record of a single step of an offboarding
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct UserOffboardingStep {
+    pub id: i32,
+    pub offboarding_id: i32,
+    pub step: String,
+    pub status: String,
+    /// What the step did, or why it was skipped
+    pub detail: String,
+    pub attempts: i32,
+    pub last_error: String,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = user_offboarding_steps)]
+struct NewUserOffboardingStep {
+    offboarding_id: i32,
+    step: String,
+    status: String,
+}
+
+/// An offboarding along with the record of each of its steps
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct OffboardingRecord {
+    #[serde(flatten)]
+    pub offboarding: UserOffboarding,
+    pub steps: Vec<UserOffboardingStep>,
+}
+
+impl OffboardingRecord {
+    pub async fn get(db: &Database, company_id: i32, id: i32) -> Result<Option<Self>> {
+        match UserOffboarding::get(db, company_id, id).await? {
+            Some(offboarding) => Ok(Some(offboarding.into_record(db).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// List the offboardings of a company along with their steps, most recently started first
+    pub async fn list(db: &Database, company_id: i32) -> Result<Vec<Self>> {
+        let mut records = vec![];
+
+        for offboarding in UserOffboarding::list(db, company_id).await? {
+            records.push(offboarding.into_record(db).await?);
+        }
+
+        Ok(records)
+    }
+}
+
+impl fmt::Display for OffboardingRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.offboarding.username, self.offboarding.status)?;
+
+        for step in &self.steps {
+            write!(f, "\n    {}: {}", step.step, step.status)?;
+
+            if !step.last_error.is_empty() && step.status == OffboardingStepStatus::Failed.to_string() {
+                write!(f, " - {}", step.last_error)?;
+            } else if !step.detail.is_empty() {
+                write!(f, " - {}", step.detail)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The result of running a step that did not fail
+#[derive(Debug, Clone, PartialEq)]
+enum StepOutcome {
+    Completed(String),
+    Skipped(String),
+    /// The step is not due to run yet, and is left pending until a later run
+    Waiting(String),
+}
+
+/// The offboardings that stop a new one from being started for a user. A user that has returned
+/// and been removed again is offboarded again.
+fn is_active(offboarding: &UserOffboarding) -> bool {
+    offboarding.status == OffboardingStatus::InProgress.to_string()
+        || (offboarding.status == OffboardingStatus::Completed.to_string() && offboarding.returned_at.is_none())
+}
+
+/// Whether a step has either completed or been skipped
+fn is_done(record: &UserOffboardingStep) -> bool {
+    record.status == OffboardingStepStatus::Completed.to_string()
+        || record.status == OffboardingStepStatus::Skipped.to_string()
+}
+
+/// The steps that are left to run, in order. A step without a record has not run yet.
+fn remaining_steps(steps: &[UserOffboardingStep]) -> Vec<OffboardingStep> {
+    OffboardingStep::ALL
+        .into_iter()
+        .filter(|step| !steps.iter().any(|record| record.step == step.name() && is_done(record)))
+        .collect()
+}
+
+impl UserOffboarding {
+    /// Start offboarding a user. Returns None if the user is already being offboarded, or has
+    /// been offboarded and not returned since.
+    pub async fn start(db: &Database, company: &Company, user: &User) -> Result<Option<Self>> {
+        let existing = user_offboardings::dsl::user_offboardings
+            .filter(user_offboardings::dsl::cio_company_id.eq(company.id))
+            .filter(user_offboardings::dsl::username.eq(user.username.to_string()))
+            .load_async::<Self>(db.pool())
+            .await?;
+
+        if existing.iter().any(is_active) {
+            return Ok(None);
+        }
+
+        // The manager is looked up now, as the manager may themselves be offboarded before every
+        // step has run
+        let manager_email = if user.manager.is_empty() {
+            String::new()
+        } else {
+            User::get_from_db(db, company.id, user.manager.to_string())
+                .await
+                .map(|manager| manager.email)
+                .unwrap_or_default()
+        };
+
+        let new_offboarding = NewUserOffboarding {
+            cio_company_id: company.id,
+            username: user.username.to_string(),
+            email: user.email.to_string(),
+            manager_email,
+            status: OffboardingStatus::InProgress.to_string(),
+        };
+
+        // The steps are inserted along with the offboarding, so that an offboarding never exists
+        // without the record of its steps
+        let offboarding = db
+            .pool()
+            .transaction_async(|conn| async move {
+                let offboarding = diesel::insert_into(user_offboardings::table)
+                    .values(&new_offboarding)
+                    .get_result_async::<Self>(&conn)
+                    .await?;
+
+                let steps = OffboardingStep::ALL
+                    .iter()
+                    .map(|step| NewUserOffboardingStep {
+                        offboarding_id: offboarding.id,
+                        step: step.name().to_string(),
+                        status: OffboardingStepStatus::Pending.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+
+                diesel::insert_into(user_offboarding_steps::table)
+                    .values(&steps)
+                    .execute_async(&conn)
+                    .await?;
+
+                Ok::<_, anyhow::Error>(offboarding)
+            })
+            .await?;
+
+        info!("Started offboarding {} for user {}", offboarding.id, user.username);
+
+        Ok(Some(offboarding))
+    }
+
+    /// Record that a user is back in the configs. An offboarding that is still in progress is
+    /// cancelled, and a completed one is marked so that the user is offboarded again if they are
+    /// removed again. Accounts that were already removed are restored by the configs sync.
+    pub async fn returned(db: &Database, company_id: i32, username: &str) -> Result<()> {
+        let now = Utc::now();
+
+        let cancelled = diesel::update(user_offboardings::dsl::user_offboardings)
+            .filter(user_offboardings::dsl::cio_company_id.eq(company_id))
+            .filter(user_offboardings::dsl::username.eq(username.to_string()))
+            .filter(user_offboardings::dsl::status.eq(OffboardingStatus::InProgress.to_string()))
+            .set((
+                user_offboardings::dsl::status.eq(OffboardingStatus::Cancelled.to_string()),
+                user_offboardings::dsl::returned_at.eq(Some(now)),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        if cancelled > 0 {
+            info!(
+                "Cancelled offboarding of user {} as they are back in the configs",
+                username
+            );
+        }
+
+        diesel::update(user_offboardings::dsl::user_offboardings)
+            .filter(user_offboardings::dsl::cio_company_id.eq(company_id))
+            .filter(user_offboardings::dsl::username.eq(username.to_string()))
+            .filter(user_offboardings::dsl::status.eq(OffboardingStatus::Completed.to_string()))
+            .filter(user_offboardings::dsl::returned_at.is_null())
+            .set(user_offboardings::dsl::returned_at.eq(Some(now)))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get(db: &Database, company_id: i32, id: i32) -> Result<Option<Self>> {
+        Ok(user_offboardings::dsl::user_offboardings
+            .filter(user_offboardings::dsl::cio_company_id.eq(company_id))
+            .filter(user_offboardings::dsl::id.eq(id))
+            .load_async::<Self>(db.pool())
+            .await?
+            .pop())
+    }
+
+    /// List the offboardings of a company, most recently started first
+    pub async fn list(db: &Database, company_id: i32) -> Result<Vec<Self>> {
+        Ok(user_offboardings::dsl::user_offboardings
+            .filter(user_offboardings::dsl::cio_company_id.eq(company_id))
+            .order_by(user_offboardings::dsl::created_at.desc())
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// The offboardings of a company that have steps left to run, oldest first
+    pub async fn in_progress(db: &Database, company_id: i32) -> Result<Vec<Self>> {
+        Ok(user_offboardings::dsl::user_offboardings
+            .filter(user_offboardings::dsl::cio_company_id.eq(company_id))
+            .filter(user_offboardings::dsl::status.eq(OffboardingStatus::InProgress.to_string()))
+            .order_by(user_offboardings::dsl::created_at.asc())
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// The records of the steps of the offboarding, in the order that they run
+    pub async fn steps(&self, db: &Database) -> Result<Vec<UserOffboardingStep>> {
+        let mut steps = user_offboarding_steps::dsl::user_offboarding_steps
+            .filter(user_offboarding_steps::dsl::offboarding_id.eq(self.id))
+            .load_async::<UserOffboardingStep>(db.pool())
+            .await?;
+
+        steps.sort_by_key(|record| {
+            OffboardingStep::ALL
+                .iter()
+                .position(|step| step.name() == record.step)
+                .unwrap_or(OffboardingStep::ALL.len())
+        });
+
+        Ok(steps)
+    }
+
+    pub async fn into_record(self, db: &Database) -> Result<OffboardingRecord> {
+        let steps = self.steps(db).await?;
+
+        Ok(OffboardingRecord {
+            offboarding: self,
+            steps,
+        })
+    }
+
+    async fn complete(&self, db: &Database) -> Result<()> {
+        diesel::update(user_offboardings::dsl::user_offboardings)
+            .filter(user_offboardings::dsl::id.eq(self.id))
+            .set((
+                user_offboardings::dsl::status.eq(OffboardingStatus::Completed.to_string()),
+                user_offboardings::dsl::completed_at.eq(Some(Utc::now())),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record that a step completed, was skipped, or is waiting to run. The record of the step is
+    /// created if it does not exist, as is the case for steps that were added after the offboarding
+    /// started.
+    async fn record_outcome(&self, db: &Database, step: OffboardingStep, outcome: &StepOutcome) -> Result<()> {
+        let now = Utc::now();
+        let (status, detail, attempts, completed_at) = match outcome {
+            StepOutcome::Completed(detail) => (OffboardingStepStatus::Completed, detail, 1, Some(now)),
+            StepOutcome::Skipped(reason) => (OffboardingStepStatus::Skipped, reason, 1, Some(now)),
+            // Waiting is not an attempt at the step
+            StepOutcome::Waiting(reason) => (OffboardingStepStatus::Pending, reason, 0, None),
+        };
+
+        let written = diesel::insert_into(user_offboarding_steps::table)
+            .values((
+                user_offboarding_steps::dsl::offboarding_id.eq(self.id),
+                user_offboarding_steps::dsl::step.eq(step.name().to_string()),
+                user_offboarding_steps::dsl::status.eq(status.to_string()),
+                user_offboarding_steps::dsl::detail.eq(detail.to_string()),
+                user_offboarding_steps::dsl::attempts.eq(attempts),
+                user_offboarding_steps::dsl::updated_at.eq(now),
+                user_offboarding_steps::dsl::completed_at.eq(completed_at),
+            ))
+            .on_conflict((
+                user_offboarding_steps::dsl::offboarding_id,
+                user_offboarding_steps::dsl::step,
+            ))
+            .do_update()
+            .set((
+                user_offboarding_steps::dsl::status.eq(status.to_string()),
+                user_offboarding_steps::dsl::detail.eq(detail.to_string()),
+                user_offboarding_steps::dsl::attempts.eq(user_offboarding_steps::dsl::attempts + attempts),
+                user_offboarding_steps::dsl::updated_at.eq(now),
+                user_offboarding_steps::dsl::completed_at.eq(completed_at),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        // Otherwise the step would be run again straight away, and forever
+        if written == 0 {
+            bail!(
+                "Failed to record outcome of offboarding step {} for offboarding {}",
+                step.name(),
+                self.id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Record that a step failed, creating the record of the step if it does not exist.
+    async fn record_failure(&self, db: &Database, step: OffboardingStep, err: &anyhow::Error) -> Result<()> {
+        let now = Utc::now();
+        let last_error = format!("{:?}", err);
+
+        let written = diesel::insert_into(user_offboarding_steps::table)
+            .values((
+                user_offboarding_steps::dsl::offboarding_id.eq(self.id),
+                user_offboarding_steps::dsl::step.eq(step.name().to_string()),
+                user_offboarding_steps::dsl::status.eq(OffboardingStepStatus::Failed.to_string()),
+                user_offboarding_steps::dsl::attempts.eq(1),
+                user_offboarding_steps::dsl::last_error.eq(last_error.to_string()),
+                user_offboarding_steps::dsl::updated_at.eq(now),
+            ))
+            .on_conflict((
+                user_offboarding_steps::dsl::offboarding_id,
+                user_offboarding_steps::dsl::step,
+            ))
+            .do_update()
+            .set((
+                user_offboarding_steps::dsl::status.eq(OffboardingStepStatus::Failed.to_string()),
+                user_offboarding_steps::dsl::attempts.eq(user_offboarding_steps::dsl::attempts + 1),
+                user_offboarding_steps::dsl::last_error.eq(last_error),
+                user_offboarding_steps::dsl::updated_at.eq(now),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        if written == 0 {
+            bail!(
+                "Failed to record failure of offboarding step {} for offboarding {}",
+                step.name(),
+                self.id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Run a step of the offboarding and record its outcome. Returns whether the step did not fail.
+    async fn run_step(
+        &self,
+        db: &Database,
+        company: &Company,
+        step: OffboardingStep,
+        records: &[UserOffboardingStep],
+    ) -> Result<bool> {
+        let user = User::get_from_db(db, company.id, self.username.to_string())
+            .await
+            .ok_or_else(|| anyhow!("User {} does not exist in the database", self.username))?;
+
+        match run_step(db, company, self, &user, step, records).await {
+            Ok(outcome) => {
+                info!(
+                    "Ran offboarding step {} for user {}. outcome: {:?}",
+                    step.name(),
+                    self.username,
+                    outcome
+                );
+                self.record_outcome(db, step, &outcome).await?;
+                Ok(true)
+            }
+            Err(err) => {
+                warn!(
+                    "Offboarding step {} failed for user {}. err: {:?}",
+                    step.name(),
+                    self.username,
+                    err
+                );
+                self.record_failure(db, step, &err).await?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// The ids of the offboardings of a company that are in progress, which are the offboardings
+/// that a run of the `offboard-users` job works through
+pub async fn offboardings_to_run(db: &Database, company: &Company) -> Result<Vec<i32>> {
+    Ok(UserOffboarding::in_progress(db, company.id)
+        .await?
+        .iter()
+        .map(|offboarding| offboarding.id)
+        .collect())
+}
+
+/// Run a single step for each of the given offboardings that is due to run it, which is those
+/// that are still in progress and have every earlier step done. The step is run for all of them
+/// before it fails with the users that it failed for.
+pub async fn run_offboarding_step(db: &Database, company: &Company, ids: &[i32], step: OffboardingStep) -> Result<()> {
+    let mut failed = vec![];
+
+    for id in ids {
+        // The user may have returned since the run started
+        let offboarding = match UserOffboarding::get(db, company.id, *id).await? {
+            Some(offboarding) if offboarding.status == OffboardingStatus::InProgress.to_string() => offboarding,
+            _ => continue,
+        };
+
+        let records = offboarding.steps(db).await?;
+        let remaining = remaining_steps(&records);
+        match remaining.first() {
+            Some(next) if *next == step => (),
+            Some(next) if remaining.contains(&step) => {
+                info!(
+                    "Offboarding step {} for user {} is waiting on step {}",
+                    step.name(),
+                    offboarding.username,
+                    next.name()
+                );
+                continue;
+            }
+            _ => continue,
+        }
+
+        if !offboarding.run_step(db, company, step, &records).await? {
+            failed.push(offboarding.username);
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!(
+            "Offboarding step {} failed for users {}",
+            step.name(),
+            failed.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Mark each of the given offboardings that has every step done as completed
+pub async fn complete_offboardings(db: &Database, company: &Company, ids: &[i32]) -> Result<()> {
+    for id in ids {
+        let offboarding = match UserOffboarding::get(db, company.id, *id).await? {
+            Some(offboarding) if offboarding.status == OffboardingStatus::InProgress.to_string() => offboarding,
+            _ => continue,
+        };
+
+        if remaining_steps(&offboarding.steps(db).await?).is_empty() {
+            offboarding.complete(db).await?;
+            info!(
+                "Completed offboarding {} for user {}",
+                offboarding.id, offboarding.username
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the steps of every offboarding that is in progress one after another, as the saga of the
+/// `offboard-users` job does. When running as a dry run the steps that would be run are only
+/// reported.
+pub async fn run_offboardings(db: &Database, company: &Company, dry_run: bool) -> Result<Vec<OffboardingRecord>> {
+    let ids = offboardings_to_run(db, company).await?;
+
+    if dry_run {
+        for offboarding in UserOffboarding::in_progress(db, company.id).await? {
+            let pending = remaining_steps(&offboarding.steps(db).await?)
+                .iter()
+                .map(|step| step.name())
+                .collect::<Vec<_>>();
+
+            info!(
+                "Would run offboarding steps {:?} for user {}",
+                pending, offboarding.username
+            );
+        }
+    } else {
+        for step in OffboardingStep::ALL {
+            if let Err(err) = run_offboarding_step(db, company, &ids, step).await {
+                warn!("{:?}", err);
+            }
+        }
+
+        complete_offboardings(db, company, &ids).await?;
+    }
+
+    // Reload the offboardings so that the records reflect the steps that were run
+    let mut records = vec![];
+    for id in ids {
+        if let Some(offboarding) = UserOffboarding::get(db, company.id, id).await? {
+            records.push(offboarding.into_record(db).await?);
+        }
+    }
+
+    Ok(records)
+}
+
+async fn run_step(
+    db: &Database,
+    company: &Company,
+    offboarding: &UserOffboarding,
+    user: &User,
+    step: OffboardingStep,
+    records: &[UserOffboardingStep],
+) -> Result<StepOutcome> {
+    match step {
+        OffboardingStep::LockOut => lock_out(db, company, user).await,
+        OffboardingStep::TransferDrive => transfer_drive(db, company, offboarding).await,
+        OffboardingStep::ForwardMail => forward_mail(company, offboarding).await,
+        OffboardingStep::RemoveGitHub => {
+            if user.github.is_empty() {
+                return Ok(StepOutcome::Skipped("User does not have a GitHub account".to_string()));
+            }
+
+            remove_from(db, company, user, ExternalServices::GitHub).await
+        }
+        OffboardingStep::RemoveOkta => {
+            if company.authenticate_okta().is_none() {
+                return Ok(StepOutcome::Skipped("Okta is not configured".to_string()));
+            }
+
+            if user.okta_id.is_empty() {
+                return Ok(StepOutcome::Skipped("User does not have an Okta account".to_string()));
+            }
+
+            remove_from(db, company, user, ExternalServices::Okta).await
+        }
+        OffboardingStep::DeactivateRampCards => deactivate_ramp_cards(company, offboarding, user).await,
+        OffboardingStep::RemoveZoom => {
+            if user.zoom_id.is_empty() {
+                return Ok(StepOutcome::Skipped("User does not have a Zoom account".to_string()));
+            }
+
+            remove_from(db, company, user, ExternalServices::Zoom).await
+        }
+        OffboardingStep::RemoveSlack => remove_from_slack(db, company, user).await,
+        OffboardingStep::RemoveAirtable => {
+            if company.airtable_enterprise_account_id.is_empty() {
+                return Ok(StepOutcome::Skipped(
+                    "Airtable enterprise account is not configured".to_string(),
+                ));
+            }
+
+            remove_from(db, company, user, ExternalServices::Airtable).await
+        }
+        OffboardingStep::DeprovisionTailscale => deprovision_tailscale(company, user).await,
+        OffboardingStep::DeleteGoogle => delete_google(db, company, offboarding, records).await,
+    }
+}
+
+async fn remove_from(db: &Database, company: &Company, user: &User, service: ExternalServices) -> Result<StepOutcome> {
+    let writer = service.get_provider_writer(db, company).await?;
+    writer.delete_user(db, company, user).await?;
+
+    Ok(StepOutcome::Completed(format!("Removed user from {}", service)))
+}
+
+/// Lock the user out of their Google account, and suspend their Okta account if the company uses
+/// Okta. The Google account is given a new password and signed out everywhere rather than being
+/// suspended, as mail is not delivered to a suspended user and their mail is forwarded to their
+/// manager until the account is deleted.
+async fn lock_out(db: &Database, company: &Company, user: &User) -> Result<StepOutcome> {
+    let gsuite = company.authenticate_google_admin(db).await?;
+
+    let mut gsuite_user = gsuite
+        .users()
+        .get(
+            &user.email,
+            gsuite_api::types::DirectoryUsersListProjection::Full,
+            gsuite_api::types::ViewType::AdminView,
+        )
+        .await?
+        .body;
+
+    // Nobody is told the new password
+    gsuite_user.password = generate_password();
+    gsuite_user.change_password_at_next_login = false;
+    gsuite.users().update(&user.email, &gsuite_user).await?;
+    gsuite.users().sign_out(&user.email).await?;
+
+    // Okta is suspended last, as suspending a user that is already suspended fails and so the
+    // step can only be retried when Okta is the part that failed
+    if let Some(okta) = company.authenticate_okta() {
+        if !user.okta_id.is_empty() {
+            okta.users().suspend(&user.okta_id).await?;
+
+            return Ok(StepOutcome::Completed(
+                "Reset Google password, signed user out of Google and suspended user in Okta".to_string(),
+            ));
+        }
+    }
+
+    Ok(StepOutcome::Completed(
+        "Reset Google password and signed user out of Google".to_string(),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct DataTransferApplications {
+    #[serde(default)]
+    applications: Vec<DataTransferApplication>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataTransferApplication {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataTransfer {
+    id: String,
+}
+
+async fn google_user_id(gsuite: &gsuite_api::Client, email: &str) -> Result<String> {
+    Ok(gsuite
+        .users()
+        .get(
+            email,
+            gsuite_api::types::DirectoryUsersListProjection::Full,
+            gsuite_api::types::ViewType::AdminView,
+        )
+        .await?
+        .body
+        .id)
+}
+
+/// Start a transfer of the user's Drive files to their manager. Google runs the transfer in the
+/// background, and the id of the transfer is recorded so that it can be checked in the admin
+/// console.
+async fn transfer_drive(db: &Database, company: &Company, offboarding: &UserOffboarding) -> Result<StepOutcome> {
+    if offboarding.manager_email.is_empty() {
+        return Ok(StepOutcome::Skipped("User does not have a manager".to_string()));
+    }
+
+    if company.google_service_account.is_empty() {
+        return Ok(StepOutcome::Skipped(
+            "Google service account is not configured".to_string(),
+        ));
+    }
+
+    let gsuite = company.authenticate_google_admin(db).await?;
+    let old_owner = google_user_id(&gsuite, &offboarding.email).await?;
+    let new_owner = google_user_id(&gsuite, &offboarding.manager_email).await?;
+
+    let token = company.google_service_account_token("", &[DATA_TRANSFER_SCOPE]).await?;
+    let client = reqwest::Client::new();
+
+    let applications: DataTransferApplications = client
+        .get(format!("{}/applications", DATA_TRANSFER_URL))
+        .bearer_auth(&token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let application = applications
+        .applications
+        .into_iter()
+        .find(|application| application.name == DRIVE_APPLICATION)
+        .ok_or_else(|| anyhow!("Data Transfer API does not list the {} application", DRIVE_APPLICATION))?;
+
+    let transfer: DataTransfer = client
+        .post(format!("{}/transfers", DATA_TRANSFER_URL))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({
+            "oldOwnerUserId": old_owner,
+            "newOwnerUserId": new_owner,
+            "applicationDataTransfers": [{
+                "applicationId": application.id,
+                "applicationTransferParams": [{
+                    "key": "PRIVACY_LEVEL",
+                    "value": ["PRIVATE", "SHARED"],
+                }],
+            }],
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(StepOutcome::Completed(format!(
+        "Started transfer {} of Drive files to {}",
+        transfer.id, offboarding.manager_email
+    )))
+}
+
+/// Find the Google user that an address belongs to, either as their primary address or as an
+/// alias. Returns None if the address does not belong to anyone.
+async fn find_google_user(gsuite: &gsuite_api::Client, email: &str) -> Result<Option<gsuite_api::types::User>> {
+    match gsuite
+        .users()
+        .get(
+            email,
+            gsuite_api::types::DirectoryUsersListProjection::Full,
+            gsuite_api::types::ViewType::AdminView,
+        )
+        .await
+    {
+        Ok(response) => Ok(Some(response.body)),
+        Err(gsuite_api::ClientError::HttpError { status, .. }) if status == reqwest::StatusCode::NOT_FOUND => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Forward the user's mail to their manager, which carries on until their Google account is
+/// deleted. Forwarding is set up as the user through the service account, which lets an address in
+/// the same domain be forwarded to without it having to be verified.
+async fn forward_mail(company: &Company, offboarding: &UserOffboarding) -> Result<StepOutcome> {
+    if offboarding.manager_email.is_empty() {
+        return Ok(StepOutcome::Skipped("User does not have a manager".to_string()));
+    }
+
+    if company.google_service_account.is_empty() {
+        return Ok(StepOutcome::Skipped(
+            "Google service account is not configured".to_string(),
+        ));
+    }
+
+    let token = company
+        .google_service_account_token(&offboarding.email, &[GMAIL_SETTINGS_SCOPE])
+        .await?;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/forwardingAddresses", GMAIL_SETTINGS_URL))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "forwardingEmail": offboarding.manager_email }))
+        .send()
+        .await?;
+
+    // A retry finds the address already added
+    if response.status() != reqwest::StatusCode::CONFLICT {
+        response.error_for_status()?;
+    }
+
+    client
+        .put(format!("{}/autoForwarding", GMAIL_SETTINGS_URL))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({
+            "enabled": true,
+            "emailAddress": offboarding.manager_email,
+            "disposition": "archive",
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(StepOutcome::Completed(format!(
+        "Forwarded mail to {}",
+        offboarding.manager_email
+    )))
+}
+
+/// When the user's Google account is due to be deleted. Mail that is forwarded to their manager
+/// keeps being forwarded for [`MAIL_FORWARDING_DAYS`], and without forwarding the account is
+/// deleted straight away.
+fn google_deletion_due(records: &[UserOffboardingStep]) -> Option<DateTime<Utc>> {
+    records
+        .iter()
+        .find(|record| {
+            record.step == OffboardingStep::ForwardMail.name()
+                && record.status == OffboardingStepStatus::Completed.to_string()
+        })
+        .and_then(|record| record.completed_at)
+        .map(|forwarded| forwarded + Duration::days(MAIL_FORWARDING_DAYS))
+}
+
+/// Delete the user's Google account once it is due to be deleted. Until then the step is left
+/// pending.
+async fn delete_google(
+    db: &Database,
+    company: &Company,
+    offboarding: &UserOffboarding,
+    records: &[UserOffboardingStep],
+) -> Result<StepOutcome> {
+    if let Some(due) = google_deletion_due(records) {
+        if Utc::now() < due {
+            return Ok(StepOutcome::Waiting(format!(
+                "Mail is forwarded to {} until {}",
+                offboarding.manager_email,
+                due.date_naive()
+            )));
+        }
+    }
+
+    let gsuite = company.authenticate_google_admin(db).await?;
+
+    match find_google_user(&gsuite, &offboarding.email).await? {
+        Some(owner) if owner.primary_email == offboarding.email => {
+            gsuite.users().delete(&offboarding.email).await?;
+            Ok(StepOutcome::Completed("Deleted Google account".to_string()))
+        }
+        Some(owner) => bail!(
+            "{} is an alias of {}, not the user's account",
+            offboarding.email,
+            owner.primary_email
+        ),
+        // A retry may find the account already deleted
+        None => Ok(StepOutcome::Completed("Google account was already deleted".to_string())),
+    }
+}
+
+/// Terminate each of the user's Ramp cards. Their Ramp account itself is left in place for
+/// auditing, and they can no longer sign in to it once they are locked out of Google.
+async fn deactivate_ramp_cards(company: &Company, offboarding: &UserOffboarding, user: &User) -> Result<StepOutcome> {
+    let ramp = company.authenticate_ramp()?;
+
+    let ramp_id = if user.ramp_id.is_empty() {
+        ramp.users()
+            .list()
+            .await?
+            .data
+            .into_iter()
+            .find(|ramp_user| ramp_user.email == user.email)
+            .map(|ramp_user| ramp_user.id)
+    } else {
+        Some(user.ramp_id.to_string())
+    };
+
+    let ramp_id = match ramp_id {
+        Some(ramp_id) => ramp_id,
+        None => return Ok(StepOutcome::Skipped("User does not have a Ramp account".to_string())),
+    };
+
+    let cards = ramp
+        .cards()
+        .list(&ramp_minimal_api::ListCardsQuery {
+            user_id: Some(ramp_id),
+            ..Default::default()
+        })
+        .await?
+        .data;
+
+    let mut terminated = vec![];
+
+    for card in cards {
+        if card.state == ramp_minimal_api::CardState::Terminated {
+            continue;
+        }
+
+        ramp.cards()
+            .deferred_termination(
+                &card.id,
+                &ramp_minimal_api::TerminateCardDeferred {
+                    // Stable across retries of the step, so that a termination is only requested once
+                    idempotency_key: format!("offboarding-{}-{}", offboarding.id, card.id),
+                },
+            )
+            .await?;
+
+        terminated.push(card.last_four);
+    }
+
+    if terminated.is_empty() {
+        Ok(StepOutcome::Completed(
+            "User does not have any active Ramp cards".to_string(),
+        ))
+    } else {
+        Ok(StepOutcome::Completed(format!(
+            "Terminated Ramp cards ending in {}",
+            terminated.join(", ")
+        )))
+    }
+}
+
+async fn remove_from_slack(db: &Database, company: &Company, user: &User) -> Result<StepOutcome> {
+    let slack = match company.authenticate_slack(db).await {
+        Ok(slack) => slack,
+        Err(_) => return Ok(StepOutcome::Skipped("Slack is not configured".to_string())),
+    };
+
+    let slack_user = slack.list_users().await?.into_iter().find(|slack_user| {
+        !slack_user.deleted && (slack_user.email == user.email || slack_user.profile.email == user.email)
+    });
+
+    match slack_user {
+        Some(slack_user) => {
+            slack.remove_user(&slack_user.id).await?;
+            Ok(StepOutcome::Completed(format!("Removed Slack user {}", slack_user.id)))
+        }
+        None => Ok(StepOutcome::Skipped("User does not have a Slack account".to_string())),
+    }
+}
+
+async fn deprovision_tailscale(company: &Company, user: &User) -> Result<StepOutcome> {
+    if company.tailscale_api_key.is_empty() {
+        return Ok(StepOutcome::Skipped("Tailscale is not configured".to_string()));
+    }
+
+    let tailscale = company.authenticate_tailscale();
+    let mut deleted = vec![];
+
+    for device in tailscale.list_devices().await? {
+        if device.user != user.email {
+            continue;
+        }
+
+        tailscale.delete_device(&device.id).await?;
+        deleted.push(device.hostname);
+    }
+
+    if deleted.is_empty() {
+        Ok(StepOutcome::Completed(
+            "User does not have any Tailscale devices".to_string(),
+        ))
+    } else {
+        Ok(StepOutcome::Completed(format!(
+            "Deleted Tailscale devices {}",
+            deleted.join(", ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offboarding(status: OffboardingStatus, returned: bool) -> UserOffboarding {
+        UserOffboarding {
+            id: 1,
+            cio_company_id: 1,
+            username: "jdoe".to_string(),
+            email: "jdoe@example.com".to_string(),
+            manager_email: "manager@example.com".to_string(),
+            status: status.to_string(),
+            created_at: Utc::now(),
+            completed_at: None,
+            returned_at: if returned { Some(Utc::now()) } else { None },
+        }
+    }
+
+    fn step(step: OffboardingStep, status: OffboardingStepStatus) -> UserOffboardingStep {
+        UserOffboardingStep {
+            id: 1,
+            offboarding_id: 1,
+            step: step.name().to_string(),
+            status: status.to_string(),
+            detail: String::new(),
+            attempts: 0,
+            last_error: String::new(),
+            updated_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_step_names() {
+        for step in OffboardingStep::ALL {
+            assert_eq!(Some(step), OffboardingStep::from_name(step.name()));
+        }
+
+        assert_eq!(None, OffboardingStep::from_name("delete-everything"));
+    }
+
+    #[test]
+    fn test_remaining_steps() {
+        let mut steps = OffboardingStep::ALL
+            .iter()
+            .map(|s| step(*s, OffboardingStepStatus::Pending))
+            .collect::<Vec<_>>();
+
+        assert_eq!(OffboardingStep::ALL.to_vec(), remaining_steps(&steps));
+
+        steps[0] = step(OffboardingStep::LockOut, OffboardingStepStatus::Completed);
+        steps[1] = step(OffboardingStep::TransferDrive, OffboardingStepStatus::Skipped);
+        steps[2] = step(OffboardingStep::ForwardMail, OffboardingStepStatus::Failed);
+        assert_eq!(Some(&OffboardingStep::ForwardMail), remaining_steps(&steps).first());
+        assert_eq!(9, remaining_steps(&steps).len());
+
+        for record in steps.iter_mut() {
+            record.status = OffboardingStepStatus::Completed.to_string();
+        }
+        assert!(remaining_steps(&steps).is_empty());
+
+        // A step without a record has not run yet
+        steps.pop();
+        assert_eq!(vec![OffboardingStep::DeleteGoogle], remaining_steps(&steps));
+    }
+
+    #[test]
+    fn test_google_deletion_due() {
+        let forwarded_at = Utc::now();
+        let mut forwarded = step(OffboardingStep::ForwardMail, OffboardingStepStatus::Completed);
+        forwarded.completed_at = Some(forwarded_at);
+
+        assert_eq!(
+            Some(forwarded_at + Duration::days(MAIL_FORWARDING_DAYS)),
+            google_deletion_due(&[
+                step(OffboardingStep::LockOut, OffboardingStepStatus::Completed),
+                forwarded
+            ])
+        );
+
+        // Without forwarding there is nothing to wait for
+        assert_eq!(
+            None,
+            google_deletion_due(&[step(OffboardingStep::ForwardMail, OffboardingStepStatus::Skipped)])
+        );
+        assert_eq!(None, google_deletion_due(&[]));
+    }
+
+    #[test]
+    fn test_is_active() {
+        assert!(is_active(&offboarding(OffboardingStatus::InProgress, false)));
+        assert!(is_active(&offboarding(OffboardingStatus::Completed, false)));
+        assert!(!is_active(&offboarding(OffboardingStatus::Completed, true)));
+        assert!(!is_active(&offboarding(OffboardingStatus::Cancelled, true)));
+    }
+
+    #[test]
+    fn test_record_display() {
+        let mut failed = step(OffboardingStep::LockOut, OffboardingStepStatus::Failed);
+        failed.last_error = "Okta returned 500".to_string();
+
+        let mut transferred = step(OffboardingStep::TransferDrive, OffboardingStepStatus::Completed);
+        transferred.detail = "Started transfer 1 of Drive files to manager@example.com".to_string();
+
+        let record = OffboardingRecord {
+            offboarding: offboarding(OffboardingStatus::InProgress, false),
+            steps: vec![
+                transferred,
+                failed,
+                step(OffboardingStep::ForwardMail, OffboardingStepStatus::Pending),
+            ],
+        };
+
+        assert_eq!(
+            "jdoe (in_progress)
+    transfer-drive: completed - Started transfer 1 of Drive files to manager@example.com
+    lock-out: failed - Okta returned 500
+    forward-mail: pending",
+            record.to_string()
+        );
+    }
+}
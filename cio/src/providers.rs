@@ -1009,12 +1009,20 @@ impl ProviderWriteOps for okta::Client {
             user_id = okta_user.id;
 
             // The user did not already exist in Okta.
-            // We should send them an email about setting up their account.
-            info!("sending email to new Okta user `{}`", user.username);
-            if user.is_consultant() {
-                user.send_email_new_consultant(db).await?;
+            // We should send them an email about setting up their account, unless their
+            // provisioning schedule sends it ahead of their start date.
+            if crate::provisioning::schedules_welcome(db, company.id, &user.username).await? {
+                info!(
+                    "skipping email to new Okta user `{}` as it is sent by their provisioning schedule",
+                    user.username
+                );
             } else {
-                user.send_email_new_user(db).await?;
+                info!("sending email to new Okta user `{}`", user.username);
+                if user.is_consultant() {
+                    user.send_email_new_consultant(db).await?;
+                } else {
+                    user.send_email_new_user(db).await?;
+                }
             }
         }
 
@@ -1661,6 +1669,376 @@ impl ProviderReadOps for airtable_api::Airtable {
     }
 }
 
+/// The name that a group from the configs is known by in Slack. Groups that are mapped to a
+/// channel are named after the channel, prefixed with `#`, and all other groups are user groups
+/// with a handle that matches the name of the group.
+pub(crate) fn slack_group_name(name: &str, slack_channel: &str) -> String {
+    if slack_channel.is_empty() {
+        name.to_string()
+    } else {
+        format!("#{}", slack_channel.trim_start_matches('#'))
+    }
+}
+
+/// The parts of a Slack profile that are managed by the configs. Pronouns and display names are
+/// left for each user to set for themselves.
+///
+/// The manager is set through a custom profile field, given as the id of the field and its value.
+fn slack_profile(user: &User, manager: Option<(String, String)>) -> slack_chat_api::UserProfile {
+    let mut profile = slack_chat_api::UserProfile {
+        first_name: user.first_name.to_string(),
+        last_name: user.last_name.to_string(),
+        real_name: user.full_name(),
+        title: user.department.to_string(),
+        ..Default::default()
+    };
+
+    if let Some((field_id, value)) = manager {
+        let mut fields = std::collections::HashMap::new();
+        fields.insert(
+            field_id,
+            slack_chat_api::UserProfileFields {
+                value,
+                ..Default::default()
+            },
+        );
+        profile.fields = Some(fields);
+    }
+
+    profile
+}
+
+/// Find the active Slack account of a user by their email.
+async fn find_slack_user(slack: &slack_chat_api::Slack, user: &User) -> Result<Option<slack_chat_api::User>> {
+    Ok(slack
+        .lookup_user_by_email(&user.email)
+        .await?
+        .filter(|slack_user| !slack_user.deleted))
+}
+
+/// Whether an error is an error response from Slack with the given error code.
+fn is_slack_error(err: &anyhow::Error, code: &str) -> bool {
+    slack_chat_api::APIError::code(err) == Some(code)
+}
+
+/// A Slack client that makes the changes of a sync. The user groups of the workspace are listed
+/// the first time that one is needed, and the list is kept up to date with the changes made
+/// through the writer, so that it is not listed again for every change to a user group.
+pub struct SlackWriter {
+    slack: slack_chat_api::Slack,
+    usergroups: std::sync::Mutex<Option<Vec<slack_chat_api::UserGroup>>>,
+}
+
+impl SlackWriter {
+    pub fn new(slack: slack_chat_api::Slack) -> Self {
+        Self {
+            slack,
+            usergroups: std::sync::Mutex::new(None),
+        }
+    }
+
+    async fn find_usergroup(&self, handle: &str) -> Result<Option<slack_chat_api::UserGroup>> {
+        if self.usergroups.lock().unwrap().is_none() {
+            let usergroups = self.slack.list_usergroups().await?;
+            *self.usergroups.lock().unwrap() = Some(usergroups);
+        }
+
+        Ok(self
+            .usergroups
+            .lock()
+            .unwrap()
+            .iter()
+            .flatten()
+            .find(|usergroup| usergroup.handle == handle)
+            .cloned())
+    }
+
+    /// Record a user group as it is after a change that was made to it
+    fn remember_usergroup(&self, usergroup: slack_chat_api::UserGroup) {
+        if let Some(usergroups) = self.usergroups.lock().unwrap().as_mut() {
+            match usergroups.iter_mut().find(|existing| existing.id == usergroup.id) {
+                Some(existing) => *existing = usergroup,
+                None => usergroups.push(usergroup),
+            }
+        }
+    }
+}
+
+impl std::ops::Deref for SlackWriter {
+    type Target = slack_chat_api::Slack;
+
+    fn deref(&self) -> &Self::Target {
+        &self.slack
+    }
+}
+
+#[async_trait]
+impl ProviderWriteOps for SlackWriter {
+    async fn ensure_user(&self, db: &Database, _company: &Company, user: &User, _config: &AppConfig) -> Result<String> {
+        if user.denied_services.contains(&ExternalServices::Slack) {
+            log::info!(
+                "User {} is denied access to {}. Exiting provisioning.",
+                user.id,
+                ExternalServices::Slack
+            );
+
+            return Ok(String::new());
+        }
+
+        let slack_user = match find_slack_user(self, user).await? {
+            Some(slack_user) => slack_user,
+            None => {
+                // An invite must add the user to at least one channel.
+                let general = self
+                    .list_channels()
+                    .await?
+                    .into_iter()
+                    .find(|channel| channel.is_general)
+                    .ok_or_else(|| anyhow::anyhow!("Failed to find the general channel in Slack"))?;
+
+                self.invite_user(slack_chat_api::UserInvite {
+                    channel_ids: vec![general.id],
+                    email: user.email.to_string(),
+                    team_id: self.workspace_id().to_string(),
+                    real_name: user.full_name(),
+                    ..Default::default()
+                })
+                .await?;
+
+                info!("invited user `{}` to slack", user.email);
+
+                // The profile of an invited user can not be updated until they have accepted
+                // their invite, so it is synced on a later run.
+                return Ok(String::new());
+            }
+        };
+
+        // The manager is only synced when the workspace has a custom profile field for it.
+        let mut manager = None;
+        if !user.manager.is_empty() {
+            let fields = self.list_team_profile_fields().await?;
+
+            if let Some(field) = fields.iter().find(|field| {
+                field.label.eq_ignore_ascii_case("manager") || field.label.eq_ignore_ascii_case("reports to")
+            }) {
+                if let Some(manager_user) = User::get_from_db(db, user.cio_company_id, user.manager.to_string()).await {
+                    let value = if field.type_ == "user" {
+                        find_slack_user(self, &manager_user)
+                            .await?
+                            .map(|slack_manager| slack_manager.id)
+                            .unwrap_or_default()
+                    } else {
+                        manager_user.full_name()
+                    };
+
+                    if !value.is_empty() {
+                        manager = Some((field.id.to_string(), value));
+                    }
+                }
+            }
+        }
+
+        self.update_user_profile(&slack_user.id, slack_profile(user, manager))
+            .await?;
+
+        info!("updated slack profile for user `{}`", user.email);
+
+        // We don't need to store the user id, so just return an empty string here.
+        Ok(String::new())
+    }
+
+    async fn ensure_group(&self, _db: &Database, _company: &Company, group: &Group) -> Result<()> {
+        if !group.slack_channel.is_empty() {
+            // Channels are not created, they only need to exist for members to be added to them.
+            self.channel_id(&group.slack_channel).await?;
+            return Ok(());
+        }
+
+        match self.find_usergroup(&group.name).await? {
+            Some(mut usergroup) => {
+                if usergroup.is_disabled() {
+                    self.enable_usergroup(&usergroup.id).await?;
+                    usergroup.date_delete = 0;
+                }
+
+                if usergroup.name != group.name || usergroup.description != group.description {
+                    self.update_usergroup(&usergroup.id, &group.name, &group.name, &group.description)
+                        .await?;
+                    usergroup.name = group.name.to_string();
+                    usergroup.description = group.description.to_string();
+                }
+
+                self.remember_usergroup(usergroup);
+
+                info!("updated slack user group `{}`", group.name);
+            }
+            None => {
+                let usergroup = self
+                    .create_usergroup(&group.name, &group.name, &group.description)
+                    .await?;
+                self.remember_usergroup(usergroup);
+
+                info!("created slack user group `{}`", group.name);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_user_is_member_of_group(&self, _company: &Company, user: &User, group: &str) -> Result<bool> {
+        let slack_user = match find_slack_user(self, user).await? {
+            Some(slack_user) => slack_user,
+            None => return Ok(false),
+        };
+
+        if let Some(channel) = group.strip_prefix('#') {
+            let channel_id = self.channel_id(channel).await?;
+            return Ok(self.list_channel_members(&channel_id).await?.contains(&slack_user.id));
+        }
+
+        Ok(self
+            .find_usergroup(group)
+            .await?
+            .map(|usergroup| !usergroup.is_disabled() && usergroup.users.contains(&slack_user.id))
+            .unwrap_or_default())
+    }
+
+    async fn add_user_to_group(&self, _company: &Company, user: &User, group: &str) -> Result<()> {
+        let slack_user = find_slack_user(self, user)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User {} does not have a Slack account", user.email))?;
+
+        if let Some(channel) = group.strip_prefix('#') {
+            let channel_id = self.channel_id(channel).await?;
+
+            if let Err(err) = self.invite_to_channel(&channel_id, &[slack_user.id.to_string()]).await {
+                if !is_slack_error(&err, "already_in_channel") {
+                    return Err(err);
+                }
+            }
+
+            info!("added user `{}` to slack channel `{}`", user.email, group);
+
+            return Ok(());
+        }
+
+        let mut usergroup = self
+            .find_usergroup(group)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Slack user group {} does not exist", group))?;
+
+        if usergroup.is_disabled() {
+            self.enable_usergroup(&usergroup.id).await?;
+            usergroup.date_delete = 0;
+        } else if usergroup.users.contains(&slack_user.id) {
+            return Ok(());
+        }
+
+        if !usergroup.users.contains(&slack_user.id) {
+            usergroup.users.push(slack_user.id);
+        }
+        self.update_usergroup_users(&usergroup.id, &usergroup.users).await?;
+        self.remember_usergroup(usergroup);
+
+        info!("added user `{}` to slack user group `{}`", user.email, group);
+
+        Ok(())
+    }
+
+    async fn remove_user_from_group(&self, _company: &Company, user: &User, group: &str) -> Result<()> {
+        let slack_user = match find_slack_user(self, user).await? {
+            Some(slack_user) => slack_user,
+            None => return Ok(()),
+        };
+
+        if let Some(channel) = group.strip_prefix('#') {
+            let channel_id = self.channel_id(channel).await?;
+
+            if let Err(err) = self.kick_from_channel(&channel_id, &slack_user.id).await {
+                if !is_slack_error(&err, "not_in_channel") {
+                    return Err(err);
+                }
+            }
+
+            info!("removed user `{}` from slack channel `{}`", user.email, group);
+
+            return Ok(());
+        }
+
+        let mut usergroup = match self.find_usergroup(group).await? {
+            Some(usergroup) if usergroup.users.contains(&slack_user.id) => usergroup,
+            _ => return Ok(()),
+        };
+
+        usergroup.users.retain(|id| id != &slack_user.id);
+
+        // A user group must have at least one member, so it is disabled when the last member
+        // is removed.
+        if usergroup.users.is_empty() {
+            usergroup.date_delete = self.disable_usergroup(&usergroup.id).await?.date_delete.max(1);
+        } else {
+            self.update_usergroup_users(&usergroup.id, &usergroup.users).await?;
+        }
+        self.remember_usergroup(usergroup);
+
+        info!("removed user `{}` from slack user group `{}`", user.email, group);
+
+        Ok(())
+    }
+
+    async fn delete_user(&self, _db: &Database, _company: &Company, user: &User) -> Result<()> {
+        if let Some(slack_user) = find_slack_user(self, user).await? {
+            self.remove_user(&slack_user.id).await?;
+
+            info!("removed user `{}` from slack", user.email);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_group(&self, _company: &Company, group: &Group) -> Result<()> {
+        if !group.slack_channel.is_empty() {
+            // Channels are never created, so they are not deleted either.
+            return Ok(());
+        }
+
+        if let Some(mut usergroup) = self.find_usergroup(&group.name).await? {
+            if !usergroup.is_disabled() {
+                usergroup.date_delete = self.disable_usergroup(&usergroup.id).await?.date_delete.max(1);
+                self.remember_usergroup(usergroup);
+
+                info!("disabled slack user group `{}`", group.name);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ProviderReadOps for slack_chat_api::Slack {
+    type ProviderUser = slack_chat_api::User;
+    type ProviderGroup = slack_chat_api::UserGroup;
+
+    async fn list_provider_users(&self, _company: &Company) -> Result<Vec<slack_chat_api::User>> {
+        Ok(self
+            .list_users()
+            .await?
+            .into_iter()
+            .filter(|user| !user.deleted && !user.is_bot && !user.is_app_user && user.id != "USLACKBOT")
+            .collect())
+    }
+
+    async fn list_provider_groups(&self, _company: &Company) -> Result<Vec<slack_chat_api::UserGroup>> {
+        Ok(self
+            .list_usergroups()
+            .await?
+            .into_iter()
+            .filter(|usergroup| !usergroup.is_disabled())
+            .collect())
+    }
+}
+
 /*
  *
  * Keep as empty boiler plate for now.
@@ -1705,3 +2083,180 @@ impl ProviderWriteOps<ramp_api::types::User, ()> for ramp_api::Client {
 }
 
 */
+
+#[cfg(test)]
+mod tests {
+    use httpmock::{
+        Method::{GET, POST},
+        MockServer,
+    };
+    use reqwest::Url;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{companies::tests::mock_company, configs::tests::mock_user};
+
+    fn mock_slack(server: &MockServer) -> SlackWriter {
+        SlackWriter::new(
+            slack_chat_api::Slack::new("", "", "T1", "", "bot-token", "user-token")
+                .base_url(Url::parse(&server.base_url()).unwrap()),
+        )
+    }
+
+    fn mock_lookup(server: &MockServer) -> httpmock::Mock<'_> {
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/users.lookupByEmail")
+                .query_param("email", "random-test@testemaildomain.com");
+            then.status(200).json_body(json!({
+                "ok": true,
+                "user": { "id": "U1", "profile": { "email": "random-test@testemaildomain.com" } },
+            }));
+        })
+    }
+
+    #[test]
+    fn test_slack_group_name() {
+        assert_eq!(slack_group_name("eng", ""), "eng");
+        assert_eq!(slack_group_name("eng", "engineering"), "#engineering");
+        assert_eq!(slack_group_name("eng", "#engineering"), "#engineering");
+    }
+
+    #[test]
+    fn test_slack_profile() {
+        let mut user = mock_user();
+        user.last_name = "user".to_string();
+        user.department = "Engineering".to_string();
+
+        let profile = slack_profile(&user, Some(("Xf1".to_string(), "U2".to_string())));
+
+        assert_eq!(
+            serde_json::to_value(&profile).unwrap(),
+            json!({
+                "first_name": "random",
+                "last_name": "user",
+                "real_name": "random user",
+                "title": "Engineering",
+                "fields": { "Xf1": { "value": "U2" } },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slack_denied_user_is_not_provisioned() {
+        // The user is skipped before the database is used, so no connection is ever made
+        let db = Database::from_url("postgres://localhost/test");
+        let server = MockServer::start();
+        let lookup = mock_lookup(&server);
+
+        let mut user = mock_user();
+        user.denied_services = vec![ExternalServices::Slack];
+
+        let result = mock_slack(&server)
+            .ensure_user(&db, &mock_company(), &user, &AppConfig::default())
+            .await;
+
+        assert_eq!(result.unwrap(), "");
+        lookup.assert_hits(0);
+    }
+
+    #[tokio::test]
+    async fn test_slack_add_user_to_channel() {
+        let server = MockServer::start();
+        let lookup = mock_lookup(&server);
+        let channels = server.mock(|when, then| {
+            when.method(GET).path("/conversations.list");
+            then.status(200).json_body(json!({
+                "ok": true,
+                "channels": [{ "id": "C1", "name": "engineering" }],
+            }));
+        });
+        let invite = server.mock(|when, then| {
+            when.method(POST)
+                .path("/conversations.invite")
+                .json_body(json!({ "channel": "C1", "users": "U1" }));
+            then.status(200)
+                .json_body(json!({ "ok": false, "error": "already_in_channel" }));
+        });
+
+        let result = mock_slack(&server)
+            .add_user_to_group(&mock_company(), &mock_user(), "#engineering")
+            .await;
+
+        assert!(result.is_ok());
+        lookup.assert();
+        channels.assert();
+        invite.assert();
+    }
+
+    #[tokio::test]
+    async fn test_slack_add_user_to_usergroup() {
+        let server = MockServer::start();
+        let lookup = mock_lookup(&server);
+        let usergroups = server.mock(|when, then| {
+            when.method(GET).path("/usergroups.list");
+            then.status(200).json_body(json!({
+                "ok": true,
+                "usergroups": [{ "id": "S1", "handle": "eng", "users": ["U0"] }],
+            }));
+        });
+        let update = server.mock(|when, then| {
+            when.method(POST)
+                .path("/usergroups.users.update")
+                .json_body(json!({ "usergroup": "S1", "users": "U0,U1" }));
+            then.status(200).json_body(json!({
+                "ok": true,
+                "usergroup": { "id": "S1", "handle": "eng", "users": ["U0", "U1"] },
+            }));
+        });
+
+        let slack = mock_slack(&server);
+        let result = slack.add_user_to_group(&mock_company(), &mock_user(), "eng").await;
+        assert!(result.is_ok());
+
+        // The user groups are only listed once, and the update is remembered
+        let result = slack.add_user_to_group(&mock_company(), &mock_user(), "eng").await;
+        assert!(result.is_ok());
+
+        lookup.assert_hits(2);
+        usergroups.assert_hits(1);
+        update.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn test_slack_remove_last_user_from_usergroup() {
+        let server = MockServer::start();
+        let lookup = mock_lookup(&server);
+        let usergroups = server.mock(|when, then| {
+            when.method(GET).path("/usergroups.list");
+            then.status(200).json_body(json!({
+                "ok": true,
+                "usergroups": [{ "id": "S1", "handle": "eng", "users": ["U1"] }],
+            }));
+        });
+        let update = server.mock(|when, then| {
+            when.method(POST).path("/usergroups.users.update");
+            then.status(200)
+                .json_body(json!({ "ok": false, "error": "invalid_users" }));
+        });
+        let disable = server.mock(|when, then| {
+            when.method(POST)
+                .path("/usergroups.disable")
+                .json_body(json!({ "usergroup": "S1" }));
+            then.status(200).json_body(json!({
+                "ok": true,
+                "usergroup": { "id": "S1", "handle": "eng", "date_delete": 1700000000 },
+            }));
+        });
+
+        let result = mock_slack(&server)
+            .remove_user_from_group(&mock_company(), &mock_user(), "eng")
+            .await;
+
+        assert!(result.is_ok());
+        lookup.assert();
+        usergroups.assert();
+        update.assert_hits(0);
+        disable.assert();
+    }
+}
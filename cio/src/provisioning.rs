@@ -0,0 +1,789 @@
+//! Provision new hires on a schedule relative to their start date.
+//!
+//! A user that is added to the configs ahead of their start date is given a schedule of
+//! [`ProvisioningStep`]s rather than having every account created as soon as the configs are
+//! synced. Their Google account is created a week ahead so that they can be reached, they are
+//! welcomed and sent swag a few days later, and access to everything else is granted on their
+//! first day. While a step is pending, the configs sync leaves the user's account in that step's
+//! service alone, and until their Google account is created it also leaves the accounts that are
+//! created for their Google address alone.
+//!
+//! Pending steps that were scheduled for a start date that has since changed are cancelled and
+//! scheduled again for the new start date. Steps that have already run are not repeated.
+
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::{ExpressionMethods, Insertable, QueryDsl, Queryable};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    companies::Company,
+    configs::{ExternalServices, User},
+    configs_plan::ensure_account,
+    db::Database,
+    schema::provisioning_steps,
+};
+
+/// The individual steps of provisioning a new hire, in the order that they run on a given day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvisioningStep {
+    CreateGoogle,
+    SendWelcome,
+    ShipSwag,
+    GrantGitHub,
+    GrantOkta,
+    ActivateRamp,
+}
+
+impl ProvisioningStep {
+    pub const ALL: [ProvisioningStep; 6] = [
+        ProvisioningStep::CreateGoogle,
+        ProvisioningStep::SendWelcome,
+        ProvisioningStep::ShipSwag,
+        ProvisioningStep::GrantGitHub,
+        ProvisioningStep::GrantOkta,
+        ProvisioningStep::ActivateRamp,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProvisioningStep::CreateGoogle => "create-google",
+            ProvisioningStep::SendWelcome => "send-welcome",
+            ProvisioningStep::ShipSwag => "ship-swag",
+            ProvisioningStep::GrantGitHub => "grant-github",
+            ProvisioningStep::GrantOkta => "grant-okta",
+            ProvisioningStep::ActivateRamp => "activate-ramp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.name() == name)
+    }
+
+    /// The number of days after the start date that the step is due. Negative for steps that are
+    /// due ahead of the start date
+    pub fn offset_days(&self) -> i64 {
+        match self {
+            ProvisioningStep::CreateGoogle => -7,
+            ProvisioningStep::SendWelcome | ProvisioningStep::ShipSwag => -5,
+            ProvisioningStep::GrantGitHub | ProvisioningStep::GrantOkta | ProvisioningStep::ActivateRamp => 0,
+        }
+    }
+
+    /// The service that the step creates an account in, if any. Companies that use Okta have their
+    /// Google accounts created by Okta, so their Google account is created through Okta instead
+    pub fn service(&self, company: &Company) -> Option<ExternalServices> {
+        match self {
+            ProvisioningStep::CreateGoogle if company.authenticate_okta().is_some() => Some(ExternalServices::Okta),
+            ProvisioningStep::CreateGoogle => Some(ExternalServices::Google),
+            ProvisioningStep::GrantGitHub => Some(ExternalServices::GitHub),
+            ProvisioningStep::GrantOkta => Some(ExternalServices::Okta),
+            // Ramp issues the user's card as part of inviting them
+            ProvisioningStep::ActivateRamp => Some(ExternalServices::Ramp),
+            ProvisioningStep::SendWelcome | ProvisioningStep::ShipSwag => None,
+        }
+    }
+
+    /// The services that the configs sync leaves alone while the step is pending. The Zoom, Slack
+    /// and Airtable accounts of a user are created for their Google address, so they wait for the
+    /// Google account to be created
+    pub fn held_services(&self, company: &Company) -> Vec<ExternalServices> {
+        let mut held = self.service(company).into_iter().collect::<Vec<_>>();
+
+        if *self == ProvisioningStep::CreateGoogle {
+            for service in [
+                ExternalServices::Google,
+                ExternalServices::Zoom,
+                ExternalServices::Slack,
+                ExternalServices::Airtable,
+            ] {
+                if !held.contains(&service) {
+                    held.push(service);
+                }
+            }
+        }
+
+        held
+    }
+
+    pub fn due_on(&self, start_date: NaiveDate) -> NaiveDate {
+        start_date + Duration::days(self.offset_days())
+    }
+
+    fn position(name: &str) -> usize {
+        Self::ALL
+            .iter()
+            .position(|step| step.name() == name)
+            .unwrap_or(Self::ALL.len())
+    }
+}
+
+/// Where a scheduled step is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvisioningStepStatus {
+    Pending,
+    Completed,
+    /// The last attempt failed. The step is attempted again on the next run
+    Failed,
+    /// The start date changed, or the user was removed from the configs, before the step ran
+    Cancelled,
+}
+
+impl fmt::Display for ProvisioningStepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProvisioningStepStatus::Pending => write!(f, "pending"),
+            ProvisioningStepStatus::Completed => write!(f, "completed"),
+            ProvisioningStepStatus::Failed => write!(f, "failed"),
+            ProvisioningStepStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// A step of provisioning a user that was scheduled for a day relative to their start date
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct ProvisioningStepRecord {
+    pub id: i32,
+    pub cio_company_id: i32,
+    pub username: String,
+    pub step: String,
+    /// The start date that the step was scheduled for
+    pub start_date: NaiveDate,
+    pub due_on: NaiveDate,
+    pub status: String,
+    /// What the step did, or why it was cancelled
+    pub detail: String,
+    pub attempts: i32,
+    pub last_error: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = provisioning_steps)]
+struct NewProvisioningStep {
+    cio_company_id: i32,
+    username: String,
+    step: String,
+    start_date: NaiveDate,
+    due_on: NaiveDate,
+    status: String,
+}
+
+/// The statuses of steps that have yet to run successfully
+const PENDING: [ProvisioningStepStatus; 2] = [ProvisioningStepStatus::Pending, ProvisioningStepStatus::Failed];
+
+fn statuses(statuses: &[ProvisioningStepStatus]) -> Vec<String> {
+    statuses.iter().map(|status| status.to_string()).collect()
+}
+
+impl ProvisioningStepRecord {
+    fn is_pending(&self) -> bool {
+        statuses(&PENDING).contains(&self.status)
+    }
+
+    fn is_completed(&self) -> bool {
+        self.status == ProvisioningStepStatus::Completed.to_string()
+    }
+
+    /// Every step that has been scheduled for a user, in any status
+    pub async fn for_user(db: &Database, company_id: i32, username: &str) -> Result<Vec<Self>> {
+        Ok(provisioning_steps::dsl::provisioning_steps
+            .filter(provisioning_steps::dsl::cio_company_id.eq(company_id))
+            .filter(provisioning_steps::dsl::username.eq(username.to_string()))
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// The steps that have yet to run, optionally only those of a single user, in the order that
+    /// they are due
+    pub async fn pending(db: &Database, company_id: i32, username: Option<&str>) -> Result<Vec<Self>> {
+        let mut query = provisioning_steps::dsl::provisioning_steps
+            .filter(provisioning_steps::dsl::cio_company_id.eq(company_id))
+            .filter(provisioning_steps::dsl::status.eq_any(statuses(&PENDING)))
+            .into_boxed();
+
+        if let Some(username) = username {
+            query = query.filter(provisioning_steps::dsl::username.eq(username.to_string()));
+        }
+
+        let mut steps = query.load_async::<Self>(db.pool()).await?;
+        steps.sort_by_key(|record| (record.due_on, record.username.to_string(), Self::position(record)));
+
+        Ok(steps)
+    }
+
+    /// The steps that are due to run on or before the given day
+    pub async fn due(db: &Database, company_id: i32, today: NaiveDate) -> Result<Vec<Self>> {
+        Ok(Self::pending(db, company_id, None)
+            .await?
+            .into_iter()
+            .filter(|record| record.due_on <= today)
+            .collect())
+    }
+
+    fn position(record: &Self) -> usize {
+        ProvisioningStep::position(&record.step)
+    }
+
+    async fn cancel(db: &Database, ids: &[i32], reason: &str) -> Result<usize> {
+        Ok(diesel::update(provisioning_steps::dsl::provisioning_steps)
+            .filter(provisioning_steps::dsl::id.eq_any(ids.to_vec()))
+            .filter(provisioning_steps::dsl::status.eq_any(statuses(&PENDING)))
+            .set((
+                provisioning_steps::dsl::status.eq(ProvisioningStepStatus::Cancelled.to_string()),
+                provisioning_steps::dsl::detail.eq(reason.to_string()),
+                provisioning_steps::dsl::updated_at.eq(Utc::now()),
+            ))
+            .execute_async(db.pool())
+            .await?)
+    }
+
+    async fn complete(&self, db: &Database, detail: &str) -> Result<()> {
+        let now = Utc::now();
+
+        diesel::update(provisioning_steps::dsl::provisioning_steps)
+            .filter(provisioning_steps::dsl::id.eq(self.id))
+            .set((
+                provisioning_steps::dsl::status.eq(ProvisioningStepStatus::Completed.to_string()),
+                provisioning_steps::dsl::detail.eq(detail.to_string()),
+                provisioning_steps::dsl::attempts.eq(provisioning_steps::dsl::attempts + 1),
+                provisioning_steps::dsl::updated_at.eq(now),
+                provisioning_steps::dsl::completed_at.eq(Some(now)),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn fail(&self, db: &Database, err: &anyhow::Error) -> Result<()> {
+        diesel::update(provisioning_steps::dsl::provisioning_steps)
+            .filter(provisioning_steps::dsl::id.eq(self.id))
+            .set((
+                provisioning_steps::dsl::status.eq(ProvisioningStepStatus::Failed.to_string()),
+                provisioning_steps::dsl::attempts.eq(provisioning_steps::dsl::attempts + 1),
+                provisioning_steps::dsl::last_error.eq(format!("{:?}", err)),
+                provisioning_steps::dsl::updated_at.eq(Utc::now()),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// The changes to make to the schedule of a user
+#[derive(Debug, Default, PartialEq)]
+struct Reschedule {
+    /// The ids of the pending steps that were scheduled for a different start date
+    cancel: Vec<i32>,
+    create: Vec<(ProvisioningStep, NaiveDate)>,
+}
+
+/// Work out the changes to a user's schedule. A new hire without any pending steps is scheduled
+/// every step that has not already completed. A user whose pending steps were scheduled for a
+/// different start date has those steps moved to the new start date, whether or not the new start
+/// date has passed.
+fn reschedule(existing: &[ProvisioningStepRecord], start_date: NaiveDate, is_new_hire: bool) -> Reschedule {
+    let pending = existing.iter().filter(|record| record.is_pending()).collect::<Vec<_>>();
+    let cancel = pending
+        .iter()
+        .filter(|record| record.start_date != start_date)
+        .map(|record| record.id)
+        .collect::<Vec<_>>();
+
+    if cancel.is_empty() && !(pending.is_empty() && is_new_hire) {
+        return Reschedule::default();
+    }
+
+    let create = ProvisioningStep::ALL
+        .into_iter()
+        .filter(|step| {
+            !existing.iter().any(|record| {
+                record.step == step.name()
+                    && (record.is_completed() || (record.is_pending() && record.start_date == start_date))
+            })
+        })
+        .map(|step| (step, step.due_on(start_date)))
+        .collect();
+
+    Reschedule { cancel, create }
+}
+
+/// Schedule the provisioning of a user that has not started yet, or move their schedule if their
+/// start date has changed. A user is a new hire until they either start or have a Google account.
+pub async fn schedule(db: &Database, company_id: i32, user: &User, today: NaiveDate) -> Result<()> {
+    let existing = ProvisioningStepRecord::for_user(db, company_id, &user.username).await?;
+    let is_new_hire = user.start_date > today && user.google_id.is_empty();
+    let changes = reschedule(&existing, user.start_date, is_new_hire);
+
+    if !changes.cancel.is_empty() {
+        let previous = existing
+            .iter()
+            .find(|record| changes.cancel.contains(&record.id))
+            .map(|record| record.start_date.to_string())
+            .unwrap_or_default();
+
+        ProvisioningStepRecord::cancel(
+            db,
+            &changes.cancel,
+            &format!("Start date changed from {} to {}", previous, user.start_date),
+        )
+        .await?;
+
+        info!(
+            "Start date of user {} changed from {} to {}. Rescheduling provisioning",
+            user.username, previous, user.start_date
+        );
+    }
+
+    if !changes.create.is_empty() {
+        let steps = changes
+            .create
+            .iter()
+            .map(|(step, due_on)| NewProvisioningStep {
+                cio_company_id: company_id,
+                username: user.username.to_string(),
+                step: step.name().to_string(),
+                start_date: user.start_date,
+                due_on: *due_on,
+                status: ProvisioningStepStatus::Pending.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(provisioning_steps::table)
+            .values(&steps)
+            .execute_async(db.pool())
+            .await?;
+
+        info!(
+            "Scheduled {} provisioning steps for user {} starting on {}",
+            steps.len(),
+            user.username,
+            user.start_date
+        );
+    }
+
+    Ok(())
+}
+
+/// Cancel the pending steps of a user that was removed from the configs
+pub async fn cancel_user(db: &Database, company_id: i32, username: &str) -> Result<()> {
+    let ids = ProvisioningStepRecord::pending(db, company_id, Some(username))
+        .await?
+        .iter()
+        .map(|record| record.id)
+        .collect::<Vec<_>>();
+
+    if !ids.is_empty() {
+        ProvisioningStepRecord::cancel(db, &ids, "User was removed from the configs").await?;
+        info!(
+            "Cancelled provisioning of user {} as they were removed from the configs",
+            username
+        );
+    }
+
+    Ok(())
+}
+
+/// The users and services that the configs sync should leave alone, as creating the user's account
+/// in the service, or the Google account it is created for, is a step that is still pending
+pub async fn held_services(db: &Database, company: &Company) -> Result<Vec<(String, ExternalServices)>> {
+    Ok(ProvisioningStepRecord::pending(db, company.id, None)
+        .await?
+        .into_iter()
+        .filter_map(|record| ProvisioningStep::from_name(&record.step).map(|step| (record.username, step)))
+        .flat_map(|(username, step)| {
+            step.held_services(company)
+                .into_iter()
+                .map(move |service| (username.to_string(), service))
+        })
+        .collect())
+}
+
+/// Whether the user's welcome email is sent by their schedule, rather than when their accounts are
+/// created
+pub async fn schedules_welcome(db: &Database, company_id: i32, username: &str) -> Result<bool> {
+    Ok(ProvisioningStepRecord::for_user(db, company_id, username)
+        .await?
+        .iter()
+        .any(|record| {
+            record.step == ProvisioningStep::SendWelcome.name()
+                && record.status != ProvisioningStepStatus::Cancelled.to_string()
+        }))
+}
+
+/// The earliest step of the same user that has yet to run successfully, and that comes before the
+/// step of `record`. Later steps build on what the earlier steps of a user do, such as the welcome
+/// email being sent to the Google account, so they wait for the earlier steps to complete.
+fn waiting_on<'a>(
+    outstanding: &'a [ProvisioningStepRecord],
+    record: &ProvisioningStepRecord,
+) -> Option<&'a ProvisioningStepRecord> {
+    outstanding
+        .iter()
+        .filter(|other| {
+            other.username == record.username
+                && ProvisioningStepRecord::position(other) < ProvisioningStepRecord::position(record)
+        })
+        .min_by_key(|other| ProvisioningStepRecord::position(other))
+}
+
+/// Run every step that is due, and return the steps that were run as they were before running
+/// them. A step that fails is attempted again on the next run, and the later steps of the user wait
+/// until it succeeds. When running as a dry run the steps that would run are only reported.
+pub async fn run_due_steps(
+    db: &Database,
+    company: &Company,
+    config: &AppConfig,
+    dry_run: bool,
+) -> Result<Vec<ProvisioningStepRecord>> {
+    let today = Utc::now().date_naive();
+    let mut outstanding = ProvisioningStepRecord::pending(db, company.id, None).await?;
+    let due = outstanding
+        .iter()
+        .filter(|record| record.due_on <= today)
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut ran = vec![];
+
+    for record in due {
+        if let Some(earlier) = waiting_on(&outstanding, &record) {
+            info!(
+                "Provisioning step {} for user {} is waiting on step {}, which is {}",
+                record.step, record.username, earlier.step, earlier.status
+            );
+            continue;
+        }
+
+        if dry_run {
+            info!(
+                "Would run provisioning step {} for user {}, due on {}",
+                record.step, record.username, record.due_on
+            );
+            outstanding.retain(|other| other.id != record.id);
+            ran.push(record);
+            continue;
+        }
+
+        let step = match ProvisioningStep::from_name(&record.step) {
+            Some(step) => step,
+            None => {
+                warn!("Skipping unknown provisioning step {}", record.step);
+                continue;
+            }
+        };
+
+        match run_step(db, company, config, &record.username, step).await {
+            Ok(detail) => {
+                info!(
+                    "Ran provisioning step {} for user {}. detail: {}",
+                    record.step, record.username, detail
+                );
+                record.complete(db, &detail).await?;
+                outstanding.retain(|other| other.id != record.id);
+            }
+            Err(err) => {
+                warn!(
+                    "Provisioning step {} failed for user {}. err: {:?}",
+                    record.step, record.username, err
+                );
+                record.fail(db, &err).await?;
+            }
+        }
+
+        ran.push(record);
+    }
+
+    Ok(ran)
+}
+
+async fn run_step(
+    db: &Database,
+    company: &Company,
+    config: &AppConfig,
+    username: &str,
+    step: ProvisioningStep,
+) -> Result<String> {
+    let mut user = User::get_from_db(db, company.id, username.to_string())
+        .await
+        .ok_or_else(|| anyhow!("User {} does not exist in the database", username))?;
+
+    if let Some(service) = step.service(company) {
+        let writer = service.get_provider_writer(db, company).await?;
+        ensure_account(db, company, config, writer.as_ref(), &service, &mut user).await?;
+
+        return Ok(format!("Provisioned {} account", service));
+    }
+
+    match step {
+        ProvisioningStep::SendWelcome => {
+            if user.is_consultant() {
+                user.send_email_new_consultant(db).await?;
+            } else {
+                user.send_email_new_user(db).await?;
+            }
+
+            Ok(format!("Sent welcome email to {}", user.recovery_email))
+        }
+        ProvisioningStep::ShipSwag => {
+            if user.home_address_formatted.is_empty() {
+                return Ok("User does not have a home address to ship to".to_string());
+            }
+
+            user.create_shipment_to_home_address(db).await?;
+
+            Ok(format!("Created shipment to {}", user.home_address_formatted))
+        }
+        _ => Err(anyhow!("Provisioning step {} does not have a service", step.name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, day).unwrap()
+    }
+
+    fn record(
+        id: i32,
+        step: ProvisioningStep,
+        start_date: NaiveDate,
+        status: ProvisioningStepStatus,
+    ) -> ProvisioningStepRecord {
+        ProvisioningStepRecord {
+            id,
+            cio_company_id: 1,
+            username: "jdoe".to_string(),
+            step: step.name().to_string(),
+            start_date,
+            due_on: step.due_on(start_date),
+            status: status.to_string(),
+            detail: String::new(),
+            attempts: 0,
+            last_error: String::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn test_later_steps_wait_on_earlier_steps() {
+        let outstanding = vec![
+            record(
+                1,
+                ProvisioningStep::CreateGoogle,
+                date(15),
+                ProvisioningStepStatus::Failed,
+            ),
+            record(
+                2,
+                ProvisioningStep::SendWelcome,
+                date(15),
+                ProvisioningStepStatus::Pending,
+            ),
+            record(3, ProvisioningStep::ShipSwag, date(15), ProvisioningStepStatus::Pending),
+        ];
+
+        assert_eq!(None, waiting_on(&outstanding, &outstanding[0]));
+        assert_eq!(Some(&outstanding[0]), waiting_on(&outstanding, &outstanding[1]));
+        assert_eq!(Some(&outstanding[0]), waiting_on(&outstanding, &outstanding[2]));
+
+        // Steps of other users do not wait
+        let mut other = record(
+            4,
+            ProvisioningStep::SendWelcome,
+            date(15),
+            ProvisioningStepStatus::Pending,
+        );
+        other.username = "asmith".to_string();
+        assert_eq!(None, waiting_on(&outstanding, &other));
+
+        // Once the earlier steps have run the later steps are free to run
+        assert_eq!(None, waiting_on(&outstanding[2..], &outstanding[2]));
+        assert_eq!(Some(&outstanding[1]), waiting_on(&outstanding[1..], &outstanding[2]));
+    }
+
+    #[test]
+    fn test_due_dates() {
+        let start = date(15);
+
+        assert_eq!(date(8), ProvisioningStep::CreateGoogle.due_on(start));
+        assert_eq!(date(10), ProvisioningStep::SendWelcome.due_on(start));
+        assert_eq!(date(10), ProvisioningStep::ShipSwag.due_on(start));
+        assert_eq!(start, ProvisioningStep::GrantGitHub.due_on(start));
+        assert_eq!(start, ProvisioningStep::GrantOkta.due_on(start));
+        assert_eq!(start, ProvisioningStep::ActivateRamp.due_on(start));
+    }
+
+    #[test]
+    fn test_google_is_created_through_okta() {
+        let mut company = crate::companies::tests::mock_company();
+
+        assert_eq!(
+            Some(ExternalServices::Google),
+            ProvisioningStep::CreateGoogle.service(&company)
+        );
+        assert_eq!(
+            vec![
+                ExternalServices::Google,
+                ExternalServices::Zoom,
+                ExternalServices::Slack,
+                ExternalServices::Airtable,
+            ],
+            ProvisioningStep::CreateGoogle.held_services(&company)
+        );
+
+        company.okta_domain = "super-computer".to_string();
+        company.okta_api_key = "key".to_string();
+
+        assert_eq!(
+            Some(ExternalServices::Okta),
+            ProvisioningStep::CreateGoogle.service(&company)
+        );
+        assert_eq!(
+            vec![
+                ExternalServices::Okta,
+                ExternalServices::Google,
+                ExternalServices::Zoom,
+                ExternalServices::Slack,
+                ExternalServices::Airtable,
+            ],
+            ProvisioningStep::CreateGoogle.held_services(&company)
+        );
+        assert_eq!(
+            vec![ExternalServices::GitHub],
+            ProvisioningStep::GrantGitHub.held_services(&company)
+        );
+        assert!(ProvisioningStep::SendWelcome.held_services(&company).is_empty());
+    }
+
+    #[test]
+    fn test_schedule_new_hire() {
+        let changes = reschedule(&[], date(15), true);
+
+        assert!(changes.cancel.is_empty());
+        assert_eq!(
+            ProvisioningStep::ALL
+                .iter()
+                .map(|step| (*step, step.due_on(date(15))))
+                .collect::<Vec<_>>(),
+            changes.create
+        );
+
+        // Users that have started, or already have accounts, are provisioned by the configs sync
+        assert_eq!(Reschedule::default(), reschedule(&[], date(15), false));
+    }
+
+    #[test]
+    fn test_schedule_unchanged() {
+        let existing = vec![
+            record(
+                1,
+                ProvisioningStep::CreateGoogle,
+                date(15),
+                ProvisioningStepStatus::Completed,
+            ),
+            record(
+                2,
+                ProvisioningStep::SendWelcome,
+                date(15),
+                ProvisioningStepStatus::Failed,
+            ),
+            record(
+                3,
+                ProvisioningStep::GrantGitHub,
+                date(15),
+                ProvisioningStepStatus::Pending,
+            ),
+        ];
+
+        assert_eq!(Reschedule::default(), reschedule(&existing, date(15), false));
+    }
+
+    #[test]
+    fn test_schedule_start_date_changed() {
+        let existing = vec![
+            record(
+                1,
+                ProvisioningStep::CreateGoogle,
+                date(15),
+                ProvisioningStepStatus::Completed,
+            ),
+            record(
+                2,
+                ProvisioningStep::SendWelcome,
+                date(15),
+                ProvisioningStepStatus::Completed,
+            ),
+            record(3, ProvisioningStep::ShipSwag, date(15), ProvisioningStepStatus::Failed),
+            record(
+                4,
+                ProvisioningStep::GrantGitHub,
+                date(15),
+                ProvisioningStepStatus::Pending,
+            ),
+            record(
+                5,
+                ProvisioningStep::GrantOkta,
+                date(15),
+                ProvisioningStepStatus::Pending,
+            ),
+            record(
+                6,
+                ProvisioningStep::ActivateRamp,
+                date(15),
+                ProvisioningStepStatus::Pending,
+            ),
+        ];
+
+        let changes = reschedule(&existing, date(22), false);
+
+        assert_eq!(vec![3, 4, 5, 6], changes.cancel);
+        assert_eq!(
+            vec![
+                (ProvisioningStep::ShipSwag, date(17)),
+                (ProvisioningStep::GrantGitHub, date(22)),
+                (ProvisioningStep::GrantOkta, date(22)),
+                (ProvisioningStep::ActivateRamp, date(22)),
+            ],
+            changes.create
+        );
+    }
+
+    #[test]
+    fn test_schedule_after_cancellation() {
+        // A new hire that was removed from the configs and then added back is scheduled again
+        let existing = vec![
+            record(
+                1,
+                ProvisioningStep::CreateGoogle,
+                date(15),
+                ProvisioningStepStatus::Cancelled,
+            ),
+            record(
+                2,
+                ProvisioningStep::SendWelcome,
+                date(15),
+                ProvisioningStepStatus::Cancelled,
+            ),
+        ];
+
+        let changes = reschedule(&existing, date(15), true);
+
+        assert!(changes.cancel.is_empty());
+        assert_eq!(ProvisioningStep::ALL.len(), changes.create.len());
+    }
+}
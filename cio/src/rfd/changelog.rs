@@ -1,54 +1,428 @@
 use anyhow::Result;
-use chrono::{Duration, Utc};
-use sendgrid_api::{traits::MailOps, Client as SendGrid};
+use chrono::{DateTime, Duration, Utc};
+use handlebars::Handlebars;
+use log::info;
+use sendgrid_api::{traits::MailOps, types::PostMailSendRequest, Client as SendGrid};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
 
-use super::RFDs;
-use crate::companies::Company;
-use crate::db::Database;
+use super::{GitHubRFDRepo, RFDNumber, RFDs, RFD};
+use crate::{
+    companies::Company,
+    db::Database,
+    sandbox::{Sandbox, SandboxService},
+};
 
-/// Create a changelog email for the RFDs.
-pub async fn send_rfd_changelog(db: &Database, company: &Company) -> Result<()> {
-    let rfds = RFDs::get_from_db(db, company.id).await?;
+/// A summary of the changes made to RFDs over a window of time, grouped by RFD
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Changelog {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub entries: Vec<ChangelogEntry>,
+}
 
-    if rfds.0.is_empty() {
-        // Return early.
-        return Ok(());
+/// The changes made to a single RFD within the changelog window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangelogEntry {
+    pub number: i32,
+    pub name: String,
+    pub link: String,
+    pub state: String,
+    /// The state of the RFD at the start of the window. This is None if the RFD did not exist at
+    /// the start of the window or if the state could not be determined
+    pub previous_state: Option<String>,
+    /// Marks RFDs that were created within the window
+    pub is_new: bool,
+    /// The distinct commit authors that made changes within the window
+    pub authors: Vec<String>,
+    pub commits: Vec<ChangelogCommit>,
+}
+
+impl ChangelogEntry {
+    pub fn state_changed(&self) -> bool {
+        self.previous_state
+            .as_ref()
+            .map(|previous| previous != &self.state)
+            .unwrap_or(false)
     }
+}
 
-    let github = company.authenticate_github()?;
-    let seven_days_ago = Utc::now() - Duration::days(7);
-    let week_format = format!(
-        "from {} to {}",
-        seven_days_ago.format("%m-%d-%Y"),
-        Utc::now().format("%m-%d-%Y")
-    );
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangelogCommit {
+    pub message: String,
+    pub author: Option<String>,
+    pub sha: String,
+    pub url: String,
+}
+
+impl Changelog {
+    /// Collect the changes made to all of the RFDs of a company over the provided window
+    pub async fn build(db: &Database, company: &Company, window: Duration) -> Result<Self> {
+        let until = Utc::now();
+        let since = until - window;
+
+        let github = Arc::new(company.authenticate_github()?);
+        let repo = GitHubRFDRepo::new_with_client(company, github).await?;
 
-    let mut changelog = format!("Changes to RFDs for the week {}:\n", week_format);
+        let mut entries = vec![];
 
-    // Iterate over the RFDs.
-    for rfd in rfds {
-        let changes = rfd.get_weekly_changelog(&github, seven_days_ago, company).await?;
-        let url = format!("https://rfd.shared.oxide.computer/rfd/{}", rfd.number_string);
-        if !changes.is_empty() {
-            changelog += &format!("\n{} {}\n{}", rfd.name, url, changes);
+        for rfd in RFDs::get_from_db(db, company.id).await? {
+            if let Some(entry) = Self::build_entry(&repo, &rfd, since, until).await? {
+                entries.push(entry);
+            }
         }
+
+        Ok(Self { since, until, entries })
     }
 
-    // Initialize the SendGrid clVient.
-    let sendgrid_client = SendGrid::new_from_env();
+    async fn build_entry(
+        repo: &GitHubRFDRepo,
+        rfd: &RFD,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Option<ChangelogEntry>> {
+        let number: RFDNumber = rfd.number.into();
+
+        let branch = if rfd.link.contains(&format!("/{}/", repo.default_branch)) {
+            repo.default_branch.clone()
+        } else {
+            number.as_number_string()
+        };
+
+        let branch = repo.branch(branch);
+        let github = branch.client();
+        let path = format!("{}/", number.repo_directory());
+
+        let commits = match github
+            .repos()
+            .list_all_commits(
+                &repo.owner,
+                &repo.repo,
+                &branch.branch,
+                &path,
+                "",
+                Some(since),
+                Some(until),
+            )
+            .await
+        {
+            Ok(response) => response.body,
+            Err(err) => {
+                // Branches for RFDs that have since been merged or deleted will fail to list
+                info!(
+                    "Failed to list commits for RFD {} on {}: {}",
+                    rfd.number, branch.branch, err
+                );
+                vec![]
+            }
+        };
+
+        if commits.is_empty() {
+            return Ok(None);
+        }
+
+        let commits = commits
+            .into_iter()
+            .filter_map(|commit| {
+                commit.commit.message.lines().next().map(|message| ChangelogCommit {
+                    message: message.to_string(),
+                    author: commit.author.map(|author| author.login),
+                    url: format!("https://github.com/{}/{}/commit/{}", repo.owner, repo.repo, commit.sha),
+                    sha: commit.sha,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut authors = commits
+            .iter()
+            .filter_map(|commit| commit.author.clone())
+            .collect::<Vec<_>>();
+        authors.sort();
+        authors.dedup();
+
+        // Find the last commit made before the window started to determine what the RFD looked
+        // like at the start of the window. If there is no such commit, then the RFD is new
+        let previous_commit = github
+            .repos()
+            .list_commits(
+                &repo.owner,
+                &repo.repo,
+                &branch.branch,
+                &path,
+                "",
+                None,
+                Some(since),
+                1,
+                0,
+            )
+            .await
+            .map(|response| response.body.into_iter().next())
+            .unwrap_or_default();
+
+        let (is_new, previous_state) = match previous_commit {
+            Some(previous) => {
+                let previous_state = repo
+                    .branch(previous.sha)
+                    .get_readme_contents(&number)
+                    .await
+                    .ok()
+                    .and_then(|readme| readme.content.metadata().state)
+                    .map(|state| state.to_string());
+
+                (false, previous_state)
+            }
+            None => (true, None),
+        };
 
-    // Send the message.
-    sendgrid_client
-        .mail_send()
-        .send_plain_text(
-            &format!("RFD changelog for the week from {}", week_format),
-            &changelog,
-            &[format!("all@{}", company.gsuite_domain)],
-            &[],
-            &[],
-            &format!("rfds@{}", company.gsuite_domain),
+        Ok(Some(ChangelogEntry {
+            number: rfd.number,
+            name: rfd.name.clone(),
+            link: rfd.rendered_link.clone(),
+            state: rfd.state.clone(),
+            previous_state,
+            is_new,
+            authors,
+            commits,
+        }))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn period(&self) -> String {
+        format!(
+            "from {} to {}",
+            self.since.format("%m-%d-%Y"),
+            self.until.format("%m-%d-%Y")
         )
-        .await?;
+    }
+
+    fn template_data(&self) -> serde_json::Value {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "entry": entry,
+                    "state_changed": entry.state_changed(),
+                    "commit_count": entry.commits.len(),
+                    "authors": entry.authors.join(", "),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "period": self.period(),
+            "new_count": self.entries.iter().filter(|entry| entry.is_new).count(),
+            "updated_count": self.entries.len(),
+            "entries": entries,
+        })
+    }
+
+    pub fn to_text(&self) -> Result<String> {
+        let mut handlebars = Handlebars::new();
+
+        // We are rendering plain text so we need to disable HTML escaping
+        handlebars.register_escape_fn(handlebars::no_escape);
+
+        Ok(handlebars.render_template(TEXT_TEMPLATE, &self.template_data())?)
+    }
+
+    pub fn to_html(&self) -> Result<String> {
+        Ok(Handlebars::new().render_template(HTML_TEMPLATE, &self.template_data())?)
+    }
+
+    pub fn to_email(&self, company: &Company) -> Result<ChangelogEmail> {
+        Ok(ChangelogEmail {
+            subject: format!("RFD changelog {}", self.period()),
+            text: self.to_text()?,
+            html: self.to_html()?,
+            to: company.changelog_recipients(),
+            from: company.changelog_sender(),
+        })
+    }
+}
+
+/// A rendered changelog that is ready to be sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangelogEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    pub to: Vec<String>,
+    pub from: String,
+}
+
+impl ChangelogEmail {
+    /// Send the changelog as a multipart email containing both the text and html versions
+    pub async fn send(&self) -> Result<()> {
+        send_email(&self.to, &self.from, &self.subject, &self.text, Some(&self.html)).await
+    }
+}
+
+/// Send an email via SendGrid. When html is supplied the email is sent as multipart containing both
+/// the text and html versions
+pub(super) async fn send_email(to: &[String], from: &str, subject: &str, text: &str, html: Option<&str>) -> Result<()> {
+    if Sandbox::intercept(
+        SandboxService::SendGrid,
+        format!("send \"{}\" from {} to {}", subject, from, to.join(", ")),
+    ) {
+        return Ok(());
+    }
+
+    let mail = SendGrid::new_from_env().mail_send();
+
+    let html = match html {
+        Some(html) => html,
+        None => {
+            mail.send_plain_text(subject, text, to, &[], &[], from).await?;
+            return Ok(());
+        }
+    };
+
+    // The plain text helper only supports a single content type, so multipart emails are built as a
+    // full mail send request instead
+    let request: PostMailSendRequest = serde_json::from_value(json!({
+        "personalizations": [{
+            "to": to.iter().map(|email| json!({ "email": email })).collect::<Vec<_>>(),
+        }],
+        "from": { "email": from },
+        "subject": subject,
+        "content": [
+            { "type": "text/plain", "value": text },
+            { "type": "text/html", "value": html },
+        ],
+    }))?;
+
+    mail.post(&request).await?;
 
     Ok(())
 }
+
+/// Create a changelog email for the RFDs. When running in dry run mode the rendered email is
+/// returned without being sent.
+pub async fn send_rfd_changelog(db: &Database, company: &Company, dry_run: bool) -> Result<Option<ChangelogEmail>> {
+    let changelog = Changelog::build(db, company, Duration::days(company.changelog_window_days())).await?;
+
+    if changelog.is_empty() {
+        info!("No RFD changes found {}. Skipping changelog", changelog.period());
+        return Ok(None);
+    }
+
+    let email = changelog.to_email(company)?;
+
+    if !dry_run {
+        email.send().await?;
+        info!("Sent RFD changelog to {:?}", email.to);
+    }
+
+    Ok(Some(email))
+}
+
+static TEXT_TEMPLATE: &str = r#"Changes to RFDs {{period}}: {{updated_count}} updated, {{new_count}} new
+{{#each entries}}
+
+{{this.entry.name}} {{this.entry.link}}
+{{#if this.entry.is_new}}	New RFD in {{this.entry.state}}
+{{/if}}{{#if this.state_changed}}	State changed from {{this.entry.previous_state}} to {{this.entry.state}}
+{{/if}}	{{this.commit_count}} commit(s){{#if this.authors}} by {{this.authors}}{{/if}}
+{{#each this.entry.commits}}	- "{{this.message}}"{{#if this.author}} by @{{this.author}}{{/if}}
+		{{this.url}}
+{{/each}}{{/each}}"#;
+
+static HTML_TEMPLATE: &str = r#"<html>
+<body>
+<p>Changes to RFDs {{period}}: {{updated_count}} updated, {{new_count}} new</p>
+{{#each entries}}
+<h3><a href="{{this.entry.link}}">{{this.entry.name}}</a></h3>
+<ul>
+{{#if this.entry.is_new}}<li>New RFD in <b>{{this.entry.state}}</b></li>
+{{/if}}{{#if this.state_changed}}<li>State changed from <b>{{this.entry.previous_state}}</b> to <b>{{this.entry.state}}</b></li>
+{{/if}}<li>{{this.commit_count}} commit(s){{#if this.authors}} by {{this.authors}}{{/if}}
+<ul>
+{{#each this.entry.commits}}<li><a href="{{this.url}}">{{this.message}}</a>{{#if this.author}} by @{{this.author}}{{/if}}</li>
+{{/each}}</ul>
+</li>
+</ul>
+{{/each}}
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn mock_changelog() -> Changelog {
+        Changelog {
+            since: Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap(),
+            until: Utc.with_ymd_and_hms(2023, 1, 8, 0, 0, 0).unwrap(),
+            entries: vec![
+                ChangelogEntry {
+                    number: 123,
+                    name: "RFD 123 Place".to_string(),
+                    link: "https://rfd.shared.oxide.computer/rfd/0123".to_string(),
+                    state: "published".to_string(),
+                    previous_state: Some("discussion".to_string()),
+                    is_new: false,
+                    authors: vec!["first".to_string()],
+                    commits: vec![ChangelogCommit {
+                        message: "Publish <RFD>".to_string(),
+                        author: Some("first".to_string()),
+                        sha: "abc".to_string(),
+                        url: "https://github.com/company/rfd/commit/abc".to_string(),
+                    }],
+                },
+                ChangelogEntry {
+                    number: 124,
+                    name: "RFD 124 New Place".to_string(),
+                    link: "https://rfd.shared.oxide.computer/rfd/0124".to_string(),
+                    state: "ideation".to_string(),
+                    previous_state: None,
+                    is_new: true,
+                    authors: vec![],
+                    commits: vec![ChangelogCommit {
+                        message: "Start".to_string(),
+                        author: None,
+                        sha: "def".to_string(),
+                        url: "https://github.com/company/rfd/commit/def".to_string(),
+                    }],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_renders_text_changelog() {
+        let expected = r#"Changes to RFDs from 01-01-2023 to 01-08-2023: 2 updated, 1 new
+
+RFD 123 Place https://rfd.shared.oxide.computer/rfd/0123
+	State changed from discussion to published
+	1 commit(s) by first
+	- "Publish <RFD>" by @first
+		https://github.com/company/rfd/commit/abc
+
+RFD 124 New Place https://rfd.shared.oxide.computer/rfd/0124
+	New RFD in ideation
+	1 commit(s)
+	- "Start"
+		https://github.com/company/rfd/commit/def
+"#;
+
+        assert_eq!(expected, mock_changelog().to_text().unwrap());
+    }
+
+    #[test]
+    fn test_renders_escaped_html_changelog() {
+        let html = mock_changelog().to_html().unwrap();
+
+        assert!(html.contains(r#"<h3><a href="https://rfd.shared.oxide.computer/rfd/0123">RFD 123 Place</a></h3>"#));
+        assert!(html.contains("State changed from <b>discussion</b> to <b>published</b>"));
+        assert!(html.contains("Publish &lt;RFD&gt;"));
+        assert!(html.contains("New RFD in <b>ideation</b>"));
+    }
+}
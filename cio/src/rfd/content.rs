@@ -4,6 +4,7 @@ use log::info;
 use regex::Regex;
 use std::{
     borrow::Cow,
+    collections::BTreeSet,
     env, fmt, fs,
     path::{Path, PathBuf},
     process::Command,
@@ -11,8 +12,16 @@ use std::{
 };
 use uuid::Uuid;
 
-use super::{GitHubRFDBranch, RFDNumber, RFDPdf};
-use crate::utils::{decode_base64, write_file};
+use super::{
+    metadata::{RFDHeaderFormat, RFDMetadata},
+    references::find_references,
+    render::{render_html, Unsupported},
+    GitHubRFDBranch, RFDNumber, RFDPdf,
+};
+use crate::{
+    features::Features,
+    utils::{decode_base64, write_file},
+};
 
 // TODO: RFDNumber should probably be stored with the content as it doesn't parsing content with a
 // mismatched RFDNumber is pretty nonsensical.
@@ -100,11 +109,12 @@ impl<'a> RFDContent<'a> {
     }
 
     /// Generate an HTML string by combining RFD contents with static resources that are stored for
-    /// a given RFD number on a specific branch
-    pub async fn to_html(&self, number: &RFDNumber, branch: &GitHubRFDBranch) -> Result<RFDHtml> {
+    /// a given RFD number on a specific branch. Links to other RFDs are rewritten to point at the
+    /// rendered RFD host
+    pub async fn to_html(&self, number: &RFDNumber, branch: &GitHubRFDBranch, rendered_host: &str) -> Result<RFDHtml> {
         match self {
-            Self::Asciidoc(adoc) => adoc.to_html(number, branch).await,
-            Self::Markdown(md) => md.to_html(number),
+            Self::Asciidoc(adoc) => adoc.to_html(number, branch, rendered_host).await,
+            Self::Markdown(md) => md.to_html(number, rendered_host),
         }
     }
 
@@ -163,135 +173,17 @@ impl<'a> RFDContent<'a> {
         *content = content.replacen(&replacement, &format!("{}state: {}", pre, state.trim()), 1);
     }
 
-    /// Extract the title from the internal content
-    pub fn get_title(&self) -> String {
-        let content = self.raw();
-
-        let mut re = Regex::new(r"(?m)^[=# ]+(RFD .*$)").unwrap();
-        match re.find(content) {
-            Some(v) => {
-                // TODO: find less horrible way to do this.
-                let trimmed = v
-                    .as_str()
-                    .replace("RFD", "")
-                    .replace("# ", "")
-                    .replace("= ", " ")
-                    .trim()
-                    .to_string();
-
-                let (_, s) = trimmed.split_once(' ').unwrap();
-                s.to_string()
-            }
-            None => {
-                // There is no "RFD" in our title. This is the case for RFD 31.
-                re = Regex::new(r"(?m)(^= .*$)").unwrap();
-                let c = re.find(content);
-
-                if let Some(results) = c {
-                    results
-                        .as_str()
-                        .replace("RFD", "")
-                        .replace("# ", "")
-                        .replace("= ", " ")
-                        .trim()
-                        .to_string()
-                } else {
-                    // If we couldn't find anything assume we have no title.
-                    // This was related to this error in Sentry:
-                    // https://sentry.io/organizations/oxide-computer-company/issues/2701636092/?project=-1
-                    String::new()
-                }
-            }
-        }
-    }
-
-    pub fn get_labels(&self) -> Vec<String> {
-        let re = Regex::new(r"(?m)(labels:.*$)").unwrap();
-
-        match re.find(self.raw()) {
-            Some(v) => v
-                .as_str()
-                .replace("labels:", "")
-                .trim()
-                .to_string()
-                .split(';')
-                .map(|label| label.to_string())
-                .collect::<Vec<_>>(),
-            None => Default::default(),
-        }
-    }
-
-    /// Get the state value stored within the document. If one can not be found, then an empty
-    /// string is returned
-    pub fn get_state(&self) -> String {
-        let re = Regex::new(r"(?m)(state:.*$)").unwrap();
-
-        // TODO: This should return Option<&str>
-        match re.find(self.raw()) {
-            Some(v) => v.as_str().replace("state:", "").trim().to_string(),
-            None => Default::default(),
+    /// Parse the metadata stored in the header of the document
+    pub fn metadata(&self) -> RFDMetadata {
+        match self {
+            Self::Asciidoc(adoc) => RFDMetadata::parse(&adoc.content, RFDHeaderFormat::Asciidoc),
+            Self::Markdown(md) => RFDMetadata::parse(&md.content, RFDHeaderFormat::Markdown),
         }
     }
 
-    /// Get the discussion link stored within the document. If one can not be found, then an empty
-    /// string is returned
-    pub fn get_discussion(&self) -> String {
-        let re = Regex::new(r"(?m)(discussion:.*$)").unwrap();
-
-        // TODO: This should return Option<&str>
-        match re.find(self.raw()) {
-            Some(v) => {
-                let d = v.as_str().replace("discussion:", "").trim().to_string();
-
-                if !d.starts_with("http") {
-                    Default::default()
-                } else {
-                    d
-                }
-            }
-            None => Default::default(),
-        }
-    }
-
-    /// Get the authors line stored within the document. The returned string may contain multiple
-    /// names. If none can be found, then and empty string is returned
-    pub fn get_authors(&self) -> String {
-        match self {
-            Self::Asciidoc(RFDAsciidoc { content, .. }) => {
-                // We must have asciidoc content.
-                // We want to find the line under the first "=" line (which is the title), authors
-                // is under that.
-                let re = Regex::new(r"(?m:^[#=].*$)[\n\r](?m)(.*$)").unwrap();
-                match re.find(content) {
-                    Some(v) => {
-                        let val = v.as_str().trim().to_string();
-                        let parts: Vec<&str> = val.split('\n').collect();
-                        if parts.len() < 2 {
-                            Default::default()
-                        } else {
-                            let mut authors = parts[1].to_string();
-                            if authors == "{authors}" {
-                                // Do the traditional check.
-                                let re = Regex::new(r"(?m)(^:authors.*$)").unwrap();
-                                if let Some(v) = re.find(content) {
-                                    authors = v.as_str().replace(":authors:", "").trim().to_string();
-                                }
-                            }
-                            authors
-                        }
-                    }
-                    None => Default::default(),
-                }
-            }
-            Self::Markdown(RFDMarkdown { content }) => {
-                // TODO: make work w asciidoc.
-                let re = Regex::new(r"(?m)(^authors.*$)").unwrap();
-                match re.find(content) {
-                    Some(v) => v.as_str().replace("authors:", "").trim().to_string(),
-                    None => Default::default(),
-                }
-            }
-        }
+    /// Find the numbers of all of the RFDs that are mentioned within the document
+    pub fn references(&self) -> BTreeSet<i32> {
+        find_references(self.raw())
     }
 }
 
@@ -312,11 +204,19 @@ impl<'a> RFDAsciidoc<'a> {
 
     /// Generate an HTML string by combining RFD contents with static resources that are stored for
     /// a given RFD number on a specific branch
-    pub async fn to_html(&self, number: &RFDNumber, branch: &GitHubRFDBranch) -> Result<RFDHtml> {
-        self.download_images(number, branch).await?;
+    pub async fn to_html(&self, number: &RFDNumber, branch: &GitHubRFDBranch, rendered_host: &str) -> Result<RFDHtml> {
+        // Images do not need to be downloaded for natively rendered content as image references are
+        // rewritten to point at static storage
+        let output = match self.render_native(RFDOutputFormat::Html) {
+            Some(output) => output,
+            None => {
+                self.download_images(number, branch).await?;
+                self.parse(RFDOutputFormat::Html).await?
+            }
+        };
 
-        let mut html = RFDHtml(from_utf8(&self.parse(RFDOutputFormat::Html).await?)?.to_string());
-        html.clean_links(&number.as_number_string());
+        let mut html = RFDHtml(from_utf8(&output)?.to_string());
+        html.clean_links(&number.as_number_string(), rendered_host);
 
         Ok(html)
     }
@@ -341,6 +241,22 @@ impl<'a> RFDAsciidoc<'a> {
         })
     }
 
+    /// Attempt to render the content in-process when native rendering is enabled. Returns None when
+    /// the content needs to be handed off to asciidoctor instead
+    fn render_native(&self, format: RFDOutputFormat) -> Option<Vec<u8>> {
+        if !Features::is_enabled("RFD_NATIVE_RENDERING") {
+            return None;
+        }
+
+        match format.render(&self.content) {
+            Ok(output) => Some(output),
+            Err(err) => {
+                info!("[asciidoc] Falling back to asciidoctor: {}", err);
+                None
+            }
+        }
+    }
+
     /// Parse the asciidoc content and generate output data of the requested format. This relies on
     /// invoking an external asciidoctor binary to perform the actual transformation.
     async fn parse(&self, format: RFDOutputFormat) -> Result<Vec<u8>> {
@@ -455,6 +371,18 @@ pub enum RFDOutputFormat {
 }
 
 impl RFDOutputFormat {
+    /// Render content in-process without invoking asciidoctor. Only HTML output is supported, and
+    /// content that uses constructs outside of the common subset of Asciidoc that RFDs rely on is
+    /// rejected so that the caller can fall back to [`RFDOutputFormat::command`]
+    pub fn render(&self, content: &str) -> Result<Vec<u8>, RFDOutputError> {
+        match self {
+            Self::Html => render_html(content)
+                .map(String::into_bytes)
+                .map_err(|Unsupported(construct)| RFDOutputError::ContentNotSupported(construct)),
+            Self::Pdf => Err(RFDOutputError::FormatNotSupported(*self)),
+        }
+    }
+
     /// Generate a command for parsing asciidoctor content
     pub fn command(&self, working_dir: &PathBuf, file_path: &Path) -> Command {
         match self {
@@ -487,6 +415,7 @@ impl RFDOutputFormat {
 #[derive(Debug)]
 pub enum RFDOutputError {
     FormatNotSupported(RFDOutputFormat),
+    ContentNotSupported(String),
     Generic(anyhow::Error),
 }
 
@@ -494,6 +423,7 @@ impl fmt::Display for RFDOutputError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::FormatNotSupported(format) => write!(f, "{:?} format is not supported", format),
+            Self::ContentNotSupported(construct) => write!(f, "Content contains unsupported {}", construct),
             Self::Generic(inner) => write!(f, "Failed to generate RFD output due to {:?}", inner),
         }
     }
@@ -520,9 +450,9 @@ impl<'a> RFDMarkdown<'a> {
 
     /// Generate an HTML string by combining RFD contents with static resources that are stored for
     /// a given RFD number on a specific branch
-    pub fn to_html(&self, number: &RFDNumber) -> Result<RFDHtml> {
+    pub fn to_html(&self, number: &RFDNumber, rendered_host: &str) -> Result<RFDHtml> {
         let mut html = RFDHtml(markdown_to_html(&self.content, &ComrakOptions::default()));
-        html.clean_links(&number.as_number_string());
+        html.clean_links(&number.as_number_string(), rendered_host);
 
         Ok(html)
     }
@@ -533,8 +463,8 @@ pub struct RFDHtml(pub String);
 impl RFDHtml {
     /// Replaces link relative to the document with links relative to the root of the RFD repo.
     /// Also replaces urls of the form (<num>\d+).rfd.oxide.computer with urls that look like
-    /// rfd.shared.oxide.computer/rfd/$num where $num is left padded with 0s
-    pub fn clean_links(&mut self, num: &str) {
+    /// {rendered_host}/rfd/$num where $num is left padded with 0s
+    pub fn clean_links(&mut self, num: &str, rendered_host: &str) {
         let mut cleaned = self
             .0
             .replace(r#"href="\#"#, &format!(r#"href="/rfd/{}#"#, num))
@@ -548,19 +478,19 @@ impl RFDHtml {
 
         let mut re = Regex::new(r"https://(?P<num>[0-9]).rfd.oxide.computer").unwrap();
         cleaned = re
-            .replace_all(&cleaned, "https://rfd.shared.oxide.computer/rfd/000$num")
+            .replace_all(&cleaned, format!("https://{}/rfd/000$num", rendered_host))
             .to_string();
         re = Regex::new(r"https://(?P<num>[0-9][0-9]).rfd.oxide.computer").unwrap();
         cleaned = re
-            .replace_all(&cleaned, "https://rfd.shared.oxide.computer/rfd/00$num")
+            .replace_all(&cleaned, format!("https://{}/rfd/00$num", rendered_host))
             .to_string();
         re = Regex::new(r"https://(?P<num>[0-9][0-9][0-9]).rfd.oxide.computer").unwrap();
         cleaned = re
-            .replace_all(&cleaned, "https://rfd.shared.oxide.computer/rfd/0$num")
+            .replace_all(&cleaned, format!("https://{}/rfd/0$num", rendered_host))
             .to_string();
         re = Regex::new(r"https://(?P<num>[0-9][0-9][0-9][0-9]).rfd.oxide.computer").unwrap();
         cleaned = re
-            .replace_all(&cleaned, "https://rfd.shared.oxide.computer/rfd/$num")
+            .replace_all(&cleaned, format!("https://{}/rfd/$num", rendered_host))
             .to_string();
 
         self.0 = cleaned
@@ -572,6 +502,7 @@ impl RFDHtml {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rfd::RFDState;
 
     #[test]
     fn test_inspects_content_for_asciidoc() {
@@ -648,7 +579,7 @@ notreallystate: discussion
 
         let mut html = RFDHtml(content.to_string());
 
-        html.clean_links("0032");
+        html.clean_links("0032", "rfd.shared.oxide.computer");
 
         let expected = r#"https://rfd.shared.oxide.computer/rfd/0003
         https://rfd.shared.oxide.computer/rfd/0041
@@ -676,7 +607,7 @@ authors: things, joe
 dsfsdf
 sdf
 authors: nope"#;
-        let authors = RFDContent::new_markdown(content).get_authors();
+        let authors = RFDContent::new_markdown(content).metadata().authors_line();
         let expected = "things, joe".to_string();
         assert_eq!(expected, authors);
     }
@@ -689,7 +620,7 @@ things, joe
 dsfsdf
 sdf
 :authors: nope"#;
-        let authors = RFDContent::new_markdown(content).get_authors();
+        let authors = RFDContent::new_markdown(content).metadata().authors_line();
         let expected = "".to_string();
         assert_eq!(expected, authors);
     }
@@ -702,7 +633,7 @@ things <things@email.com>, joe <joe@email.com>
 dsfsdf
 sdf
 authors: nope"#;
-        let authors = RFDContent::new_asciidoc(content).get_authors();
+        let authors = RFDContent::new_asciidoc(content).metadata().authors_line();
         let expected = r#"things <things@email.com>, joe <joe@email.com>"#.to_string();
         assert_eq!(expected, authors);
     }
@@ -714,7 +645,7 @@ authors: nope"#;
 {authors}
 dsfsdf
 sdf"#;
-        let authors = RFDContent::new_asciidoc(content).get_authors();
+        let authors = RFDContent::new_asciidoc(content).metadata().authors_line();
         let expected = r#"Jess <jess@thing.com>"#.to_string();
         assert_eq!(expected, authors);
     }
@@ -732,7 +663,7 @@ sdf"#;
 * https://company.com[link to company] - An external reference
 "#;
 
-        let authors = RFDContent::new_asciidoc(content).get_authors();
+        let authors = RFDContent::new_asciidoc(content).metadata().authors_line();
         let expected = r#"Author One <one@company.com>, Author Two <two@company.com>"#.to_string();
         assert_eq!(expected, authors);
     }
@@ -747,8 +678,8 @@ state: discussion
 dsfsdf
 sdf
 authors: nope"#;
-        let state = RFDContent::new_markdown(content).get_state();
-        let expected = "discussion".to_string();
+        let state = RFDContent::new_markdown(content).metadata().state;
+        let expected = Some(RFDState::Discussion);
         assert_eq!(expected, state);
     }
 
@@ -760,8 +691,8 @@ authors: nope"#;
 dsfsdf
 sdf
 :state: nope"#;
-        let state = RFDContent::new_asciidoc(content).get_state();
-        let expected = "prediscussion".to_string();
+        let state = RFDContent::new_asciidoc(content).metadata().state;
+        let expected = Some(RFDState::Prediscussion);
         assert_eq!(expected, state);
     }
 
@@ -775,8 +706,8 @@ discussion: https://github.com/org/repo/pulls/1
 dsfsdf
 sdf
 authors: nope"#;
-        let discussion = RFDContent::new_markdown(content).get_discussion();
-        let expected = "https://github.com/org/repo/pulls/1".to_string();
+        let discussion = RFDContent::new_markdown(content).metadata().discussion;
+        let expected = Some("https://github.com/org/repo/pulls/1".to_string());
         assert_eq!(expected, discussion);
     }
 
@@ -788,8 +719,8 @@ authors: nope"#;
 dsfsdf
 sdf
 :discussion: nope"#;
-        let discussion = RFDContent::new_asciidoc(content).get_discussion();
-        let expected = "https://github.com/org/repo/pulls/1".to_string();
+        let discussion = RFDContent::new_asciidoc(content).metadata().discussion;
+        let expected = Some("https://github.com/org/repo/pulls/1".to_string());
         assert_eq!(expected, discussion);
     }
 
@@ -945,7 +876,7 @@ sdf
 authors: nope"#;
         let rfd = RFDContent::new_markdown(content);
         let expected = "Identity and Access Management (IAM)".to_string();
-        assert_eq!(expected, rfd.get_title());
+        assert_eq!(expected, rfd.metadata().title);
     }
 
     #[test]
@@ -959,7 +890,7 @@ sdf
 :title: nope"#;
         let rfd = RFDContent::new_asciidoc(content);
         let expected = "Identity and Access Management (IAM)".to_string();
-        assert_eq!(expected, rfd.get_title());
+        assert_eq!(expected, rfd.metadata().title);
     }
 
     #[test]
@@ -974,7 +905,7 @@ sdf
 :title: nope"#;
         let rfd = RFDContent::new_asciidoc(content);
         let expected = "Identity and Access Management (IAM)".to_string();
-        assert_eq!(expected, rfd.get_title());
+        assert_eq!(expected, rfd.metadata().title);
     }
 
     #[test]
@@ -998,7 +929,7 @@ sdf
         let rfd = RFDContent::new_asciidoc(Cow::Borrowed(content));
         let expected = "This should be the title";
 
-        assert_eq!(expected, &rfd.get_title());
+        assert_eq!(expected, &rfd.metadata().title);
     }
 
     fn test_rfd_content() -> &'static str {
@@ -1041,19 +972,43 @@ in velit.
 "#
     }
 
+    fn test_rfd_html() -> &'static str {
+        "<h1>RFD 123 Place</h1>\n<div class=\"paragraph\">\n<p>Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nunc et dignissim nisi. Donec ut libero in\ndolor tempor aliquam quis quis nisl. Proin sit amet nunc in orci suscipit placerat. Mauris\npellentesque fringilla lacus id gravida. Donec in velit luctus, elementum mauris eu, pellentesque\nmassa. In lectus orci, vehicula at aliquet nec, elementum eu nisi. Vivamus viverra imperdiet\nmalesuada.</p>\n</div>\n<div class=\"olist arabic\">\n<ol class=\"arabic\">\n<li>\n<p>Suspendisse blandit sem ligula, ac luctus metus condimentum non. Fusce enim purus, tincidunt ut\ntortor eget, sollicitudin vestibulum sem. Proin eu velit orci.</p>\n</li>\n<li>\n<p>Proin eu finibus velit. Morbi eget blandit neque.</p>\n</li>\n</ol>\n</div>\n<div class=\"listingblock\">\n<div class=\"content\">\n<pre class=\"highlight\"><code class=\"language-mermaid\" data-lang=\"mermaid\">graph TD;\n    A--&gt;B;\n    A--&gt;C;\n    B--&gt;D;\n    C--&gt;D;</code></pre>\n</div>\n</div>\n<div class=\"olist arabic\">\n<ol class=\"arabic\">\n<li>\n<p>Maecenas molestie, quam nec lacinia porta, lectus turpis molestie quam, at fringilla neque ipsum\nin velit.</p>\n</li>\n<li>\n<p>Donec elementum luctus mauris.</p>\n</li>\n</ol>\n</div>\n"
+    }
+
     #[tokio::test]
     async fn test_asciidoc_to_html() {
         let _ = env_logger::builder().is_test(true).try_init();
 
         let rfd = RFDAsciidoc::new(Cow::Borrowed(test_rfd_content()));
-        let expected = "<h1>RFD 123 Place</h1>\n<div class=\"paragraph\">\n<p>Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nunc et dignissim nisi. Donec ut libero in\ndolor tempor aliquam quis quis nisl. Proin sit amet nunc in orci suscipit placerat. Mauris\npellentesque fringilla lacus id gravida. Donec in velit luctus, elementum mauris eu, pellentesque\nmassa. In lectus orci, vehicula at aliquet nec, elementum eu nisi. Vivamus viverra imperdiet\nmalesuada.</p>\n</div>\n<div class=\"olist arabic\">\n<ol class=\"arabic\">\n<li>\n<p>Suspendisse blandit sem ligula, ac luctus metus condimentum non. Fusce enim purus, tincidunt ut\ntortor eget, sollicitudin vestibulum sem. Proin eu velit orci.</p>\n</li>\n<li>\n<p>Proin eu finibus velit. Morbi eget blandit neque.</p>\n</li>\n</ol>\n</div>\n<div class=\"listingblock\">\n<div class=\"content\">\n<pre class=\"highlight\"><code class=\"language-mermaid\" data-lang=\"mermaid\">graph TD;\n    A--&gt;B;\n    A--&gt;C;\n    B--&gt;D;\n    C--&gt;D;</code></pre>\n</div>\n</div>\n<div class=\"olist arabic\">\n<ol class=\"arabic\">\n<li>\n<p>Maecenas molestie, quam nec lacinia porta, lectus turpis molestie quam, at fringilla neque ipsum\nin velit.</p>\n</li>\n<li>\n<p>Donec elementum luctus mauris.</p>\n</li>\n</ol>\n</div>\n";
 
         assert_eq!(
-            expected,
+            test_rfd_html(),
             from_utf8(&rfd.parse(RFDOutputFormat::Html).await.unwrap()).unwrap()
         );
     }
 
+    #[test]
+    fn test_asciidoc_native_html_matches_asciidoctor() {
+        let html = RFDOutputFormat::Html.render(test_rfd_content()).unwrap();
+
+        assert_eq!(test_rfd_html(), from_utf8(&html).unwrap());
+    }
+
+    #[test]
+    fn test_asciidoc_native_html_rejects_unsupported_content() {
+        let content = format!("{}\ninclude::other.adoc[]\n", test_rfd_content());
+
+        assert!(matches!(
+            RFDOutputFormat::Html.render(&content),
+            Err(RFDOutputError::ContentNotSupported(_))
+        ));
+        assert!(matches!(
+            RFDOutputFormat::Pdf.render(test_rfd_content()),
+            Err(RFDOutputError::FormatNotSupported(RFDOutputFormat::Pdf))
+        ));
+    }
+
     // TODO: Find a way to generate a reproducable PDF across systems
     #[ignore]
     #[tokio::test]
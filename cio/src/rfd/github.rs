@@ -304,6 +304,43 @@ impl GitHubRFDBranch {
         Ok(prs)
     }
 
+    pub async fn get_latest_commit(&self, rfd_number: &RFDNumber) -> Result<GitHubRFDCommit> {
+        let commits = self
+            .client
+            .repos()
+            .list_commits(
+                &self.owner,
+                &self.repo,
+                &self.branch,
+                &rfd_number.repo_directory(),
+                "",
+                None,
+                None,
+                0,
+                0,
+            )
+            .await?
+            .body;
+        let latest_commit = commits
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No commits found for branch {}", self.branch))?;
+
+        let author = match latest_commit.author {
+            Some(author) => author.login,
+            None => latest_commit
+                .commit
+                .author
+                .map(|author| author.name)
+                .unwrap_or_default(),
+        };
+
+        Ok(GitHubRFDCommit {
+            sha: latest_commit.sha,
+            author,
+        })
+    }
+
     pub async fn get_latest_commit_date(&self, rfd_number: &RFDNumber) -> Result<DateTime<Utc>> {
         let commits = self
             .client
@@ -353,6 +390,15 @@ impl PDFStorage for GitHubRFDBranch {
     }
 }
 
+/// The most recent commit that modified an RFD on a branch
+#[derive(Debug, Clone)]
+pub struct GitHubRFDCommit {
+    pub sha: String,
+    /// author is the GitHub login of the commit author, falling back to the name recorded in the
+    /// commit when the author is not linked to a GitHub account
+    pub author: String,
+}
+
 pub struct GitHubRFDReadme<'a> {
     pub content: RFDContent<'a>,
     pub sha: String,
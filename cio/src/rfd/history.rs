@@ -0,0 +1,64 @@
+use anyhow::Result;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Utc};
+use diesel::{ExpressionMethods, Insertable, QueryDsl, Queryable};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{db::Database, schema::rfd_state_changes};
+
+/// A record of an RFD moving from one state to another
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct RFDStateChange {
+    pub id: i32,
+    pub rfd_number: i32,
+    /// from_state is empty for the first recorded state of an RFD
+    pub from_state: String,
+    pub to_state: String,
+    /// changed_by is the GitHub login (or name) of the author of the commit that changed the state
+    pub changed_by: String,
+    /// sha is the commit that changed the state
+    pub sha: String,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = rfd_state_changes)]
+pub struct NewRFDStateChange {
+    pub rfd_number: i32,
+    pub from_state: String,
+    pub to_state: String,
+    pub changed_by: String,
+    pub sha: String,
+}
+
+impl NewRFDStateChange {
+    pub async fn create(self, db: &Database) -> Result<RFDStateChange> {
+        Ok(diesel::insert_into(rfd_state_changes::table)
+            .values(self)
+            .get_result_async::<RFDStateChange>(db.pool())
+            .await?)
+    }
+}
+
+impl RFDStateChange {
+    /// Get all of the recorded state changes of an RFD, oldest first
+    pub async fn history(db: &Database, number: i32) -> Result<Vec<Self>> {
+        Ok(rfd_state_changes::dsl::rfd_state_changes
+            .filter(rfd_state_changes::dsl::rfd_number.eq(number))
+            .order_by(rfd_state_changes::dsl::changed_at.asc())
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// Get the most recent state change of an RFD
+    pub async fn latest(db: &Database, number: i32) -> Result<Option<Self>> {
+        Ok(rfd_state_changes::dsl::rfd_state_changes
+            .filter(rfd_state_changes::dsl::rfd_number.eq(number))
+            .order_by(rfd_state_changes::dsl::changed_at.desc())
+            .limit(1)
+            .load_async::<Self>(db.pool())
+            .await?
+            .pop())
+    }
+}
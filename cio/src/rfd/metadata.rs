@@ -0,0 +1,639 @@
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fmt,
+    str::FromStr,
+};
+
+/// The states that an RFD may be in over its lifetime
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RFDState {
+    /// An idea that is being shared for initial thoughts, but is not yet ready for discussion
+    Ideation,
+    /// The RFD is being written and has not yet been opened for discussion
+    Prediscussion,
+    /// The RFD has an open pull request and is being actively discussed
+    Discussion,
+    /// The RFD has been merged and represents a decision that has been reached
+    Published,
+    /// The RFD has been published and its contents have been implemented
+    Committed,
+    /// The RFD is no longer being pursued
+    Abandoned,
+}
+
+impl RFDState {
+    pub fn all() -> [RFDState; 6] {
+        [
+            Self::Ideation,
+            Self::Prediscussion,
+            Self::Discussion,
+            Self::Published,
+            Self::Committed,
+            Self::Abandoned,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ideation => "ideation",
+            Self::Prediscussion => "prediscussion",
+            Self::Discussion => "discussion",
+            Self::Published => "published",
+            Self::Committed => "committed",
+            Self::Abandoned => "abandoned",
+        }
+    }
+
+    /// Determine if an RFD in this state may have an open pull request against the default branch.
+    /// The only state excluded is prediscussion, as opening a pull request is what moves an RFD
+    /// in to discussion.
+    pub fn is_valid_with_open_pull_request(&self) -> bool {
+        !matches!(self, Self::Prediscussion)
+    }
+
+    /// Determine if an RFD in this state may exist on the default branch
+    pub fn is_valid_on_default_branch(&self) -> bool {
+        matches!(self, Self::Published | Self::Committed | Self::Abandoned)
+    }
+
+    /// The states that an RFD may move to directly from this state. RFDs only move forward, one
+    /// step at a time, through prediscussion → ideation → discussion → published → committed and
+    /// may be abandoned at any point. Abandoned is final. The one exception is that an RFD may move
+    /// from prediscussion straight to discussion, as opening a pull request for an RFD in
+    /// prediscussion moves it in to discussion.
+    pub fn next_states(&self) -> &'static [RFDState] {
+        match self {
+            Self::Prediscussion => &[Self::Ideation, Self::Discussion, Self::Abandoned],
+            Self::Ideation => &[Self::Discussion, Self::Abandoned],
+            Self::Discussion => &[Self::Published, Self::Abandoned],
+            Self::Published => &[Self::Committed, Self::Abandoned],
+            Self::Committed => &[Self::Abandoned],
+            Self::Abandoned => &[],
+        }
+    }
+
+    /// Check that an RFD may move from this state to the given state. Remaining in the same state
+    /// is always allowed
+    pub fn validate_transition(&self, next: RFDState) -> Result<(), InvalidRFDStateTransition> {
+        if *self == next || self.next_states().contains(&next) {
+            Ok(())
+        } else {
+            Err(InvalidRFDStateTransition { from: *self, to: next })
+        }
+    }
+}
+
+impl fmt::Display for RFDState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for RFDState {
+    type Err = InvalidRFDState;
+
+    fn from_str(state: &str) -> Result<Self, Self::Err> {
+        let state = state.trim();
+
+        Self::all()
+            .into_iter()
+            .find(|candidate| candidate.as_str().eq_ignore_ascii_case(state))
+            .ok_or_else(|| InvalidRFDState(state.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidRFDState(pub String);
+
+impl fmt::Display for InvalidRFDState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\" is not a valid RFD state", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRFDState {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidRFDStateTransition {
+    pub from: RFDState,
+    pub to: RFDState,
+}
+
+impl fmt::Display for InvalidRFDStateTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.from.next_states().is_empty() {
+            return write!(
+                f,
+                "An RFD can not move from {} to {}. An RFD in {} can not change state",
+                self.from, self.to, self.from
+            );
+        }
+
+        write!(
+            f,
+            "An RFD can not move from {} to {}. From {} it may only move to: {}",
+            self.from,
+            self.to,
+            self.from,
+            self.from
+                .next_states()
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for InvalidRFDStateTransition {}
+
+/// An author as listed in the header of an RFD
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct RFDAuthor {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+impl fmt::Display for RFDAuthor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.email {
+            Some(email) => write!(f, "{} <{}>", self.name, email),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Issues found while reading the header of an RFD. These do not prevent the metadata from being
+/// read, but indicate that the document should be fixed by its authors.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RFDMetadataDiagnostic {
+    MissingTitle,
+    MissingState,
+    InvalidState(String),
+    MissingAuthors,
+    MalformedAuthor(String),
+    InvalidDiscussionLink(String),
+    DuplicateAttribute(String),
+}
+
+impl fmt::Display for RFDMetadataDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTitle => write!(f, "Document does not have a title"),
+            Self::MissingState => write!(f, "Document does not declare a state"),
+            Self::InvalidState(state) => write!(
+                f,
+                "\"{}\" is not a valid state. Expected one of: {}",
+                state,
+                RFDState::all().map(|s| s.as_str()).join(", ")
+            ),
+            Self::MissingAuthors => write!(f, "Document does not list any authors"),
+            Self::MalformedAuthor(author) => write!(f, "Author \"{}\" has a malformed email address", author),
+            Self::InvalidDiscussionLink(link) => write!(f, "Discussion link \"{}\" is not a url", link),
+            Self::DuplicateAttribute(name) => write!(f, "Attribute \"{}\" is defined multiple times", name),
+        }
+    }
+}
+
+/// Whether the header being read uses Asciidoc (:name: value) or Markdown (name: value) attributes
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) enum RFDHeaderFormat {
+    Asciidoc,
+    Markdown,
+}
+
+/// The metadata stored in the header of an RFD document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RFDMetadata {
+    pub title: String,
+    pub state: Option<RFDState>,
+    /// The state as it is written in the header, which is kept even when it is not a known state
+    pub raw_state: Option<String>,
+    pub authors: Vec<RFDAuthor>,
+    pub labels: Vec<String>,
+    pub discussion: Option<String>,
+    /// Any additional attributes defined by the document
+    pub attributes: BTreeMap<String, String>,
+    pub diagnostics: Vec<RFDMetadataDiagnostic>,
+}
+
+impl RFDMetadata {
+    pub(super) fn parse(content: &str, format: RFDHeaderFormat) -> Self {
+        let mut metadata = Self::default();
+        let mut attributes = Self::read_attributes(content, format, &mut metadata.diagnostics);
+
+        metadata.title = Self::read_title(content);
+
+        if metadata.title.is_empty() {
+            metadata.diagnostics.push(RFDMetadataDiagnostic::MissingTitle);
+        }
+
+        match attributes.remove("state").filter(|state| !state.is_empty()) {
+            Some(state) => {
+                match state.parse::<RFDState>() {
+                    Ok(state) => metadata.state = Some(state),
+                    Err(_) => metadata
+                        .diagnostics
+                        .push(RFDMetadataDiagnostic::InvalidState(state.to_string())),
+                }
+
+                metadata.raw_state = Some(state);
+            }
+            None => metadata.diagnostics.push(RFDMetadataDiagnostic::MissingState),
+        }
+
+        if let Some(discussion) = attributes.remove("discussion").filter(|link| !link.is_empty()) {
+            if discussion.starts_with("http") {
+                metadata.discussion = Some(discussion);
+            } else {
+                metadata
+                    .diagnostics
+                    .push(RFDMetadataDiagnostic::InvalidDiscussionLink(discussion));
+            }
+        }
+
+        metadata.labels = attributes
+            .remove("labels")
+            .map(|labels| {
+                labels
+                    .split(';')
+                    .map(|label| label.trim().to_string())
+                    .filter(|label| !label.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let authors_attribute = attributes.remove("authors");
+
+        let authors_line = match format {
+            // Asciidoc authors are listed on the line directly following the title. This line may
+            // reference the authors attribute instead of listing the authors directly
+            RFDHeaderFormat::Asciidoc => match Self::read_author_line(content) {
+                Some(line) if line == "{authors}" => authors_attribute,
+                Some(line) => Some(line),
+                None => None,
+            },
+            RFDHeaderFormat::Markdown => authors_attribute,
+        };
+
+        metadata.authors = authors_line
+            .map(|line| Self::parse_authors(&line, &mut metadata.diagnostics))
+            .unwrap_or_default();
+
+        if metadata.authors.is_empty() {
+            metadata.diagnostics.push(RFDMetadataDiagnostic::MissingAuthors);
+        }
+
+        metadata.attributes = attributes;
+
+        metadata
+    }
+
+    /// The state to store for the RFD. A state that is not known is stored as it is written, rather
+    /// than being dropped
+    pub fn state_string(&self) -> String {
+        match (&self.state, &self.raw_state) {
+            (Some(state), _) => state.to_string(),
+            (None, Some(raw_state)) => raw_state.to_string(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Get the list of authors in the form that they are written in the document header
+    pub fn authors_line(&self) -> String {
+        self.authors
+            .iter()
+            .map(|author| author.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Read all of the attributes defined in the document. If an attribute is defined multiple
+    /// times then the first definition is used
+    fn read_attributes(
+        content: &str,
+        format: RFDHeaderFormat,
+        diagnostics: &mut Vec<RFDMetadataDiagnostic>,
+    ) -> BTreeMap<String, String> {
+        let re = match format {
+            RFDHeaderFormat::Asciidoc => Regex::new(r"^:([A-Za-z0-9_][A-Za-z0-9_-]*):(\s.*)?$").unwrap(),
+            RFDHeaderFormat::Markdown => Regex::new(r"^\s*([A-Za-z][A-Za-z0-9_-]*):(\s.*)?$").unwrap(),
+        };
+
+        // Markdown documents may use a front matter block to hold their attributes. When one is
+        // present, attributes are only read from within that block. Otherwise they are only read
+        // from the first run of attribute lines ahead of the first heading, so that text in the
+        // body that happens to look like an attribute is not read as one
+        let lines = content.lines().collect::<Vec<_>>();
+        let lines = match format {
+            RFDHeaderFormat::Markdown => {
+                let start = lines.iter().position(|line| !line.trim().is_empty());
+
+                match start.filter(|start| lines[*start].trim() == "---") {
+                    Some(start) => {
+                        let end = lines[start + 1..]
+                            .iter()
+                            .position(|line| line.trim() == "---")
+                            .map(|end| start + 1 + end)
+                            .unwrap_or(lines.len());
+                        &lines[start + 1..end]
+                    }
+                    None => {
+                        let heading = lines
+                            .iter()
+                            .position(|line| line.trim_start().starts_with('#'))
+                            .unwrap_or(lines.len());
+                        let start = lines[..heading]
+                            .iter()
+                            .position(|line| re.is_match(line))
+                            .unwrap_or(heading);
+                        let end = lines[start..heading]
+                            .iter()
+                            .position(|line| !line.trim().is_empty() && !re.is_match(line))
+                            .map(|end| start + end)
+                            .unwrap_or(heading);
+                        &lines[start..end]
+                    }
+                }
+            }
+            RFDHeaderFormat::Asciidoc => &lines[..],
+        };
+
+        let mut attributes = BTreeMap::new();
+
+        for captures in lines.iter().filter_map(|line| re.captures(line)) {
+            let name = captures[1].to_string();
+            let value = captures.get(2).map(|value| value.as_str().trim()).unwrap_or_default();
+
+            match attributes.entry(name) {
+                Entry::Vacant(entry) => {
+                    entry.insert(value.to_string());
+                }
+                Entry::Occupied(entry) => {
+                    let diagnostic = RFDMetadataDiagnostic::DuplicateAttribute(entry.key().to_string());
+
+                    if !diagnostics.contains(&diagnostic) {
+                        diagnostics.push(diagnostic);
+                    }
+                }
+            }
+        }
+
+        attributes
+    }
+
+    fn read_title(content: &str) -> String {
+        let re = Regex::new(r"(?m)^[=# ]+(RFD .*$)").unwrap();
+
+        match re.captures(content) {
+            Some(captures) => {
+                let title = captures[1].trim_start_matches("RFD").trim();
+
+                // Drop the RFD number that precedes the title
+                match title.split_once(' ') {
+                    Some((_, title)) => title.trim().to_string(),
+                    None => String::new(),
+                }
+            }
+            None => {
+                // There is no "RFD" in our title. This is the case for RFD 31.
+                let re = Regex::new(r"(?m)^= (.*$)").unwrap();
+
+                re.captures(content)
+                    .map(|captures| captures[1].trim().to_string())
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    /// Read the line directly following the document title, which holds the authors of an Asciidoc
+    /// document
+    fn read_author_line(content: &str) -> Option<String> {
+        let mut lines = content.lines().skip_while(|line| !line.starts_with(['#', '=']));
+        lines.next()?;
+
+        lines
+            .next()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+    }
+
+    fn parse_authors(line: &str, diagnostics: &mut Vec<RFDMetadataDiagnostic>) -> Vec<RFDAuthor> {
+        let re = Regex::new(r"^([^<>]*?)\s*(?:<([^<>]*)>)?$").unwrap();
+
+        line.split([',', ';'])
+            .map(|author| author.trim())
+            .filter(|author| !author.is_empty())
+            .filter_map(|author| match re.captures(author) {
+                Some(captures) if !captures[1].is_empty() => {
+                    let email = captures.get(2).map(|email| email.as_str().trim().to_string());
+
+                    if email.as_ref().map(|email| !email.contains('@')).unwrap_or(false) {
+                        diagnostics.push(RFDMetadataDiagnostic::MalformedAuthor(author.to_string()));
+                    }
+
+                    Some(RFDAuthor {
+                        name: captures[1].to_string(),
+                        email,
+                    })
+                }
+                _ => {
+                    diagnostics.push(RFDMetadataDiagnostic::MalformedAuthor(author.to_string()));
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_states() {
+        for state in RFDState::all() {
+            assert_eq!(Ok(state), state.as_str().parse::<RFDState>());
+            assert_eq!(Ok(state), state.as_str().to_uppercase().parse::<RFDState>());
+        }
+
+        assert_eq!(
+            Err(InvalidRFDState("pending".to_string())),
+            " pending ".parse::<RFDState>()
+        );
+    }
+
+    #[test]
+    fn test_state_transitions() {
+        assert!(RFDState::Prediscussion.validate_transition(RFDState::Ideation).is_ok());
+        assert!(RFDState::Prediscussion
+            .validate_transition(RFDState::Discussion)
+            .is_ok());
+        assert!(RFDState::Ideation.validate_transition(RFDState::Discussion).is_ok());
+        assert!(RFDState::Discussion.validate_transition(RFDState::Published).is_ok());
+        assert!(RFDState::Published.validate_transition(RFDState::Committed).is_ok());
+        assert!(RFDState::Committed.validate_transition(RFDState::Committed).is_ok());
+
+        for state in RFDState::all() {
+            assert!(state.validate_transition(state).is_ok());
+
+            if state != RFDState::Abandoned {
+                assert!(state.validate_transition(RFDState::Abandoned).is_ok());
+            }
+        }
+
+        assert_eq!(
+            Err(InvalidRFDStateTransition {
+                from: RFDState::Ideation,
+                to: RFDState::Published
+            }),
+            RFDState::Ideation.validate_transition(RFDState::Published)
+        );
+        assert!(RFDState::Prediscussion
+            .validate_transition(RFDState::Committed)
+            .is_err());
+        assert!(RFDState::Discussion.validate_transition(RFDState::Committed).is_err());
+        assert!(RFDState::Committed.validate_transition(RFDState::Discussion).is_err());
+
+        // Skipping ahead and moving backwards are both rejected
+        assert!(RFDState::Prediscussion
+            .validate_transition(RFDState::Published)
+            .is_err());
+        assert!(RFDState::Discussion.validate_transition(RFDState::Ideation).is_err());
+        assert!(RFDState::Published.validate_transition(RFDState::Discussion).is_err());
+        assert!(RFDState::Abandoned.validate_transition(RFDState::Discussion).is_err());
+
+        assert_eq!(
+            "An RFD can not move from discussion to committed. From discussion it may only move to: published, \
+             abandoned",
+            RFDState::Discussion
+                .validate_transition(RFDState::Committed)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "An RFD can not move from abandoned to discussion. An RFD in abandoned can not change state",
+            RFDState::Abandoned
+                .validate_transition(RFDState::Discussion)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_parses_asciidoc_metadata() {
+        let content = r#"
+:showtitle:
+:toc: left
+:state: discussion
+:discussion: https://github.com/company/repo/pull/123
+:labels: storage; networking
+:authors: First Last <first@company.com>, Second <second@company.com>
+
+= RFD 123 Metadata
+{authors}
+"#;
+
+        let metadata = RFDMetadata::parse(content, RFDHeaderFormat::Asciidoc);
+
+        assert_eq!("Metadata", metadata.title);
+        assert_eq!(Some(RFDState::Discussion), metadata.state);
+        assert_eq!(
+            Some("https://github.com/company/repo/pull/123".to_string()),
+            metadata.discussion
+        );
+        assert_eq!(vec!["storage".to_string(), "networking".to_string()], metadata.labels);
+        assert_eq!(
+            vec![
+                RFDAuthor {
+                    name: "First Last".to_string(),
+                    email: Some("first@company.com".to_string()),
+                },
+                RFDAuthor {
+                    name: "Second".to_string(),
+                    email: Some("second@company.com".to_string()),
+                },
+            ],
+            metadata.authors
+        );
+        assert_eq!(Some(&"left".to_string()), metadata.attributes.get("toc"));
+        assert!(metadata.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_reads_markdown_front_matter() {
+        let content = r#"
+---
+authors: First Last <first@company.com>
+state: published
+---
+
+# RFD 12 Markdown Metadata
+
+note: this is not an attribute
+"#;
+
+        let metadata = RFDMetadata::parse(content, RFDHeaderFormat::Markdown);
+
+        assert_eq!("Markdown Metadata", metadata.title);
+        assert_eq!(Some(RFDState::Published), metadata.state);
+        assert_eq!("First Last <first@company.com>", metadata.authors_line());
+        assert!(metadata.attributes.is_empty());
+    }
+
+    #[test]
+    fn test_reads_leading_markdown_attributes() {
+        let content = r#"
+authors: First Last <first@company.com>
+state: discussion
+
+This is not an attribute
+labels: and so neither is this
+
+# RFD 12 Markdown Metadata
+
+note: this is not an attribute
+note: and neither is this
+state: published
+"#;
+
+        let metadata = RFDMetadata::parse(content, RFDHeaderFormat::Markdown);
+
+        assert_eq!(Some(RFDState::Discussion), metadata.state);
+        assert_eq!("First Last <first@company.com>", metadata.authors_line());
+        assert!(metadata.attributes.is_empty());
+        assert!(metadata.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_reports_malformed_headers() {
+        let content = r#"
+:state: pending
+:discussion: soon
+:state: published
+
+= RFD 123 Malformed
+First Last <first.company.com>
+"#;
+
+        let metadata = RFDMetadata::parse(content, RFDHeaderFormat::Asciidoc);
+
+        assert_eq!(None, metadata.state);
+        assert_eq!("pending", metadata.state_string());
+        assert_eq!(None, metadata.discussion);
+        assert_eq!(
+            vec![
+                RFDMetadataDiagnostic::DuplicateAttribute("state".to_string()),
+                RFDMetadataDiagnostic::InvalidState("pending".to_string()),
+                RFDMetadataDiagnostic::InvalidDiscussionLink("soon".to_string()),
+                RFDMetadataDiagnostic::MalformedAuthor("First Last <first.company.com>".to_string()),
+            ],
+            metadata.diagnostics
+        );
+    }
+}
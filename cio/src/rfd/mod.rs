@@ -5,16 +5,34 @@ mod changelog;
 mod content;
 pub mod drive;
 mod github;
+mod history;
+mod metadata;
 mod model;
 mod pdf;
+mod references;
+mod render;
 mod search;
+mod stale;
+mod storage;
 
-pub use changelog::send_rfd_changelog;
+pub use changelog::{send_rfd_changelog, Changelog, ChangelogCommit, ChangelogEmail, ChangelogEntry};
 pub use content::{RFDContent, RFDOutputError, RFDOutputFormat};
-pub use github::{GitHubRFDBranch, GitHubRFDReadme, GitHubRFDReadmeLocation, GitHubRFDRepo, GitHubRFDUpdate};
-pub use model::{NewRFD, RFDEntry, RFDIndexEntry, RFDs, RemoteRFD, RFD};
-pub use pdf::{PDFStorage, RFDPdf};
-pub use search::{IndexDocument, RFDSearchIndex};
+pub use github::{
+    GitHubRFDBranch, GitHubRFDCommit, GitHubRFDReadme, GitHubRFDReadmeLocation, GitHubRFDRepo, GitHubRFDUpdate,
+};
+pub use history::{NewRFDStateChange, RFDStateChange};
+pub use metadata::{
+    InvalidRFDState, InvalidRFDStateTransition, RFDAuthor, RFDMetadata, RFDMetadataDiagnostic, RFDState,
+};
+pub use model::{NewRFD, RFDEntry, RFDFields, RFDIndexEntry, RFDs, RemoteRFD, RFD};
+pub use pdf::{PDFStorage, PDFStorageBackend, RFDPdf, RFDPdfUpload};
+pub use references::RFDReference;
+pub use search::{
+    IndexDocument, RFDIndexDiff, RFDSearchFilter, RFDSearchHit, RFDSearchIndex, RFDSearchKey, RFDSearchKeySigner,
+    RFDSearchResults,
+};
+pub use stale::{check_stale_rfds, NewRFDSnooze, RFDSnooze, StaleRFD, StaleRFDReport, MAX_SNOOZE_DAYS};
+pub use storage::{LocalPDFStorage, S3Credentials, S3PDFStorage};
 
 #[derive(Debug, Copy, Clone, Deserialize, Serialize)]
 pub struct RFDNumber(i32);
@@ -11,9 +11,14 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     airtable::AIRTABLE_RFD_TABLE,
+    app_config::RFDConfig,
     companies::Company,
     core::UpdateAirtableRecord,
-    rfd::{GitHubRFDBranch, GitHubRFDReadmeLocation, GitHubRFDRepo, GitHubRFDUpdate, RFDContent},
+    db::Database,
+    rfd::{
+        GitHubRFDBranch, GitHubRFDReadmeLocation, GitHubRFDRepo, GitHubRFDUpdate, RFDContent, RFDReference, RFDState,
+        RFDStateChange,
+    },
     schema::rfds as r_f_ds,
     schema::rfds,
     utils::truncate,
@@ -21,7 +26,7 @@ use crate::{
 
 /// The data type for an RFD.
 #[partial(RFDIndexEntry, with(Queryable), without(Insertable, AsChangeset))]
-#[partial(RFDEntry)]
+#[partial(RFDFields)]
 #[db {
     target_struct = "NewRFD",
     new_struct_name = "RFD",
@@ -53,7 +58,7 @@ pub struct NewRFD {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub short_link: String,
     /// (generated) rendered_link is the link to the rfd in the rendered html website in the form of
-    /// https://rfd.shared.oxide.computer/rfd/{{number_string}}
+    /// https://{{rendered_host}}/rfd/{{number_string}}
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub rendered_link: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
@@ -80,19 +85,68 @@ pub struct NewRFD {
     pub relevant_components: Vec<String>,
     #[serde(default, skip_serializing_if = "String::is_empty")]
     #[partial(RFDIndexEntry(skip))]
-    #[partial(RFDEntry(skip))]
+    #[partial(RFDFields(skip))]
     pub pdf_link_github: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub pdf_link_google_drive: String,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[partial(RFDIndexEntry(skip))]
+    pub pdf_link_local: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[partial(RFDIndexEntry(skip))]
+    pub pdf_link_s3: String,
     /// The CIO company ID.
     #[serde(default)]
     #[partial(RFDIndexEntry(skip))]
-    #[partial(RFDEntry(skip))]
+    #[partial(RFDFields(skip))]
     pub cio_company_id: i32,
 }
 
+/// An RFD along with the RFDs that it references and the RFDs that reference it.
+#[derive(Debug, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct RFDEntry {
+    #[serde(flatten)]
+    pub rfd: RFDFields,
+    /// references are the numbers of the RFDs mentioned in this RFD
+    #[serde(default)]
+    pub references: Vec<i32>,
+    /// referenced_by are the numbers of the RFDs that mention this RFD
+    #[serde(default)]
+    pub referenced_by: Vec<i32>,
+    /// state_changed_at is when the RFD moved into its current state, if a change has been recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_changed_at: Option<DateTime<Utc>>,
+    /// seconds_in_state is how long the RFD has been in its current state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seconds_in_state: Option<i64>,
+}
+
+impl RFDEntry {
+    /// Get an RFD along with its references and state history from the database.
+    pub async fn get_from_db(db: &Database, number: i32) -> Result<Option<Self>> {
+        let rfd: NewRFD = match RFD::get_from_db(db, number).await {
+            Some(rfd) => rfd.into(),
+            None => return Ok(None),
+        };
+
+        // Only trust the latest recorded change if it agrees with the state currently on the RFD
+        let state_changed_at = RFDStateChange::latest(db, number)
+            .await?
+            .filter(|change| change.to_state == rfd.state)
+            .map(|change| change.changed_at);
+
+        Ok(Some(Self {
+            rfd: rfd.into(),
+            references: RFDReference::references(db, number).await?,
+            referenced_by: RFDReference::referenced_by(db, number).await?,
+            state_changed_at,
+            seconds_in_state: state_changed_at.map(|changed_at| (Utc::now() - changed_at).num_seconds()),
+        }))
+    }
+}
+
 pub struct RemoteRFD {
     pub rfd: NewRFD,
     pub location: GitHubRFDReadmeLocation,
@@ -107,20 +161,32 @@ impl NewRFD {
     ///
     /// This function will return both the old RFD (representing our internal state) as well as the
     /// new merged/updated version.
-    pub async fn new_from_update(company: &Company, update: &GitHubRFDUpdate) -> Result<RemoteRFD> {
+    pub async fn new_from_update(company: &Company, config: &RFDConfig, update: &GitHubRFDUpdate) -> Result<RemoteRFD> {
         let github = update.client();
 
         // If we can not find a remote file from GitHub then we abandon here.
         let readme = update.branch.get_readme_contents(&update.number).await?;
 
-        // Parse the RFD title from the contents.
-        let title = readme.content.get_title().trim().to_string();
-        let name = Self::generate_name(update.number.into(), &title);
+        // Parse the RFD header from the contents.
+        let metadata = readme.content.metadata();
 
-        // Parse the discussion from the contents.
-        let discussion = readme.content.get_discussion();
+        for diagnostic in &metadata.diagnostics {
+            log::warn!(
+                "RFD {} on {} has a malformed header: {}",
+                update.number,
+                update.branch.branch,
+                diagnostic
+            );
+        }
 
-        let html = readme.content.to_html(&update.number, &update.branch).await?.0;
+        let title = metadata.title.trim().to_string();
+        let name = Self::generate_name(update.number.into(), &title);
+
+        let html = readme
+            .content
+            .to_html(&update.number, &update.branch, &config.rendered_host)
+            .await?
+            .0;
 
         // TODO: Unsure if this should actually be an error, but this mirrors the previous logic
         if html.trim().is_empty() {
@@ -172,7 +238,7 @@ impl NewRFD {
                 number_string: update.number.as_number_string(),
                 title,
                 name,
-                state: readme.content.get_state(),
+                state: metadata.state_string(),
                 link: readme
                     .location
                     .tree_link
@@ -180,9 +246,9 @@ impl NewRFD {
                     .unwrap_or(&readme.location.blob_link)
                     .to_string(),
                 short_link: Self::generate_short_link(update.number.into()),
-                rendered_link: Self::generate_rendered_link(&update.number.as_number_string()),
-                discussion,
-                authors: readme.content.get_authors(),
+                rendered_link: Self::generate_rendered_link(&config.rendered_host, &update.number.as_number_string()),
+                discussion: metadata.discussion.unwrap_or_default(),
+                authors: metadata.authors_line(),
 
                 html,
                 content: readme.content.raw().to_string(),
@@ -199,6 +265,8 @@ impl NewRFD {
                 // while new PDFs are generated
                 pdf_link_github: Default::default(),
                 pdf_link_google_drive: Default::default(),
+                pdf_link_local: Default::default(),
+                pdf_link_s3: Default::default(),
                 labels: Default::default(),
                 cio_company_id: company.id,
             },
@@ -214,71 +282,12 @@ impl NewRFD {
         format!("https://{}.rfd.oxide.computer", number)
     }
 
-    fn generate_rendered_link(number_string: &str) -> String {
-        format!("https://rfd.shared.oxide.computer/rfd/{}", number_string)
+    fn generate_rendered_link(host: &str, number_string: &str) -> String {
+        format!("https://{}/rfd/{}", host, number_string)
     }
 }
 
 impl RFD {
-    /// Get a changelog for the RFD.
-    pub async fn get_weekly_changelog(
-        &self,
-        github: &octorust::Client,
-        since: DateTime<Utc>,
-        company: &Company,
-    ) -> Result<String> {
-        let owner = &company.github_org;
-        let repo = "rfd";
-        let r = github.repos().get(owner, repo).await?.body;
-        let mut changelog = String::new();
-
-        let mut branch = self.number_string.to_string();
-        if self.link.contains(&format!("/{}/", r.default_branch)) {
-            branch = r.default_branch.to_string();
-        }
-
-        // Get the commits from the last seven days to the file.
-        let commits = match github
-            .repos()
-            .list_all_commits(
-                owner,
-                repo,
-                &branch,
-                &format!("/rfd/{}/", self.number_string),
-                "",
-                Some(since),
-                None,
-            )
-            .await
-            .map(|response| response.body)
-        {
-            Ok(v) => v,
-            Err(_) => {
-                // Ignore the error and create an empty list.
-                vec![]
-            }
-        };
-
-        for commit in commits {
-            let message: Vec<&str> = commit.commit.message.lines().collect();
-            if !message.is_empty() {
-                if let Some(author) = commit.author {
-                    changelog += &format!(
-                        "\t- \"{}\" by @{}\n\t\thttps://github.com/oxidecomputer/rfd/commit/{}\n",
-                        message[0], author.login, commit.sha
-                    );
-                } else {
-                    changelog += &format!(
-                        "\t- \"{}\"\n\t\thttps://github.com/oxidecomputer/rfd/commit/{}\n",
-                        message[0], commit.sha
-                    );
-                }
-            }
-        }
-
-        Ok(changelog)
-    }
-
     /// Get the filename for the PDF of the RFD.
     pub fn get_pdf_filename(&self) -> String {
         format!(
@@ -288,10 +297,15 @@ impl RFD {
         )
     }
 
+    /// Get the state of the RFD. If the stored state is not a known state, then None is returned
+    pub fn get_state(&self) -> Option<RFDState> {
+        self.state.parse().ok()
+    }
+
     /// Update an RFDs state.
-    pub fn update_state(&mut self, state: &str) -> Result<()> {
+    pub fn update_state(&mut self, state: RFDState) -> Result<()> {
         let mut content = RFDContent::new(&self.content)?;
-        content.update_state(state);
+        content.update_state(state.as_str());
 
         self.content = content.into_inner();
         self.state = state.to_string();
@@ -347,6 +361,11 @@ impl UpdateAirtableRecord<RFD> for RFD {
         self.content = truncate(&self.content, 100000);
         self.html = "".to_string();
 
+        // The local and S3 PDF links do not have fields in Airtable. Blanking them keeps them out of
+        // the serialized record.
+        self.pdf_link_local = "".to_string();
+        self.pdf_link_s3 = "".to_string();
+
         Ok(())
     }
 }
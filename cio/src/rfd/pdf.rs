@@ -1,10 +1,13 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use log::info;
+use log::{info, warn};
 
-use crate::{companies::Company, db::Database, features::Features};
+use crate::{app_config::RFDPdfStorageConfig, companies::Company, db::Database};
 
-use super::{GitHubRFDRepo, RFDNumber};
+use super::{
+    storage::{LocalPDFStorage, S3Credentials, S3PDFStorage},
+    GitHubRFDRepo, RFDNumber,
+};
 
 #[async_trait]
 pub trait PDFStorage {
@@ -18,45 +21,117 @@ pub struct RFDPdf {
     pub contents: Vec<u8>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PDFStorageBackend {
+    GitHub,
+    GoogleDrive,
+    Local,
+    S3,
+}
+
+#[derive(Debug, Default)]
 pub struct RFDPdfUpload {
     pub github_url: Option<String>,
     pub google_drive_url: Option<String>,
+    pub local_url: Option<String>,
+    pub s3_url: Option<String>,
 }
 
-impl RFDPdf {
-    /// Upload the PDF to GitHub and/or Google Drive depending on which backends are supported
-    pub async fn upload(&self, db: &Database, company: &Company) -> Result<RFDPdfUpload> {
-        if Features::is_enabled("RFD_PDFS_IN_GITHUB") || Features::is_enabled("RFD_PDFS_IN_GOOGLE_DRIVE") {
-            // Create or update the file in the github repository.
-            let github_url = if Features::is_enabled("RFD_PDFS_IN_GITHUB") {
-                let repo = GitHubRFDRepo::new(company).await?;
-                let branch = repo.branch(repo.default_branch.clone());
+impl RFDPdfUpload {
+    fn set(&mut self, backend: PDFStorageBackend, url: String) {
+        let field = match backend {
+            PDFStorageBackend::GitHub => &mut self.github_url,
+            PDFStorageBackend::GoogleDrive => &mut self.google_drive_url,
+            PDFStorageBackend::Local => &mut self.local_url,
+            PDFStorageBackend::S3 => &mut self.s3_url,
+        };
 
-                Some(branch.store_rfd_pdf(self).await?)
-            } else {
-                None
-            };
+        *field = Some(url);
+    }
 
-            let google_drive_url = if Features::is_enabled("RFD_PDFS_IN_GOOGLE_DRIVE") {
-                Some(company.authenticate_google_drive(db).await?.store_rfd_pdf(self).await?)
-            } else {
-                None
-            };
+    fn is_empty(&self) -> bool {
+        self.github_url.is_none()
+            && self.google_drive_url.is_none()
+            && self.local_url.is_none()
+            && self.s3_url.is_none()
+    }
+}
+
+type BoxedPDFStorage = Box<dyn PDFStorage + Send + Sync>;
+
+impl RFDPdf {
+    /// Upload the PDF to each of the configured storage backends. A failure in one backend, whether
+    /// it is in setting up the backend or in writing to it, does not prevent writing to the others,
+    /// an error is only returned if every backend failed
+    pub async fn upload(&self, db: &Database, company: &Company, config: &RFDPdfStorageConfig) -> Result<RFDPdfUpload> {
+        let mut upload = RFDPdfUpload::default();
 
-            Ok(RFDPdfUpload {
-                github_url,
-                google_drive_url,
-            })
-        } else {
+        if !config.any_enabled() {
             info!(
                 "No RFD PDF storage locations are configured. Skipping PDF generation for RFD {}.",
                 self.number.as_number_string()
             );
 
-            Ok(RFDPdfUpload {
-                github_url: None,
-                google_drive_url: None,
-            })
+            return Ok(upload);
+        }
+
+        let mut backends: Vec<(PDFStorageBackend, Result<BoxedPDFStorage>)> = vec![];
+
+        if config.github_enabled() {
+            // Create or update the file in the github repository.
+            let storage = GitHubRFDRepo::new(company)
+                .await
+                .map(|repo| Box::new(repo.branch(repo.default_branch.clone())) as BoxedPDFStorage);
+            backends.push((PDFStorageBackend::GitHub, storage));
+        }
+
+        if config.google_drive_enabled() {
+            let storage = company
+                .authenticate_google_drive(db)
+                .await
+                .map(|drive| Box::new(drive) as BoxedPDFStorage);
+            backends.push((PDFStorageBackend::GoogleDrive, storage));
+        }
+
+        if let Some(local) = &config.local {
+            backends.push((
+                PDFStorageBackend::Local,
+                Ok(Box::new(LocalPDFStorage::new(local)) as BoxedPDFStorage),
+            ));
+        }
+
+        if let Some(s3) = &config.s3 {
+            let storage = S3Credentials::from_env()
+                .and_then(|credentials| S3PDFStorage::new(s3, credentials))
+                .map(|storage| Box::new(storage) as BoxedPDFStorage);
+            backends.push((PDFStorageBackend::S3, storage));
+        }
+
+        let mut first_err = None;
+
+        for (backend, storage) in backends {
+            let result = match storage {
+                Ok(storage) => storage.store_rfd_pdf(self).await,
+                Err(err) => Err(err),
+            };
+
+            match result {
+                Ok(url) => upload.set(backend, url),
+                Err(err) => {
+                    warn!(
+                        "Failed to store PDF for RFD {} in {:?}: {:?}",
+                        self.number.as_number_string(),
+                        backend,
+                        err
+                    );
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        match first_err {
+            Some(err) if upload.is_empty() => Err(err),
+            _ => Ok(upload),
         }
     }
 }
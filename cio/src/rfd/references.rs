@@ -0,0 +1,143 @@
+use anyhow::Result;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Utc};
+use diesel::{ExpressionMethods, Insertable, QueryDsl, Queryable};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::{db::Database, schema::rfd_references};
+
+/// A mention of one RFD from within the contents of another RFD
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct RFDReference {
+    pub id: i32,
+    /// The RFD whose contents contain the reference
+    pub rfd_number: i32,
+    /// The RFD that is being referenced
+    pub referenced_rfd_number: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = rfd_references)]
+struct NewRFDReference {
+    rfd_number: i32,
+    referenced_rfd_number: i32,
+}
+
+impl RFDReference {
+    /// Replace the stored references of an RFD with the given list of RFD numbers. References from
+    /// an RFD to itself are dropped
+    pub async fn replace_for_rfd(db: &Database, number: i32, references: &BTreeSet<i32>) -> Result<()> {
+        let references = references
+            .iter()
+            .copied()
+            .filter(|referenced| *referenced != number)
+            .collect::<Vec<_>>();
+
+        diesel::delete(
+            rfd_references::dsl::rfd_references
+                .filter(rfd_references::dsl::rfd_number.eq(number))
+                .filter(rfd_references::dsl::referenced_rfd_number.ne_all(references.clone())),
+        )
+        .execute_async(db.pool())
+        .await?;
+
+        if !references.is_empty() {
+            let rows = references
+                .into_iter()
+                .map(|referenced_rfd_number| NewRFDReference {
+                    rfd_number: number,
+                    referenced_rfd_number,
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_into(rfd_references::table)
+                .values(rows)
+                .on_conflict((
+                    rfd_references::dsl::rfd_number,
+                    rfd_references::dsl::referenced_rfd_number,
+                ))
+                .do_nothing()
+                .execute_async(db.pool())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the numbers of the RFDs that the given RFD references
+    pub async fn references(db: &Database, number: i32) -> Result<Vec<i32>> {
+        Ok(rfd_references::dsl::rfd_references
+            .filter(rfd_references::dsl::rfd_number.eq(number))
+            .select(rfd_references::dsl::referenced_rfd_number)
+            .order_by(rfd_references::dsl::referenced_rfd_number)
+            .load_async::<i32>(db.pool())
+            .await?)
+    }
+
+    /// Get the numbers of the RFDs that reference the given RFD
+    pub async fn referenced_by(db: &Database, number: i32) -> Result<Vec<i32>> {
+        Ok(rfd_references::dsl::rfd_references
+            .filter(rfd_references::dsl::referenced_rfd_number.eq(number))
+            .select(rfd_references::dsl::rfd_number)
+            .order_by(rfd_references::dsl::rfd_number)
+            .load_async::<i32>(db.pool())
+            .await?)
+    }
+}
+
+/// Find the numbers of all of the RFDs mentioned in a document. Mentions may be written out as
+/// text (RFD 123), as links to a rendered or source RFD (rfd/0123) or as short links
+/// (123.rfd.oxide.computer)
+pub(super) fn find_references(content: &str) -> BTreeSet<i32> {
+    let patterns = [
+        Regex::new(r"(?i)\bRFD[ \t-]?0*(\d{1,4})\b").unwrap(),
+        Regex::new(r"(?i)\brfd/0*(\d{1,4})\b").unwrap(),
+        Regex::new(r"(?i)\b0*(\d{1,4})\.rfd\.oxide\.computer\b").unwrap(),
+    ];
+
+    patterns
+        .iter()
+        .flat_map(|pattern| pattern.captures_iter(content))
+        .filter_map(|captures| captures.get(1).and_then(|number| number.as_str().parse().ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_references() {
+        let content = r#":showtitle:
+:toc: left
+:numbered:
+:icons: font
+:state: discussion
+:discussion: https://github.com/oxidecomputer/rfd/pull/1
+:authors: Some One <some@one.com>
+
+= RFD 1 Best Title
+
+This builds on RFD 2 and rfd-0003, and replaces the ideas in https://rfd.shared.oxide.computer/rfd/0004.
+See also https://github.com/oxidecomputer/rfd/blob/master/rfd/0005/README.adoc and
+https://6.rfd.oxide.computer for background. RFD 2 is mentioned twice.
+"#;
+
+        assert_eq!(
+            vec![1, 2, 3, 4, 5, 6],
+            find_references(content).into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_find_references_ignores_non_references() {
+        let content = "RFDs are great. The rfd/blob/master path has no number, nor does \
+            RFD 12345 or the rfd.oxide.computer site. Neither does BRFD 7.";
+
+        assert!(find_references(content).is_empty());
+    }
+}
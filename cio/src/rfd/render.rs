@@ -0,0 +1,1884 @@
+//! An in-process renderer for the subset of Asciidoc that RFDs commonly use. The output mirrors the
+//! embedded (--no-header-footer) HTML that asciidoctor generates so that the two can be used
+//! interchangeably. Any construct that is not understood is reported as unsupported so that the
+//! caller can fall back to running asciidoctor itself.
+
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+
+const ADMONITIONS: [&str; 5] = ["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"];
+
+/// Line prefixes for directives and block macros that the native renderer does not handle
+const UNSUPPORTED_PREFIXES: [&str; 8] = [
+    "include::",
+    "ifdef::",
+    "ifndef::",
+    "ifeval::",
+    "endif::",
+    "toc::[]",
+    "video::",
+    "audio::",
+];
+
+/// Inline macros and markup that the native renderer does not handle
+const UNSUPPORTED_INLINE: [&str; 11] = [
+    "footnote:",
+    "footnoteref:",
+    "pass:[",
+    "stem:[",
+    "latexmath:[",
+    "asciimath:[",
+    "kbd:[",
+    "btn:[",
+    "menu:",
+    "indexterm",
+    "(((",
+];
+
+/// Document attributes that alter the generated output in ways that are not handled
+const UNSUPPORTED_ATTRIBUTES: [&str; 4] = ["leveloffset", "stem", "source-highlighter", "sectnumlevels-offset"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Unsupported(pub(super) String);
+
+type RenderResult<T> = Result<T, Unsupported>;
+
+fn unsupported<T>(construct: impl Into<String>) -> RenderResult<T> {
+    Err(Unsupported(construct.into()))
+}
+
+/// Render Asciidoc content to HTML, returning an error describing the first construct encountered
+/// that the renderer does not support
+pub(super) fn render_html(content: &str) -> Result<String, Unsupported> {
+    Renderer::new(content).render()
+}
+
+struct Patterns {
+    attribute_entry: Regex,
+    section: Regex,
+    block_anchor: Regex,
+    block_attributes: Regex,
+    block_title: Regex,
+    list_item: Regex,
+    description_list: Regex,
+    image_block: Regex,
+    admonition_paragraph: Regex,
+    callout: Regex,
+    attribute_reference: Regex,
+    raw_passthrough: Regex,
+    unconstrained_passthrough: Regex,
+    xref: Regex,
+    xref_macro: Regex,
+    angle_url: Regex,
+    url: Regex,
+    link_macro: Regex,
+    mailto_macro: Regex,
+    email: Regex,
+    inline_image: Regex,
+    escaped_markup: Regex,
+    inline_role: Regex,
+    strong: Regex,
+    emphasis: Regex,
+    monospace: Regex,
+    mark: Regex,
+    superscript: Regex,
+    subscript: Regex,
+    apostrophe: Regex,
+    spaced_em_dash: Regex,
+    em_dash: Regex,
+    hard_break: Regex,
+    tag: Regex,
+    entity: Regex,
+    table_cell_spec: Regex,
+    placeholder: Regex,
+    xref_placeholder: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        let re = |pattern: &str| Regex::new(pattern).unwrap();
+
+        Self {
+            attribute_entry: re(r"^:(!?)([A-Za-z0-9_][A-Za-z0-9_-]*)(!?):(?:[ \t]+(.*))?$"),
+            section: re(r"^(={1,6}|#{1,6})[ \t]+(\S.*?)[ \t]*$"),
+            block_anchor: re(r"^\[\[([A-Za-z_:][\w:.-]*)\]\]$"),
+            block_attributes: re(r"^\[(.*)\]$"),
+            block_title: re(r"^\.(\.?[^ \t.].*)$"),
+            list_item: re(r"^[ \t]*(\*{1,5}|-|\.{1,5}|\d+\.)[ \t]+(.*)$"),
+            description_list: re(r"^(.*?[^:;])(:::{0,2}|;;)(?:[ \t]+.*)?$"),
+            image_block: re(r"^image::([^\s\[][^\[]*)\[(.*)\]$"),
+            admonition_paragraph: re(r"^(NOTE|TIP|IMPORTANT|WARNING|CAUTION):[ \t]+(.*)$"),
+            callout: re(r"(?:<\d+>|<\.>)\s*$"),
+            attribute_reference: re(r"\{([A-Za-z0-9_][A-Za-z0-9_-]*)\}"),
+            raw_passthrough: re(r"\+\+\+(.+?)\+\+\+"),
+            unconstrained_passthrough: re(r"\+\+(.+?)\+\+"),
+            xref: re(r"&lt;&lt;([A-Za-z_:][\w:.-]*)(?:,[ \t]*(.+?))?&gt;&gt;"),
+            xref_macro: re(r"xref:([A-Za-z_:#][\w:.#-]*)\[(.*?)\]"),
+            angle_url: re(r#"&lt;((?:https?|ftp|irc)://[^\s\[\]<"]+?)&gt;"#),
+            url: re(r#"(^|[\s(\[>,;"'])((?:https?|ftp|irc)://[^\s\[\]<"]+)(?:\[(.*?)\])?"#),
+            link_macro: re(r"link:([^\s\[]+)\[(.*?)\]"),
+            mailto_macro: re(r"mailto:([^\s\[]+)\[(.*?)\]"),
+            email: re(r"(^|[\s(>:;,])([\w.%+-]+@[\w-]+(?:\.[\w-]+)+)"),
+            inline_image: re(r"image:([^\s:\[][^\s\[]*)\[(.*?)\]"),
+            escaped_markup: re(r#"\\[*_`{+#^~]|\\&lt;&lt;|["']`"#),
+            inline_role: re(r"\[[.#%][\w.#%-]+\]#"),
+            strong: re(r"\*\*(.+?)\*\*"),
+            emphasis: re(r"__(.+?)__"),
+            monospace: re(r"``(.+?)``"),
+            mark: re(r"##(.+?)##"),
+            superscript: re(r"\^(\S+?)\^"),
+            subscript: re(r"~(\S+?)~"),
+            apostrophe: re(r"([A-Za-z0-9])'([A-Za-z])"),
+            spaced_em_dash: re(r"(^|\n| )--( |\n|$)"),
+            em_dash: re(r"(\w)--(\w)"),
+            hard_break: re(r"(?m) \+$"),
+            tag: re(r"<[^>]+>"),
+            entity: re(r"&(?:[a-z][a-z]+\d{0,2}|#\d\d\d{0,4}|#x[\da-f][\da-f][\da-f]{0,3});"),
+            table_cell_spec: re(r"(?:^|\s)(?:\d+(?:\.\d+)?[+*])?[<>^]?(?:\.[<>^])?[adehlmsv]?$"),
+            placeholder: re("\u{1}(\\d+)\u{2}"),
+            xref_placeholder: re("\u{3}([^\u{3}]+)\u{3}"),
+        }
+    }
+}
+
+/// Metadata that may precede a block: anchors, titles and attribute lists
+#[derive(Debug, Default)]
+struct BlockMeta {
+    id: Option<String>,
+    title: Option<String>,
+    style: Option<String>,
+    positional: Vec<String>,
+    named: HashMap<String, String>,
+    options: HashSet<String>,
+}
+
+impl BlockMeta {
+    fn is_empty(&self) -> bool {
+        self.id.is_none()
+            && self.title.is_none()
+            && self.style.is_none()
+            && self.positional.is_empty()
+            && self.named.is_empty()
+            && self.options.is_empty()
+    }
+
+    fn id_attr(&self) -> String {
+        self.id
+            .as_ref()
+            .map(|id| format!(r#" id="{}""#, id))
+            .unwrap_or_default()
+    }
+
+    fn merge_attributes(&mut self, list: &str) -> RenderResult<()> {
+        for (index, attribute) in split_attribute_list(list).into_iter().enumerate() {
+            if let Some((name, value)) = attribute.split_once('=') {
+                let name = name.trim().to_string();
+                let value = value.trim().trim_matches('"').to_string();
+
+                if name == "options" || name == "opts" {
+                    self.options
+                        .extend(value.split(',').map(|option| option.trim().to_string()));
+                } else if name == "id" {
+                    self.id = Some(value);
+                } else if name == "role" {
+                    return unsupported("block roles");
+                } else {
+                    self.named.insert(name, value);
+                }
+            } else if index == 0 {
+                self.merge_shorthand(&attribute)?;
+            } else {
+                self.positional.push(attribute);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parse the first positional attribute which may contain a style along with #id, .role and
+    /// %option shorthands
+    fn merge_shorthand(&mut self, shorthand: &str) -> RenderResult<()> {
+        let mut parts = vec![];
+        let mut current = String::new();
+
+        for c in shorthand.chars() {
+            if matches!(c, '#' | '.' | '%') {
+                parts.push(current);
+                current = c.to_string();
+            } else {
+                current.push(c);
+            }
+        }
+        parts.push(current);
+
+        for part in parts.into_iter().filter(|part| !part.is_empty()) {
+            if let Some(id) = part.strip_prefix('#') {
+                self.id = Some(id.to_string());
+            } else if let Some(option) = part.strip_prefix('%') {
+                self.options.insert(option.to_string());
+            } else if part.starts_with('.') {
+                return unsupported("block roles");
+            } else {
+                self.style = Some(part);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn split_attribute_list(list: &str) -> Vec<String> {
+    let mut attributes = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in list.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            ',' if !in_quotes => attributes.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+
+    if !current.trim().is_empty() || !attributes.is_empty() {
+        attributes.push(current.trim().to_string());
+    }
+
+    attributes
+}
+
+#[derive(Debug)]
+struct OutlineEntry {
+    level: usize,
+    id: String,
+    title: String,
+    children: Vec<OutlineEntry>,
+}
+
+struct Renderer<'a> {
+    lines: Vec<&'a str>,
+    pos: usize,
+    patterns: Patterns,
+    attributes: HashMap<String, String>,
+    ids: HashSet<String>,
+    xref_titles: HashMap<String, String>,
+    placeholders: Vec<String>,
+    section_counters: Vec<usize>,
+    open_sections: Vec<(usize, usize)>,
+    outline: Vec<OutlineEntry>,
+    figure_count: usize,
+    table_count: usize,
+    has_sections: bool,
+    out: String,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(content: &'a str) -> Self {
+        let mut attributes = HashMap::new();
+        attributes.insert("idprefix".to_string(), "_".to_string());
+        attributes.insert("idseparator".to_string(), "_".to_string());
+        attributes.insert("toc-title".to_string(), "Table of Contents".to_string());
+        attributes.insert("toclevels".to_string(), "2".to_string());
+        attributes.insert("sectnumlevels".to_string(), "3".to_string());
+
+        Self {
+            lines: content.lines().collect(),
+            pos: 0,
+            patterns: Patterns::new(),
+            attributes,
+            ids: HashSet::new(),
+            xref_titles: HashMap::new(),
+            placeholders: vec![],
+            section_counters: vec![0; 6],
+            open_sections: vec![],
+            outline: vec![],
+            figure_count: 0,
+            table_count: 0,
+            has_sections: false,
+            out: String::new(),
+        }
+    }
+
+    fn render(mut self) -> RenderResult<String> {
+        let title = self.header()?;
+
+        for attribute in UNSUPPORTED_ATTRIBUTES {
+            if self.attributes.contains_key(attribute) {
+                return unsupported(format!("{} attribute", attribute));
+            }
+        }
+
+        self.blocks(None)?;
+
+        while !self.open_sections.is_empty() {
+            self.close_section();
+        }
+
+        let mut html = String::new();
+
+        if let Some(title) = title {
+            if self.attributes.contains_key("showtitle") && !self.attributes.contains_key("notitle") {
+                html.push_str(&format!("<h1>{}</h1>\n", title));
+            }
+        }
+
+        if self.has_sections && self.attributes.contains_key("toc") {
+            html.push_str(&format!(
+                "<div id=\"toc\" class=\"toc\">\n<div id=\"toctitle\">{}</div>\n{}</div>\n",
+                self.attributes["toc-title"],
+                render_outline(&self.outline)
+            ));
+        }
+
+        html.push_str(&self.out);
+
+        let xref_titles = &self.xref_titles;
+        let html = self
+            .patterns
+            .xref_placeholder
+            .replace_all(&html, |caps: &Captures| {
+                xref_titles
+                    .get(&caps[1])
+                    .cloned()
+                    .unwrap_or_else(|| format!("[{}]", &caps[1]))
+            })
+            .to_string();
+
+        Ok(html)
+    }
+
+    /// Consume the document header, returning the rendered document title if there is one
+    fn header(&mut self) -> RenderResult<Option<String>> {
+        while self.pos < self.lines.len() {
+            let line = self.lines[self.pos].trim_end();
+
+            if line.is_empty() || (line.starts_with("//") && !line.starts_with("////")) {
+                self.pos += 1;
+            } else if self.patterns.attribute_entry.is_match(line) {
+                self.attribute_entry(line)?;
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+
+        let title = match self.lines.get(self.pos).map(|line| line.trim_end()) {
+            Some(line) if line.starts_with("= ") || line.starts_with("# ") => line[2..].trim().to_string(),
+            _ => return Ok(None),
+        };
+        self.pos += 1;
+
+        // The remainder of the header contains the author and revision lines, which are not
+        // rendered, along with additional attribute entries
+        while self.pos < self.lines.len() {
+            let line = self.lines[self.pos].trim_end();
+
+            if line.is_empty() {
+                break;
+            } else if self.patterns.attribute_entry.is_match(line) {
+                self.attribute_entry(line)?;
+            }
+
+            self.pos += 1;
+        }
+
+        if self.attributes.contains_key("doctitle") {
+            return unsupported("doctitle attribute");
+        }
+
+        Ok(Some(self.inline(&title)?))
+    }
+
+    fn attribute_entry(&mut self, line: &str) -> RenderResult<()> {
+        let caps = self.patterns.attribute_entry.captures(line).unwrap();
+        let name = caps[2].to_string();
+
+        if !caps[1].is_empty() || !caps[3].is_empty() {
+            self.attributes.remove(&name);
+            return Ok(());
+        }
+
+        let value = caps.get(4).map(|m| m.as_str().trim()).unwrap_or_default();
+
+        if value.ends_with(" \\") || value.ends_with(" +") {
+            return unsupported("multiline attribute values");
+        }
+
+        if name == "toc" && !matches!(value, "" | "auto" | "left" | "right") {
+            return unsupported(format!("toc placement {}", value));
+        }
+
+        if name == "icons" && !matches!(value, "" | "font") {
+            return unsupported(format!("icons {}", value));
+        }
+
+        let value = self.substitute_attributes(&escape(value));
+        self.attributes.insert(name, value);
+
+        Ok(())
+    }
+
+    /// Render blocks until the end of the document or until the given delimiter is encountered
+    fn blocks(&mut self, until: Option<&str>) -> RenderResult<()> {
+        let mut meta = BlockMeta::default();
+
+        while self.pos < self.lines.len() {
+            let line = self.lines[self.pos].trim_end();
+
+            if Some(line) == until {
+                self.pos += 1;
+
+                if !meta.is_empty() {
+                    return unsupported("dangling block attributes");
+                }
+
+                return Ok(());
+            }
+
+            if line.is_empty() {
+                self.pos += 1;
+                continue;
+            }
+
+            if line.starts_with("////") {
+                self.skip_delimited(line);
+                continue;
+            }
+
+            if line.starts_with("//") {
+                self.pos += 1;
+                continue;
+            }
+
+            if self.patterns.attribute_entry.is_match(line) {
+                self.attribute_entry(line)?;
+                self.pos += 1;
+                continue;
+            }
+
+            if let Some(caps) = self.patterns.block_anchor.captures(line) {
+                meta.id = Some(caps[1].to_string());
+                self.pos += 1;
+                continue;
+            }
+
+            if let Some(caps) = self.patterns.block_attributes.captures(line) {
+                meta.merge_attributes(&caps[1])?;
+                self.pos += 1;
+                continue;
+            }
+
+            if let Some(caps) = self.patterns.block_title.captures(line) {
+                meta.title = Some(caps[1].to_string());
+                self.pos += 1;
+                continue;
+            }
+
+            if let Some(prefix) = UNSUPPORTED_PREFIXES.iter().find(|prefix| line.starts_with(*prefix)) {
+                return unsupported(prefix.trim_end_matches(':').to_string());
+            }
+
+            let meta = std::mem::take(&mut meta);
+
+            if let Some(caps) = self.patterns.section.captures(line) {
+                if until.is_some() {
+                    return unsupported("sections inside of blocks");
+                }
+
+                let (level, title) = (caps[1].len() - 1, caps[2].to_string());
+                self.section(level, &title, meta)?;
+            } else if let Some(caps) = self.patterns.image_block.captures(line) {
+                let (target, attributes) = (caps[1].to_string(), caps[2].to_string());
+                self.image(&target, &attributes, meta)?;
+            } else if let Some(kind) = delimiter_kind(line) {
+                match kind {
+                    '-' | '`' => self.listing(line, meta)?,
+                    '.' => self.literal(line, meta)?,
+                    '=' => self.admonition_block(line, meta)?,
+                    '|' => self.table(line, meta)?,
+                    _ => return unsupported(format!("{} delimited blocks", line)),
+                }
+            } else if line == "'''" {
+                self.pos += 1;
+                self.out.push_str("<hr>\n");
+            } else if line == "<<<" {
+                self.pos += 1;
+                self.out.push_str("<div style=\"page-break-after: always;\"></div>\n");
+            } else if self.patterns.list_item.is_match(line) {
+                let html = self.list(until, &meta)?;
+                self.out.push_str(&html);
+            } else if self.patterns.description_list.is_match(line) {
+                return unsupported("description lists");
+            } else {
+                self.paragraph(until, meta)?;
+            }
+        }
+
+        match until {
+            Some(delimiter) => unsupported(format!("unterminated {} block", delimiter)),
+            None => Ok(()),
+        }
+    }
+
+    fn skip_delimited(&mut self, delimiter: &str) {
+        self.pos += 1;
+
+        while self.pos < self.lines.len() && self.lines[self.pos].trim_end() != delimiter {
+            self.pos += 1;
+        }
+
+        self.pos += 1;
+    }
+
+    fn section(&mut self, level: usize, title: &str, meta: BlockMeta) -> RenderResult<()> {
+        if level == 0 {
+            return unsupported("multiple level 0 sections");
+        }
+
+        self.pos += 1;
+
+        if meta.style.is_some() || meta.title.is_some() || !meta.positional.is_empty() {
+            return unsupported("section styles");
+        }
+
+        while matches!(self.open_sections.last(), Some((open, _)) if *open >= level) {
+            self.close_section();
+        }
+
+        if !self.has_sections && !self.out.is_empty() {
+            self.out = format!(
+                "<div id=\"preamble\">\n<div class=\"sectionbody\">\n{}</div>\n</div>\n",
+                self.out
+            );
+        }
+        self.has_sections = true;
+
+        let title = self.inline(title)?;
+        let id = match meta.id {
+            Some(id) => id,
+            None => self.generate_id(&title),
+        };
+        self.ids.insert(id.clone());
+        self.xref_titles.insert(id.clone(), title.clone());
+
+        self.section_counters[level - 1] += 1;
+        for counter in self.section_counters.iter_mut().skip(level) {
+            *counter = 0;
+        }
+
+        let numbered = self.attributes.contains_key("sectnums") || self.attributes.contains_key("numbered");
+        let sectnumlevels = self
+            .attributes
+            .get("sectnumlevels")
+            .and_then(|levels| levels.parse::<usize>().ok())
+            .unwrap_or(3);
+
+        let display = if numbered && level <= sectnumlevels {
+            let number = self.section_counters[..level]
+                .iter()
+                .map(|counter| counter.to_string())
+                .collect::<Vec<_>>()
+                .join(".");
+            format!("{}. {}", number, title)
+        } else {
+            title
+        };
+
+        let mut heading = display.clone();
+        if self.attributes.contains_key("sectlinks") {
+            heading = format!("<a class=\"link\" href=\"#{}\">{}</a>", id, heading);
+        }
+        if self.attributes.contains_key("sectanchors") {
+            heading = format!("<a class=\"anchor\" href=\"#{}\"></a>{}", id, heading);
+        }
+
+        self.out.push_str(&format!(
+            "<div class=\"sect{level}\">\n<h{h} id=\"{id}\">{heading}</h{h}>\n",
+            level = level,
+            h = level + 1,
+            id = id,
+            heading = heading
+        ));
+
+        if level == 1 {
+            self.out.push_str("<div class=\"sectionbody\">\n");
+        }
+
+        self.open_sections.push((level, self.out.len()));
+        self.add_outline_entry(OutlineEntry {
+            level,
+            id,
+            title: display,
+            children: vec![],
+        });
+
+        Ok(())
+    }
+
+    fn close_section(&mut self) {
+        if let Some((level, start)) = self.open_sections.pop() {
+            if self.out.len() == start {
+                self.out.push('\n');
+            }
+
+            if level == 1 {
+                self.out.push_str("</div>\n");
+            }
+
+            self.out.push_str("</div>\n");
+        }
+    }
+
+    fn add_outline_entry(&mut self, entry: OutlineEntry) {
+        let toclevels = self
+            .attributes
+            .get("toclevels")
+            .and_then(|levels| levels.parse::<usize>().ok())
+            .unwrap_or(2);
+
+        if entry.level > toclevels {
+            return;
+        }
+
+        let mut siblings = &mut self.outline;
+
+        while matches!(siblings.last(), Some(last) if last.level < entry.level) {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+
+        siblings.push(entry);
+    }
+
+    /// Generate a section id in the same manner as asciidoctor. Tags and character references are
+    /// dropped, spaces, periods and hyphens are replaced by the separator, and any other non-word
+    /// characters are removed
+    fn generate_id(&self, title: &str) -> String {
+        let prefix = self.attributes.get("idprefix").cloned().unwrap_or_default();
+        let separator = self
+            .attributes
+            .get("idseparator")
+            .and_then(|separator| separator.chars().next())
+            .map(|c| c.to_string())
+            .unwrap_or_default();
+
+        let text = self.patterns.tag.replace_all(title, "");
+        let text = self.patterns.entity.replace_all(&text, "");
+
+        let mut id = prefix.clone();
+        let mut last_was_separator = false;
+
+        for c in text.to_lowercase().chars() {
+            if c == ' ' || c == '.' || c == '-' || separator.starts_with(c) {
+                if !last_was_separator {
+                    id.push_str(&separator);
+                }
+                last_was_separator = true;
+            } else if c.is_alphanumeric() || c == '_' {
+                id.push(c);
+                last_was_separator = false;
+            }
+        }
+
+        if !separator.is_empty() {
+            if id.ends_with(&separator) && id.len() > prefix.len() {
+                id.truncate(id.len() - separator.len());
+            }
+
+            if prefix.is_empty() {
+                id = id.trim_start_matches(&separator).to_string();
+            }
+        }
+
+        if self.ids.contains(&id) {
+            (2..)
+                .map(|count| format!("{}{}{}", id, separator, count))
+                .find(|candidate| !self.ids.contains(candidate))
+                .unwrap()
+        } else {
+            id
+        }
+    }
+
+    fn paragraph(&mut self, until: Option<&str>, meta: BlockMeta) -> RenderResult<()> {
+        let first = self.lines[self.pos];
+        let mut lines = vec![];
+
+        while self.pos < self.lines.len() {
+            let line = self.lines[self.pos].trim_end();
+
+            // Paragraphs are interrupted by the start of a delimited block or a block attribute line
+            if !lines.is_empty() && self.starts_block(line) {
+                break;
+            }
+
+            if line.is_empty() || Some(line) == until {
+                break;
+            }
+
+            if line.starts_with("//") {
+                return unsupported("comments inside of paragraphs");
+            }
+
+            lines.push(line);
+            self.pos += 1;
+        }
+
+        if meta.options.contains("hardbreaks") {
+            return unsupported("hardbreaks paragraphs");
+        }
+
+        // Paragraphs that are indented are treated as literal blocks
+        if first.starts_with(' ') || first.starts_with('\t') {
+            let indent = lines
+                .iter()
+                .map(|line| line.len() - line.trim_start().len())
+                .min()
+                .unwrap_or_default();
+            let content = lines.iter().map(|line| &line[indent..]).collect::<Vec<_>>().join("\n");
+
+            return self.verbatim("literalblock", &format!("<pre>{}</pre>", escape(&content)), meta);
+        }
+
+        let text = lines.join("\n");
+
+        if let Some(caps) = self.patterns.admonition_paragraph.captures(&text) {
+            if meta.style.is_some() {
+                return unsupported("styled admonition paragraphs");
+            }
+
+            let (label, content) = (caps[1].to_string(), caps[2].to_string());
+            let content = format!("{}\n", self.inline(&content)?);
+            return self.admonition(&label, &content, meta);
+        }
+
+        match meta.style.clone().as_deref() {
+            Some(style) if ADMONITIONS.contains(&style) => {
+                let content = format!("{}\n", self.inline(&text)?);
+                self.admonition(style, &content, meta)
+            }
+            Some(style) => unsupported(format!("{} paragraphs", style)),
+            None => {
+                let title = self.block_title(&meta)?;
+                let html = format!(
+                    "<div{} class=\"paragraph\">\n{}<p>{}</p>\n</div>\n",
+                    meta.id_attr(),
+                    title,
+                    self.inline(&text)?
+                );
+                self.out.push_str(&html);
+
+                Ok(())
+            }
+        }
+    }
+
+    fn starts_block(&self, line: &str) -> bool {
+        delimiter_kind(line).is_some()
+            || self.patterns.block_anchor.is_match(line)
+            || self.patterns.block_attributes.is_match(line)
+    }
+
+    fn block_title(&mut self, meta: &BlockMeta) -> RenderResult<String> {
+        match &meta.title {
+            Some(title) => Ok(format!("<div class=\"title\">{}</div>\n", self.inline(title)?)),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// Collect the raw lines of a delimited block, stripping leading and trailing blank lines
+    fn delimited_lines(&mut self, delimiter: &str) -> RenderResult<Vec<&'a str>> {
+        self.pos += 1;
+        let start = self.pos;
+
+        while self.pos < self.lines.len() && self.lines[self.pos].trim_end() != delimiter {
+            self.pos += 1;
+        }
+
+        if self.pos >= self.lines.len() {
+            return unsupported(format!("unterminated {} block", delimiter));
+        }
+
+        let mut lines = self.lines[start..self.pos]
+            .iter()
+            .map(|line| line.trim_end())
+            .collect::<Vec<_>>();
+        self.pos += 1;
+
+        while lines.first().map(|line| line.is_empty()).unwrap_or(false) {
+            lines.remove(0);
+        }
+
+        while lines.last().map(|line| line.is_empty()).unwrap_or(false) {
+            lines.pop();
+        }
+
+        Ok(lines)
+    }
+
+    fn listing(&mut self, delimiter: &str, meta: BlockMeta) -> RenderResult<()> {
+        let (closing, language) = if let Some(fence) = delimiter.strip_prefix("```") {
+            let language = fence.split(',').next().unwrap_or_default().trim();
+            (
+                "```",
+                Some(language.to_string()).filter(|language| !language.is_empty()),
+            )
+        } else {
+            match meta.style.as_deref() {
+                Some("source") => (delimiter, meta.positional.first().cloned()),
+                Some("listing") | None => (delimiter, None),
+                Some(style) => return unsupported(format!("{} listing blocks", style)),
+            }
+        };
+
+        if meta.named.contains_key("subs") {
+            return unsupported("custom block substitutions");
+        }
+
+        let is_source = delimiter.starts_with("```") || meta.style.as_deref() == Some("source");
+        let lines = self.delimited_lines(closing)?;
+
+        if lines.iter().any(|line| self.patterns.callout.is_match(line)) {
+            return unsupported("callouts");
+        }
+
+        let content = escape(&lines.join("\n"));
+        let pre = match (is_source, language) {
+            (true, Some(language)) => format!(
+                "<pre class=\"highlight\"><code class=\"language-{language}\" data-lang=\"{language}\">\
+                 {content}</code></pre>",
+                language = language,
+                content = content
+            ),
+            (true, None) => format!("<pre class=\"highlight\"><code>{}</code></pre>", content),
+            (false, _) => format!("<pre>{}</pre>", content),
+        };
+
+        self.verbatim("listingblock", &pre, meta)
+    }
+
+    fn literal(&mut self, delimiter: &str, meta: BlockMeta) -> RenderResult<()> {
+        if meta.style.is_some() {
+            return unsupported("styled literal blocks");
+        }
+
+        let lines = self.delimited_lines(delimiter)?;
+        let pre = format!("<pre>{}</pre>", escape(&lines.join("\n")));
+
+        self.verbatim("literalblock", &pre, meta)
+    }
+
+    fn verbatim(&mut self, class: &str, pre: &str, meta: BlockMeta) -> RenderResult<()> {
+        let title = self.block_title(&meta)?;
+        self.out.push_str(&format!(
+            "<div{} class=\"{}\">\n{}<div class=\"content\">\n{}\n</div>\n</div>\n",
+            meta.id_attr(),
+            class,
+            title,
+            pre
+        ));
+
+        Ok(())
+    }
+
+    fn admonition_block(&mut self, delimiter: &str, meta: BlockMeta) -> RenderResult<()> {
+        let label = match meta.style.as_deref() {
+            Some(style) if ADMONITIONS.contains(&style) => style.to_string(),
+            _ => return unsupported("example blocks"),
+        };
+
+        // Render the nested blocks into their own buffer
+        let outer = std::mem::take(&mut self.out);
+        self.pos += 1;
+        let result = self.blocks(Some(delimiter));
+        let content = std::mem::replace(&mut self.out, outer);
+        result?;
+
+        self.admonition(&label, &content, meta)
+    }
+
+    fn admonition(&mut self, label: &str, content: &str, meta: BlockMeta) -> RenderResult<()> {
+        let name = label.to_lowercase();
+        let caption = format!("{}{}", &label[..1], &name[1..]);
+
+        let icon = if self.attributes.get("icons").map(|icons| icons.as_str()) == Some("font") {
+            format!("<i class=\"fa icon-{}\" title=\"{}\"></i>", name, caption)
+        } else {
+            format!("<div class=\"title\">{}</div>", caption)
+        };
+
+        let title = self.block_title(&meta)?;
+        self.out.push_str(&format!(
+            "<div{id} class=\"admonitionblock {name}\">\n<table>\n<tr>\n<td class=\"icon\">\n{icon}\n</td>\n\
+             <td class=\"content\">\n{title}{content}</td>\n</tr>\n</table>\n</div>\n",
+            id = meta.id_attr(),
+            name = name,
+            icon = icon,
+            title = title,
+            content = content
+        ));
+
+        Ok(())
+    }
+
+    fn image(&mut self, target: &str, attributes: &str, meta: BlockMeta) -> RenderResult<()> {
+        self.pos += 1;
+
+        let mut image_meta = BlockMeta::default();
+        for (index, attribute) in split_attribute_list(attributes).into_iter().enumerate() {
+            match attribute.split_once('=') {
+                Some((name, value)) if matches!(name.trim(), "alt" | "width" | "height") => {
+                    image_meta
+                        .named
+                        .insert(name.trim().to_string(), value.trim().trim_matches('"').to_string());
+                }
+                Some((name, _)) => return unsupported(format!("image attribute {}", name.trim())),
+                None => {
+                    let name = ["alt", "width", "height"].get(index).copied();
+                    match name {
+                        Some(name) if !attribute.is_empty() => {
+                            image_meta.named.insert(name.to_string(), attribute);
+                        }
+                        Some(_) => (),
+                        None => return unsupported("image positional attributes"),
+                    }
+                }
+            }
+        }
+
+        if meta.style.is_some() {
+            return unsupported("styled images");
+        }
+
+        let img = self.img(target, &image_meta.named)?;
+        let title = match &meta.title {
+            Some(title) => {
+                let title = self.inline(title)?;
+                self.figure_count += 1;
+                format!("<div class=\"title\">Figure {}. {}</div>\n", self.figure_count, title)
+            }
+            None => String::new(),
+        };
+
+        self.out.push_str(&format!(
+            "<div{} class=\"imageblock\">\n<div class=\"content\">\n{}\n</div>\n{}</div>\n",
+            meta.id_attr(),
+            img,
+            title
+        ));
+
+        Ok(())
+    }
+
+    fn img(&self, target: &str, attributes: &HashMap<String, String>) -> RenderResult<String> {
+        let target = self.substitute_attributes(target.trim());
+        let target = match self.attributes.get("imagesdir") {
+            Some(dir) if !dir.is_empty() && !target.contains("://") && !target.starts_with('/') => {
+                format!("{}/{}", dir.trim_end_matches('/'), target)
+            }
+            _ => target,
+        };
+
+        let alt = attributes.get("alt").cloned().unwrap_or_else(|| {
+            let file = target.rsplit('/').next().unwrap_or_default();
+            let stem = file.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(file);
+            stem.replace(['_', '-'], " ")
+        });
+
+        let mut img = format!(
+            "<img src=\"{}\" alt=\"{}\"",
+            escape_attribute(&target),
+            escape_attribute(&alt)
+        );
+        for dimension in ["width", "height"] {
+            if let Some(value) = attributes.get(dimension) {
+                img.push_str(&format!(" {}=\"{}\"", dimension, escape_attribute(value)));
+            }
+        }
+        img.push('>');
+
+        Ok(img)
+    }
+
+    fn table(&mut self, delimiter: &str, meta: BlockMeta) -> RenderResult<()> {
+        if meta.style.is_some() || meta.named.keys().any(|name| name != "cols") {
+            return unsupported("table attributes");
+        }
+
+        let lines = self.delimited_lines(delimiter)?;
+        let text = lines.join("\n");
+
+        // Split the table contents in to cells
+        let mut segments = split_cells(&text).into_iter();
+        if !segments.next().unwrap_or_default().trim().is_empty() {
+            return unsupported("table cell formatting");
+        }
+
+        let mut cells = vec![];
+        let mut segments = segments.peekable();
+        while let Some(segment) = segments.next() {
+            // Cell specifiers are attached to the end of the preceding cell
+            if segments.peek().is_some() && !segment.ends_with(char::is_whitespace) {
+                let last = segment.rsplit(char::is_whitespace).next().unwrap_or_default();
+                if !last.is_empty() && self.patterns.table_cell_spec.is_match(&segment) {
+                    return unsupported("table cell specifiers");
+                }
+            }
+
+            cells.push(segment.trim().to_string());
+        }
+
+        let first_line_cells = lines
+            .first()
+            .map(|line| split_cells(line).len().saturating_sub(1))
+            .unwrap_or_default();
+
+        let widths = match meta.named.get("cols") {
+            Some(cols) => column_widths(cols)?,
+            None => vec![1; first_line_cells.max(1)],
+        };
+        let columns = widths.len();
+
+        if cells.is_empty() || cells.len() % columns != 0 {
+            return unsupported("tables with spanning cells");
+        }
+
+        let implicit_header = first_line_cells == columns && lines.get(1).map(|line| line.is_empty()).unwrap_or(false);
+        let header = !meta.options.contains("noheader") && (meta.options.contains("header") || implicit_header);
+
+        let mut html = format!(
+            "<table{} class=\"tableblock frame-all grid-all stretch\">\n",
+            meta.id_attr()
+        );
+
+        if let Some(title) = &meta.title {
+            let title = self.inline(title)?;
+            self.table_count += 1;
+            html.push_str(&format!(
+                "<caption class=\"title\">Table {}. {}</caption>\n",
+                self.table_count, title
+            ));
+        }
+
+        html.push_str("<colgroup>\n");
+        for width in format_widths(&widths) {
+            html.push_str(&format!("<col style=\"width: {}%;\">\n", width));
+        }
+        html.push_str("</colgroup>\n");
+
+        let mut rows = cells.chunks(columns);
+
+        if header {
+            if let Some(row) = rows.next() {
+                html.push_str("<thead>\n<tr>\n");
+                for cell in row {
+                    html.push_str(&format!(
+                        "<th class=\"tableblock halign-left valign-top\">{}</th>\n",
+                        self.inline(cell)?
+                    ));
+                }
+                html.push_str("</tr>\n</thead>\n");
+            }
+        }
+
+        html.push_str("<tbody>\n");
+        for row in rows {
+            html.push_str("<tr>\n");
+            for cell in row {
+                let paragraphs = cell
+                    .split("\n\n")
+                    .map(|paragraph| paragraph.trim())
+                    .filter(|paragraph| !paragraph.is_empty())
+                    .map(|paragraph| Ok(format!("<p class=\"tableblock\">{}</p>", self.inline(paragraph)?)))
+                    .collect::<RenderResult<Vec<_>>>()?;
+
+                html.push_str(&format!(
+                    "<td class=\"tableblock halign-left valign-top\">{}</td>\n",
+                    paragraphs.join("\n")
+                ));
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</tbody>\n</table>\n");
+
+        self.out.push_str(&html);
+
+        Ok(())
+    }
+
+    fn list_marker(&self, line: &str) -> Option<(String, String)> {
+        self.patterns.list_item.captures(line).map(|caps| {
+            let marker = if caps[1].ends_with('.') && caps[1].starts_with(|c: char| c.is_ascii_digit()) {
+                ".".to_string()
+            } else {
+                caps[1].to_string()
+            };
+
+            (marker, caps[2].to_string())
+        })
+    }
+
+    fn list(&mut self, until: Option<&str>, meta: &BlockMeta) -> RenderResult<String> {
+        if meta.style.is_some() || !meta.positional.is_empty() || !meta.named.is_empty() {
+            return unsupported("list styles");
+        }
+
+        let (marker, _) = self.list_marker(self.lines[self.pos]).unwrap();
+        let mut ancestors = vec![];
+        let html = self.list_at(&marker, until, &mut ancestors)?;
+
+        if meta.id.is_none() && meta.title.is_none() {
+            return Ok(html);
+        }
+
+        // Splice the id and title in to the wrapping div of the list
+        let (open, rest) = html.split_once('\n').unwrap_or_default();
+        let open = open.replacen("<div", &format!("<div{}", meta.id_attr()), 1);
+
+        Ok(format!("{}\n{}{}", open, self.block_title(meta)?, rest))
+    }
+
+    fn list_at(&mut self, marker: &str, until: Option<&str>, ancestors: &mut Vec<String>) -> RenderResult<String> {
+        let mut items = String::new();
+
+        loop {
+            let (_, text) = self.list_marker(self.lines[self.pos]).unwrap();
+            self.pos += 1;
+
+            if text.starts_with("[ ]") || text.starts_with("[x]") || text.starts_with("[*]") {
+                return unsupported("checklists");
+            }
+
+            let mut lines = vec![text.trim().to_string()];
+
+            while self.pos < self.lines.len() {
+                let line = self.lines[self.pos].trim_end();
+
+                if line.is_empty() || Some(line) == until || self.patterns.list_item.is_match(line) {
+                    break;
+                }
+
+                if line == "+" {
+                    return unsupported("list continuations");
+                }
+
+                if !line.starts_with("//") {
+                    lines.push(line.trim().to_string());
+                }
+
+                self.pos += 1;
+            }
+
+            items.push_str(&format!("<li>\n<p>{}</p>\n", self.inline(&lines.join("\n"))?));
+
+            // Determine if the following list item is a sibling, a child or belongs to an ancestor
+            let next = loop {
+                let resume = self.pos;
+
+                while self.pos < self.lines.len() && self.lines[self.pos].trim().is_empty() {
+                    self.pos += 1;
+                }
+
+                let next_marker = self
+                    .lines
+                    .get(self.pos)
+                    .filter(|line| Some(line.trim_end()) != until)
+                    .and_then(|line| self.list_marker(line))
+                    .map(|(marker, _)| marker);
+
+                match next_marker {
+                    Some(next) if next != marker && !ancestors.iter().any(|ancestor| ancestor == &next) => {
+                        ancestors.push(marker.to_string());
+                        let nested = self.list_at(&next, until, ancestors);
+                        ancestors.pop();
+                        items.push_str(&nested?);
+                    }
+                    Some(next) => break Some(next),
+                    None => {
+                        self.pos = resume;
+                        break None;
+                    }
+                }
+            };
+
+            items.push_str("</li>\n");
+
+            if next.as_deref() != Some(marker) {
+                break;
+            }
+        }
+
+        let html = if marker.starts_with('.') {
+            let (class, kind) = match marker.len() {
+                1 => ("arabic", None),
+                2 => ("loweralpha", Some("a")),
+                3 => ("lowerroman", Some("i")),
+                4 => ("upperalpha", Some("A")),
+                _ => ("upperroman", Some("I")),
+            };
+            let kind = kind.map(|kind| format!(" type=\"{}\"", kind)).unwrap_or_default();
+
+            format!(
+                "<div class=\"olist {class}\">\n<ol class=\"{class}\"{kind}>\n{items}</ol>\n</div>\n",
+                class = class,
+                kind = kind,
+                items = items
+            )
+        } else {
+            format!("<div class=\"ulist\">\n<ul>\n{}</ul>\n</div>\n", items)
+        };
+
+        Ok(html)
+    }
+
+    fn substitute_attributes(&self, text: &str) -> String {
+        self.patterns
+            .attribute_reference
+            .replace_all(text, |caps: &Captures| {
+                let name = &caps[1];
+
+                match self.attributes.get(name) {
+                    Some(value) => value.clone(),
+                    None => match name {
+                        "nbsp" => "&#160;".to_string(),
+                        "sp" => " ".to_string(),
+                        "empty" => String::new(),
+                        "amp" => "&amp;".to_string(),
+                        "lt" => "&lt;".to_string(),
+                        "gt" => "&gt;".to_string(),
+                        "plus" => "&#43;".to_string(),
+                        "vbar" => "|".to_string(),
+                        "zwsp" => "&#8203;".to_string(),
+                        _ => caps[0].to_string(),
+                    },
+                }
+            })
+            .to_string()
+    }
+
+    fn placeholder(&mut self, html: String) -> String {
+        self.placeholders.push(html);
+        format!("\u{1}{}\u{2}", self.placeholders.len() - 1)
+    }
+
+    fn link(&mut self, target: &str, text: &str, bare: bool) -> RenderResult<String> {
+        let (text, blank) = match text.strip_suffix('^') {
+            Some(text) => (text, true),
+            None => (text, false),
+        };
+
+        let window = if blank {
+            " target=\"_blank\" rel=\"noopener\""
+        } else {
+            ""
+        };
+
+        let html = if text.is_empty() {
+            let class = if bare { " class=\"bare\"" } else { "" };
+            format!("<a href=\"{}\"{}{}>{}</a>", target, class, window, target)
+        } else {
+            format!("<a href=\"{}\"{}>{}</a>", target, window, self.quotes(text))
+        };
+
+        Ok(self.placeholder(html))
+    }
+
+    /// Apply inline substitutions to a fragment of text in the same order that asciidoctor does for
+    /// normal content: special characters, passthroughs, attributes, macros, quotes, replacements
+    /// and line breaks
+    fn inline(&mut self, text: &str) -> RenderResult<String> {
+        if let Some(construct) = UNSUPPORTED_INLINE.iter().find(|construct| text.contains(*construct)) {
+            return unsupported(construct.trim_end_matches(['[', ':']).to_string());
+        }
+
+        if text.contains("[[") {
+            return unsupported("inline anchors");
+        }
+
+        let mut text = escape(text);
+
+        if self.patterns.escaped_markup.is_match(&text) || self.patterns.inline_role.is_match(&text) {
+            return unsupported("escaped or styled inline markup");
+        }
+
+        // Passthroughs
+        text = self.replace(
+            &text,
+            |r| &r.patterns.raw_passthrough,
+            |r, caps| Ok(r.placeholder(unescape(&caps[1]))),
+        )?;
+        text = self.replace(
+            &text,
+            |r| &r.patterns.unconstrained_passthrough,
+            |r, caps| Ok(r.placeholder(caps[1].to_string())),
+        )?;
+        text = replace_constrained(&text, '+', |content| self.placeholder(content.to_string()));
+
+        text = self.substitute_attributes(&text);
+
+        // Macros
+        text = self.replace(
+            &text,
+            |r| &r.patterns.inline_image,
+            |r, caps| {
+                let mut attributes = HashMap::new();
+                let alt = caps[2].split(',').next().unwrap_or_default().trim();
+                if !alt.is_empty() {
+                    attributes.insert("alt".to_string(), alt.to_string());
+                }
+                let img = r.img(&caps[1], &attributes)?;
+                Ok(r.placeholder(format!("<span class=\"image\">{}</span>", img)))
+            },
+        )?;
+        text = self.replace(
+            &text,
+            |r| &r.patterns.xref,
+            |r, caps| {
+                let label = match caps.get(2) {
+                    Some(label) => r.quotes(label.as_str()),
+                    None => format!("\u{3}{}\u{3}", &caps[1]),
+                };
+                Ok(r.placeholder(format!("<a href=\"#{}\">{}</a>", &caps[1], label)))
+            },
+        )?;
+        text = self.replace(
+            &text,
+            |r| &r.patterns.xref_macro,
+            |r, caps| {
+                let id = caps[1].trim_start_matches('#');
+                let label = if caps[2].is_empty() {
+                    format!("\u{3}{}\u{3}", id)
+                } else {
+                    r.quotes(&caps[2])
+                };
+                Ok(r.placeholder(format!("<a href=\"#{}\">{}</a>", id, label)))
+            },
+        )?;
+        text = self.replace(
+            &text,
+            |r| &r.patterns.link_macro,
+            |r, caps| r.link(&caps[1], &caps[2], false),
+        )?;
+        text = self.replace(
+            &text,
+            |r| &r.patterns.mailto_macro,
+            |r, caps| {
+                let label = if caps[2].is_empty() { &caps[1] } else { &caps[2] };
+                r.link(&format!("mailto:{}", &caps[1]), label, false)
+            },
+        )?;
+        text = self.replace(&text, |r| &r.patterns.angle_url, |r, caps| r.link(&caps[1], "", true))?;
+        text = self.replace(
+            &text,
+            |r| &r.patterns.url,
+            |r, caps| {
+                let mut url = caps[2].to_string();
+                let mut trailing = String::new();
+
+                // Trailing punctuation and escaped angle brackets are not considered part of bare urls
+                if caps.get(3).is_none() {
+                    loop {
+                        if let Some(stripped) = url.strip_suffix("&gt;") {
+                            trailing.insert_str(0, "&gt;");
+                            url = stripped.to_string();
+                        } else if url.ends_with(['.', ',', ';', ':', '!', '?', ')']) {
+                            trailing.insert(0, url.pop().unwrap());
+                        } else {
+                            break;
+                        }
+                    }
+                }
+
+                let label = caps.get(3).map(|label| label.as_str()).unwrap_or_default();
+                let link = r.link(&url, label, true)?;
+                Ok(format!("{}{}{}", &caps[1], link, trailing))
+            },
+        )?;
+        text = self.replace(
+            &text,
+            |r| &r.patterns.email,
+            |r, caps| {
+                let link = r.placeholder(format!("<a href=\"mailto:{}\">{}</a>", &caps[2], &caps[2]));
+                Ok(format!("{}{}", &caps[1], link))
+            },
+        )?;
+
+        text = self.quotes(&text);
+        text = self.replacements(&text);
+        text = self.patterns.hard_break.replace_all(&text, "<br>").to_string();
+
+        // Placeholders may themselves contain placeholders (i.e. passthroughs inside of link text)
+        while self.patterns.placeholder.is_match(&text) {
+            let placeholders = &self.placeholders;
+            text = self
+                .patterns
+                .placeholder
+                .replace_all(&text, |caps: &Captures| {
+                    placeholders[caps[1].parse::<usize>().unwrap()].clone()
+                })
+                .to_string();
+        }
+
+        Ok(text)
+    }
+
+    fn replace<P, F>(&mut self, text: &str, pattern: P, mut f: F) -> RenderResult<String>
+    where
+        P: Fn(&Self) -> &Regex,
+        F: FnMut(&mut Self, &Captures) -> RenderResult<String>,
+    {
+        let captures = pattern(self)
+            .captures_iter(text)
+            .map(|caps| {
+                let whole = caps.get(0).unwrap();
+                (whole.start(), whole.end(), caps)
+            })
+            .collect::<Vec<_>>();
+
+        let mut result = String::new();
+        let mut last = 0;
+
+        for (start, end, caps) in captures {
+            result.push_str(&text[last..start]);
+            result.push_str(&f(self, &caps)?);
+            last = end;
+        }
+
+        result.push_str(&text[last..]);
+
+        Ok(result)
+    }
+
+    fn quotes(&self, text: &str) -> String {
+        let text = self.patterns.strong.replace_all(text, "<strong>$1</strong>");
+        let text = replace_constrained(&text, '*', |content| format!("<strong>{}</strong>", content));
+        let text = self.patterns.monospace.replace_all(&text, "<code>$1</code>");
+        let text = replace_constrained(&text, '`', |content| format!("<code>{}</code>", content));
+        let text = self.patterns.emphasis.replace_all(&text, "<em>$1</em>");
+        let text = replace_constrained(&text, '_', |content| format!("<em>{}</em>", content));
+        let text = self.patterns.mark.replace_all(&text, "<mark>$1</mark>");
+        let text = replace_constrained(&text, '#', |content| format!("<mark>{}</mark>", content));
+        let text = self.patterns.superscript.replace_all(&text, "<sup>$1</sup>");
+
+        self.patterns.subscript.replace_all(&text, "<sub>$1</sub>").to_string()
+    }
+
+    fn replacements(&self, text: &str) -> String {
+        let text = text
+            .replace("(C)", "&#169;")
+            .replace("(R)", "&#174;")
+            .replace("(TM)", "&#8482;");
+        let text = self.patterns.spaced_em_dash.replace_all(&text, "&#8201;&#8212;&#8201;");
+        let text = self.patterns.em_dash.replace_all(&text, "$1&#8212;&#8203;$2");
+        let text = text
+            .replace("...", "&#8230;&#8203;")
+            .replace("-&gt;", "&#8594;")
+            .replace("=&gt;", "&#8658;")
+            .replace("&lt;-", "&#8592;")
+            .replace("&lt;=", "&#8656;");
+
+        self.patterns.apostrophe.replace_all(&text, "$1&#8217;$2").to_string()
+    }
+}
+
+/// Replace constrained formatting pairs (i.e. *bold*). A constrained pair must not be surrounded by
+/// word characters and its content may not begin or end with whitespace
+fn replace_constrained<F>(text: &str, mark: char, mut f: F) -> String
+where
+    F: FnMut(&str) -> String,
+{
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut result = String::new();
+    let mut last = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let opens = c == mark
+            && (i == 0 || !(is_word(chars[i - 1].1) || matches!(chars[i - 1].1, ';' | ':' | '}' | '&')))
+            && chars
+                .get(i + 1)
+                .map(|(_, next)| !next.is_whitespace() && *next != mark)
+                .unwrap_or(false);
+
+        if opens {
+            let close = (i + 1..chars.len()).find(|&j| {
+                chars[j].1 == mark
+                    && !chars[j - 1].1.is_whitespace()
+                    && chars.get(j + 1).map(|(_, next)| !is_word(*next)).unwrap_or(true)
+            });
+
+            if let Some(j) = close {
+                let content_end = chars[j].0;
+                result.push_str(&text[last..start]);
+                result.push_str(&f(&text[start + c.len_utf8()..content_end]));
+                last = content_end + mark.len_utf8();
+                i = j + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    result.push_str(&text[last..]);
+    result
+}
+
+/// Determine the kind of delimited block that a line opens, if any, identified by the character
+/// that the delimiter is composed of
+fn delimiter_kind(line: &str) -> Option<char> {
+    if line.starts_with("```") {
+        return Some('`');
+    }
+
+    if line == "--" {
+        return Some('o');
+    }
+
+    if line.len() >= 4 && line.starts_with('|') && line[1..].chars().all(|c| c == '=') {
+        return Some('|');
+    }
+
+    let first = line.chars().next()?;
+
+    if line.len() >= 4 && matches!(first, '-' | '.' | '=' | '*' | '_' | '+') && line.chars().all(|c| c == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// Split table contents on unescaped cell separators
+fn split_cells(text: &str) -> Vec<String> {
+    let mut cells = vec![];
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                current.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+
+    cells.push(current);
+    cells
+}
+
+fn column_widths(cols: &str) -> RenderResult<Vec<usize>> {
+    if let Ok(count) = cols.trim().parse::<usize>() {
+        return Ok(vec![1; count.max(1)]);
+    }
+
+    cols.split([',', ';'])
+        .map(|col| {
+            let col = col.trim();
+
+            if col.is_empty() {
+                Ok(1)
+            } else {
+                col.parse::<usize>()
+                    .map_err(|_| Unsupported(format!("table column specifier {}", col)))
+            }
+        })
+        .collect()
+}
+
+/// Convert proportional widths to percentages in the same manner as asciidoctor, where the final
+/// column absorbs any rounding error
+fn format_widths(widths: &[usize]) -> Vec<String> {
+    let total = widths.iter().sum::<usize>() as f64;
+    let mut assigned = 0.0;
+    let mut formatted = vec![];
+
+    for (index, width) in widths.iter().enumerate() {
+        let percent = if index == widths.len() - 1 {
+            ((100.0 - assigned) * 10000.0_f64).round() / 10000.0
+        } else {
+            (*width as f64 * 100.0 / total * 10000.0).floor() / 10000.0
+        };
+        assigned += percent;
+
+        let mut value = format!("{:.4}", percent);
+        while value.ends_with('0') {
+            value.pop();
+        }
+        if value.ends_with('.') {
+            value.pop();
+        }
+
+        formatted.push(value);
+    }
+
+    formatted
+}
+
+fn render_outline(entries: &[OutlineEntry]) -> String {
+    let level = entries.first().map(|entry| entry.level).unwrap_or(1);
+    let mut html = format!("<ul class=\"sectlevel{}\">\n", level);
+
+    for entry in entries {
+        if entry.children.is_empty() {
+            html.push_str(&format!("<li><a href=\"#{}\">{}</a></li>\n", entry.id, entry.title));
+        } else {
+            html.push_str(&format!(
+                "<li><a href=\"#{}\">{}</a>\n{}</li>\n",
+                entry.id,
+                entry.title,
+                render_outline(&entry.children)
+            ));
+        }
+    }
+
+    html.push_str("</ul>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
+
+/// Escape text for use as the value of a double quoted HTML attribute. Inline content has already
+/// been escaped by the time that it is read, so the text is unescaped first to avoid escaping it
+/// twice
+fn escape_attribute(text: &str) -> String {
+    escape(&unescape(text)).replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_sections_with_numbers_and_toc() {
+        let content = r#":toc: left
+:numbered:
+
+= RFD 1 Sections
+
+Preamble text.
+
+== First Section
+
+Body of the first section.
+
+=== Don't Panic
+
+See <<_first_section>>.
+
+== Second Section
+"#;
+
+        let expected = r##"<div id="toc" class="toc">
+<div id="toctitle">Table of Contents</div>
+<ul class="sectlevel1">
+<li><a href="#_first_section">1. First Section</a>
+<ul class="sectlevel2">
+<li><a href="#_dont_panic">1.1. Don&#8217;t Panic</a></li>
+</ul>
+</li>
+<li><a href="#_second_section">2. Second Section</a></li>
+</ul>
+</div>
+<div id="preamble">
+<div class="sectionbody">
+<div class="paragraph">
+<p>Preamble text.</p>
+</div>
+</div>
+</div>
+<div class="sect1">
+<h2 id="_first_section">1. First Section</h2>
+<div class="sectionbody">
+<div class="paragraph">
+<p>Body of the first section.</p>
+</div>
+<div class="sect2">
+<h3 id="_dont_panic">1.1. Don&#8217;t Panic</h3>
+<div class="paragraph">
+<p>See <a href="#_first_section">First Section</a>.</p>
+</div>
+</div>
+</div>
+</div>
+<div class="sect1">
+<h2 id="_second_section">2. Second Section</h2>
+<div class="sectionbody">
+
+</div>
+</div>
+"##;
+
+        assert_eq!(expected, render_html(content).unwrap());
+    }
+
+    #[test]
+    fn test_renders_inline_markup() {
+        let content = "Some *bold*, _emphasized_ and `+monospace_text+` with a https://oxide.computer[link] \
+                       and https://example.com/a_b_c.";
+
+        assert_eq!(
+            "<div class=\"paragraph\">\n<p>Some <strong>bold</strong>, <em>emphasized</em> and \
+             <code>monospace_text</code> with a <a href=\"https://oxide.computer\">link</a> and \
+             <a href=\"https://example.com/a_b_c\" class=\"bare\">https://example.com/a_b_c</a>.</p>\n</div>\n",
+            render_html(content).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_escapes_image_attributes() {
+        let content = r#"image::diagram.svg" onerror="alert(1).svg[]
+
+See image:icon.png[a"><script>alert(1)</script>] here.
+"#;
+
+        let expected = r#"<div class="imageblock">
+<div class="content">
+<img src="diagram.svg&quot; onerror=&quot;alert(1).svg" alt="diagram.svg&quot; onerror=&quot;alert(1)">
+</div>
+</div>
+<div class="paragraph">
+<p>See <span class="image"><img src="icon.png" alt="a&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;"></span> here.</p>
+</div>
+"#;
+
+        assert_eq!(expected, render_html(content).unwrap());
+    }
+
+    #[test]
+    fn test_renders_nested_lists_admonitions_and_images() {
+        let content = r#":icons: font
+
+* One
+** Nested
+* Two
+
+NOTE: Take note.
+
+[WARNING]
+====
+A warning.
+====
+
+.A diagram
+image::diagram-one.svg[]
+"#;
+
+        let expected = r#"<div class="ulist">
+<ul>
+<li>
+<p>One</p>
+<div class="ulist">
+<ul>
+<li>
+<p>Nested</p>
+</li>
+</ul>
+</div>
+</li>
+<li>
+<p>Two</p>
+</li>
+</ul>
+</div>
+<div class="admonitionblock note">
+<table>
+<tr>
+<td class="icon">
+<i class="fa icon-note" title="Note"></i>
+</td>
+<td class="content">
+Take note.
+</td>
+</tr>
+</table>
+</div>
+<div class="admonitionblock warning">
+<table>
+<tr>
+<td class="icon">
+<i class="fa icon-warning" title="Warning"></i>
+</td>
+<td class="content">
+<div class="paragraph">
+<p>A warning.</p>
+</div>
+</td>
+</tr>
+</table>
+</div>
+<div class="imageblock">
+<div class="content">
+<img src="diagram-one.svg" alt="diagram one">
+</div>
+<div class="title">Figure 1. A diagram</div>
+</div>
+"#;
+
+        assert_eq!(expected, render_html(content).unwrap());
+    }
+
+    #[test]
+    fn test_renders_tables() {
+        let content = r#"[cols="1,2"]
+|===
+|Name |Description
+
+|`foo`
+|The foo
+|===
+"#;
+
+        let expected = r#"<table class="tableblock frame-all grid-all stretch">
+<colgroup>
+<col style="width: 33.3333%;">
+<col style="width: 66.6667%;">
+</colgroup>
+<thead>
+<tr>
+<th class="tableblock halign-left valign-top">Name</th>
+<th class="tableblock halign-left valign-top">Description</th>
+</tr>
+</thead>
+<tbody>
+<tr>
+<td class="tableblock halign-left valign-top"><p class="tableblock"><code>foo</code></p></td>
+<td class="tableblock halign-left valign-top"><p class="tableblock">The foo</p></td>
+</tr>
+</tbody>
+</table>
+"#;
+
+        assert_eq!(expected, render_html(content).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_unsupported_constructs() {
+        let cases = [
+            "include::other.adoc[]",
+            "Text with a footnote:[note].",
+            "[source,rust]\n----\nfn main() {} // <1>\n----",
+            "Term:: Definition",
+            "****\nSidebar\n****",
+            "|===\n2+|Spanning\n|===",
+            "* Item\n+\nContinuation",
+        ];
+
+        for case in cases {
+            assert!(render_html(case).is_err(), "Expected {:?} to be unsupported", case);
+        }
+    }
+}
@@ -1,12 +1,22 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use hmac::{Hmac, Mac};
 use md5::Md5;
-use meilisearch_minimal_api::{IndexClient, MeiliClient, SearchQuery};
+use meilisearch_minimal_api::{IndexClient, IndexSettings, MeiliClient, SearchQuery};
 use parse_rfd::{parse, ParsedDoc, Section};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{cmp::min, collections::HashMap};
-
-use super::RFDNumber;
+use sha2::Sha256;
+use std::{
+    cmp::min,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::{
+    metadata::{RFDHeaderFormat, RFDMetadata},
+    RFDContent, RFDNumber, RFDState,
+};
 
 pub struct RFDSearchIndex {}
 
@@ -17,6 +27,10 @@ pub struct RfdId {
 }
 
 type HmacMd5 = Hmac<Md5>;
+type HmacSha256 = Hmac<Sha256>;
+
+const HIGHLIGHT_PRE_TAG: &str = "<mark>";
+const HIGHLIGHT_POST_TAG: &str = "</mark>";
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct IndexDocument {
@@ -28,6 +42,15 @@ pub struct IndexDocument {
     pub anchor: String,
     pub content: String,
     pub rfd_number: i32,
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// The names of the authors of the RFD
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub author_emails: Vec<String>,
     #[serde(flatten)]
     pub hierarchy: HashMap<String, String>,
     #[serde(flatten)]
@@ -35,7 +58,7 @@ pub struct IndexDocument {
 }
 
 impl IndexDocument {
-    pub fn new(section: Section, rfd_number: &RFDNumber, title: &str) -> Self {
+    pub fn new(section: Section, rfd_number: &RFDNumber, title: &str, metadata: &RFDMetadata) -> Self {
         let level = section.parents.len() + 1;
 
         let mut hierarchy_radio = HashMap::new();
@@ -79,6 +102,14 @@ impl IndexDocument {
             anchor: section.section_id,
             content: section.content,
             rfd_number: rfd_number.into(),
+            state: metadata.state_string(),
+            labels: metadata.labels.clone(),
+            authors: metadata.authors.iter().map(|author| author.name.clone()).collect(),
+            author_emails: metadata
+                .authors
+                .iter()
+                .filter_map(|author| author.email.clone())
+                .collect(),
             hierarchy,
             hierarchy_radio,
         }
@@ -107,26 +138,425 @@ impl RFDSearchIndex {
         Ok(())
     }
 
+    /// Compare the sections parsed from the contents of an RFD against the documents that are
+    /// currently stored in the index for that RFD
+    pub async fn diff_rfd(index: &IndexClient, rfd_number: &RFDNumber, content: &str) -> Result<RFDIndexDiff> {
+        let existing = Self::find_rfd_documents(index, rfd_number).await?;
+        let parsed = Self::parse_document(rfd_number, content)?;
+
+        Ok(RFDIndexDiff::new(rfd_number, parsed, existing))
+    }
+
+    /// Add the missing documents, replace the outdated documents and remove the orphaned documents
+    /// described by a diff
+    pub async fn apply_diff(index: &IndexClient, diff: &RFDIndexDiff) -> Result<()> {
+        if !diff.orphaned.is_empty() {
+            index.delete_documents(&diff.orphaned).await?;
+        }
+
+        if !diff.missing.is_empty() {
+            index.index_documents(&diff.missing, "objectID").await?;
+        }
+
+        // Indexing a document with an existing id replaces the stored document
+        if !diff.outdated.is_empty() {
+            index.index_documents(&diff.outdated, "objectID").await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn find_rfd_ids(index: &IndexClient, rfd_number: &RFDNumber) -> Result<Vec<String>> {
         let mut query = SearchQuery::default();
         query.filter = Some(vec![format!("rfd_number = {}", rfd_number.0)]);
+        query.attributes_to_retrieve = Some(vec!["objectID".to_string()]);
+
+        // Searches only return 20 hits by default. Request enough hits to cover every section of
+        // even the longest RFDs
+        query.limit = Some(1000);
 
         let results = index.search::<RfdId>(query).await?;
 
         Ok(results.hits.into_iter().map(|hit| hit.object_id).collect::<Vec<_>>())
     }
 
+    /// Find the documents of an RFD along with the metadata fields that searches filter on
+    async fn find_rfd_documents(index: &IndexClient, rfd_number: &RFDNumber) -> Result<Vec<IndexedDocument>> {
+        let mut query = SearchQuery::default();
+        query.filter = Some(vec![format!("rfd_number = {}", rfd_number.0)]);
+        query.attributes_to_retrieve = Some(
+            ["objectID", "state", "labels", "authors", "author_emails"]
+                .iter()
+                .map(|attribute| attribute.to_string())
+                .collect(),
+        );
+        query.limit = Some(1000);
+
+        Ok(index.search::<IndexedDocument>(query).await?.hits)
+    }
+
     pub fn parse_document(rfd_number: &RFDNumber, content: &str) -> Result<Vec<IndexDocument>> {
         let ParsedDoc { title, sections } = parse(content)?;
+
+        // Read the header in the format of the RFD, falling back to Asciidoc when the format can
+        // not be detected
+        let metadata = RFDContent::new(content)
+            .map(|content| content.metadata())
+            .unwrap_or_else(|_| RFDMetadata::parse(content, RFDHeaderFormat::Asciidoc));
+
         Ok(sections
             .into_iter()
-            .map(|section| IndexDocument::new(section, rfd_number, &title))
+            .map(|section| IndexDocument::new(section, rfd_number, &title, &metadata))
             .collect::<Vec<_>>())
     }
+
+    /// Mark the attributes that searches filter on as filterable. This only needs to be run when
+    /// an index is created or when the list of filterable attributes changes
+    pub async fn configure_index(client: &MeiliClient, index: String) -> Result<()> {
+        let mut settings = IndexSettings::default();
+        settings.filterable_attributes = Some(
+            ["rfd_number", "state", "labels", "authors", "author_emails"]
+                .iter()
+                .map(|attribute| attribute.to_string())
+                .collect(),
+        );
+
+        client.index(index).settings(settings).await?;
+
+        Ok(())
+    }
+
+    /// Search the sections of all indexed RFDs. Matching terms in the returned hits are wrapped in
+    /// <mark> tags
+    pub async fn search(
+        client: &MeiliClient,
+        index: String,
+        query: &str,
+        filter: &RFDSearchFilter,
+        limit: usize,
+        offset: usize,
+    ) -> Result<RFDSearchResults> {
+        let mut search = SearchQuery::default();
+        search.query = Some(query.to_string());
+        search.limit = Some(limit);
+        search.offset = Some(offset);
+        search.attributes_to_highlight = Some(vec!["name".to_string(), "content".to_string()]);
+        search.highlight_pre_tag = Some(HIGHLIGHT_PRE_TAG.to_string());
+        search.highlight_post_tag = Some(HIGHLIGHT_POST_TAG.to_string());
+        search.attributes_to_crop = Some(vec!["content".to_string()]);
+        search.crop_length = Some(30);
+
+        let expressions = filter.expressions();
+        if !expressions.is_empty() {
+            search.filter = Some(expressions);
+        }
+
+        let results = client.index(index).search::<SearchHit>(search).await?;
+
+        Ok(RFDSearchResults {
+            hits: results.hits.into_iter().map(RFDSearchHit::from).collect(),
+            estimated_total_hits: results.estimated_total_hits,
+        })
+    }
+}
+
+/// The id and filterable metadata of a document that is stored in the index
+#[derive(Debug, Default, Deserialize)]
+struct IndexedDocument {
+    #[serde(rename = "objectID")]
+    object_id: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    author_emails: Vec<String>,
+}
+
+impl IndexedDocument {
+    fn has_metadata_of(&self, document: &IndexDocument) -> bool {
+        self.state == document.state
+            && self.labels == document.labels
+            && self.authors == document.authors
+            && self.author_emails == document.author_emails
+    }
+}
+
+/// The documents that need to be added to, replaced in or removed from the search index so that it
+/// matches the current contents of an RFD
+#[derive(Debug, Default, PartialEq)]
+pub struct RFDIndexDiff {
+    pub rfd_number: i32,
+    /// Sections of the RFD that do not have a document in the index
+    pub missing: Vec<IndexDocument>,
+    /// Sections of the RFD whose indexed document does not carry the current metadata of the RFD.
+    /// This includes documents that were indexed before the metadata fields were added
+    pub outdated: Vec<IndexDocument>,
+    /// Ids of indexed documents that no longer correspond to a section of the RFD
+    pub orphaned: Vec<String>,
+}
+
+impl RFDIndexDiff {
+    fn new(rfd_number: &RFDNumber, parsed: Vec<IndexDocument>, existing: Vec<IndexedDocument>) -> Self {
+        let expected = parsed
+            .iter()
+            .map(|document| document.object_id.as_str())
+            .collect::<HashSet<_>>();
+        let orphaned = existing
+            .iter()
+            .filter(|indexed| !expected.contains(indexed.object_id.as_str()))
+            .map(|indexed| indexed.object_id.clone())
+            .collect::<Vec<_>>();
+
+        let existing = existing
+            .iter()
+            .map(|indexed| (indexed.object_id.as_str(), indexed))
+            .collect::<HashMap<_, _>>();
+
+        let mut missing = vec![];
+        let mut outdated = vec![];
+
+        for document in parsed {
+            match existing.get(document.object_id.as_str()) {
+                None => missing.push(document),
+                Some(indexed) if !indexed.has_metadata_of(&document) => outdated.push(document),
+                Some(_) => {}
+            }
+        }
+
+        Self {
+            rfd_number: rfd_number.into(),
+            missing,
+            outdated,
+            orphaned,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.outdated.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+impl fmt::Display for RFDIndexDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RFD {}: {} missing, {} outdated, {} orphaned",
+            self.rfd_number,
+            self.missing.len(),
+            self.outdated.len(),
+            self.orphaned.len()
+        )?;
+
+        for document in &self.missing {
+            write!(f, "\n  + {} ({})", document.anchor, document.object_id)?;
+        }
+
+        for document in &self.outdated {
+            write!(f, "\n  ~ {} ({})", document.anchor, document.object_id)?;
+        }
+
+        for id in &self.orphaned {
+            write!(f, "\n  - {}", id)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Filters that can be applied to a search of the RFD index. All of the supplied filters must
+/// match for a section to be returned
+#[derive(Debug, Clone, Default, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct RFDSearchFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<RFDState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Matches against either the name or the email of an author
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+impl RFDSearchFilter {
+    fn expressions(&self) -> Vec<String> {
+        let mut expressions = vec![];
+
+        if let Some(state) = &self.state {
+            expressions.push(format!("state = {}", quote_filter_value(state.as_str())));
+        }
+
+        if let Some(label) = &self.label {
+            expressions.push(format!("labels = {}", quote_filter_value(label)));
+        }
+
+        if let Some(author) = &self.author {
+            let author = quote_filter_value(author);
+            expressions.push(format!("(authors = {} OR author_emails = {})", author, author));
+        }
+
+        expressions
+    }
+}
+
+fn quote_filter_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchHit {
+    name: String,
+    level: usize,
+    url: String,
+    anchor: String,
+    content: String,
+    rfd_number: i32,
+    #[serde(rename = "hierarchy_lvl0", default)]
+    title: String,
+    #[serde(rename = "_formatted")]
+    formatted: Option<FormattedSearchHit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FormattedSearchHit {
+    name: String,
+    content: String,
+}
+
+/// A section of an RFD that matched a search
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct RFDSearchHit {
+    pub rfd_number: i32,
+    /// title is the full title of the RFD in the form of RFD {number} {title}
+    pub title: String,
+    /// section is the name of the matching section
+    pub section: String,
+    pub level: usize,
+    /// anchor is the id of the matching section within the rendered RFD
+    pub anchor: String,
+    /// url is the link to the matching section within the rendered RFD
+    pub url: String,
+    /// highlighted_section is the section name with matching terms wrapped in <mark> tags
+    pub highlighted_section: String,
+    /// highlighted_content is an excerpt of the section with matching terms wrapped in <mark> tags
+    pub highlighted_content: String,
+}
+
+impl From<SearchHit> for RFDSearchHit {
+    fn from(hit: SearchHit) -> Self {
+        let (highlighted_section, highlighted_content) = match hit.formatted {
+            Some(formatted) => (formatted.name, formatted.content),
+            None => (hit.name.clone(), hit.content),
+        };
+
+        Self {
+            rfd_number: hit.rfd_number,
+            title: hit.title,
+            section: hit.name,
+            level: hit.level,
+            anchor: hit.anchor,
+            url: hit.url,
+            highlighted_section,
+            highlighted_content,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct RFDSearchResults {
+    pub hits: Vec<RFDSearchHit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_total_hits: Option<usize>,
+}
+
+/// A search key that can be handed to a client so that it may query the RFD index directly
+#[derive(Debug, Clone, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct RFDSearchKey {
+    pub key: String,
+    pub index: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Generates Meilisearch tenant tokens. Tenant tokens are JWTs signed (HS256) by a parent search
+/// key that restrict searches to a single index and, optionally, to a filter. Meilisearch verifies
+/// the tokens itself, so keys can be generated without making a request to the search server.
+pub struct RFDSearchKeySigner {
+    key: String,
+    key_uid: String,
+}
+
+impl RFDSearchKeySigner {
+    pub fn new(key: String, key_uid: String) -> Self {
+        Self { key, key_uid }
+    }
+
+    /// Read the parent key from the MEILI_SEARCH_KEY and MEILI_SEARCH_KEY_UID variables
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::new(
+            std::env::var("MEILI_SEARCH_KEY")
+                .map_err(|_| anyhow!("MEILI_SEARCH_KEY must be set to generate RFD search keys"))?,
+            std::env::var("MEILI_SEARCH_KEY_UID")
+                .map_err(|_| anyhow!("MEILI_SEARCH_KEY_UID must be set to generate RFD search keys"))?,
+        ))
+    }
+
+    /// Generate a key for a user. Searches performed with the key are limited to the given index and
+    /// are always restricted by the given filter. The user is recorded in the claims of the key so
+    /// that every key can be traced back to who it was issued to
+    pub fn sign(
+        &self,
+        user: &str,
+        index: &str,
+        filter: &RFDSearchFilter,
+        expires_at: DateTime<Utc>,
+    ) -> Result<RFDSearchKey> {
+        let expressions = filter.expressions();
+        let rules = if expressions.is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::json!({ "filter": expressions.join(" AND ") })
+        };
+
+        let header = serde_json::json!({ "alg": "HS256", "typ": "JWT" });
+        let claims = serde_json::json!({
+            "apiKeyUid": self.key_uid,
+            "exp": expires_at.timestamp(),
+            "searchRules": { index: rules },
+            "user": user,
+        });
+
+        let message = format!(
+            "{}.{}",
+            base64::encode_config(serde_json::to_vec(&header)?, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(serde_json::to_vec(&claims)?, base64::URL_SAFE_NO_PAD)
+        );
+
+        let mut mac = HmacSha256::new_from_slice(self.key.as_bytes()).expect("HMAC can take key of any size");
+        mac.update(message.as_bytes());
+        let signature = base64::encode_config(mac.finalize().into_bytes(), base64::URL_SAFE_NO_PAD);
+
+        log::info!(
+            "Generated RFD search key on {} for {} that expires at {}",
+            index,
+            user,
+            expires_at
+        );
+
+        Ok(RFDSearchKey {
+            key: format!("{}.{}", message, signature),
+            index: index.to_string(),
+            expires_at,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::TimeZone;
+    use httpmock::{Method::POST, MockServer};
+    use serde_json::json;
+
     use super::*;
 
     #[test]
@@ -173,9 +603,201 @@ Third in the list"#,
         )
         .unwrap();
 
-        let expected: serde_json::Value = serde_json::from_str(r#"[{"objectID":"d4cb86c0f047968689bfb31b3b0e8777","anchor":"_background","url":"https://rfd.shared.oxide.computer/rfd/0123#_background","name":"Background","level":1,"content":"A paragraph about background topics","rfd_number":123,"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"Background","hierarchy_radio_lvl1":"Background"},{"objectID":"78f5e7630699137ab79f8ebc28f1f969","anchor":"_possibilities","url":"https://rfd.shared.oxide.computer/rfd/0123#_possibilities","name":"Possibilities","level":1,"content":"Nested sections describing possible options","rfd_number":123,"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"Possibilities","hierarchy_radio_lvl1":"Possibilities"},{"objectID":"ae183605f83b74f65d9b8581d62df7a4","anchor":"_the_first_option","url":"https://rfd.shared.oxide.computer/rfd/0123#_the_first_option","name":"The First Option","level":2,"content":"First in the list","rfd_number":123,"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"The First Option","hierarchy_lvl2":"Possibilities","hierarchy_radio_lvl2":"Possibilities"},{"objectID":"2cc8b5223efebcc9688249fcbbc513a3","anchor":"_the_second_option","url":"https://rfd.shared.oxide.computer/rfd/0123#_the_second_option","name":"The Second Option","level":2,"content":"Second in the list","rfd_number":123,"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"The Second Option","hierarchy_lvl2":"Possibilities","hierarchy_radio_lvl2":"Possibilities"},{"objectID":"1c37370ab346614df6e78a5003eb11b1","anchor":"_further_nested_details","url":"https://rfd.shared.oxide.computer/rfd/0123#_further_nested_details","name":"Further Nested Details","level":3,"content":"This options contains further information","rfd_number":123,"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"Further Nested Details","hierarchy_lvl2":"The Second Option","hierarchy_lvl3":"Possibilities","hierarchy_radio_lvl3":"Possibilities"},{"objectID":"476fe6d1ff7a522859fc71bbc146fd60","anchor":"_the_third_option","url":"https://rfd.shared.oxide.computer/rfd/0123#_the_third_option","name":"The Third Option","level":2,"content":"Third in the list","rfd_number":123,"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"The Third Option","hierarchy_lvl2":"Possibilities","hierarchy_radio_lvl2":"Possibilities"}]"#).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(r#"[{"objectID":"d4cb86c0f047968689bfb31b3b0e8777","anchor":"_background","url":"https://rfd.shared.oxide.computer/rfd/0123#_background","name":"Background","level":1,"content":"A paragraph about background topics","rfd_number":123,"state":"published","labels":[],"authors":["Firstname Lastname"],"author_emails":["author@organization.com"],"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"Background","hierarchy_radio_lvl1":"Background"},{"objectID":"78f5e7630699137ab79f8ebc28f1f969","anchor":"_possibilities","url":"https://rfd.shared.oxide.computer/rfd/0123#_possibilities","name":"Possibilities","level":1,"content":"Nested sections describing possible options","rfd_number":123,"state":"published","labels":[],"authors":["Firstname Lastname"],"author_emails":["author@organization.com"],"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"Possibilities","hierarchy_radio_lvl1":"Possibilities"},{"objectID":"ae183605f83b74f65d9b8581d62df7a4","anchor":"_the_first_option","url":"https://rfd.shared.oxide.computer/rfd/0123#_the_first_option","name":"The First Option","level":2,"content":"First in the list","rfd_number":123,"state":"published","labels":[],"authors":["Firstname Lastname"],"author_emails":["author@organization.com"],"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"The First Option","hierarchy_lvl2":"Possibilities","hierarchy_radio_lvl2":"Possibilities"},{"objectID":"2cc8b5223efebcc9688249fcbbc513a3","anchor":"_the_second_option","url":"https://rfd.shared.oxide.computer/rfd/0123#_the_second_option","name":"The Second Option","level":2,"content":"Second in the list","rfd_number":123,"state":"published","labels":[],"authors":["Firstname Lastname"],"author_emails":["author@organization.com"],"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"The Second Option","hierarchy_lvl2":"Possibilities","hierarchy_radio_lvl2":"Possibilities"},{"objectID":"1c37370ab346614df6e78a5003eb11b1","anchor":"_further_nested_details","url":"https://rfd.shared.oxide.computer/rfd/0123#_further_nested_details","name":"Further Nested Details","level":3,"content":"This options contains further information","rfd_number":123,"state":"published","labels":[],"authors":["Firstname Lastname"],"author_emails":["author@organization.com"],"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"Further Nested Details","hierarchy_lvl2":"The Second Option","hierarchy_lvl3":"Possibilities","hierarchy_radio_lvl3":"Possibilities"},{"objectID":"476fe6d1ff7a522859fc71bbc146fd60","anchor":"_the_third_option","url":"https://rfd.shared.oxide.computer/rfd/0123#_the_third_option","name":"The Third Option","level":2,"content":"Third in the list","rfd_number":123,"state":"published","labels":[],"authors":["Firstname Lastname"],"author_emails":["author@organization.com"],"hierarchy_lvl0":"RFD 123 On Parsing Documents","hierarchy_lvl1":"The Third Option","hierarchy_lvl2":"Possibilities","hierarchy_radio_lvl2":"Possibilities"}]"#).unwrap();
         let deser: serde_json::Value = serde_json::from_str(&serde_json::to_string(&documents).unwrap()).unwrap();
 
         assert_eq!(expected, deser);
     }
+
+    #[test]
+    fn test_index_diff() {
+        let document = |anchor: &str| IndexDocument {
+            object_id: format!("id{}", anchor),
+            name: anchor.to_string(),
+            level: 1,
+            url: format!("https://rfd.shared.oxide.computer/rfd/0123#{}", anchor),
+            anchor: anchor.to_string(),
+            content: String::new(),
+            rfd_number: 123,
+            state: "published".to_string(),
+            labels: vec!["storage".to_string()],
+            authors: vec![],
+            author_emails: vec![],
+            hierarchy: HashMap::new(),
+            hierarchy_radio: HashMap::new(),
+        };
+        let indexed = |anchor: &str| IndexedDocument {
+            object_id: format!("id{}", anchor),
+            state: "published".to_string(),
+            labels: vec!["storage".to_string()],
+            ..Default::default()
+        };
+        let indexed_without_metadata = |anchor: &str| IndexedDocument {
+            object_id: format!("id{}", anchor),
+            ..Default::default()
+        };
+
+        let diff = RFDIndexDiff::new(
+            &123.into(),
+            vec![document("_background"), document("_options"), document("_summary")],
+            vec![
+                indexed("_background"),
+                indexed_without_metadata("_summary"),
+                indexed("_removed"),
+            ],
+        );
+
+        assert_eq!(123, diff.rfd_number);
+        assert_eq!(vec![document("_options")], diff.missing);
+        assert_eq!(vec![document("_summary")], diff.outdated);
+        assert_eq!(vec!["id_removed".to_string()], diff.orphaned);
+        assert_eq!(
+            "RFD 123: 1 missing, 1 outdated, 1 orphaned\n  + _options (id_options)\n  ~ _summary (id_summary)\n  - id_removed",
+            diff.to_string()
+        );
+
+        assert!(RFDIndexDiff::new(&123.into(), vec![document("_background")], vec![indexed("_background")]).is_empty());
+    }
+
+    #[test]
+    fn test_markdown_documents_use_markdown_metadata() {
+        let documents = RFDSearchIndex::parse_document(
+            &123.into(),
+            r#"---
+authors: Firstname Lastname <author@organization.com>
+state: discussion
+discussion: https://github.com/organization/repo/pull/123
+labels: storage
+---
+
+# RFD 123 On Parsing Markdown
+
+== Background
+
+A paragraph about background topics"#,
+        )
+        .unwrap();
+
+        assert_eq!(1, documents.len());
+        assert_eq!("discussion", documents[0].state);
+        assert_eq!(vec!["storage".to_string()], documents[0].labels);
+        assert_eq!(vec!["Firstname Lastname".to_string()], documents[0].authors);
+    }
+
+    #[test]
+    fn test_search_filter_expressions() {
+        let filter = RFDSearchFilter {
+            state: Some(RFDState::Discussion),
+            label: Some("storage".to_string()),
+            author: Some("Firstname \"Nick\" Lastname".to_string()),
+        };
+
+        assert_eq!(
+            vec![
+                r#"state = "discussion""#.to_string(),
+                r#"labels = "storage""#.to_string(),
+                r#"(authors = "Firstname \"Nick\" Lastname" OR author_emails = "Firstname \"Nick\" Lastname")"#
+                    .to_string(),
+            ],
+            filter.expressions()
+        );
+        assert!(RFDSearchFilter::default().expressions().is_empty());
+    }
+
+    #[test]
+    fn test_search_key_is_signed_tenant_token() {
+        let signer = RFDSearchKeySigner::new("parent-key".to_string(), "parent-key-uid".to_string());
+        let filter = RFDSearchFilter {
+            label: Some("storage".to_string()),
+            ..Default::default()
+        };
+        let expires_at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        let key = signer.sign("user@company.com", "rfd", &filter, expires_at).unwrap();
+        let parts = key.key.split('.').collect::<Vec<_>>();
+        assert_eq!(3, parts.len());
+
+        let claims: serde_json::Value =
+            serde_json::from_slice(&base64::decode_config(parts[1], base64::URL_SAFE_NO_PAD).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "apiKeyUid": "parent-key-uid",
+                "exp": 1704067200,
+                "searchRules": { "rfd": { "filter": r#"labels = "storage""# } },
+                "user": "user@company.com",
+            }),
+            claims
+        );
+
+        let mut mac = HmacSha256::new_from_slice(b"parent-key").unwrap();
+        mac.update(format!("{}.{}", parts[0], parts[1]).as_bytes());
+        mac.verify_slice(&base64::decode_config(parts[2], base64::URL_SAFE_NO_PAD).unwrap())
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_returns_highlighted_hits() {
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/indexes/rfd/search")
+                .header("authorization", "Bearer search-key")
+                .json_body(json!({
+                    "q": "parsing",
+                    "filter": [r#"state = "published""#],
+                    "limit": 10,
+                    "offset": 0,
+                    "attributesToHighlight": ["name", "content"],
+                    "highlightPreTag": "<mark>",
+                    "highlightPostTag": "</mark>",
+                    "attributesToCrop": ["content"],
+                    "cropLength": 30,
+                }));
+            then.status(200).json_body(json!({
+                "hits": [{
+                    "objectID": "d4cb86c0f047968689bfb31b3b0e8777",
+                    "anchor": "_background",
+                    "url": "https://rfd.shared.oxide.computer/rfd/0123#_background",
+                    "name": "Background",
+                    "level": 1,
+                    "content": "A paragraph about parsing topics",
+                    "rfd_number": 123,
+                    "state": "published",
+                    "hierarchy_lvl0": "RFD 123 On Parsing Documents",
+                    "hierarchy_lvl1": "Background",
+                    "_formatted": {
+                        "name": "Background",
+                        "content": "A paragraph about <mark>parsing</mark> topics",
+                    },
+                }],
+                "estimatedTotalHits": 1,
+            }));
+        });
+
+        let client = MeiliClient::new(server.base_url(), "search-key".to_string());
+        let filter = RFDSearchFilter {
+            state: Some(RFDState::Published),
+            ..Default::default()
+        };
+
+        let results = RFDSearchIndex::search(&client, "rfd".to_string(), "parsing", &filter, 10, 0)
+            .await
+            .unwrap();
+
+        mock.assert();
+        assert_eq!(Some(1), results.estimated_total_hits);
+        assert_eq!(
+            vec![RFDSearchHit {
+                rfd_number: 123,
+                title: "RFD 123 On Parsing Documents".to_string(),
+                section: "Background".to_string(),
+                level: 1,
+                anchor: "_background".to_string(),
+                url: "https://rfd.shared.oxide.computer/rfd/0123#_background".to_string(),
+                highlighted_section: "Background".to_string(),
+                highlighted_content: "A paragraph about <mark>parsing</mark> topics".to_string(),
+            }],
+            results.hits
+        );
+    }
 }
@@ -0,0 +1,439 @@
+use anyhow::Result;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Utc};
+use diesel::{AsChangeset, ExpressionMethods, Insertable, QueryDsl, Queryable};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use slack_chat_api::{FormattedMessage, MessageBlock, MessageBlockText, MessageBlockType, MessageType};
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use super::{changelog::send_email, GitHubRFDRepo, RFDAuthor, RFDNumber, RFDs, RFD};
+use crate::{
+    app_config::RFDStaleConfig,
+    companies::Company,
+    db::Database,
+    sandbox::{Sandbox, SandboxService},
+    schema::rfd_snoozes,
+};
+
+/// The longest that an RFD may be snoozed for in a single request
+pub const MAX_SNOOZE_DAYS: u32 = 365;
+
+/// A request to stop reporting an RFD as stale until a point in time
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct RFDSnooze {
+    pub id: i32,
+    pub rfd_number: i32,
+    pub snoozed_until: DateTime<Utc>,
+    pub snoozed_by: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = rfd_snoozes)]
+pub struct NewRFDSnooze {
+    pub rfd_number: i32,
+    pub snoozed_until: DateTime<Utc>,
+    pub snoozed_by: String,
+    pub reason: String,
+}
+
+impl NewRFDSnooze {
+    /// Store the snooze, replacing any existing snooze for the RFD
+    pub async fn upsert(self, db: &Database) -> Result<RFDSnooze> {
+        Ok(diesel::insert_into(rfd_snoozes::table)
+            .values(&self)
+            .on_conflict(rfd_snoozes::dsl::rfd_number)
+            .do_update()
+            .set(&self)
+            .get_result_async::<RFDSnooze>(db.pool())
+            .await?)
+    }
+}
+
+impl RFDSnooze {
+    pub async fn get(db: &Database, number: i32) -> Result<Option<Self>> {
+        Ok(rfd_snoozes::dsl::rfd_snoozes
+            .filter(rfd_snoozes::dsl::rfd_number.eq(number))
+            .load_async::<Self>(db.pool())
+            .await?
+            .pop())
+    }
+
+    /// Get all of the snoozes that have not yet expired
+    pub async fn active(db: &Database, now: DateTime<Utc>) -> Result<Vec<Self>> {
+        Ok(rfd_snoozes::dsl::rfd_snoozes
+            .filter(rfd_snoozes::dsl::snoozed_until.gt(now))
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    pub async fn delete(db: &Database, number: i32) -> Result<()> {
+        diesel::delete(rfd_snoozes::dsl::rfd_snoozes.filter(rfd_snoozes::dsl::rfd_number.eq(number)))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// An RFD that has not been changed for longer than the threshold of its current state
+#[derive(Debug, Clone, PartialEq, JsonSchema, Serialize)]
+pub struct StaleRFD {
+    pub number: i32,
+    pub name: String,
+    pub state: String,
+    pub link: String,
+    /// The open pull request of the RFD, if there is one
+    pub pull_request: Option<String>,
+    pub last_activity: DateTime<Utc>,
+    pub idle_days: i64,
+    pub threshold_days: i64,
+    pub authors: Vec<RFDAuthor>,
+}
+
+impl StaleRFD {
+    fn message(&self) -> String {
+        let mut message = format!(
+            "{} has been in {} for {} days without any changes ({} days is the limit for {}).",
+            self.name, self.state, self.idle_days, self.threshold_days, self.state
+        );
+
+        if let Some(pull_request) = &self.pull_request {
+            message += &format!(" The discussion is taking place at {}.", pull_request);
+        }
+
+        message += " If it is still being worked on, it can be snoozed to stop these reminders. \
+            Otherwise consider moving it forward or marking it as abandoned.";
+
+        message
+    }
+}
+
+/// The RFDs that were found to be stale during a single check
+#[derive(Debug, Clone, PartialEq, JsonSchema, Serialize)]
+pub struct StaleRFDReport {
+    pub generated_at: DateTime<Utc>,
+    pub stale: Vec<StaleRFD>,
+    /// RFDs that would otherwise have been checked, but are currently snoozed
+    pub snoozed: Vec<i32>,
+}
+
+impl StaleRFDReport {
+    /// Check every RFD of a company against the configured thresholds
+    pub async fn build(db: &Database, company: &Company, config: &RFDStaleConfig) -> Result<Self> {
+        let now = Utc::now();
+
+        let snoozes = RFDSnooze::active(db, now)
+            .await?
+            .into_iter()
+            .map(|snooze| snooze.rfd_number)
+            .collect::<Vec<_>>();
+
+        let github = Arc::new(company.authenticate_github()?);
+        let repo = GitHubRFDRepo::new_with_client(company, github).await?;
+
+        let mut stale = vec![];
+        let mut snoozed = vec![];
+
+        for rfd in RFDs::get_from_db(db, company.id).await? {
+            let threshold_days = match rfd.get_state().and_then(|state| config.threshold_days(&state)) {
+                Some(threshold_days) => threshold_days,
+                None => continue,
+            };
+
+            if snoozes.contains(&rfd.number) {
+                snoozed.push(rfd.number);
+                continue;
+            }
+
+            if let Some(entry) = Self::check_rfd(&repo, &rfd, threshold_days, now).await {
+                stale.push(entry);
+            }
+        }
+
+        Ok(Self {
+            generated_at: now,
+            stale,
+            snoozed,
+        })
+    }
+
+    async fn check_rfd(repo: &GitHubRFDRepo, rfd: &RFD, threshold_days: i64, now: DateTime<Utc>) -> Option<StaleRFD> {
+        let number: RFDNumber = rfd.number.into();
+
+        let branch = if rfd.link.contains(&format!("/{}/", repo.default_branch)) {
+            repo.default_branch.clone()
+        } else {
+            number.as_number_string()
+        };
+        let branch = repo.branch(branch);
+
+        // Fall back to the commit date that was recorded the last time the RFD was synced if the
+        // branch can no longer be read
+        let last_activity = match branch.get_latest_commit_date(&number).await {
+            Ok(date) => date,
+            Err(err) => {
+                info!(
+                    "Failed to find latest commit for RFD {} on {}: {}",
+                    rfd.number, branch.branch, err
+                );
+                rfd.commit_date
+            }
+        };
+
+        let idle_days = idle_days_past_threshold(last_activity, now, threshold_days)?;
+
+        let pull_request = branch
+            .find_pull_requests()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .find(|pr| pr.state == "open")
+            .map(|pr| pr.html_url);
+
+        let authors = rfd
+            .content()
+            .map(|content| content.metadata().authors)
+            .unwrap_or_default();
+
+        Some(StaleRFD {
+            number: rfd.number,
+            name: rfd.name.clone(),
+            state: rfd.state.clone(),
+            link: rfd.rendered_link.clone(),
+            pull_request,
+            last_activity,
+            idle_days,
+            threshold_days,
+            authors,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stale.is_empty()
+    }
+
+    /// Send a reminder to each author of each stale RFD via the channels enabled in the config.
+    /// Failing to reach an individual author is logged and does not stop the remaining reminders
+    pub async fn nudge_authors(&self, db: &Database, company: &Company, config: &RFDStaleConfig) -> Result<()> {
+        let slack = if config.slack {
+            let slack = company.authenticate_slack(db).await?;
+
+            // Authors are only known by the email addresses listed in the RFD header
+            let users = slack
+                .list_users()
+                .await?
+                .into_iter()
+                .filter(|user| !user.deleted)
+                .flat_map(|user| {
+                    [user.email.clone(), user.profile.email.clone()]
+                        .into_iter()
+                        .filter(|email| !email.is_empty())
+                        .map(move |email| (email.to_lowercase(), user.id.clone()))
+                })
+                .collect::<HashMap<_, _>>();
+
+            Some((slack, users))
+        } else {
+            None
+        };
+
+        for rfd in &self.stale {
+            let message = rfd.message();
+
+            for email in rfd.authors.iter().filter_map(|author| author.email.as_ref()) {
+                if let Some((slack, users)) = &slack {
+                    match users.get(&email.to_lowercase()) {
+                        Some(user_id) => {
+                            let msg = FormattedMessage {
+                                channel: user_id.to_string(),
+                                blocks: vec![MessageBlock {
+                                    block_type: MessageBlockType::Section,
+                                    text: Some(MessageBlockText {
+                                        text_type: MessageType::Markdown,
+                                        text: message.clone(),
+                                    }),
+                                    elements: Default::default(),
+                                    accessory: Default::default(),
+                                    block_id: Default::default(),
+                                    fields: Default::default(),
+                                }],
+                                attachments: Default::default(),
+                            };
+
+                            if Sandbox::intercept(
+                                SandboxService::Slack,
+                                format!("message {} about stale RFD {}", email, rfd.number),
+                            ) {
+                                continue;
+                            }
+
+                            if let Err(err) = slack.post_message(&msg).await {
+                                warn!("Failed to message {} about stale RFD {}: {}", email, rfd.number, err);
+                            }
+                        }
+                        None => warn!("Unable to find a Slack user for {} of RFD {}", email, rfd.number),
+                    }
+                }
+
+                if config.email {
+                    if let Err(err) = send_email(
+                        &[email.to_string()],
+                        &config.from(company),
+                        &format!("RFD {} is stale", rfd.number),
+                        &message,
+                        None,
+                    )
+                    .await
+                    {
+                        warn!("Failed to email {} about stale RFD {}: {}", email, rfd.number, err);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for StaleRFDReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} stale RFD(s) as of {}",
+            self.stale.len(),
+            self.generated_at.format("%m-%d-%Y")
+        )?;
+
+        for rfd in &self.stale {
+            writeln!(
+                f,
+                "\n{} {}\n\t{} for {} days (threshold {} days), last changed {}",
+                rfd.name,
+                rfd.link,
+                rfd.state,
+                rfd.idle_days,
+                rfd.threshold_days,
+                rfd.last_activity.format("%m-%d-%Y")
+            )?;
+
+            if let Some(pull_request) = &rfd.pull_request {
+                writeln!(f, "\tDiscussion: {}", pull_request)?;
+            }
+
+            let authors = rfd
+                .authors
+                .iter()
+                .map(|author| author.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "\tAuthors: {}", authors)?;
+        }
+
+        if !self.snoozed.is_empty() {
+            let snoozed = self
+                .snoozed
+                .iter()
+                .map(|number| number.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(f, "\nSnoozed: {}", snoozed)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of days that have passed since the last activity if it meets or exceeds the
+/// threshold
+fn idle_days_past_threshold(last_activity: DateTime<Utc>, now: DateTime<Utc>, threshold_days: i64) -> Option<i64> {
+    let idle_days = (now - last_activity).num_days();
+
+    if idle_days >= threshold_days {
+        Some(idle_days)
+    } else {
+        None
+    }
+}
+
+/// Find the RFDs that have been idle for longer than their state allows. Unless running in dry run
+/// mode, the authors of each stale RFD are reminded via the channels enabled in the config
+pub async fn check_stale_rfds(
+    db: &Database,
+    company: &Company,
+    config: &RFDStaleConfig,
+    dry_run: bool,
+) -> Result<StaleRFDReport> {
+    let report = StaleRFDReport::build(db, company, config).await?;
+
+    info!(
+        "Found {} stale RFDs ({} snoozed)",
+        report.stale.len(),
+        report.snoozed.len()
+    );
+
+    if !dry_run && !report.is_empty() {
+        report.nudge_authors(db, company, config).await?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::rfd::RFDState;
+
+    #[test]
+    fn test_idle_days_past_threshold() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 5, 12, 0, 0).unwrap();
+
+        assert_eq!(None, idle_days_past_threshold(now - Duration::days(59), now, 60));
+        assert_eq!(Some(60), idle_days_past_threshold(now - Duration::days(60), now, 60));
+        assert_eq!(Some(200), idle_days_past_threshold(now - Duration::days(200), now, 60));
+
+        // Commits that appear to be from the future are never stale
+        assert_eq!(None, idle_days_past_threshold(now + Duration::days(1), now, 0));
+    }
+
+    #[test]
+    fn test_report_display() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 5, 12, 0, 0).unwrap();
+        let report = StaleRFDReport {
+            generated_at: now,
+            stale: vec![StaleRFD {
+                number: 12,
+                name: "RFD 12 Stuck".to_string(),
+                state: RFDState::Discussion.to_string(),
+                link: "https://rfd.shared.oxide.computer/rfd/0012".to_string(),
+                pull_request: Some("https://github.com/oxidecomputer/rfd/pull/30".to_string()),
+                last_activity: now - Duration::days(75),
+                idle_days: 75,
+                threshold_days: 60,
+                authors: vec![RFDAuthor {
+                    name: "Some One".to_string(),
+                    email: Some("some@one.com".to_string()),
+                }],
+            }],
+            snoozed: vec![4, 7],
+        };
+
+        assert_eq!(
+            "1 stale RFD(s) as of 02-05-2024
+
+RFD 12 Stuck https://rfd.shared.oxide.computer/rfd/0012
+\tdiscussion for 75 days (threshold 60 days), last changed 11-22-2023
+\tDiscussion: https://github.com/oxidecomputer/rfd/pull/30
+\tAuthors: Some One <some@one.com>
+
+Snoozed: 4, 7
+",
+            report.to_string()
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Sandbox mode, where writes to Airtable, Slack, SendGrid, GitHub and DNS are logged and recorded
+//! instead of being made. Reads from those services, and everything written to our own database,
+//! still happen as normal.
+//!
+//! A sandbox is active for everything run within [`Sandbox::scope`]. Work that is spawned onto
+//! another task has to be spawned with [`spawn`] to stay inside the sandbox.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use log::info;
+use task_local_extensions::Extensions;
+
+tokio::task_local! {
+    static SANDBOX: Sandbox;
+}
+
+/// A service that the sandbox keeps writes from reaching
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SandboxService {
+    Airtable,
+    Dns,
+    GitHub,
+    SendGrid,
+    Slack,
+}
+
+impl fmt::Display for SandboxService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxService::Airtable => write!(f, "airtable"),
+            SandboxService::Dns => write!(f, "dns"),
+            SandboxService::GitHub => write!(f, "github"),
+            SandboxService::SendGrid => write!(f, "sendgrid"),
+            SandboxService::Slack => write!(f, "slack"),
+        }
+    }
+}
+
+/// A write that was not made because it happened inside of a sandbox
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxWrite {
+    pub service: SandboxService,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    writes: Arc<Mutex<Vec<SandboxWrite>>>,
+}
+
+impl Sandbox {
+    /// Run a future inside of this sandbox
+    pub async fn scope<F: Future>(&self, f: F) -> F::Output {
+        SANDBOX.scope(self.clone(), f).await
+    }
+
+    /// The sandbox of the current task, if there is one
+    pub fn current() -> Option<Sandbox> {
+        SANDBOX.try_with(|sandbox| sandbox.clone()).ok()
+    }
+
+    pub fn is_active() -> bool {
+        Self::current().is_some()
+    }
+
+    /// Check a write before making it. When a sandbox is active the write is recorded and true is
+    /// returned, in which case the caller must skip the write.
+    pub fn intercept<D>(service: SandboxService, description: D) -> bool
+    where
+        D: fmt::Display,
+    {
+        match Self::current() {
+            Some(sandbox) => {
+                let description = description.to_string();
+                info!("[sandbox] skipped {} write: {}", service, description);
+                sandbox
+                    .writes
+                    .lock()
+                    .unwrap()
+                    .push(SandboxWrite { service, description });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The writes that were skipped, in the order they were attempted
+    pub fn writes(&self) -> Vec<SandboxWrite> {
+        self.writes.lock().unwrap().clone()
+    }
+
+    /// A summary of the writes that would have been made
+    pub fn summary(&self) -> String {
+        let writes = self.writes();
+
+        if writes.is_empty() {
+            return "Dry run complete, no writes would have been made".to_string();
+        }
+
+        let mut counts: BTreeMap<SandboxService, usize> = BTreeMap::new();
+        for write in &writes {
+            *counts.entry(write.service).or_default() += 1;
+        }
+
+        let mut summary = format!(
+            "Dry run complete, {} writes would have been made ({})",
+            writes.len(),
+            counts
+                .iter()
+                .map(|(service, count)| format!("{} {}", service, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        for write in writes {
+            summary.push_str(&format!("\n  [{}] {}", write.service, write.description));
+        }
+
+        summary
+    }
+}
+
+/// Spawn a task that shares the sandbox of the current task, if there is one
+pub fn spawn<F>(f: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match Sandbox::current() {
+        Some(sandbox) => tokio::spawn(SANDBOX.scope(sandbox, f)),
+        None => tokio::spawn(f),
+    }
+}
+
+/// Client middleware that keeps requests which change anything from being sent while a sandbox is
+/// active. They are answered with an empty JSON object instead, which the generated API clients
+/// read as a response with every field left at its default.
+pub struct SandboxMiddleware {
+    pub service: SandboxService,
+}
+
+#[async_trait]
+impl reqwest_middleware::Middleware for SandboxMiddleware {
+    async fn handle(
+        &self,
+        req: reqwest::Request,
+        extensions: &mut Extensions,
+        next: reqwest_middleware::Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        let method = req.method().clone();
+
+        if method == reqwest::Method::GET
+            || method == reqwest::Method::HEAD
+            || !Sandbox::intercept(self.service, format!("{} {}", method, req.url()))
+        {
+            return next.run(req, extensions).await;
+        }
+
+        let response = http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body("{}")
+            .map_err(|err| reqwest_middleware::Error::Middleware(err.into()))?;
+
+        Ok(response.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sandbox_records_writes() {
+        assert!(!Sandbox::intercept(SandboxService::Slack, "outside of a sandbox"));
+
+        let sandbox = Sandbox::default();
+
+        sandbox
+            .scope(async {
+                assert!(Sandbox::intercept(SandboxService::Slack, "post to #general"));
+
+                spawn(async {
+                    assert!(Sandbox::intercept(SandboxService::Airtable, "create row in Applicants"));
+                })
+                .await
+                .unwrap();
+            })
+            .await;
+
+        assert_eq!(
+            vec![
+                SandboxWrite {
+                    service: SandboxService::Slack,
+                    description: "post to #general".to_string(),
+                },
+                SandboxWrite {
+                    service: SandboxService::Airtable,
+                    description: "create row in Applicants".to_string(),
+                },
+            ],
+            sandbox.writes()
+        );
+        assert_eq!(
+            "Dry run complete, 2 writes would have been made (airtable 1, slack 1)\n  [slack] post to #general\n  [airtable] create row in Applicants",
+            sandbox.summary()
+        );
+
+        assert!(!Sandbox::is_active());
+    }
+}
@@ -398,6 +398,9 @@ table! {
         slack_channel_debug -> Varchar,
         google_service_account -> Varchar,
         nginx_ip -> Varchar,
+        rfd_changelog_recipients -> Array<Text>,
+        rfd_changelog_from -> Varchar,
+        rfd_changelog_window_days -> Int4,
         cio_company_id -> Int4,
         airtable_record_id -> Varchar,
     }
@@ -420,6 +423,16 @@ table! {
     }
 }
 
+table! {
+    configs_plans (id) {
+        id -> Int4,
+        cio_company_id -> Int4,
+        sha -> Varchar,
+        plan -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     credit_card_transactions (id) {
         id -> Int4,
@@ -442,6 +455,15 @@ table! {
     }
 }
 
+table! {
+    drift_reports (id) {
+        id -> Int4,
+        cio_company_id -> Int4,
+        report -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     expensed_items (id) {
         id -> Int4,
@@ -464,6 +486,36 @@ table! {
     }
 }
 
+table! {
+    function_dead_letters (id) {
+        id -> Int4,
+        saga_id -> Varchar,
+        name -> Varchar,
+        cio_company_id -> Int4,
+        attempts -> Int4,
+        error_class -> Varchar,
+        last_error -> Text,
+        created_at -> Timestamptz,
+        retried_saga_id -> Nullable<Varchar>,
+        retried_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    function_steps (id) {
+        id -> Int4,
+        saga_id -> Varchar,
+        name -> Varchar,
+        status -> Varchar,
+        conclusion -> Varchar,
+        output -> Text,
+        error -> Text,
+        started_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        undone_at -> Nullable<Timestamptz>,
+    }
+}
+
 table! {
     functions (id) {
         id -> Int4,
@@ -563,6 +615,7 @@ table! {
         members -> Array<Text>,
         restricted_to -> Array<Text>,
         repos -> Array<Text>,
+        slack_channel -> Varchar,
         allow_external_members -> Bool,
         allow_web_posting -> Bool,
         is_archived -> Bool,
@@ -578,6 +631,14 @@ table! {
     }
 }
 
+table! {
+    idempotency_keys (key) {
+        key -> Varchar,
+        received_at -> Timestamptz,
+        expires_at -> Timestamptz,
+    }
+}
+
 table! {
     inbound_shipments (id) {
         id -> Int4,
@@ -748,6 +809,24 @@ table! {
     }
 }
 
+table! {
+    provisioning_steps (id) {
+        id -> Int4,
+        cio_company_id -> Int4,
+        username -> Varchar,
+        step -> Varchar,
+        start_date -> Date,
+        due_on -> Date,
+        status -> Varchar,
+        detail -> Text,
+        attempts -> Int4,
+        last_error -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+    }
+}
+
 table! {
     rack_line_subscribers (id) {
         id -> Int4,
@@ -793,6 +872,38 @@ table! {
     }
 }
 
+table! {
+    rfd_references (id) {
+        id -> Int4,
+        rfd_number -> Int4,
+        referenced_rfd_number -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    rfd_snoozes (id) {
+        id -> Int4,
+        rfd_number -> Int4,
+        snoozed_until -> Timestamptz,
+        snoozed_by -> Varchar,
+        reason -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    rfd_state_changes (id) {
+        id -> Int4,
+        rfd_number -> Int4,
+        from_state -> Varchar,
+        to_state -> Varchar,
+        changed_by -> Varchar,
+        sha -> Varchar,
+        changed_at -> Timestamptz,
+    }
+}
+
 table! {
     rfds (id) {
         id -> Int4,
@@ -815,6 +926,8 @@ table! {
         pdf_link_github -> Varchar,
         pdf_link_google_drive -> Varchar,
         labels -> Array<Text>,
+        pdf_link_local -> Varchar,
+        pdf_link_s3 -> Varchar,
         cio_company_id -> Int4,
         airtable_record_id -> Varchar,
     }
@@ -881,6 +994,34 @@ table! {
     }
 }
 
+table! {
+    user_offboarding_steps (id) {
+        id -> Int4,
+        offboarding_id -> Int4,
+        step -> Varchar,
+        status -> Varchar,
+        detail -> Text,
+        attempts -> Int4,
+        last_error -> Text,
+        updated_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    user_offboardings (id) {
+        id -> Int4,
+        cio_company_id -> Int4,
+        username -> Varchar,
+        email -> Varchar,
+        manager_email -> Varchar,
+        status -> Varchar,
+        created_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+        returned_at -> Nullable<Timestamptz>,
+    }
+}
+
 table! {
     users (id) {
         id -> Int4,
@@ -941,6 +1082,24 @@ table! {
     }
 }
 
+table! {
+    webhook_events (id) {
+        id -> Int4,
+        source -> Varchar,
+        kind -> Varchar,
+        headers -> Jsonb,
+        body -> Text,
+        verified -> Bool,
+        status -> Varchar,
+        attempts -> Int4,
+        last_error -> Text,
+        next_attempt_at -> Timestamptz,
+        received_at -> Timestamptz,
+        processed_at -> Nullable<Timestamptz>,
+        replayed_at -> Nullable<Timestamptz>,
+    }
+}
+
 joinable!(accounts_payables -> companys (cio_company_id));
 joinable!(api_tokens -> companys (auth_company_id));
 joinable!(applicant_interviews -> companys (cio_company_id));
@@ -955,6 +1114,7 @@ joinable!(bookings -> companys (cio_company_id));
 joinable!(buildings -> companys (cio_company_id));
 joinable!(certificates -> companys (cio_company_id));
 joinable!(credit_card_transactions -> companys (cio_company_id));
+joinable!(drift_reports -> companys (cio_company_id));
 joinable!(expensed_items -> companys (cio_company_id));
 joinable!(functions -> companys (cio_company_id));
 joinable!(github_repos -> companys (cio_company_id));
@@ -967,6 +1127,7 @@ joinable!(mailing_list_subscribers -> companys (cio_company_id));
 joinable!(outbound_shipments -> companys (cio_company_id));
 joinable!(package_pickups -> companys (cio_company_id));
 joinable!(page_views -> companys (cio_company_id));
+joinable!(provisioning_steps -> companys (cio_company_id));
 joinable!(rack_line_subscribers -> companys (cio_company_id));
 joinable!(recorded_meetings -> companys (cio_company_id));
 joinable!(resources -> companys (cio_company_id));
@@ -974,6 +1135,8 @@ joinable!(rfds -> companys (cio_company_id));
 joinable!(software_vendors -> companys (cio_company_id));
 joinable!(swag_inventory_items -> companys (cio_company_id));
 joinable!(swag_items -> companys (cio_company_id));
+joinable!(user_offboarding_steps -> user_offboardings (offboarding_id));
+joinable!(user_offboardings -> companys (cio_company_id));
 joinable!(users -> companys (cio_company_id));
 
 allow_tables_to_appear_in_same_query!(
@@ -991,11 +1154,16 @@ allow_tables_to_appear_in_same_query!(
     buildings,
     certificates,
     companys,
+    configs_plans,
     credit_card_transactions,
+    drift_reports,
     expensed_items,
+    function_dead_letters,
+    function_steps,
     functions,
     github_repos,
     groups,
+    idempotency_keys,
     inbound_shipments,
     journal_club_meetings,
     journal_club_papers,
@@ -1004,12 +1172,19 @@ allow_tables_to_appear_in_same_query!(
     outbound_shipments,
     package_pickups,
     page_views,
+    provisioning_steps,
     rack_line_subscribers,
     recorded_meetings,
     resources,
+    rfd_references,
+    rfd_snoozes,
+    rfd_state_changes,
     rfds,
     software_vendors,
     swag_inventory_items,
     swag_items,
+    user_offboarding_steps,
+    user_offboardings,
     users,
+    webhook_events,
 );
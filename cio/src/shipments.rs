@@ -11,7 +11,6 @@ use log::{info, warn};
 use macros::db;
 use reqwest::StatusCode;
 use schemars::JsonSchema;
-use sendgrid_api::{traits::MailOps, Client as SendGrid};
 use serde::{Deserialize, Serialize};
 use shippo::{Address, CustomsDeclaration, CustomsItem, NewShipment, NewTransaction, Parcel, Shippo};
 use slack_chat_api::{
@@ -26,6 +25,7 @@ use crate::{
     db::Database,
     printer::Printer,
     schema::{inbound_shipments, outbound_shipments, package_pickups},
+    utils::send_plain_text_email,
 };
 
 /// The data type for an inbound shipment.
@@ -1093,15 +1093,11 @@ impl OutboundShipment {
 
         let company = self.company(db).await?;
 
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
         // Send the message.
-        sendgrid_client
-            .mail_send()
-            .send_plain_text(
-                &format!("{}, your order from {} has been received!", self.name, company.name),
-                &format!(
-                    "Below is the information for your order:
+        send_plain_text_email(
+            &format!("{}, your order from {} has been received!", self.name, company.name),
+            &format!(
+                "Below is the information for your order:
 
 **Contents:**
 {}
@@ -1117,16 +1113,16 @@ Have a splendid day!
 
 xoxo,
   The Shipping Bot",
-                    self.contents,
-                    self.name,
-                    self.format_address(),
-                ),
-                &[self.email.to_string()],
-                &[format!("packages@{}", &company.gsuite_domain)],
-                &[],
-                &format!("packages@{}", &company.gsuite_domain),
-            )
-            .await?;
+                self.contents,
+                self.name,
+                self.format_address(),
+            ),
+            &[self.email.to_string()],
+            &[format!("packages@{}", &company.gsuite_domain)],
+            &[],
+            &format!("packages@{}", &company.gsuite_domain),
+        )
+        .await?;
 
         Ok(())
     }
@@ -1138,15 +1134,11 @@ xoxo,
         }
 
         let company = self.company(db).await?;
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
         // Send the message.
-        sendgrid_client
-            .mail_send()
-            .send_plain_text(
-                &format!("{}, your package from {} is on the way!", self.name, company.name),
-                &format!(
-                    "Below is the information for your package:
+        send_plain_text_email(
+            &format!("{}, your package from {} is on the way!", self.name, company.name),
+            &format!(
+                "Below is the information for your package:
 
 **Contents:**
 {}
@@ -1163,17 +1155,17 @@ Have a splendid day!
 
 xoxo,
   The Shipping Bot",
-                    self.contents,
-                    self.name,
-                    self.format_address(),
-                    self.oxide_tracking_link
-                ),
-                &[self.email.to_string()],
-                &[format!("packages@{}", &company.gsuite_domain)],
-                &[],
-                &format!("packages@{}", &company.gsuite_domain),
-            )
-            .await?;
+                self.contents,
+                self.name,
+                self.format_address(),
+                self.oxide_tracking_link
+            ),
+            &[self.email.to_string()],
+            &[format!("packages@{}", &company.gsuite_domain)],
+            &[],
+            &format!("packages@{}", &company.gsuite_domain),
+        )
+        .await?;
 
         Ok(())
     }
@@ -1181,15 +1173,11 @@ xoxo,
     /// Send an email internally that we need to package the shipment.
     pub async fn send_email_internally(&self, db: &Database) -> Result<()> {
         let company = self.company(db).await?;
-        // Initialize the SendGrid client.
-        let sendgrid_client = SendGrid::new_from_env();
         // Send the message.
-        sendgrid_client
-            .mail_send()
-            .send_plain_text(
-                &format!("Shipment to {} is ready to be packaged", self.name),
-                &format!(
-                    "Below is the information the package:
+        send_plain_text_email(
+            &format!("Shipment to {} is ready to be packaged", self.name),
+            &format!(
+                "Below is the information the package:
 
 **Contents:**
 {}
@@ -1215,18 +1203,18 @@ https://airtable-shipments.corp.oxide.computer.
 xoxo,
 
 The Shipping Bot",
-                    self.contents,
-                    self.name,
-                    self.format_address(),
-                    self.oxide_tracking_link,
-                    self.carrier,
-                ),
-                &[format!("packages@{}", &company.gsuite_domain)],
-                &[],
-                &[],
-                &format!("packages@{}", &company.gsuite_domain),
-            )
-            .await?;
+                self.contents,
+                self.name,
+                self.format_address(),
+                self.oxide_tracking_link,
+                self.carrier,
+            ),
+            &[format!("packages@{}", &company.gsuite_domain)],
+            &[],
+            &[],
+            &format!("packages@{}", &company.gsuite_domain),
+        )
+        .await?;
 
         Ok(())
     }
@@ -5,7 +5,10 @@ use chrono::{Duration, Utc};
 use cloudflare::endpoints::dns;
 use log::info;
 
-use crate::companies::Company;
+use crate::{
+    companies::Company,
+    sandbox::{Sandbox, SandboxService},
+};
 
 /// When we generate VMs for the console repo on every branch we get lingering
 /// Tailscale devices that need to cleaned up when they are no longer active.
@@ -113,6 +116,10 @@ pub async fn cleanup_old_tailscale_cloudflare_dns(company: &Company) -> Result<(
 
         // If it does not exist in Tailscale, delete it.
         if !tailscale_devices.contains_key(&name) {
+            if Sandbox::intercept(SandboxService::Dns, format!("delete record {}", dns_record.name)) {
+                continue;
+            }
+
             info!("deleting dns record {}", name);
             cloudflare
                 .request(&dns::DeleteDnsRecord {
@@ -3,6 +3,7 @@ use log::info;
 use octorust::Client as GitHub;
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use reqwest::get;
+use sendgrid_api::{traits::MailOps, Client as SendGrid};
 use serde::{Deserialize, Deserializer};
 use serde_json::Value;
 use std::{
@@ -13,7 +14,10 @@ use std::{
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
-use crate::companies::Company;
+use crate::{
+    companies::Company,
+    sandbox::{Sandbox, SandboxService},
+};
 
 /// Write a file.
 pub async fn write_file(file: &Path, contents: &[u8]) -> Result<()> {
@@ -250,6 +254,30 @@ impl SliceExt for Vec<u8> {
     }
 }
 
+/// Send a plain text email through SendGrid.
+pub async fn send_plain_text_email(
+    subject: &str,
+    message: &str,
+    to: &[String],
+    cc: &[String],
+    bcc: &[String],
+    from: &str,
+) -> Result<()> {
+    if Sandbox::intercept(
+        SandboxService::SendGrid,
+        format!("send \"{}\" from {} to {}", subject, from, to.join(", ")),
+    ) {
+        return Ok(());
+    }
+
+    SendGrid::new_from_env()
+        .mail_send()
+        .send_plain_text(subject, message, to, cc, bcc, from)
+        .await?;
+
+    Ok(())
+}
+
 pub fn default_date() -> chrono::naive::NaiveDate {
     chrono::naive::NaiveDate::parse_from_str("1970-01-01", "%Y-%m-%d").unwrap()
 }
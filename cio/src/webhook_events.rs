@@ -0,0 +1,288 @@
+use std::fmt;
+
+use anyhow::Result;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Duration, Utc};
+use diesel::{ExpressionMethods, Insertable, QueryDsl, Queryable};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{db::Database, schema::webhook_events};
+
+/// Where an inbound webhook event is in its processing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventStatus {
+    /// Waiting to be processed, either for the first time or after a failed attempt
+    Pending,
+    /// Claimed by a processor. Events that stay claimed past their lease are claimed again
+    Processing,
+    Processed,
+    /// Failed with a permanent error, or ran out of attempts
+    Failed,
+}
+
+impl fmt::Display for WebhookEventStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookEventStatus::Pending => write!(f, "pending"),
+            WebhookEventStatus::Processing => write!(f, "processing"),
+            WebhookEventStatus::Processed => write!(f, "processed"),
+            WebhookEventStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A webhook event that was received from a vendor, stored before it is processed so that it can
+/// be retried and replayed
+#[derive(Debug, Queryable, PartialEq, Clone, JsonSchema, Deserialize, Serialize)]
+pub struct WebhookEvent {
+    pub id: i32,
+    /// The vendor that sent the event
+    pub source: String,
+    /// The path of the endpoint that received the event
+    pub kind: String,
+    /// The headers of the request, with credentials redacted
+    pub headers: serde_json::Value,
+    /// The body of the request, exactly as it was received
+    pub body: String,
+    /// Whether the request passed verification. Endpoints that reject unverified requests only
+    /// record verified events
+    pub verified: bool,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: String,
+    /// When the event is next due to be processed
+    pub next_attempt_at: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+    pub replayed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = webhook_events)]
+pub struct NewWebhookEvent {
+    source: String,
+    kind: String,
+    headers: serde_json::Value,
+    body: String,
+    verified: bool,
+    status: String,
+    next_attempt_at: DateTime<Utc>,
+}
+
+impl NewWebhookEvent {
+    /// An event that is due to be processed as soon as it is recorded. The time that it is due is
+    /// taken from our clock rather than the clock of the database, as that is what it is claimed by
+    pub fn new(source: &str, kind: &str, headers: serde_json::Value, body: String, verified: bool) -> Self {
+        NewWebhookEvent {
+            source: source.to_string(),
+            kind: kind.to_string(),
+            headers,
+            body,
+            verified,
+            status: WebhookEventStatus::Pending.to_string(),
+            next_attempt_at: Utc::now(),
+        }
+    }
+}
+
+/// Criteria for listing webhook events. All criteria are optional and are combined together
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct WebhookEventFilter {
+    pub source: Option<String>,
+    pub kind: Option<String>,
+    pub status: Option<WebhookEventStatus>,
+    /// Only include events received at or after this time
+    pub received_after: Option<DateTime<Utc>>,
+    /// Only include events received before this time
+    pub received_before: Option<DateTime<Utc>>,
+    /// Maximum number of events to return. Defaults to 100 and may not be more than 1000
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+impl WebhookEventFilter {
+    fn limit(&self) -> i64 {
+        self.limit.unwrap_or(100).clamp(1, 1000)
+    }
+}
+
+/// The events received within a time range, to be processed again
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct WebhookEventReplay {
+    /// Only replay events from this vendor
+    pub source: Option<String>,
+    /// Replay events received at or after this time
+    pub received_after: DateTime<Utc>,
+    /// Replay events received before this time
+    pub received_before: DateTime<Utc>,
+}
+
+/// The statuses of events that may be claimed by a processor
+const CLAIMABLE: [WebhookEventStatus; 2] = [WebhookEventStatus::Pending, WebhookEventStatus::Processing];
+
+fn statuses(statuses: &[WebhookEventStatus]) -> Vec<String> {
+    statuses.iter().map(|status| status.to_string()).collect()
+}
+
+impl WebhookEvent {
+    /// Store an event that was received
+    pub async fn record(db: &Database, event: &NewWebhookEvent) -> Result<Self> {
+        Ok(diesel::insert_into(webhook_events::table)
+            .values(event)
+            .get_result_async::<Self>(db.pool())
+            .await?)
+    }
+
+    pub async fn get(db: &Database, id: i32) -> Result<Option<Self>> {
+        Ok(webhook_events::dsl::webhook_events
+            .filter(webhook_events::dsl::id.eq(id))
+            .load_async::<Self>(db.pool())
+            .await?
+            .pop())
+    }
+
+    /// List events matching the filter, most recently received first
+    pub async fn list(db: &Database, filter: &WebhookEventFilter) -> Result<Vec<Self>> {
+        let mut query = webhook_events::dsl::webhook_events.into_boxed();
+
+        if let Some(source) = &filter.source {
+            query = query.filter(webhook_events::dsl::source.eq(source.to_string()));
+        }
+
+        if let Some(kind) = &filter.kind {
+            query = query.filter(webhook_events::dsl::kind.eq(kind.to_string()));
+        }
+
+        if let Some(status) = &filter.status {
+            query = query.filter(webhook_events::dsl::status.eq(status.to_string()));
+        }
+
+        if let Some(received_after) = filter.received_after {
+            query = query.filter(webhook_events::dsl::received_at.ge(received_after));
+        }
+
+        if let Some(received_before) = filter.received_before {
+            query = query.filter(webhook_events::dsl::received_at.lt(received_before));
+        }
+
+        Ok(query
+            .order_by(webhook_events::dsl::received_at.desc())
+            .limit(filter.limit())
+            .offset(filter.offset.unwrap_or(0).max(0))
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// The events that are due to be processed, oldest first
+    pub async fn due(db: &Database, now: DateTime<Utc>, limit: i64) -> Result<Vec<Self>> {
+        Ok(webhook_events::dsl::webhook_events
+            .filter(webhook_events::dsl::status.eq_any(statuses(&CLAIMABLE)))
+            .filter(webhook_events::dsl::next_attempt_at.le(now))
+            .order_by(webhook_events::dsl::received_at.asc())
+            .limit(limit)
+            .load_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// Claim an event that is due for an attempt at processing it. The event stays claimed for the
+    /// length of the lease, after which it is due again in case its processor stopped. Returns None
+    /// if the event is not due, which includes it having been claimed by another processor.
+    pub async fn claim(db: &Database, id: i32, lease: Duration) -> Result<Option<Self>> {
+        let now = Utc::now();
+
+        Ok(diesel::update(webhook_events::dsl::webhook_events)
+            .filter(webhook_events::dsl::id.eq(id))
+            .filter(webhook_events::dsl::status.eq_any(statuses(&CLAIMABLE)))
+            .filter(webhook_events::dsl::next_attempt_at.le(now))
+            .set((
+                webhook_events::dsl::status.eq(WebhookEventStatus::Processing.to_string()),
+                webhook_events::dsl::attempts.eq(webhook_events::dsl::attempts + 1),
+                webhook_events::dsl::next_attempt_at.eq(now + lease),
+            ))
+            .get_results_async::<Self>(db.pool())
+            .await?
+            .pop())
+    }
+
+    pub async fn mark_processed(db: &Database, id: i32) -> Result<()> {
+        diesel::update(webhook_events::dsl::webhook_events)
+            .filter(webhook_events::dsl::id.eq(id))
+            .set((
+                webhook_events::dsl::status.eq(WebhookEventStatus::Processed.to_string()),
+                webhook_events::dsl::processed_at.eq(Some(Utc::now())),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed attempt at processing an event. Events with a next attempt are put back to
+    /// wait for it, the rest have failed for good
+    pub async fn mark_attempt_failed(
+        db: &Database,
+        id: i32,
+        error: &str,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let status = match next_attempt_at {
+            Some(_) => WebhookEventStatus::Pending,
+            None => WebhookEventStatus::Failed,
+        };
+
+        diesel::update(webhook_events::dsl::webhook_events)
+            .filter(webhook_events::dsl::id.eq(id))
+            .set((
+                webhook_events::dsl::status.eq(status.to_string()),
+                webhook_events::dsl::last_error.eq(error.to_string()),
+                webhook_events::dsl::next_attempt_at.eq(next_attempt_at.unwrap_or_else(Utc::now)),
+            ))
+            .execute_async(db.pool())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Put events back to be processed again from their first attempt. Events that are currently
+    /// being processed are left alone. Returns the events that were put back
+    async fn requeue(db: &Database, ids: Vec<i32>) -> Result<Vec<Self>> {
+        let now = Utc::now();
+
+        Ok(diesel::update(webhook_events::dsl::webhook_events)
+            .filter(webhook_events::dsl::id.eq_any(ids))
+            .filter(webhook_events::dsl::status.ne(WebhookEventStatus::Processing.to_string()))
+            .set((
+                webhook_events::dsl::status.eq(WebhookEventStatus::Pending.to_string()),
+                webhook_events::dsl::attempts.eq(0),
+                webhook_events::dsl::next_attempt_at.eq(now),
+                webhook_events::dsl::replayed_at.eq(Some(now)),
+            ))
+            .get_results_async::<Self>(db.pool())
+            .await?)
+    }
+
+    /// Put a single event back to be processed again. Returns None if the event does not exist or
+    /// is currently being processed
+    pub async fn replay(db: &Database, id: i32) -> Result<Option<Self>> {
+        Ok(Self::requeue(db, vec![id]).await?.pop())
+    }
+
+    /// Put the events received within a time range back to be processed again
+    pub async fn replay_range(db: &Database, replay: &WebhookEventReplay) -> Result<Vec<Self>> {
+        let mut query = webhook_events::dsl::webhook_events
+            .select(webhook_events::dsl::id)
+            .filter(webhook_events::dsl::received_at.ge(replay.received_after))
+            .filter(webhook_events::dsl::received_at.lt(replay.received_before))
+            .into_boxed();
+
+        if let Some(source) = &replay.source {
+            query = query.filter(webhook_events::dsl::source.eq(source.to_string()));
+        }
+
+        let ids = query.load_async::<i32>(db.pool()).await?;
+
+        Self::requeue(db, ids).await
+    }
+}
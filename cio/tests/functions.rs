@@ -0,0 +1,78 @@
+use chrono::{Duration, Utc};
+use cio_api::{
+    db::Database,
+    functions::{Function, FunctionFilter, NewFunction},
+};
+use uuid::Uuid;
+
+fn new_function(name: &str, status: octorust::types::JobStatus, age: Duration) -> NewFunction {
+    NewFunction {
+        name: name.to_string(),
+        status: status.to_string(),
+        conclusion: String::new(),
+        created_at: Utc::now() - age,
+        completed_at: None,
+        logs: String::new(),
+        saga_id: Uuid::new_v4().to_string(),
+        cio_company_id: 1,
+    }
+}
+
+// Requires a migrated database. Run with CIO_DATABASE_URL set and --ignored
+#[ignore]
+#[tokio::test]
+async fn test_find_in_progress() {
+    let db = Database::new().await;
+    let name = format!("test-find-in-progress-{}", Uuid::new_v4());
+
+    let orphaned = new_function(&name, octorust::types::JobStatus::InProgress, Duration::hours(3))
+        .create_in_db(&db)
+        .await
+        .unwrap();
+    let running = new_function(&name, octorust::types::JobStatus::InProgress, Duration::minutes(10))
+        .create_in_db(&db)
+        .await
+        .unwrap();
+    let completed = new_function(&name, octorust::types::JobStatus::Completed, Duration::minutes(1))
+        .create_in_db(&db)
+        .await
+        .unwrap();
+
+    let found = Function::find_in_progress(&db, &name, 1, Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(Some(running.saga_id.clone()), found.map(|f| f.saga_id));
+
+    let found = Function::find_in_progress(&db, &name, 1, Duration::minutes(5))
+        .await
+        .unwrap();
+    assert_eq!(None, found);
+
+    // Runs for other companies do not count
+    let found = Function::find_in_progress(&db, &name, 2, Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(None, found);
+
+    let all = Function::list(
+        &db,
+        &FunctionFilter {
+            name: Some(name.clone()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        vec![
+            completed.saga_id.clone(),
+            running.saga_id.clone(),
+            orphaned.saga_id.clone()
+        ],
+        all.into_iter().map(|f| f.saga_id).collect::<Vec<_>>()
+    );
+
+    for function in [orphaned, running, completed] {
+        function.delete_from_db(&db).await.unwrap();
+    }
+}
@@ -0,0 +1,45 @@
+use cio_api::{
+    app_config::RFDS3PdfStorageConfig,
+    rfd::{PDFStorage, RFDPdf, S3Credentials, S3PDFStorage},
+};
+use uuid::Uuid;
+
+// Runs against any S3 compatible store. Locally this is expected to be a MinIO instance, i.e.
+//   docker run -p 9000:9000 minio/minio server /data
+// with a pre-created bucket
+fn test_storage() -> S3PDFStorage {
+    let config = RFDS3PdfStorageConfig {
+        endpoint: std::env::var("TEST_S3_ENDPOINT").unwrap(),
+        bucket: std::env::var("TEST_S3_BUCKET").unwrap(),
+        region: "us-east-1".to_string(),
+        prefix: format!("test-{}", Uuid::new_v4()),
+        public_url: Some("https://rfd-pdfs.example.com".to_string()),
+    };
+
+    S3PDFStorage::new(
+        &config,
+        S3Credentials {
+            access_key_id: std::env::var("TEST_S3_ACCESS_KEY_ID").unwrap(),
+            secret_access_key: std::env::var("TEST_S3_SECRET_ACCESS_KEY").unwrap(),
+        },
+    )
+    .unwrap()
+}
+
+#[ignore]
+#[tokio::test]
+async fn test_store_rfd_pdf_in_s3() {
+    let storage = test_storage();
+    let pdf = RFDPdf {
+        number: 123.into(),
+        filename: "RFD 0123 On Parsing Documents.pdf".to_string(),
+        contents: b"%PDF-1.4\n%%EOF".to_vec(),
+    };
+
+    let url = storage.store_rfd_pdf(&pdf).await.unwrap();
+
+    assert!(url.starts_with("https://rfd-pdfs.example.com/test-"));
+    assert!(url.ends_with("/RFD%200123%20On%20Parsing%20Documents.pdf"));
+
+    storage.delete_rfd_pdf(&pdf.filename).await.unwrap();
+}
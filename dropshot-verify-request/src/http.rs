@@ -28,3 +28,15 @@ pub fn unauthorized() -> HttpError {
 pub fn internal_error() -> HttpError {
     HttpError::for_internal_error("".to_string())
 }
+
+/// A response for a request that has already been handled. This is returned as an error so that
+/// extraction stops before the handler is run, but carries a success status so that the sender
+/// does not deliver the request again
+pub fn already_received() -> HttpError {
+    HttpError {
+        status_code: http::StatusCode::OK,
+        error_code: None,
+        external_message: "Already received".to_string(),
+        internal_message: "Delivery has already been received".to_string(),
+    }
+}
@@ -0,0 +1,256 @@
+use async_trait::async_trait;
+use dropshot::{
+    ApiEndpointBodyContentType, ExclusiveExtractor, ExtractorMetadata, HttpError, RequestContext, ServerContext,
+    UntypedBody,
+};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::http::already_received;
+
+/// A trait to be implemented for each source of webhooks that may deliver the same event more
+/// than once. The provider derives a key that is the same for every delivery of an event, and
+/// names the store that keys are recorded in.
+#[async_trait]
+pub trait IdempotencyKeyProvider {
+    type Store: IdempotencyStore;
+
+    /// How long a key is remembered for. Deliveries that arrive after a key has expired are
+    /// treated as new. Defaults to a day.
+    fn ttl() -> Duration {
+        Duration::from_secs(24 * 60 * 60)
+    }
+
+    /// Provides the key for the delivery. Keys should be prefixed with the source, as all sources
+    /// may share a store. Returns `None` if no key can be derived, in which case the delivery is
+    /// always treated as new.
+    async fn key<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        body: &[u8],
+    ) -> anyhow::Result<Option<String>>;
+}
+
+/// A trait for storing the keys of deliveries that have been received.
+#[async_trait]
+pub trait IdempotencyStore {
+    /// Records a key for the length of `ttl`. Returns `false` if the key was already recorded and
+    /// has not yet expired.
+    async fn record(key: &str, ttl: Duration) -> anyhow::Result<bool>;
+
+    /// Removes a key, so that the next delivery with the key is treated as new.
+    async fn forget(key: &str) -> anyhow::Result<()>;
+}
+
+/// An [`IdempotencyStore`] that keeps keys in the memory of the process. Keys are shared by
+/// every provider that uses this store and are lost on restart.
+pub struct MemoryStore;
+
+fn memory_keys() -> &'static Mutex<HashMap<String, Instant>> {
+    static KEYS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    KEYS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[async_trait]
+impl IdempotencyStore for MemoryStore {
+    async fn record(key: &str, ttl: Duration) -> anyhow::Result<bool> {
+        let now = Instant::now();
+        let mut keys = memory_keys()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Idempotency key store is poisoned"))?;
+
+        keys.retain(|_, expires_at| *expires_at > now);
+
+        if keys.contains_key(key) {
+            Ok(false)
+        } else {
+            keys.insert(key.to_string(), now + ttl);
+            Ok(true)
+        }
+    }
+
+    async fn forget(key: &str) -> anyhow::Result<()> {
+        memory_keys()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Idempotency key store is poisoned"))?
+            .remove(key);
+
+        Ok(())
+    }
+}
+
+/// A request that is extracted by the inner extractor `E`, and whose delivery has not been seen
+/// before according to the provider `T`.
+#[derive(Debug)]
+pub struct Idempotent<T, E> {
+    inner: E,
+    delivery: Delivery<T>,
+}
+
+impl<T, E> Idempotent<T, E> {
+    /// Returns the extracted value of the inner extractor along with the delivery it was received
+    /// in. The delivery must be completed with the result of handling the request, so that the
+    /// key is forgotten again if the event could not be stored
+    pub fn into_parts(self) -> (E, Delivery<T>) {
+        (self.inner, self.delivery)
+    }
+
+    /// Returns the key that the delivery was recorded under, if one could be derived
+    pub fn key(&self) -> Option<&str> {
+        self.delivery.key()
+    }
+}
+
+/// The delivery of a request that was extracted as [`Idempotent`]
+#[derive(Debug)]
+pub struct Delivery<T> {
+    key: Option<String>,
+    _provider: PhantomData<T>,
+}
+
+impl<T> Delivery<T>
+where
+    T: IdempotencyKeyProvider,
+{
+    /// Returns the key that the delivery was recorded under, if one could be derived
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Passes through the result of handling the delivery. If handling failed, the key of the
+    /// delivery is forgotten, so that a redelivery of the event is handled again instead of being
+    /// skipped. Failures to forget a key are logged, as the result of handling is more useful to
+    /// the caller.
+    pub async fn complete<R, Err>(&self, result: Result<R, Err>) -> Result<R, Err> {
+        if let (Err(_), Some(key)) = (&result, &self.key) {
+            if let Err(err) = T::Store::forget(key).await {
+                log::warn!("Failed to forget idempotency key. key: {} err: {}", key, err);
+            }
+        }
+
+        result
+    }
+}
+
+/// Extracting an [`Idempotent`] will return an [`OK`](http::status::StatusCode::OK) [`HttpError`](dropshot::HttpError)
+/// if the delivery has already been received, so that the handler is not run and the sender stops
+/// redelivering. The inner extractor `E` is run first, so that a request that fails verification
+/// can not record the key of a real delivery. The key is recorded before the handler runs, so that
+/// concurrent deliveries of the same event are not both handled, and is forgotten again by
+/// [`Delivery::complete`] if the handler fails. Failures to derive or record a key are logged and
+/// the delivery is treated as new, as it is safer to process an event twice than to drop it.
+#[async_trait]
+impl<T, E> ExclusiveExtractor for Idempotent<T, E>
+where
+    T: IdempotencyKeyProvider + Send + Sync,
+    E: ExclusiveExtractor + Send + Sync,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<Idempotent<T, E>, HttpError> {
+        // The body is read here so that it is available to both the provider and the inner
+        // extractor. The inner extractor receives a request with the original parts
+        let (parts, body) = request.into_parts();
+        let body = UntypedBody::from_request(rqctx, hyper::Request::new(body)).await?;
+        let bytes = body.as_bytes().to_vec();
+
+        let inner = E::from_request(
+            rqctx,
+            hyper::Request::from_parts(parts, hyper::Body::from(bytes.clone())),
+        )
+        .await?;
+
+        let key = match T::key(rqctx, &bytes).await {
+            Ok(key) => key,
+            Err(err) => {
+                log::warn!(
+                    "Failed to derive idempotency key. req_id: {} uri: {} err: {}",
+                    rqctx.request_id,
+                    rqctx.request.uri(),
+                    err
+                );
+                None
+            }
+        };
+
+        if let Some(key) = &key {
+            match T::Store::record(key, T::ttl()).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    log::info!(
+                        "Skipping delivery that was already received. req_id: {} uri: {} key: {}",
+                        rqctx.request_id,
+                        rqctx.request.uri(),
+                        key
+                    );
+
+                    return Err(already_received());
+                }
+                Err(err) => {
+                    log::warn!(
+                        "Failed to record idempotency key. req_id: {} uri: {} key: {} err: {}",
+                        rqctx.request_id,
+                        rqctx.request.uri(),
+                        key,
+                        err
+                    );
+                }
+            }
+        }
+
+        Ok(Idempotent {
+            inner,
+            delivery: Delivery {
+                key,
+                _provider: PhantomData,
+            },
+        })
+    }
+
+    fn metadata(body_content_type: ApiEndpointBodyContentType) -> ExtractorMetadata {
+        // The idempotency check does not alter the request, so the inner extractor describes it
+        E::metadata(body_content_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_store_rejects_redelivery() {
+        let ttl = Duration::from_secs(60);
+
+        assert!(MemoryStore::record("test:redelivery:1", ttl).await.unwrap());
+        assert!(!MemoryStore::record("test:redelivery:1", ttl).await.unwrap());
+        assert!(MemoryStore::record("test:redelivery:2", ttl).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_forgets_keys() {
+        let ttl = Duration::from_secs(60);
+
+        assert!(MemoryStore::record("test:forget:1", ttl).await.unwrap());
+        MemoryStore::forget("test:forget:1").await.unwrap();
+        assert!(MemoryStore::record("test:forget:1", ttl).await.unwrap());
+
+        // Forgetting a key that was never recorded is not an error
+        MemoryStore::forget("test:forget:2").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_forgets_expired_keys() {
+        let ttl = Duration::from_millis(10);
+
+        assert!(MemoryStore::record("test:expiry:1", ttl).await.unwrap());
+        assert!(!MemoryStore::record("test:expiry:1", ttl).await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(MemoryStore::record("test:expiry:1", ttl).await.unwrap());
+    }
+}
@@ -1,9 +1,15 @@
-use dropshot::{ApiEndpointBodyContentType, ExclusiveExtractor, ExtractorMetadata, HttpError, TypedBody};
+use async_trait::async_trait;
+use dropshot::{
+    ApiEndpointBodyContentType, ExclusiveExtractor, ExtractorMetadata, HttpError, RequestContext, ServerContext,
+    TypedBody, UntypedBody,
+};
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
 
 pub mod bearer;
 mod http;
+pub mod idempotency;
 pub mod query;
 pub mod sig;
 
@@ -39,3 +45,43 @@ where
         TypedBody::<Self>::metadata(body_content_type)
     }
 }
+
+/// A request body that is checked to deserialize into `BodyType`, but that is kept as the bytes
+/// it was received as. Useful for handlers that store a request to be handled later.
+#[derive(Debug)]
+pub struct RawBody<BodyType> {
+    body: UntypedBody,
+    _body_type: PhantomData<BodyType>,
+}
+
+impl<BodyType> RawBody<BodyType> {
+    /// Returns the request body exactly as it was received
+    pub fn as_bytes(&self) -> &[u8] {
+        self.body.as_bytes()
+    }
+}
+
+/// Extracting a [`RawBody`] will return a [`BAD_REQUEST`](http::status::StatusCode::BAD_REQUEST) [`HttpError`](dropshot::HttpError)
+/// if the body does not deserialize into `BodyType`, in the same way as a [`TypedBody`](dropshot::TypedBody)
+#[async_trait]
+impl<BodyType> ExclusiveExtractor for RawBody<BodyType>
+where
+    BodyType: FromBytes<HttpError>,
+{
+    async fn from_request<Context: ServerContext>(
+        rqctx: &RequestContext<Context>,
+        request: hyper::Request<hyper::Body>,
+    ) -> Result<RawBody<BodyType>, HttpError> {
+        let body = UntypedBody::from_request(rqctx, request).await?;
+        BodyType::from_bytes(body.as_bytes(), &rqctx.body_content_type)?;
+
+        Ok(RawBody {
+            body,
+            _body_type: PhantomData,
+        })
+    }
+
+    fn metadata(body_content_type: ApiEndpointBodyContentType) -> ExtractorMetadata {
+        BodyType::metadata(body_content_type)
+    }
+}
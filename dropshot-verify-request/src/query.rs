@@ -25,6 +25,13 @@ pub struct QueryTokenAudit<T> {
     _provider: PhantomData<T>,
 }
 
+impl<T> QueryTokenAudit<T> {
+    /// Returns that status of if this request passed verification
+    pub fn verified(&self) -> bool {
+        self.verified
+    }
+}
+
 #[derive(Deserialize, JsonSchema)]
 struct Token {
     token: String,
@@ -28,6 +28,17 @@ where
     pub fn into_inner(self) -> Result<BodyType, HttpError> {
         self.audit.into_inner()
     }
+
+    /// Attempts to deserialize the request body into the specified `BodyType` without consuming the
+    /// body. Returns a [`BAD_REQUEST`](http::status::StatusCode::BAD_REQUEST) [`HttpError`](dropshot::HttpError) if the deserialization of `BodyType` fails
+    pub fn parse(&self) -> Result<BodyType, HttpError> {
+        self.audit.parse()
+    }
+
+    /// Returns the verified request body exactly as it was received
+    pub fn as_bytes(&self) -> &[u8] {
+        self.audit.as_bytes()
+    }
 }
 
 /// A request body that performs the HMAC verification specified by the verifier `T`, but does not
@@ -54,8 +65,19 @@ where
     /// Attempts to deserialize the request body into the specified `BodyType`. Returns a
     /// [`BAD_REQUEST`](http::status::StatusCode::BAD_REQUEST) [`HttpError`](dropshot::HttpError) if the deserialization of `BodyType` fails.
     pub fn into_inner(self) -> Result<BodyType, HttpError> {
+        self.parse()
+    }
+
+    /// Attempts to deserialize the request body into the specified `BodyType` without consuming the
+    /// body. Returns a [`BAD_REQUEST`](http::status::StatusCode::BAD_REQUEST) [`HttpError`](dropshot::HttpError) if the deserialization of `BodyType` fails
+    pub fn parse(&self) -> Result<BodyType, HttpError> {
         BodyType::from_bytes(self.body.as_bytes(), &self.content_type)
     }
+
+    /// Returns the request body exactly as it was received, whether or not it passed verification
+    pub fn as_bytes(&self) -> &[u8] {
+        self.body.as_bytes()
+    }
 }
 
 /// A trait to be used to implement various HMAC verification strategies. By default a strategy
@@ -0,0 +1,137 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use dropshot::{
+    endpoint, ApiDescription, ConfigDropshot, ConfigLogging, ConfigLoggingLevel, HttpError, HttpResponseAccepted,
+    HttpServer, HttpServerStarter, RequestContext, ServerContext, TypedBody,
+};
+use dropshot_verify_request::idempotency::{IdempotencyKeyProvider, Idempotent, MemoryStore};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+use uuid::Uuid;
+
+/// Identifies deliveries by a header, in the same way that GitHub deliveries are identified
+struct TestDelivery;
+
+#[async_trait]
+impl IdempotencyKeyProvider for TestDelivery {
+    type Store = MemoryStore;
+
+    async fn key<Context: ServerContext>(rqctx: &RequestContext<Context>, _: &[u8]) -> Result<Option<String>> {
+        Ok(rqctx
+            .request
+            .headers()
+            .get("X-Test-Delivery")
+            .and_then(|value| value.to_str().ok())
+            .map(|delivery| format!("test:{}", delivery)))
+    }
+}
+
+/// Stands in for the webhook inbox. Storing an event fails as many times as `failures` is set to
+#[derive(Default)]
+struct Inbox {
+    events: Mutex<Vec<serde_json::Value>>,
+    failures: AtomicUsize,
+}
+
+impl Inbox {
+    fn store(&self, event: serde_json::Value) -> Result<(), HttpError> {
+        if self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| failures.checked_sub(1))
+            .is_ok()
+        {
+            return Err(HttpError::for_internal_error("Failed to store event".to_string()));
+        }
+
+        self.events.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+#[endpoint {
+    method = POST,
+    path = "/deliveries",
+}]
+async fn receive_delivery(
+    rqctx: RequestContext<Inbox>,
+    body: Idempotent<TestDelivery, TypedBody<serde_json::Value>>,
+) -> Result<HttpResponseAccepted<String>, HttpError> {
+    let (body, delivery) = body.into_parts();
+
+    delivery
+        .complete(rqctx.context().store(body.into_inner()))
+        .await
+        .map(|_| HttpResponseAccepted("ok".to_string()))
+}
+
+fn make_server(failures: usize) -> (u16, HttpServer<Inbox>) {
+    let config_dropshot = ConfigDropshot {
+        bind_address: "127.0.0.1:0".parse().unwrap(),
+        ..Default::default()
+    };
+    let config_logging = ConfigLogging::StderrTerminal {
+        level: ConfigLoggingLevel::Error,
+    };
+    let log = config_logging.to_logger("dropshot-verify-request").unwrap();
+
+    let mut api = ApiDescription::new();
+    api.register(receive_delivery).unwrap();
+
+    let inbox = Inbox {
+        failures: AtomicUsize::new(failures),
+        ..Default::default()
+    };
+    let server = HttpServerStarter::new(&config_dropshot, api, inbox, &log)
+        .map_err(|error| anyhow!("failed to create server: {}", error))
+        .unwrap()
+        .start();
+
+    (server.local_addr().port(), server)
+}
+
+async fn deliver(port: u16, delivery: &str) -> reqwest::StatusCode {
+    reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/deliveries", port))
+        .header("X-Test-Delivery", delivery)
+        .body(r#"{"action":"created"}"#)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_redelivery_is_skipped() {
+    let (port, server) = make_server(0);
+    let delivery = Uuid::new_v4().to_string();
+
+    assert_eq!(deliver(port, &delivery).await, reqwest::StatusCode::ACCEPTED);
+    assert_eq!(deliver(port, &delivery).await, reqwest::StatusCode::OK);
+    assert_eq!(
+        deliver(port, &Uuid::new_v4().to_string()).await,
+        reqwest::StatusCode::ACCEPTED
+    );
+
+    assert_eq!(server.app_private().events.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_redelivery_after_failure_is_handled() {
+    let (port, server) = make_server(1);
+    let delivery = Uuid::new_v4().to_string();
+
+    // The first delivery can not be stored, so its key must not keep the redelivery from being
+    // handled
+    assert_eq!(
+        deliver(port, &delivery).await,
+        reqwest::StatusCode::INTERNAL_SERVER_ERROR
+    );
+    assert_eq!(server.app_private().events.lock().unwrap().len(), 0);
+
+    assert_eq!(deliver(port, &delivery).await, reqwest::StatusCode::ACCEPTED);
+    assert_eq!(deliver(port, &delivery).await, reqwest::StatusCode::OK);
+
+    assert_eq!(server.app_private().events.lock().unwrap().len(), 1);
+}
@@ -313,6 +313,13 @@ fn do_db(attr: TokenStream, item: TokenStream) -> TokenStream {
                     fields: mut_self,
                 };
 
+                if crate::sandbox::Sandbox::intercept(
+                    crate::sandbox::SandboxService::Airtable,
+                    format!("id={} table={} create", self.id, #new_struct_name::airtable_table()),
+                ) {
+                    return Ok(record);
+                }
+
                 // Send the new record to the Airtable client.
                 let records : Vec<airtable_api::Record<#new_struct_name>> = self.airtable(db).await?
                     .create_records(&#new_struct_name::airtable_table(), vec![record])
@@ -344,6 +351,13 @@ fn do_db(attr: TokenStream, item: TokenStream) -> TokenStream {
 
                 existing_record.fields = mut_self;
 
+                if crate::sandbox::Sandbox::intercept(
+                    crate::sandbox::SandboxService::Airtable,
+                    format!("id={} table={} update", self.id, #new_struct_name::airtable_table()),
+                ) {
+                    return Ok(existing_record.clone());
+                }
+
                 // Send the updated record to Airtable.
                 let records : Vec<airtable_api::Record<#new_struct_name>> = self.airtable(db).await?.update_records(
                     &#new_struct_name::airtable_table(),
@@ -423,6 +437,13 @@ fn do_db(attr: TokenStream, item: TokenStream) -> TokenStream {
             /// Delete a record from Airtable.
             pub async fn delete_from_airtable(&self, db: &crate::db::Database) -> anyhow::Result<()> {
                 if !self.airtable_record_id.is_empty() {
+                    if crate::sandbox::Sandbox::intercept(
+                        crate::sandbox::SandboxService::Airtable,
+                        format!("id={} table={} delete", self.id, #new_struct_name::airtable_table()),
+                    ) {
+                        return Ok(());
+                    }
+
                     // Delete the record from airtable.
                     if let Err(e) = self.airtable(db).await?.delete_record(&#new_struct_name::airtable_table(), &self.airtable_record_id).await {
                         // Ignore if we got a NOT_FOUND error since then the record does not exist.
@@ -70,8 +70,22 @@ pub struct SearchQuery {
     pub query: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub filter: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "attributesToRetrieve", skip_serializing_if = "Option::is_none")]
     pub attributes_to_retrieve: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(rename = "attributesToHighlight", skip_serializing_if = "Option::is_none")]
+    pub attributes_to_highlight: Option<Vec<String>>,
+    #[serde(rename = "highlightPreTag", skip_serializing_if = "Option::is_none")]
+    pub highlight_pre_tag: Option<String>,
+    #[serde(rename = "highlightPostTag", skip_serializing_if = "Option::is_none")]
+    pub highlight_post_tag: Option<String>,
+    #[serde(rename = "attributesToCrop", skip_serializing_if = "Option::is_none")]
+    pub attributes_to_crop: Option<Vec<String>>,
+    #[serde(rename = "cropLength", skip_serializing_if = "Option::is_none")]
+    pub crop_length: Option<usize>,
 }
 
 impl IndexClient {
@@ -102,7 +116,10 @@ impl IndexClient {
             .await?;
 
         if response.status() == StatusCode::NOT_FOUND {
-            Ok(SearchResponse { hits: vec![] })
+            Ok(SearchResponse {
+                hits: vec![],
+                estimated_total_hits: None,
+            })
         } else {
             let content = response.text().await?;
             Ok(serde_json::from_str::<SearchResponse<T>>(&content)?)
@@ -170,6 +187,8 @@ pub struct IndexSettings {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SearchResponse<T> {
     pub hits: Vec<T>,
+    #[serde(rename = "estimatedTotalHits", default, skip_serializing_if = "Option::is_none")]
+    pub estimated_total_hits: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
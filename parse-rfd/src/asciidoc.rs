@@ -0,0 +1,618 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+
+use crate::{ParsedDoc, ParserError, Section};
+
+lazy_static! {
+    // Both Asciidoc (=) and Markdown (#) style section titles are accepted by Asciidoctor. Trailing
+    // markers are optional and only stripped when they match the leading marker.
+    static ref HEADING: Regex = Regex::new(r"^(={1,6}|#{1,6})[ \t]+(\S.*?)(?:[ \t]+=+)?[ \t]*$").unwrap();
+    static ref ATTRIBUTE_ENTRY: Regex = Regex::new(r"^:(!?[A-Za-z0-9_][A-Za-z0-9_-]*!?):(?:[ \t]+(.*))?$").unwrap();
+    static ref ATTRIBUTE_REFERENCE: Regex = Regex::new(r"(\\)?\{([A-Za-z0-9_][A-Za-z0-9_-]*)\}").unwrap();
+    static ref PREPROCESSOR_DIRECTIVE: Regex = Regex::new(r"^(ifdef|ifndef|ifeval|endif|include)::\S*\[.*\]$").unwrap();
+    static ref BLOCK_MACRO: Regex = Regex::new(r"^([a-z0-9_-]+)::(\S*?)\[(.*)\]$").unwrap();
+    static ref LIST_ITEM: Regex = Regex::new(r"^[ \t]*(?:\*{1,5}|-|\.{1,5}|\d+\.|[a-zA-Z]\.)[ \t]+(.*)$").unwrap();
+    static ref DESCRIPTION_ITEM: Regex = Regex::new(r"^[ \t]*(.*?\S)(?::{2,4}|;;)(?:[ \t]+(.*))?$").unwrap();
+    static ref TABLE_CELL_SPEC: Regex = Regex::new(r"^\d*(?:\.\d+)?[+*]?[<^>]?(?:\.[<^>])?[adehlmsv]?$").unwrap();
+
+    // Inline macros and cross references are replaced by their display text
+    static ref URL_MACRO: Regex =
+        Regex::new(r"(?:(?:link|mailto):([^\s\[]+)|((?:https?|ftp|irc)://[^\s\[]+))\[([^\]]*)\]").unwrap();
+    static ref NAMED_MACRO: Regex =
+        Regex::new(r"\b(?:xref|footnote|kbd|btn|menu|pass|stem|latexmath|asciimath|image|icon|anchor):([^\s\[]*)\[([^\]]*)\]").unwrap();
+    static ref CROSS_REFERENCE: Regex = Regex::new(r"<<([^,>]+)(?:,\s*([^>]+))?>>").unwrap();
+    static ref INLINE_ANCHOR: Regex = Regex::new(r"\[\[[^\]]*\]\]").unwrap();
+
+    // Formatting marks. Unconstrained marks may appear anywhere while constrained marks must be
+    // bounded by whitespace or punctuation
+    static ref UNCONSTRAINED: Regex = Regex::new(r"(\*\*|__|``|##|\+\+)(.+?)(\*\*|__|``|##|\+\+)").unwrap();
+    static ref CONSTRAINED: Regex =
+        Regex::new(r#"(^|[\s(\[{"'])([*_`#+])(\S|\S.*?\S)([*_`#+])($|[\s)\]},.;:!?"'])"#).unwrap();
+}
+
+/// The attributes that Asciidoctor uses when generating section ids
+const ID_PREFIX: &str = "idprefix";
+const ID_SEPARATOR: &str = "idseparator";
+
+/// Parse Asciidoc content into a title and a flattened list of sections. Sections are returned in
+/// document order with each section directly followed by its children.
+pub(crate) fn parse(content: &str) -> Result<ParsedDoc, ParserError> {
+    let lines = content
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !PREPROCESSOR_DIRECTIVE.is_match(line))
+        .collect::<Vec<_>>();
+
+    let mut reader = Reader::new(&lines);
+    let title = reader.read_header();
+    reader.read_body();
+
+    let Reader {
+        attributes, sections, ..
+    } = reader;
+
+    let title = title
+        .or_else(|| attributes.get("doctitle").cloned())
+        .map(|title| strip_rfd_prefix(&inline_text(&title, &attributes)))
+        .ok_or(ParserError::MissingTitle)?;
+
+    let mut ids = HashSet::new();
+    let mut stack: Vec<(usize, String)> = vec![];
+
+    let sections = sections
+        .into_iter()
+        .map(|raw| {
+            let name = inline_text(&raw.name, &attributes);
+
+            while stack.last().map(|(level, _)| *level >= raw.level).unwrap_or(false) {
+                stack.pop();
+            }
+
+            let parents = stack.iter().rev().map(|(_, name)| name.clone()).collect::<Vec<_>>();
+            stack.push((raw.level, name.clone()));
+
+            let section_id = match raw.id {
+                Some(id) => id,
+                None => generate_id(&name, &attributes, &ids),
+            };
+            ids.insert(section_id.clone());
+
+            Section {
+                section_id,
+                name,
+                content: render_text(&raw.lines, &attributes),
+                parents,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(ParsedDoc { title, sections })
+}
+
+/// A section as it appears in the source document prior to any text processing
+struct RawSection<'a> {
+    level: usize,
+    name: String,
+    id: Option<String>,
+    lines: Vec<&'a str>,
+}
+
+/// Attributes collected from a block attribute line (i.e. [source,rust] or [[anchor]]) that apply
+/// to the block or section directly following them
+#[derive(Default)]
+struct BlockAttributes {
+    id: Option<String>,
+    style: Option<String>,
+}
+
+impl BlockAttributes {
+    fn merge(&mut self, line: &str) {
+        let inner = &line[1..line.len() - 1];
+
+        // [[id]] and [[id, reference text]] anchors
+        if inner.starts_with('[') && inner.ends_with(']') {
+            let anchor = &inner[1..inner.len() - 1];
+            let id = anchor.split(',').next().unwrap_or_default().trim();
+
+            if !id.is_empty() {
+                self.id = Some(id.to_string());
+            }
+
+            return;
+        }
+
+        for (index, attribute) in inner.split(',').map(|a| a.trim()).enumerate() {
+            if let Some(id) = attribute.strip_prefix("id=") {
+                self.id = Some(id.trim_matches('"').to_string());
+            } else if index == 0 && !attribute.contains('=') {
+                // The first positional attribute is the block style and may carry the shorthand
+                // forms for id (#), role (.) and options (%)
+                let end = attribute.find(['#', '.', '%']).unwrap_or(attribute.len());
+                let style = &attribute[..end];
+
+                if !style.is_empty() {
+                    self.style = Some(style.to_string());
+                }
+
+                if let Some(start) = attribute.find('#') {
+                    let id = &attribute[start + 1..];
+                    let end = id.find(['.', '%']).unwrap_or(id.len());
+
+                    if end > 0 {
+                        self.id = Some(id[..end].to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    fn is_discrete(&self) -> bool {
+        matches!(self.style.as_deref(), Some("discrete") | Some("float"))
+    }
+}
+
+struct Reader<'a, 'b> {
+    lines: &'b [&'a str],
+    position: usize,
+    attributes: HashMap<String, String>,
+    sections: Vec<RawSection<'a>>,
+}
+
+impl<'a, 'b> Reader<'a, 'b> {
+    fn new(lines: &'b [&'a str]) -> Self {
+        let mut attributes = HashMap::new();
+        attributes.insert(ID_PREFIX.to_string(), "_".to_string());
+        attributes.insert(ID_SEPARATOR.to_string(), "_".to_string());
+
+        Self {
+            lines,
+            position: 0,
+            attributes,
+            sections: vec![],
+        }
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.lines.get(self.position).copied()
+    }
+
+    /// Reads the document header, returning the document title if one is found. The header is
+    /// made up of attribute entries and comments, followed by an optional level 0 title and any
+    /// author and revision lines that immediately follow it.
+    fn read_header(&mut self) -> Option<String> {
+        while let Some(line) = self.peek() {
+            if line.is_empty() || is_line_comment(line) {
+                self.position += 1;
+            } else if line == "////" {
+                self.position = find_block_end(self.lines, self.position, line) + 1;
+            } else if ATTRIBUTE_ENTRY.is_match(line) {
+                self.read_attribute_entry();
+            } else {
+                break;
+            }
+        }
+
+        let title = match self.peek().and_then(heading) {
+            Some((0, title)) => {
+                self.position += 1;
+                title
+            }
+            _ => return None,
+        };
+
+        // Author, revision and attribute lines continue until the first empty line
+        while let Some(line) = self.peek() {
+            if line.is_empty() {
+                break;
+            } else if ATTRIBUTE_ENTRY.is_match(line) {
+                self.read_attribute_entry();
+            } else {
+                self.position += 1;
+            }
+        }
+
+        Some(title)
+    }
+
+    fn read_body(&mut self) {
+        let mut pending = BlockAttributes::default();
+
+        while let Some(line) = self.peek() {
+            if let Some(delimiter) = block_delimiter(line) {
+                let end = find_block_end(self.lines, self.position, delimiter);
+                self.push_lines(self.position, end);
+                self.position = end + 1;
+                pending = BlockAttributes::default();
+            } else if ATTRIBUTE_ENTRY.is_match(line) {
+                self.read_attribute_entry();
+            } else if is_line_comment(line) {
+                self.position += 1;
+            } else if is_block_attribute_line(line) {
+                pending.merge(line);
+                self.push_lines(self.position, self.position);
+                self.position += 1;
+            } else {
+                match heading(line) {
+                    Some((level, name)) if level > 0 && !pending.is_discrete() => {
+                        self.sections.push(RawSection {
+                            level,
+                            name,
+                            id: pending.id.take(),
+                            lines: vec![],
+                        });
+                    }
+                    _ => self.push_lines(self.position, self.position),
+                }
+
+                if !line.is_empty() {
+                    pending = BlockAttributes::default();
+                }
+
+                self.position += 1;
+            }
+        }
+    }
+
+    /// Reads an attribute entry, including any values that are continued across multiple lines
+    fn read_attribute_entry(&mut self) {
+        let captures = match self.peek().and_then(|line| ATTRIBUTE_ENTRY.captures(line)) {
+            Some(captures) => captures,
+            None => return,
+        };
+
+        self.position += 1;
+
+        let name = captures.get(1).map(|m| m.as_str()).unwrap_or_default();
+        let mut value = captures.get(2).map(|m| m.as_str().to_string()).unwrap_or_default();
+
+        while value.ends_with(" \\") {
+            value.truncate(value.len() - 2);
+
+            match self.peek() {
+                Some(next) => {
+                    value.push(' ');
+                    value.push_str(next.trim());
+                    self.position += 1;
+                }
+                None => break,
+            }
+        }
+
+        if name.starts_with('!') || name.ends_with('!') {
+            self.attributes.remove(name.trim_matches('!'));
+        } else {
+            let value = substitute_attributes(&value, &self.attributes);
+            self.attributes.insert(name.to_string(), value);
+        }
+    }
+
+    /// Appends the source lines between start and end (inclusive) to the section currently being
+    /// read. Lines that occur before the first section belong to the preamble and are dropped.
+    fn push_lines(&mut self, start: usize, end: usize) {
+        if let Some(section) = self.sections.last_mut() {
+            let end = end.min(self.lines.len() - 1);
+            section.lines.extend_from_slice(&self.lines[start..=end]);
+        }
+    }
+}
+
+fn heading(line: &str) -> Option<(usize, String)> {
+    HEADING
+        .captures(line)
+        .map(|captures| (captures[1].len() - 1, captures[2].to_string()))
+}
+
+fn is_line_comment(line: &str) -> bool {
+    line.starts_with("//") && !line.starts_with("///")
+}
+
+fn is_block_attribute_line(line: &str) -> bool {
+    line.len() > 2 && line.starts_with('[') && line.ends_with(']') && !line.starts_with("[ ")
+}
+
+/// Returns the delimiter string that closes the block opened by this line, if the line opens a
+/// delimited block
+fn block_delimiter(line: &str) -> Option<&str> {
+    if line.starts_with("```") {
+        return Some("```");
+    }
+
+    if line == "--" {
+        return Some(line);
+    }
+
+    if line.len() >= 4 && line.starts_with(['|', ',', ':', '!']) && line[1..].chars().all(|c| c == '=') {
+        return Some(line);
+    }
+
+    let mut chars = line.chars();
+    let first = chars.next()?;
+
+    if line.len() >= 4 && matches!(first, '-' | '.' | '=' | '*' | '_' | '+' | '/') && chars.all(|c| c == first) {
+        Some(line)
+    } else {
+        None
+    }
+}
+
+/// Find the index of the line that closes the block that is opened at the start index. If the block
+/// is never closed, then the block extends to the end of the document.
+fn find_block_end(lines: &[&str], start: usize, delimiter: &str) -> usize {
+    lines
+        .iter()
+        .enumerate()
+        .skip(start + 1)
+        .find(|(_, line)| **line == delimiter)
+        .map(|(index, _)| index)
+        .unwrap_or(lines.len() - 1)
+}
+
+/// Generate a section id following the same rules that Asciidoctor uses for auto-generated ids
+fn generate_id(name: &str, attributes: &HashMap<String, String>, ids: &HashSet<String>) -> String {
+    let prefix = attributes.get(ID_PREFIX).map(|s| s.as_str()).unwrap_or_default();
+    let separator = attributes
+        .get(ID_SEPARATOR)
+        .and_then(|s| s.chars().next())
+        .map(|c| c.to_string())
+        .unwrap_or_default();
+
+    let squeezed = [' ', '.', '-'].into_iter().chain(separator.chars()).collect::<Vec<_>>();
+
+    let mut id = prefix.to_string();
+    let mut last_was_separator = false;
+
+    for c in name.to_lowercase().chars() {
+        if squeezed.contains(&c) {
+            if !last_was_separator {
+                id.push_str(&separator);
+            }
+            last_was_separator = true;
+        } else if c.is_alphanumeric() || c == '_' {
+            id.push(c);
+            last_was_separator = false;
+        }
+    }
+
+    if !separator.is_empty() {
+        if id.ends_with(&separator) {
+            id.truncate(id.len() - separator.len());
+        }
+
+        if prefix.is_empty() {
+            id = id.trim_start_matches(&separator).to_string();
+        }
+    }
+
+    if ids.contains(&id) {
+        (2..)
+            .map(|count| format!("{}{}{}", id, separator, count))
+            .find(|candidate| !ids.contains(candidate))
+            .unwrap()
+    } else {
+        id
+    }
+}
+
+/// Document titles are expected to be of the form "RFD <number> <title>"
+fn strip_rfd_prefix(title: &str) -> String {
+    let title = title.trim();
+    let title = title.strip_prefix("RFD").map(|t| t.trim_start()).unwrap_or(title);
+
+    match title.split_once(' ') {
+        Some((number, rest)) if number.trim_end_matches(':').chars().all(|c| c.is_ascii_digit()) => {
+            rest.trim().to_string()
+        }
+        _ => title.to_string(),
+    }
+}
+
+/// Render the body of a section to plain text. Blocks are separated by empty lines, paragraph lines
+/// are joined, and verbatim blocks keep their line structure.
+fn render_text(lines: &[&str], attributes: &HashMap<String, String>) -> String {
+    let mut blocks: Vec<String> = vec![];
+    let mut paragraph: Vec<String> = vec![];
+    let mut list: Vec<String> = vec![];
+
+    fn flush(blocks: &mut Vec<String>, paragraph: &mut Vec<String>, list: &mut Vec<String>) {
+        if !paragraph.is_empty() {
+            list.push(paragraph.join(" "));
+            paragraph.clear();
+        }
+
+        if !list.is_empty() {
+            blocks.push(list.join("\n"));
+            list.clear();
+        }
+    }
+
+    let mut position = 0;
+
+    while position < lines.len() {
+        let line = lines[position];
+        position += 1;
+
+        if let Some(delimiter) = block_delimiter(line) {
+            flush(&mut blocks, &mut paragraph, &mut list);
+
+            let end = find_block_end(lines, position - 1, delimiter);
+            let inner = if end >= position {
+                &lines[position..end]
+            } else {
+                &[][..]
+            };
+            position = end + 1;
+
+            let text = match delimiter.chars().next() {
+                Some('/') | Some('+') => String::new(),
+                Some('-') if delimiter != "--" => inner.join("\n"),
+                Some('.') | Some('`') => inner.join("\n"),
+                Some('|') | Some(',') | Some(':') | Some('!') => {
+                    render_table(inner, delimiter.chars().next().unwrap(), attributes)
+                }
+                _ => render_text(inner, attributes),
+            };
+
+            if !text.trim().is_empty() {
+                blocks.push(text.trim_end().to_string());
+            }
+        } else if line.trim().is_empty() {
+            flush(&mut blocks, &mut paragraph, &mut list);
+        } else if is_line_comment(line)
+            || is_block_attribute_line(line)
+            || ATTRIBUTE_ENTRY.is_match(line)
+            || matches!(line, "+" | "'''" | "<<<")
+        {
+            // Comments, attributes, list continuations and breaks do not produce any text
+        } else if let Some(captures) = BLOCK_MACRO.captures(line) {
+            flush(&mut blocks, &mut paragraph, &mut list);
+
+            // Only images carry any meaningful text in the form of their alt text
+            if &captures[1] == "image" && !captures[3].is_empty() {
+                let alt = captures[3].split(',').next().unwrap_or_default();
+                blocks.push(inline_text(alt, attributes));
+            }
+        } else if let Some((_, name)) = heading(line) {
+            flush(&mut blocks, &mut paragraph, &mut list);
+            blocks.push(inline_text(&name, attributes));
+        } else if let Some(captures) = LIST_ITEM.captures(line) {
+            if !paragraph.is_empty() {
+                list.push(paragraph.join(" "));
+                paragraph.clear();
+            }
+
+            paragraph.push(inline_text(&captures[1], attributes));
+        } else if let Some(title) = line.strip_prefix('.').filter(|t| !t.starts_with([' ', '.'])) {
+            flush(&mut blocks, &mut paragraph, &mut list);
+            blocks.push(inline_text(title, attributes));
+        } else if let Some(captures) = DESCRIPTION_ITEM.captures(line).filter(|_| paragraph.is_empty()) {
+            let term = inline_text(&captures[1], attributes);
+
+            match captures.get(2) {
+                Some(description) => {
+                    paragraph.push(format!("{} {}", term, inline_text(description.as_str(), attributes)))
+                }
+                None => paragraph.push(term),
+            }
+        } else {
+            paragraph.push(inline_text(line.trim(), attributes));
+        }
+    }
+
+    flush(&mut blocks, &mut paragraph, &mut list);
+
+    blocks.join("\n\n")
+}
+
+fn render_table(lines: &[&str], separator: char, attributes: &HashMap<String, String>) -> String {
+    lines
+        .iter()
+        .filter_map(|line| {
+            let mut cells = line.split(separator).map(|cell| cell.trim()).collect::<Vec<_>>();
+
+            // The content before the first separator on a line is either empty or a cell spec
+            if cells.first().map(|c| TABLE_CELL_SPEC.is_match(c)).unwrap_or(false) {
+                cells.remove(0);
+            }
+
+            let row = cells
+                .into_iter()
+                .filter(|cell| !cell.is_empty())
+                .map(|cell| inline_text(cell, attributes))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            if row.is_empty() {
+                None
+            } else {
+                Some(row)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn substitute_attributes(text: &str, attributes: &HashMap<String, String>) -> String {
+    ATTRIBUTE_REFERENCE
+        .replace_all(text, |captures: &Captures| {
+            if captures.get(1).is_some() {
+                // Escaped references are left in place without the escape character
+                captures[0][1..].to_string()
+            } else {
+                attributes
+                    .get(&captures[2])
+                    .cloned()
+                    .unwrap_or_else(|| captures[0].to_string())
+            }
+        })
+        .to_string()
+}
+
+/// Apply attribute substitutions and strip inline macros and formatting marks from a line of text
+fn inline_text(text: &str, attributes: &HashMap<String, String>) -> String {
+    let text = substitute_attributes(text, attributes);
+
+    let text = INLINE_ANCHOR.replace_all(&text, "");
+    let text = CROSS_REFERENCE.replace_all(&text, |captures: &Captures| {
+        captures
+            .get(2)
+            .map(|m| m.as_str())
+            .unwrap_or_else(|| captures[1].trim_start_matches('#'))
+            .trim()
+            .to_string()
+    });
+    let text = NAMED_MACRO.replace_all(&text, |captures: &Captures| {
+        let display = captures[2].split(',').next().unwrap_or_default().trim();
+
+        if display.is_empty() {
+            captures[1].to_string()
+        } else {
+            display.to_string()
+        }
+    });
+    let text = URL_MACRO.replace_all(&text, |captures: &Captures| {
+        let display = captures[3].split(',').next().unwrap_or_default().trim();
+
+        if display.is_empty() {
+            captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .map(|m| m.as_str().to_string())
+                .unwrap_or_default()
+        } else {
+            display.trim_matches('"').to_string()
+        }
+    });
+
+    let mut text = UNCONSTRAINED.replace_all(&text, unwrap_marks).to_string();
+
+    // Constrained marks may be directly adjacent to each other (i.e. *bold* _em_) which requires
+    // multiple passes as adjacent matches share their boundary character
+    loop {
+        let next = CONSTRAINED
+            .replace_all(&text, |captures: &Captures| {
+                if captures[2] == captures[4] {
+                    format!("{}{}{}", &captures[1], &captures[3], &captures[5])
+                } else {
+                    captures[0].to_string()
+                }
+            })
+            .to_string();
+
+        if next == text {
+            break;
+        }
+
+        text = next;
+    }
+
+    text.trim().to_string()
+}
+
+fn unwrap_marks(captures: &Captures) -> String {
+    if captures[1] == captures[3] {
+        captures[2].to_string()
+    } else {
+        captures[0].to_string()
+    }
+}
@@ -1,15 +1,7 @@
 use serde::Deserialize;
-use std::{
-    error::Error,
-    fmt,
-    fs::{create_dir_all, remove_dir, remove_file, File},
-    io::Write,
-    path::PathBuf,
-    process::{Command, Stdio},
-    str::from_utf8,
-};
-
-static PARSER: &str = include_str!("../parser/dist/index.js");
+use std::{error::Error, fmt};
+
+mod asciidoc;
 
 #[derive(Debug, Deserialize, PartialEq)]
 pub struct ParsedDoc {
@@ -27,122 +19,21 @@ pub struct Section {
 
 #[derive(Debug)]
 pub enum ParserError {
-    Create(FailedToCreateParser),
-    Delete(FailedToDeleteParser),
-    Execute(std::io::Error),
-    InvalidResponse(std::str::Utf8Error),
-    UnexpectedResponse(serde_json::Error),
-}
-
-impl From<std::io::Error> for ParserError {
-    fn from(err: std::io::Error) -> Self {
-        Self::Execute(err)
-    }
-}
-
-impl From<FailedToCreateParser> for ParserError {
-    fn from(err: FailedToCreateParser) -> Self {
-        Self::Create(err)
-    }
-}
-
-impl From<FailedToDeleteParser> for ParserError {
-    fn from(err: FailedToDeleteParser) -> Self {
-        Self::Delete(err)
-    }
+    MissingTitle,
 }
 
 impl fmt::Display for ParserError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ParserError::Create(err) => write!(f, "Failed to create parser {err:?}"),
-            ParserError::Delete(err) => write!(f, "Failed to delete parser {err:?}"),
-            ParserError::Execute(err) => write!(f, "Failed to run parser {err:?}"),
-            ParserError::InvalidResponse(err) => write!(f, "Parser return unusable data {err:?}"),
-            ParserError::UnexpectedResponse(err) => write!(f, "Parser return data that could not be parsed {err:?}"),
-        }
-    }
-}
-
-impl Error for ParserError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        match self {
-            ParserError::Create(err) => Some(err),
-            ParserError::Delete(err) => Some(err),
-            ParserError::Execute(err) => Some(err),
-            ParserError::InvalidResponse(err) => Some(err),
-            ParserError::UnexpectedResponse(err) => Some(err),
+            ParserError::MissingTitle => write!(f, "Document does not have a title"),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct FailedToCreateParser(std::io::Error);
-
-impl fmt::Display for FailedToCreateParser {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Failed to create parser file: {:?}", self.0)
-    }
-}
-
-impl Error for FailedToCreateParser {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&self.0)
-    }
-}
-
-#[derive(Debug)]
-pub struct FailedToDeleteParser(std::io::Error);
-
-impl fmt::Display for FailedToDeleteParser {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Failed to delete parser file: {:?}", self.0)
-    }
-}
-
-impl Error for FailedToDeleteParser {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&self.0)
-    }
-}
-
-fn parser() -> Result<PathBuf, FailedToCreateParser> {
-    let mut tmp = std::env::temp_dir();
-    tmp.push(uuid::Uuid::new_v4().to_string());
-
-    create_dir_all(&tmp).map_err(FailedToCreateParser)?;
-
-    tmp.push("cio-rfd-parser");
-    tmp.set_extension("js");
-
-    let mut file = File::create(tmp.clone()).map_err(FailedToCreateParser)?;
-    file.write_all(PARSER.as_bytes()).map_err(FailedToCreateParser)?;
-
-    Ok(tmp)
-}
+impl Error for ParserError {}
 
 pub fn parse(content: &str) -> Result<ParsedDoc, ParserError> {
-    let mut tmp = parser()?;
-    let path_arg = format!("{}", tmp.display());
-
-    let mut cmd = Command::new("node")
-        .args([path_arg])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    cmd.stdin
-        .as_mut()
-        .unwrap() // We always assign stdin above. Does that ensure this is Some?
-        .write_all(content.as_bytes())?;
-    let output = cmd.wait_with_output()?.stdout;
-
-    remove_file(&tmp).map_err(FailedToDeleteParser)?;
-    tmp.pop();
-    remove_dir(&tmp).map_err(FailedToDeleteParser)?;
-
-    serde_json::from_str(from_utf8(&output).map_err(ParserError::InvalidResponse)?)
-        .map_err(ParserError::UnexpectedResponse)
+    asciidoc::parse(content)
 }
 
 #[cfg(test)]
@@ -236,4 +127,103 @@ Third in the list"#,
 
         assert_eq!(expected, value);
     }
+
+    #[test]
+    fn parse_explicit_and_duplicate_ids() {
+        let value = crate::parse(
+            r#"= RFD 42 Identifiers
+
+[[custom-anchor]]
+== Explicit Anchor
+
+Anchored
+
+[#shorthand.role]
+== Shorthand Anchor
+
+Shorthand
+
+== Repeated Title
+
+First
+
+== Repeated Title
+
+Second"#,
+        )
+        .unwrap();
+
+        let ids = value.sections.iter().map(|s| s.section_id.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(value.title, "Identifiers");
+        assert_eq!(
+            ids,
+            vec!["custom-anchor", "shorthand", "_repeated_title", "_repeated_title_2"]
+        );
+    }
+
+    #[test]
+    fn parse_ignores_headings_in_blocks() {
+        let value = crate::parse(
+            r#"= RFD 7 Blocks
+
+== Example
+
+Some *bold* and `code` text with a https://example.com[link].
+
+----
+== Not a heading
+----
+
+[discrete]
+== Discrete Heading
+
+* First item
+* Second item
+
+////
+== Commented out
+////"#,
+        )
+        .unwrap();
+
+        assert_eq!(value.sections.len(), 1);
+        assert_eq!(
+            value.sections[0].content,
+            "Some bold and code text with a link.\n\n== Not a heading\n\nDiscrete Heading\n\nFirst item\nSecond item"
+        );
+    }
+
+    #[test]
+    fn parse_markdown_headings() {
+        let value = crate::parse(
+            r#"# RFD 8 Markdown Style
+
+## The Problem
+
+Described here
+
+### Sub Problem
+
+In detail"#,
+        )
+        .unwrap();
+
+        assert_eq!(value.title, "Markdown Style");
+        assert_eq!(value.sections[1].section_id, "_sub_problem");
+        assert_eq!(value.sections[1].parents, vec!["The Problem".to_string()]);
+    }
+
+    #[test]
+    fn parse_lines_starting_with_multibyte_characters() {
+        let value = crate::parse("= RFD 9 Unicode\n\n== Über\n\n→ first\n€€€€\n").unwrap();
+
+        assert_eq!(value.sections[0].name, "Über");
+        assert_eq!(value.sections[0].content, "→ first €€€€");
+    }
+
+    #[test]
+    fn fails_without_title() {
+        assert!(matches!(crate::parse("== Section"), Err(ParserError::MissingTitle)));
+    }
 }
@@ -114,6 +114,34 @@ pub struct Transaction {
     pub user_transaction_time: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub enum CardState {
+    #[serde(rename = "ACTIVE")]
+    Active,
+    #[serde(rename = "CHIP_LOCKED")]
+    ChipLocked,
+    #[serde(rename = "SUSPENDED")]
+    Suspended,
+    #[serde(rename = "TERMINATED")]
+    Terminated,
+    #[serde(rename = "UNACTIVATED")]
+    Unactivated,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Card {
+    pub id: String,
+    pub cardholder_id: String,
+    pub display_name: String,
+    pub last_four: String,
+    pub state: CardState,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TerminateCardDeferred {
+    pub idempotency_key: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Department {
     pub id: String,
@@ -374,6 +402,10 @@ impl RampClient {
             .request(method, format!("https://api.ramp.com/developer/v1/{path}"))
     }
 
+    pub fn cards(&self) -> CardClient {
+        CardClient { client: self }
+    }
+
     pub fn departments(&self) -> DepartmentClient {
         DepartmentClient { client: self }
     }
@@ -395,6 +427,37 @@ impl RampClient {
     }
 }
 
+pub struct CardClient<'a> {
+    client: &'a RampClient,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ListCardsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+}
+
+impl<'a> CardClient<'a> {
+    pub async fn list(&self, query: &ListCardsQuery) -> Result<ResponseList<Card>, Error> {
+        let req = self.client.request(Method::GET, "cards").query(query);
+        Ok(self.client.execute(req).await?.json().await?)
+    }
+
+    pub async fn deferred_termination(
+        &self,
+        card_id: &str,
+        payload: &TerminateCardDeferred,
+    ) -> Result<DeferredTaskId, Error> {
+        let req = self
+            .client
+            .request(Method::POST, &format!("cards/{card_id}/deferred/termination"))
+            .json(payload);
+        Ok(self.client.execute(req).await?.json().await?)
+    }
+}
+
 pub struct DepartmentClient<'a> {
     client: &'a RampClient,
 }
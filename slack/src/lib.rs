@@ -25,12 +25,12 @@
  */
 #![allow(clippy::field_reassign_with_default)]
 #![allow(clippy::nonstandard_macro_braces)]
-use std::{collections::HashMap, env, sync::Arc};
+use std::{collections::HashMap, env, fmt, sync::Arc};
 
 use anyhow::{bail, Result};
-use reqwest::{header, Body, Client, Method, Request, StatusCode, Url};
+use reqwest::{header, Body, Client, Method, Request, Response, StatusCode, Url};
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 
 /// Endpoint for the Slack API.
@@ -46,10 +46,15 @@ pub struct Slack {
     client_secret: String,
     redirect_uri: String,
     workspace_id: String,
+    base_url: Url,
 
     client: Arc<Client>,
+    write_filter: Option<Arc<WriteFilter>>,
 }
 
+/// Decides whether a request that changes anything in Slack is sent.
+type WriteFilter = dyn Fn(&Request) -> bool + Send + Sync;
+
 impl Slack {
     /// Create a new Slack client struct. It takes a type that can convert into
     /// an &str (`String` or `Vec<u8>` for example). As long as the function is
@@ -80,8 +85,10 @@ impl Slack {
                     redirect_uri: redirect_uri.to_string(),
                     token: token.to_string(),
                     user_token: user_token.to_string(),
+                    base_url: Url::parse(ENDPOINT).unwrap(),
 
                     client: Arc::new(c),
+                    write_filter: None,
                 };
 
                 if s.token.is_empty() || s.user_token.is_empty() {
@@ -115,6 +122,28 @@ impl Slack {
         Slack::new(client_id, client_secret, workspace_id, redirect_uri, token, user_token)
     }
 
+    /// Set the base url for `Slack`.
+    pub fn base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Set a filter that every request that changes anything in Slack is checked against before
+    /// it is sent. Requests that the filter returns false for are not sent, and are answered as if
+    /// they succeeded with an otherwise empty response.
+    pub fn write_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        self.write_filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// The ID of the workspace the client is authenticated with.
+    pub fn workspace_id(&self) -> &str {
+        &self.workspace_id
+    }
+
     fn request<B>(
         &self,
         token: &str,
@@ -126,8 +155,7 @@ impl Slack {
     where
         B: Serialize,
     {
-        let base = Url::parse(ENDPOINT)?;
-        let url = base.join(path)?;
+        let url = self.base_url.join(path)?;
 
         let bt = format!("Bearer {token}");
         let bearer = header::HeaderValue::from_str(&bt)?;
@@ -203,17 +231,51 @@ impl Slack {
     /// List users on a workspace.
     /// FROM: https://api.slack.com/methods/users.list
     pub async fn list_users(&self) -> Result<Vec<User>> {
+        let mut users = vec![];
+        let mut cursor = String::new();
+
+        loop {
+            let mut query = vec![("limit", "100".to_string())];
+            if !cursor.is_empty() {
+                query.push(("cursor", cursor.to_string()));
+            }
+
+            // Build the request.
+            let request = self.request(&self.token, Method::GET, "users.list", (), Some(query))?;
+
+            let resp = self.send(request).await?;
+            match resp.status() {
+                StatusCode::OK => (),
+                s => {
+                    bail!("status code: {}, body: {}", s, resp.text().await?);
+                }
+            };
+
+            let mut r: APIResponse = resp.json().await?;
+            users.append(&mut r.users);
+
+            if r.response_metadata.next_cursor.is_empty() {
+                break;
+            }
+            cursor = r.response_metadata.next_cursor;
+        }
+
+        Ok(users)
+    }
+
+    /// Find a user by their email address. Returns `None` if no user has the email.
+    /// FROM: https://api.slack.com/methods/users.lookupByEmail
+    pub async fn lookup_user_by_email(&self, email: &str) -> Result<Option<User>> {
         // Build the request.
-        // TODO: paginate.
         let request = self.request(
             &self.token,
             Method::GET,
-            "users.list",
+            "users.lookupByEmail",
             (),
-            Some(vec![("limit", "100".to_string())]),
+            Some(vec![("email", email.to_string())]),
         )?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -221,9 +283,17 @@ impl Slack {
             }
         };
 
-        let r: APIResponse = resp.json().await?;
+        let r: UserResponse = resp.json().await?;
+
+        if !r.ok {
+            if r.error == "users_not_found" {
+                return Ok(None);
+            }
+
+            bail!("status code: {}, body: {}", StatusCode::OK, serde_json::json!(r));
+        }
 
-        Ok(r.users)
+        Ok(Some(r.user))
     }
 
     /// Get the current user's identity.
@@ -232,7 +302,7 @@ impl Slack {
         // Build the request.
         let request = self.request(&self.user_token, Method::GET, "users.identity", (), None)?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -251,7 +321,7 @@ impl Slack {
         // TODO: paginate.
         let request = self.request(&self.user_token, Method::GET, "team.billableInfo", (), None)?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -269,7 +339,7 @@ impl Slack {
         // Build the request.
         let request = self.request(&self.token, Method::POST, "views.open", body, None)?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -280,11 +350,7 @@ impl Slack {
         let r: ViewResponse = resp.json().await?;
 
         if !r.ok {
-            bail!(
-                "status code: {}, body: {}",
-                StatusCode::OK,
-                serde_json::json!(r).to_string()
-            );
+            bail!("status code: {}, body: {}", StatusCode::OK, serde_json::json!(r));
         }
         Ok(r.view)
     }
@@ -310,7 +376,7 @@ impl Slack {
         // Build the request.
         let mut request = self.request(&self.token, Method::GET, "conversations.list", (), None)?;
 
-        let mut resp = self.client.execute(request).await?;
+        let mut resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -321,11 +387,7 @@ impl Slack {
         let mut r: ListChannelsResponse = resp.json().await?;
 
         if !r.ok {
-            bail!(
-                "status code: {}, body: {}",
-                StatusCode::OK,
-                serde_json::json!(r).to_string()
-            );
+            bail!("status code: {}, body: {}", StatusCode::OK, serde_json::json!(r));
         }
 
         let mut channels = r.channels;
@@ -340,7 +402,7 @@ impl Slack {
                 Some(vec![("cursor", r.response_metadata.next_cursor.to_string())]),
             )?;
 
-            resp = self.client.execute(request).await?;
+            resp = self.send(request).await?;
             match resp.status() {
                 StatusCode::OK => (),
                 s => {
@@ -352,11 +414,7 @@ impl Slack {
             r = resp.json().await?;
 
             if !r.ok {
-                bail!(
-                    "status code: {}, body: {}",
-                    StatusCode::OK,
-                    serde_json::json!(r).to_string()
-                );
+                bail!("status code: {}, body: {}", StatusCode::OK, serde_json::json!(r));
             }
 
             channels.append(&mut r.channels);
@@ -371,7 +429,7 @@ impl Slack {
         // Build the request.
         let request = self.request(&self.user_token, Method::POST, "admin.users.invite", invite, None)?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -398,7 +456,7 @@ impl Slack {
 
         let request = self.request(&self.token, Method::POST, "conversations.join", body, None)?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -409,11 +467,7 @@ impl Slack {
         let f: JoinChannelResponse = resp.json().await?;
 
         if !f.ok {
-            bail!(
-                "status code: {}, body: {}",
-                StatusCode::OK,
-                serde_json::json!(f).to_string()
-            );
+            bail!("status code: {}, body: {}", StatusCode::OK, serde_json::json!(f));
         }
 
         Ok(f.channel)
@@ -431,11 +485,7 @@ impl Slack {
                 self.join_channel(&body.channel).await?;
                 return self.post_message_raw(body).await;
             }
-            bail!(
-                "status code: {}, body: {}",
-                StatusCode::OK,
-                serde_json::json!(f).to_string()
-            );
+            bail!("status code: {}, body: {}", StatusCode::OK, serde_json::json!(f));
         }
 
         Ok(f)
@@ -444,7 +494,7 @@ impl Slack {
     pub async fn post_message_raw(&self, body: &FormattedMessage) -> Result<FormattedMessageResponse> {
         let request = self.request(&self.token, Method::POST, "chat.postMessage", body, None)?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -466,7 +516,7 @@ impl Slack {
         body.insert("user_id", user_id);
         let request = self.request(&self.user_token, Method::POST, "admin.users.remove", body, None)?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -492,7 +542,7 @@ impl Slack {
             None,
         )?;
 
-        let resp = self.client.execute(request).await?;
+        let resp = self.send(request).await?;
         match resp.status() {
             StatusCode::OK => (),
             s => {
@@ -503,6 +553,194 @@ impl Slack {
         Ok(())
     }
 
+    /// Get the custom profile fields that are defined for the workspace.
+    /// FROM: https://api.slack.com/methods/team.profile.get
+    pub async fn list_team_profile_fields(&self) -> Result<Vec<TeamProfileField>> {
+        let request = self.request(&self.token, Method::GET, "team.profile.get", (), None)?;
+
+        let r: TeamProfileResponse = self.execute(request).await?;
+        Ok(r.profile.fields)
+    }
+
+    /// List the members of a channel by their user ids.
+    /// FROM: https://api.slack.com/methods/conversations.members
+    pub async fn list_channel_members(&self, channel_id: &str) -> Result<Vec<String>> {
+        let mut members = vec![];
+        let mut cursor = String::new();
+
+        loop {
+            let mut query = vec![("channel", channel_id.to_string()), ("limit", "200".to_string())];
+            if !cursor.is_empty() {
+                query.push(("cursor", cursor.to_string()));
+            }
+
+            let request = self.request(&self.token, Method::GET, "conversations.members", (), Some(query))?;
+
+            let mut r: ChannelMembersResponse = self.execute(request).await?;
+            members.append(&mut r.members);
+
+            if r.response_metadata.next_cursor.is_empty() {
+                break;
+            }
+            cursor = r.response_metadata.next_cursor;
+        }
+
+        Ok(members)
+    }
+
+    /// Invite users to a channel.
+    /// FROM: https://api.slack.com/methods/conversations.invite
+    pub async fn invite_to_channel(&self, channel_id: &str, user_ids: &[String]) -> Result<()> {
+        let users = user_ids.join(",");
+
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("channel", channel_id);
+        body.insert("users", &users);
+
+        let request = self.request(&self.user_token, Method::POST, "conversations.invite", body, None)?;
+
+        let _: Value = self.execute(request).await?;
+        Ok(())
+    }
+
+    /// Remove a user from a channel.
+    /// FROM: https://api.slack.com/methods/conversations.kick
+    pub async fn kick_from_channel(&self, channel_id: &str, user_id: &str) -> Result<()> {
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("channel", channel_id);
+        body.insert("user", user_id);
+
+        let request = self.request(&self.user_token, Method::POST, "conversations.kick", body, None)?;
+
+        let _: Value = self.execute(request).await?;
+        Ok(())
+    }
+
+    /// List the user groups of the workspace, including disabled user groups and the ids of
+    /// their members.
+    /// FROM: https://api.slack.com/methods/usergroups.list
+    pub async fn list_usergroups(&self) -> Result<Vec<UserGroup>> {
+        let request = self.request(
+            &self.user_token,
+            Method::GET,
+            "usergroups.list",
+            (),
+            Some(vec![
+                ("include_disabled", "true".to_string()),
+                ("include_users", "true".to_string()),
+            ]),
+        )?;
+
+        let r: ListUserGroupsResponse = self.execute(request).await?;
+        Ok(r.usergroups)
+    }
+
+    /// Create a user group.
+    /// FROM: https://api.slack.com/methods/usergroups.create
+    pub async fn create_usergroup(&self, name: &str, handle: &str, description: &str) -> Result<UserGroup> {
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("name", name);
+        body.insert("handle", handle);
+        body.insert("description", description);
+
+        let request = self.request(&self.user_token, Method::POST, "usergroups.create", body, None)?;
+
+        let r: UserGroupResponse = self.execute(request).await?;
+        Ok(r.usergroup)
+    }
+
+    /// Update the name, handle and description of a user group.
+    /// FROM: https://api.slack.com/methods/usergroups.update
+    pub async fn update_usergroup(&self, id: &str, name: &str, handle: &str, description: &str) -> Result<UserGroup> {
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("usergroup", id);
+        body.insert("name", name);
+        body.insert("handle", handle);
+        body.insert("description", description);
+
+        let request = self.request(&self.user_token, Method::POST, "usergroups.update", body, None)?;
+
+        let r: UserGroupResponse = self.execute(request).await?;
+        Ok(r.usergroup)
+    }
+
+    /// Replace the members of a user group. A user group must have at least one member.
+    /// FROM: https://api.slack.com/methods/usergroups.users.update
+    pub async fn update_usergroup_users(&self, id: &str, user_ids: &[String]) -> Result<UserGroup> {
+        let users = user_ids.join(",");
+
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("usergroup", id);
+        body.insert("users", &users);
+
+        let request = self.request(&self.user_token, Method::POST, "usergroups.users.update", body, None)?;
+
+        let r: UserGroupResponse = self.execute(request).await?;
+        Ok(r.usergroup)
+    }
+
+    /// Enable a user group that was previously disabled.
+    /// FROM: https://api.slack.com/methods/usergroups.enable
+    pub async fn enable_usergroup(&self, id: &str) -> Result<UserGroup> {
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("usergroup", id);
+
+        let request = self.request(&self.user_token, Method::POST, "usergroups.enable", body, None)?;
+
+        let r: UserGroupResponse = self.execute(request).await?;
+        Ok(r.usergroup)
+    }
+
+    /// Disable a user group. User groups can not be deleted, so they are disabled instead.
+    /// FROM: https://api.slack.com/methods/usergroups.disable
+    pub async fn disable_usergroup(&self, id: &str) -> Result<UserGroup> {
+        let mut body: HashMap<&str, &str> = HashMap::new();
+        body.insert("usergroup", id);
+
+        let request = self.request(&self.user_token, Method::POST, "usergroups.disable", body, None)?;
+
+        let r: UserGroupResponse = self.execute(request).await?;
+        Ok(r.usergroup)
+    }
+
+    /// Send a request, unless it is a write that the write filter rejects.
+    async fn send(&self, request: Request) -> Result<Response> {
+        if let Some(filter) = &self.write_filter {
+            if request.method() != Method::GET && !filter(&request) {
+                return Ok(http::Response::builder()
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(r#"{"ok":true}"#)?
+                    .into());
+            }
+        }
+
+        Ok(self.client.execute(request).await?)
+    }
+
+    /// Execute a request, failing with an [`APIError`] if either the status code or the `ok` field
+    /// of the response reports an error.
+    async fn execute<T>(&self, request: Request) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let resp = self.send(request).await?;
+        match resp.status() {
+            StatusCode::OK => (),
+            s => {
+                return Err(APIError::new(s, resp.text().await?).into());
+            }
+        };
+
+        let r: Value = resp.json().await?;
+
+        if !r["ok"].as_bool().unwrap_or_default() {
+            return Err(APIError::new(StatusCode::OK, r.to_string()).into());
+        }
+
+        Ok(serde_json::from_value(r)?)
+    }
+
     /// Post text to a channel.
     pub async fn post_to_channel(url: &str, v: &Value) -> Result<()> {
         let client = Client::new();
@@ -519,6 +757,46 @@ impl Slack {
     }
 }
 
+/// An error response from the Slack API. Slack reports most errors with a 200 status code and the
+/// reason in the `error` field of the body.
+/// FROM: https://api.slack.com/web#evaluating_responses
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct APIError {
+    pub status_code: StatusCode,
+    /// The error code of the response, such as `already_in_channel`. Empty if the response did not
+    /// include one.
+    pub error: String,
+    pub body: String,
+}
+
+impl APIError {
+    fn new(status_code: StatusCode, body: String) -> Self {
+        let error = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|value| value["error"].as_str().map(|error| error.to_string()))
+            .unwrap_or_default();
+
+        APIError {
+            status_code,
+            error,
+            body,
+        }
+    }
+
+    /// The error code of an error, if it is an error response from the Slack API.
+    pub fn code(err: &anyhow::Error) -> Option<&str> {
+        err.downcast_ref::<APIError>().map(|err| err.error.as_str())
+    }
+}
+
+impl fmt::Display for APIError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "status code: {}, body: {}", self.status_code, self.body)
+    }
+}
+
+impl std::error::Error for APIError {}
+
 /// A message to be sent in Slack.
 ///
 /// Docs: https://api.slack.com/interactivity/slash-commands#responding_to_commands
@@ -1059,7 +1337,7 @@ pub struct UserProfile {
     pub title: String,
 }
 
-#[derive(Clone, Debug, Serialize, JsonSchema, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, JsonSchema, Deserialize)]
 pub struct UserProfileFields {
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub alt: String,
@@ -1104,6 +1382,20 @@ pub struct APIResponse {
 
     #[serde(default, skip_serializing_if = "Vec::is_empty", alias = "members")]
     pub users: Vec<User>,
+    #[serde(default)]
+    pub response_metadata: ResponseMetadata,
+}
+
+/// The data type for a single user response.
+/// FROM: https://api.slack.com/methods/users.lookupByEmail
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct UserResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default)]
+    pub user: User,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub error: String,
 }
 
 /// The data type for a User.
@@ -1159,6 +1451,87 @@ pub struct UpdateUserProfileRequest {
     pub profile: UserProfile,
 }
 
+/// A custom profile field defined for a workspace.
+/// FROM: https://api.slack.com/methods/team.profile.get
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct TeamProfileField {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub hint: String,
+    /// The kind of value the field holds, for example `text` or `user`.
+    #[serde(default, rename = "type", skip_serializing_if = "String::is_empty")]
+    pub type_: String,
+}
+
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct TeamProfile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<TeamProfileField>,
+}
+
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct TeamProfileResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default)]
+    pub profile: TeamProfile,
+}
+
+/// The members of a channel.
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct ChannelMembersResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub response_metadata: ResponseMetadata,
+}
+
+/// A user group, which can be mentioned in messages to notify all of its members.
+/// FROM: https://api.slack.com/types/usergroup
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct UserGroup {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub team_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub handle: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// The time the user group was disabled at, or zero if it is enabled.
+    #[serde(default)]
+    pub date_delete: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+}
+
+impl UserGroup {
+    pub fn is_disabled(&self) -> bool {
+        self.date_delete != 0
+    }
+}
+
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct UserGroupResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default)]
+    pub usergroup: UserGroup,
+}
+
+#[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
+pub struct ListUserGroupsResponse {
+    #[serde(default)]
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub usergroups: Vec<UserGroup>,
+}
+
 #[derive(Clone, Debug, Default, JsonSchema, Serialize, Deserialize)]
 pub struct BillableInfoResponse {
     #[serde(default)]
@@ -0,0 +1,155 @@
+use httpmock::MockServer;
+use reqwest::Url;
+use serde_json::json;
+
+use slack_chat_api::{APIError, Slack};
+
+fn client(server: &MockServer) -> Slack {
+    Slack::new("client", "secret", "T123", "", "bot-token", "user-token")
+        .base_url(Url::parse(&server.base_url()).unwrap())
+}
+
+#[tokio::test]
+async fn list_users_test() {
+    let server = MockServer::start();
+    let first = server.mock(|when, then| {
+        when.method("GET")
+            .path("/users.list")
+            .query_param("limit", "100")
+            .matches(|req| {
+                !req.query_params
+                    .as_ref()
+                    .map(|params| params.iter().any(|(key, _)| key == "cursor"))
+                    .unwrap_or_default()
+            });
+        then.status(200).json_body(json!({
+            "ok": true,
+            "members": [{ "id": "U1", "profile": { "email": "one@example.com" } }],
+            "response_metadata": { "next_cursor": "page2" },
+        }));
+    });
+    let second = server.mock(|when, then| {
+        when.method("GET").path("/users.list").query_param("cursor", "page2");
+        then.status(200).json_body(json!({
+            "ok": true,
+            "members": [{ "id": "U2", "profile": { "email": "two@example.com" } }],
+            "response_metadata": { "next_cursor": "" },
+        }));
+    });
+
+    // Act
+    let result = client(&server).list_users().await;
+
+    // Assert
+    first.assert();
+    second.assert();
+    let users = result.unwrap();
+    assert_eq!(
+        users.iter().map(|user| user.id.as_str()).collect::<Vec<_>>(),
+        vec!["U1", "U2"]
+    );
+}
+
+#[tokio::test]
+async fn lookup_user_by_email_test() {
+    let server = MockServer::start();
+    let found = server.mock(|when, then| {
+        when.method("GET")
+            .path("/users.lookupByEmail")
+            .query_param("email", "one@example.com");
+        then.status(200).json_body(json!({
+            "ok": true,
+            "user": { "id": "U1", "profile": { "email": "one@example.com" } },
+        }));
+    });
+    let missing = server.mock(|when, then| {
+        when.method("GET")
+            .path("/users.lookupByEmail")
+            .query_param("email", "two@example.com");
+        then.status(200)
+            .json_body(json!({ "ok": false, "error": "users_not_found" }));
+    });
+
+    // Act
+    let slack = client(&server);
+    let user = slack.lookup_user_by_email("one@example.com").await;
+    let no_user = slack.lookup_user_by_email("two@example.com").await;
+
+    // Assert
+    found.assert();
+    missing.assert();
+    assert_eq!(user.unwrap().unwrap().id, "U1");
+    assert!(no_user.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn update_usergroup_users_test() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method("POST")
+            .path("/usergroups.users.update")
+            .json_body(json!({ "usergroup": "S1", "users": "U1,U2" }));
+        then.status(200).json_body(json!({
+            "ok": true,
+            "usergroup": { "id": "S1", "handle": "eng", "users": ["U1", "U2"] },
+        }));
+    });
+
+    // Act
+    let result = client(&server)
+        .update_usergroup_users("S1", &["U1".to_string(), "U2".to_string()])
+        .await;
+
+    // Assert
+    mock.assert();
+    let group = result.unwrap();
+    assert_eq!(group.handle, "eng");
+    assert_eq!(group.users, vec!["U1", "U2"]);
+}
+
+#[tokio::test]
+async fn api_error_test() {
+    let server = MockServer::start();
+    let mock = server.mock(|when, then| {
+        when.method("POST").path("/conversations.kick");
+        then.status(200)
+            .json_body(json!({ "ok": false, "error": "not_in_channel" }));
+    });
+
+    // Act
+    let result = client(&server).kick_from_channel("C1", "U1").await;
+
+    // Assert
+    mock.assert();
+    let err = result.unwrap_err();
+    assert_eq!(APIError::code(&err), Some("not_in_channel"));
+    assert!(err.to_string().contains("not_in_channel"));
+    assert_eq!(APIError::code(&anyhow::anyhow!("not_in_channel")), None);
+}
+
+#[tokio::test]
+async fn write_filter_test() {
+    let server = MockServer::start();
+    let kick = server.mock(|when, then| {
+        when.method("POST").path("/conversations.kick");
+        then.status(200).json_body(json!({ "ok": true }));
+    });
+    let lookup = server.mock(|when, then| {
+        when.method("GET").path("/users.lookupByEmail");
+        then.status(200).json_body(json!({
+            "ok": true,
+            "user": { "id": "U1", "profile": { "email": "one@example.com" } },
+        }));
+    });
+
+    // Act
+    let slack = client(&server).write_filter(|_| false);
+    let kicked = slack.kick_from_channel("C1", "U1").await;
+    let user = slack.lookup_user_by_email("one@example.com").await;
+
+    // Assert
+    kick.assert_hits(0);
+    lookup.assert();
+    assert!(kicked.is_ok());
+    assert_eq!(user.unwrap().unwrap().id, "U1");
+}
@@ -5,15 +5,20 @@ use cio_api::{
     companies::{Company, Companys},
     configs::get_configs_from_repo,
     db::Database,
+    sandbox::Sandbox,
 };
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
-use crate::sagas::{create_registry, Saga};
+use crate::{
+    sagas::{create_registry, Saga},
+    schedule::SchedulePlanner,
+};
 
 #[derive(Clone, Debug)]
 pub struct ServerContext {
     pub sec: Arc<steno::SecClient>,
     pub exec_registry: Arc<steno::ActionRegistry<Saga>>,
+    pub schedule: Arc<Mutex<SchedulePlanner>>,
     pub app: Context,
 }
 
@@ -24,6 +29,7 @@ impl ServerContext {
         Ok(Self {
             sec: Arc::new(steno::sec(logger, Arc::new(context.db.clone()))),
             exec_registry: Arc::new(create_registry()),
+            schedule: Arc::new(Mutex::new(SchedulePlanner::default())),
             app: context,
         })
     }
@@ -35,6 +41,8 @@ pub struct Context {
     pub db: Database,
     pub company: Company,
     pub upload_token_store: UploadTokenStore,
+    /// Jobs run with a sandbox log their writes to external services instead of making them
+    pub sandbox: Option<Sandbox>,
 }
 
 impl Context {
@@ -49,6 +57,21 @@ impl Context {
             .0
             .pop()
             .ok_or_else(|| anyhow::anyhow!("Failed to find company record"))?;
+
+        Self::with_company(db, company).await
+    }
+
+    /**
+     * Return a new Context for running jobs on behalf of the company with the given id.
+     */
+    pub async fn for_company(company_id: i32) -> Result<Context> {
+        let db = Database::new().await;
+        let company = Company::get_by_id(&db, company_id).await?;
+
+        Self::with_company(db, company).await
+    }
+
+    async fn with_company(db: Database, company: Company) -> Result<Context> {
         let github = company.authenticate_github()?;
         let configs = get_configs_from_repo(&github, &company).await?;
 
@@ -58,6 +81,20 @@ impl Context {
             db: db.clone(),
             company,
             upload_token_store: UploadTokenStore::new(db, chrono::Duration::minutes(10)),
+            sandbox: None,
         })
     }
+
+    /**
+     * Return this Context with a sandbox, so that jobs run with it do not write to external
+     * services.
+     */
+    pub fn sandboxed(mut self) -> Context {
+        self.sandbox = Some(Sandbox::default());
+        self
+    }
+
+    pub fn is_sandboxed(&self) -> bool {
+        self.sandbox.is_some()
+    }
 }
@@ -13,6 +13,11 @@ pub struct Opts {
     #[clap(short, long)]
     pub json: bool,
 
+    /// Run a job without making any writes to Airtable, Slack, SendGrid, GitHub or DNS. The writes
+    /// are logged instead, along with a summary of them once the job is done
+    #[clap(long, global = true)]
+    pub dry_run: bool,
+
     #[clap(subcommand)]
     pub subcmd: SubCommand,
 }
@@ -21,7 +26,14 @@ pub struct Opts {
 pub enum SubCommand {
     Server(Server),
 
+    #[clap(name = "check-stale-rfds")]
+    CheckStaleRFDs(CheckStaleRFDs),
     CreateServerSpec(SpecOut),
+    OffboardUsers(OffboardUsers),
+    ProvisionUsers(ProvisionUsers),
+    #[clap(name = "reindex-rfds")]
+    ReindexRFDs(ReindexRFDs),
+    ReportDrift(ReportDrift),
     SendRFDChangelog(SendRFDChangelog),
     SyncAnalytics(SyncAnalytics),
     #[clap(name = "sync-api-tokens")]
@@ -69,6 +81,30 @@ pub struct SpecOut {
     pub spec_file: std::path::PathBuf,
 }
 
+/// A subcommand for finding RFDs that have gone without changes for too long and reminding their
+/// authors.
+#[derive(Parser, Clone, Debug)]
+pub struct CheckStaleRFDs {}
+
+/// A subcommand for running the remaining steps of offboarding the users that have been removed
+/// from the configs repo.
+#[derive(Parser, Clone, Debug)]
+pub struct OffboardUsers {}
+
+/// A subcommand for running the steps of provisioning new hires that are due ahead of, or on, their
+/// start date.
+#[derive(Parser, Clone, Debug)]
+pub struct ProvisionUsers {}
+
+/// A subcommand for rebuilding the RFD search index from the RFDs stored in the database.
+#[derive(Parser, Clone, Debug)]
+pub struct ReindexRFDs {}
+
+/// A subcommand for reporting the differences between the configs repo and the accounts in
+/// external services.
+#[derive(Parser, Clone, Debug)]
+pub struct ReportDrift {}
+
 /// A subcommand for sending the RFD changelog.
 #[derive(Parser, Clone, Debug)]
 pub struct SendRFDChangelog {}
@@ -163,6 +199,11 @@ pub struct SyncZoho {}
 
 pub fn into_job_command(cmd: &str) -> Option<SubCommand> {
     match cmd {
+        "check-stale-rfds" => Some(SubCommand::CheckStaleRFDs(CheckStaleRFDs {})),
+        "offboard-users" => Some(SubCommand::OffboardUsers(OffboardUsers {})),
+        "provision-users" => Some(SubCommand::ProvisionUsers(ProvisionUsers {})),
+        "reindex-rfds" => Some(SubCommand::ReindexRFDs(ReindexRFDs {})),
+        "report-drift" => Some(SubCommand::ReportDrift(ReportDrift {})),
         "send-rfd-changelog" => Some(SubCommand::SendRFDChangelog(SendRFDChangelog {})),
         "sync-analytics" => Some(SubCommand::SyncAnalytics(SyncAnalytics {})),
         "sync-api-tokens" => Some(SubCommand::SyncAPITokens(SyncAPITokens {})),
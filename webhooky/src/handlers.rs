@@ -112,11 +112,7 @@ pub async fn handle_github_rate_limit(rqctx: &RequestContext<ServerContext>) ->
     })
 }
 
-pub async fn handle_slack_commands(
-    rqctx: &RequestContext<ServerContext>,
-    bot_command: BotCommand,
-) -> Result<serde_json::Value> {
-    let api_context = rqctx.context();
+pub async fn handle_slack_commands(api_context: &ServerContext, bot_command: BotCommand) -> Result<serde_json::Value> {
     let db = &api_context.app.db;
 
     // Get the company from the Slack team id.
@@ -434,10 +430,7 @@ pub async fn handle_slack_commands(
     Ok(response)
 }
 
-pub async fn handle_slack_interactive(
-    rqctx: &RequestContext<ServerContext>,
-    body_param: String,
-) -> Result<InteractiveResponse> {
+pub async fn handle_slack_interactive(ctx: &ServerContext, body_param: String) -> Result<InteractiveResponse> {
     // Decode the URL encoded struct.
     let decoded = urlencoding::decode(body_param.trim_start_matches("payload="))?;
 
@@ -450,7 +443,6 @@ pub async fn handle_slack_interactive(
         }
     };
 
-    let ctx = rqctx.context();
     let db = &ctx.app.db;
 
     let mut interactive_response: InteractiveResponse = Default::default();
@@ -581,8 +573,14 @@ pub async fn handle_slack_interactive(
     for action in payload.actions {
         // Trigger the action if it's a function.
         if action.action_id == "function" {
+            // Buttons from before jobs were run per company only carry the name of the job.
+            let (cmd_name, company_id) = match action.value.split_once(':') {
+                Some((cmd_name, company_id)) => (cmd_name, company_id.parse().unwrap_or(ctx.app.company.id)),
+                None => (action.value.as_str(), ctx.app.company.id),
+            };
+
             // Run the command in the background so we don't have to wait for it.
-            if let Err(e) = crate::handlers_cron::run_subcmd_job(ctx, &action.value).await {
+            if let Err(e) = crate::handlers_cron::run_subcmd_job(ctx, cmd_name, company_id).await {
                 error!("Subcommand execution failed {:?}", e);
             }
         }
@@ -592,11 +590,9 @@ pub async fn handle_slack_interactive(
 }
 
 pub async fn handle_airtable_employees_print_home_address_label(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
-    let api_context = rqctx.context();
-
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -610,12 +606,7 @@ pub async fn handle_airtable_employees_print_home_address_label(
     Ok(())
 }
 
-pub async fn handle_airtable_certificates_renew(
-    rqctx: &RequestContext<ServerContext>,
-    event: AirtableRowEvent,
-) -> Result<()> {
-    let api_context = rqctx.context();
-
+pub async fn handle_airtable_certificates_renew(api_context: &ServerContext, event: AirtableRowEvent) -> Result<()> {
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -638,11 +629,9 @@ pub async fn handle_airtable_certificates_renew(
 }
 
 pub async fn handle_airtable_assets_items_print_barcode_label(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
-    let api_context = rqctx.context();
-
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -658,11 +647,9 @@ pub async fn handle_airtable_assets_items_print_barcode_label(
 }
 
 pub async fn handle_airtable_swag_inventory_items_print_barcode_labels(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
-    let api_context = rqctx.context();
-
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -679,11 +666,9 @@ pub async fn handle_airtable_swag_inventory_items_print_barcode_labels(
 }
 
 pub async fn handle_airtable_applicants_request_background_check(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
-    let api_context = rqctx.context();
-
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -700,12 +685,7 @@ pub async fn handle_airtable_applicants_request_background_check(
     Ok(())
 }
 
-pub async fn handle_airtable_applicants_update(
-    rqctx: &RequestContext<ServerContext>,
-    event: AirtableRowEvent,
-) -> Result<()> {
-    let api_context = rqctx.context();
-
+pub async fn handle_airtable_applicants_update(api_context: &ServerContext, event: AirtableRowEvent) -> Result<()> {
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -786,11 +766,9 @@ pub async fn handle_airtable_applicants_update(
 }
 
 pub async fn listen_airtable_applicants_recreate_piia_webhooks(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
-    let api_context = rqctx.context();
-
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -826,11 +804,9 @@ pub async fn listen_airtable_applicants_recreate_piia_webhooks(
 }
 
 pub async fn handle_airtable_shipments_outbound_create(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
-    let api_context = rqctx.context();
-
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
@@ -864,15 +840,13 @@ pub async fn handle_airtable_shipments_outbound_create(
 }
 
 pub async fn handle_airtable_shipments_outbound_reprint_label(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
     if event.record_id.is_empty() {
         bail!("got an empty email for row");
     }
 
-    let api_context = rqctx.context();
-
     // Get the row from airtable.
     let mut shipment =
         OutboundShipment::get_from_airtable(&event.record_id, &api_context.app.db, event.cio_company_id).await?;
@@ -891,15 +865,13 @@ pub async fn handle_airtable_shipments_outbound_reprint_label(
 }
 
 pub async fn handle_airtable_shipments_outbound_reprint_receipt(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
     if event.record_id.is_empty() {
         bail!("got an empty email for row");
     }
 
-    let api_context = rqctx.context();
-
     // Get the row from airtable.
     let shipment =
         OutboundShipment::get_from_airtable(&event.record_id, &api_context.app.db, event.cio_company_id).await?;
@@ -915,15 +887,13 @@ pub async fn handle_airtable_shipments_outbound_reprint_receipt(
 }
 
 pub async fn handle_airtable_shipments_outbound_resend_shipment_status_email_to_recipient(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
 
-    let api_context = rqctx.context();
-
     // Get the row from airtable.
     let shipment =
         OutboundShipment::get_from_airtable(&event.record_id, &api_context.app.db, event.cio_company_id).await?;
@@ -936,7 +906,7 @@ pub async fn handle_airtable_shipments_outbound_resend_shipment_status_email_to_
 }
 
 pub async fn handle_airtable_shipments_outbound_schedule_pickup(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
     if event.record_id.is_empty() {
@@ -944,7 +914,6 @@ pub async fn handle_airtable_shipments_outbound_schedule_pickup(
     }
 
     // Schedule the pickup.
-    let api_context = rqctx.context();
     let company = Company::get_by_id(&api_context.app.db, event.cio_company_id).await?;
     OutboundShipments::create_pickup(&api_context.app.db, &company).await?;
 
@@ -1223,14 +1192,13 @@ fn get_extension_from_filename(filename: &str) -> Option<&str> {
 }
 
 pub async fn handle_airtable_shipments_inbound_create(
-    rqctx: &RequestContext<ServerContext>,
+    api_context: &ServerContext,
     event: AirtableRowEvent,
 ) -> Result<()> {
     if event.record_id.is_empty() {
         bail!("record id is empty");
     }
 
-    let api_context = rqctx.context();
     let db = &api_context.app.db;
 
     // Get the row from airtable.
@@ -1266,17 +1234,13 @@ pub async fn handle_store_order_create(rqctx: &RequestContext<ServerContext>, ev
 }
 
 pub async fn handle_easypost_tracking_update(
-    _rqctx: &RequestContext<ServerContext>,
+    _api_context: &ServerContext,
     _event: crate::server::EasyPostTrackingUpdateEvent,
 ) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle_shippo_tracking_update(
-    rqctx: &RequestContext<ServerContext>,
-    event: serde_json::Value,
-) -> Result<()> {
-    let api_context = rqctx.context();
+pub async fn handle_shippo_tracking_update(api_context: &ServerContext, event: serde_json::Value) -> Result<()> {
     let body: ShippoTrackingUpdateEvent = match serde_json::from_str(&event.to_string()) {
         Ok(b) => b,
         Err(e) => bail!("decoding event body for shippo `{}` failed: {}", event.to_string(), e),
@@ -1320,12 +1284,7 @@ pub async fn handle_shippo_tracking_update(
     Ok(())
 }
 
-pub async fn handle_checkr_background_update(
-    rqctx: &RequestContext<ServerContext>,
-    event: checkr::WebhookEvent,
-) -> Result<()> {
-    let api_context = rqctx.context();
-
+pub async fn handle_checkr_background_update(api_context: &ServerContext, event: checkr::WebhookEvent) -> Result<()> {
     // Run the update of the background checks.
     // If we have a candidate ID let's get them from checkr.
     if event.data.object.candidate_id.is_empty()
@@ -1380,11 +1339,7 @@ pub async fn handle_checkr_background_update(
     Ok(())
 }
 
-pub async fn handle_docusign_envelope_update(
-    rqctx: &RequestContext<ServerContext>,
-    event: docusign::Envelope,
-) -> Result<()> {
-    let api_context = rqctx.context();
+pub async fn handle_docusign_envelope_update(api_context: &ServerContext, event: docusign::Envelope) -> Result<()> {
     let db = &api_context.app.db;
 
     // We need to get the applicant for the envelope.
@@ -1464,10 +1419,12 @@ pub async fn handle_analytics_page_view(rqctx: &RequestContext<ServerContext>, m
     Ok(())
 }
 
-pub async fn handle_shipbob(rqctx: &RequestContext<ServerContext>, event: serde_json::Value) -> Result<()> {
+pub async fn handle_shipbob(
+    _api_context: &ServerContext,
+    headers: &http::HeaderMap,
+    event: serde_json::Value,
+) -> Result<()> {
     // We need to get the webhook type from the header.
-    let headers = rqctx.request.headers().clone();
-
     let shipbob_topic = headers.get("shipbob-topic").unwrap().to_str()?;
     let shipbob_subscription_id = headers.get("shipbob-subscription-id").unwrap().to_str()?;
 
@@ -1,41 +1,103 @@
 use anyhow::Result;
-use async_bb8_diesel::AsyncRunQueryDsl;
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use chrono_humanize::HumanTime;
-use cio_api::{functions::Function, schema::functions};
-use diesel::{ExpressionMethods, QueryDsl};
-use log::info;
+use cio_api::{
+    companies::{Company, Companys},
+    functions::{
+        DeadLetterFilter, Function, FunctionDeadLetter, FunctionDetails, FunctionFilter, FunctionStep, FunctionSummary,
+    },
+};
+use log::{info, warn};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
 
-use crate::context::ServerContext;
+use crate::{context::ServerContext, sagas::SagaLogTail};
 
-pub async fn run_subcmd_job(server_context: &ServerContext, cmd_name: &str) -> Result<uuid::Uuid> {
-    let db = &server_context.app.db;
+/// Run a job for every active company. Jobs that work across all companies are only run for the
+/// company of the server.
+pub async fn run_subcmd_job_for_companies(server_context: &ServerContext, cmd_name: &str) -> Result<Vec<uuid::Uuid>> {
+    let server_company_id = server_context.app.company.id;
 
-    // Check if we already have an in-progress run for this job.
-    if let Ok(f) = functions::dsl::functions
-        .filter(functions::dsl::name.eq(cmd_name.to_string()))
-        .filter(functions::dsl::status.eq(octorust::types::JobStatus::InProgress.to_string()))
-        .order_by(functions::dsl::created_at.desc()) // Get the most recent one first.
-        .first_async::<Function>(db.pool())
-        .await
-    {
-        let u = uuid::Uuid::parse_str(&f.saga_id)?;
-
-        // If the server stopped and restarted, we might have a lingering job
-        // that we want to ignore and instead start a new one.
-        // Check if the duration it was started is longer than a few hours ago.
-        let hours = -1;
-        let duration_from_now = f.created_at.signed_duration_since(Utc::now());
-        if (duration_from_now.num_hours()) > hours {
+    let company_ids = if crate::job::SHARED_JOBS.contains(&cmd_name) {
+        vec![server_company_id]
+    } else {
+        Companys::get_active(&server_context.app.db, server_context.app.company.cio_company_id)
+            .await?
+            .into_iter()
+            .map(|company| company.id)
+            .collect()
+    };
+
+    let mut ids = vec![];
+
+    // A failure to start the job for one company should not keep it from running for the others.
+    for company_id in company_ids {
+        match run_subcmd_job(server_context, cmd_name, company_id).await {
+            Ok(id) => ids.push(id),
+            Err(err) => warn!("failed to start `{}` for company {}: {}", cmd_name, company_id, err),
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Run a job on behalf of a single company. Every run starts from the first step of the job, so
+/// stepped jobs always pin the current revision of their inputs.
+pub async fn run_subcmd_job(server_context: &ServerContext, cmd_name: &str, company_id: i32) -> Result<uuid::Uuid> {
+    start_subcmd_job(server_context, cmd_name, company_id, Default::default()).await
+}
+
+/// Run a job again for the company of an earlier run of the job. If the earlier run did not
+/// succeed, the new run resumes from the step that failed in it. The steps that are carried over
+/// include the revision that a configs sync pinned, which is why runs are only resumed on an
+/// explicit retry.
+pub async fn retry_subcmd_job(server_context: &ServerContext, function: &Function) -> Result<uuid::Uuid> {
+    let resumed_steps = if function.conclusion == octorust::types::Conclusion::Success.to_string() {
+        Default::default()
+    } else {
+        let steps = FunctionStep::resumable(&server_context.app.db, &function.saga_id).await?;
+
+        if !steps.is_empty() {
             info!(
-                "existing job for `{}` was created `{}`, returning that job",
-                cmd_name,
-                HumanTime::from(duration_from_now),
+                "resuming `{}` from run {}, skipping {} completed steps",
+                function.name,
+                function.saga_id,
+                steps.len()
             );
-            // TODO: a better way to be to check if we know about the saga.
-            // Return that uuid versus starting another.
-            return Ok(u);
         }
+
+        steps
+    };
+
+    start_subcmd_job(server_context, &function.name, function.cio_company_id, resumed_steps).await
+}
+
+async fn start_subcmd_job(
+    server_context: &ServerContext,
+    cmd_name: &str,
+    company_id: i32,
+    resumed_steps: BTreeMap<String, String>,
+) -> Result<uuid::Uuid> {
+    let db = &server_context.app.db;
+
+    // Make sure that the company exists before a run is recorded for it.
+    Company::get_by_id(db, company_id).await?;
+
+    // Check if we already have an in-progress run for this job. If the server stopped and
+    // restarted, we might have a lingering job that we want to ignore and instead start a new
+    // one, so only runs started within the last hour are considered.
+    if let Some(f) = Function::find_in_progress(db, cmd_name, company_id, Duration::hours(1)).await? {
+        info!(
+            "existing job for `{}` for company {} was created `{}`, returning that job",
+            cmd_name,
+            company_id,
+            HumanTime::from(f.created_at.signed_duration_since(Utc::now())),
+        );
+
+        // TODO: a better way to be to check if we know about the saga.
+        // Return that uuid versus starting another.
+        return Ok(uuid::Uuid::parse_str(&f.saga_id)?);
     }
 
     let id = uuid::Uuid::new_v4();
@@ -47,8 +109,113 @@ pub async fn run_subcmd_job(server_context: &ServerContext, cmd_name: &str) -> R
         server_context.exec_registry.clone(),
         &id,
         cmd_name,
+        company_id,
+        resumed_steps,
     )
     .await?;
 
     Ok(id)
 }
+
+pub async fn handle_functions_list(
+    server_context: &ServerContext,
+    filter: &FunctionFilter,
+) -> Result<Vec<FunctionSummary>> {
+    let now = Utc::now();
+
+    Ok(Function::list(&server_context.app.db, filter)
+        .await?
+        .iter()
+        .map(|function| FunctionSummary::new(function, now))
+        .collect())
+}
+
+pub async fn handle_function_view(server_context: &ServerContext, saga_id: &str) -> Result<Option<FunctionDetails>> {
+    let db = &server_context.app.db;
+
+    let mut details: FunctionDetails = match Function::get_from_db(db, saga_id.to_string()).await {
+        Some(function) => function.into(),
+        None => return Ok(None),
+    };
+
+    details.steps = FunctionStep::list(db, saga_id).await?;
+
+    Ok(Some(details))
+}
+
+/// Cancel an in-progress function run. The run is recorded as cancelled even when it is not being
+/// run by this server, as that means it was orphaned and will never complete
+pub async fn handle_function_cancel(
+    server_context: &ServerContext,
+    saga_id: &uuid::Uuid,
+) -> Result<Option<FunctionDetails>> {
+    let db = &server_context.app.db;
+
+    let mut function = match Function::get_from_db(db, saga_id.to_string()).await {
+        Some(function) => function,
+        None => return Ok(None),
+    };
+
+    function.cancel(db).await?;
+
+    if !crate::sagas::cancel_cmd(saga_id) {
+        warn!(
+            "Function {} is not running on this server, it has only been marked as cancelled",
+            saga_id
+        );
+    }
+
+    Ok(Some(function.into()))
+}
+
+/// Run a function again for the same company. Returns the id of the new run, or None if there is
+/// no such function
+pub async fn handle_function_retry(server_context: &ServerContext, saga_id: &uuid::Uuid) -> Result<Option<uuid::Uuid>> {
+    let db = &server_context.app.db;
+
+    let function = match Function::get_from_db(db, saga_id.to_string()).await {
+        Some(function) => function,
+        None => return Ok(None),
+    };
+
+    let id = retry_subcmd_job(server_context, &function).await?;
+    FunctionDeadLetter::mark_retried(db, &function.saga_id, &id).await?;
+
+    Ok(Some(id))
+}
+
+pub async fn handle_dead_letters_list(
+    server_context: &ServerContext,
+    filter: &DeadLetterFilter,
+) -> Result<Vec<FunctionDeadLetter>> {
+    FunctionDeadLetter::list(&server_context.app.db, filter).await
+}
+
+#[derive(Debug, Clone, JsonSchema, Serialize)]
+pub struct FunctionLogs {
+    /// Whether the logs are being read live from this server. When false they were read from the
+    /// last logs that were written to the database and the cursor can not be followed
+    pub live: bool,
+    #[serde(flatten)]
+    pub tail: SagaLogTail,
+}
+
+/// Read the tail of the logs of a function run. Runs that are in progress on this server are read
+/// from memory, and can be followed by passing the returned cursor back in as `after`
+pub async fn handle_function_logs(
+    server_context: &ServerContext,
+    saga_id: &uuid::Uuid,
+    after: Option<u64>,
+    limit: usize,
+) -> Result<Option<FunctionLogs>> {
+    if let Some(tail) = crate::sagas::tail_cmd_logs(saga_id, after, limit) {
+        return Ok(Some(FunctionLogs { live: true, tail }));
+    }
+
+    Ok(Function::get_from_db(&server_context.app.db, saga_id.to_string())
+        .await
+        .map(|function| FunctionLogs {
+            live: false,
+            tail: crate::sagas::tail_persisted_logs(&function.logs, limit),
+        }))
+}
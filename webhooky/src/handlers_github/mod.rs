@@ -4,8 +4,10 @@ use chrono::offset::Utc;
 use cio_api::{
     companies::Company,
     configs::{
-        get_configs_from_repo, sync_buildings, sync_certificates, sync_groups, sync_links, sync_resources, sync_users,
+        get_configs_from_repo, get_configs_from_repo_at, sync_buildings, sync_certificates, sync_groups, sync_links,
+        sync_resources, sync_users,
     },
+    configs_plan::{reviewed_revisions, ConfigsPlan},
     core::GitHubCommit,
     repos::NewRepo,
     rfd::{GitHubRFDBranch, GitHubRFDRepo, GitHubRFDUpdate},
@@ -65,22 +67,14 @@ impl HmacSignatureVerifier for GitHubWebhookVerification {
 }
 
 /// Handle a request to the /github endpoint.
-pub async fn handle_github(rqctx: &RequestContext<ServerContext>, event: GitHubWebhook) -> Result<()> {
-    let api_context = rqctx.context();
-
-    // Parse the `X-GitHub-Event` header. Ensure the request lock is dropped once the
-    // event_type has been extracted.
-    // TODO: make this nicer when supported as a first class method in dropshot.
-    let event_type_string = {
-        let req = &rqctx.request;
-        let req_headers = req.headers();
-        req_headers
-            .get("X-GitHub-Event")
-            .unwrap_or(&http::header::HeaderValue::from_str("")?)
-            .to_str()
-            .unwrap()
-            .to_string()
-    };
+pub async fn handle_github(api_context: &ServerContext, headers: &http::HeaderMap, event: GitHubWebhook) -> Result<()> {
+    // Parse the `X-GitHub-Event` header.
+    let event_type_string = headers
+        .get("X-GitHub-Event")
+        .unwrap_or(&http::header::HeaderValue::from_str("")?)
+        .to_str()
+        .unwrap()
+        .to_string();
 
     let event_type =
         EventType::from_str(&event_type_string).expect("Event type from GitHub does not match a known event type");
@@ -193,8 +187,8 @@ pub async fn handle_github(rqctx: &RequestContext<ServerContext>, event: GitHubW
             //     EventType::CheckSuite => {}
             //     _ => (),
             // },
-            Repo::Configs => {
-                if let EventType::Push = event_type {
+            Repo::Configs => match event_type {
+                EventType::Push => {
                     match handle_configs_push(&github, &api_context.app, event.clone(), &company).await {
                         Ok(message) => {
                             info!("{}", message);
@@ -207,7 +201,30 @@ pub async fn handle_github(rqctx: &RequestContext<ServerContext>, event: GitHubW
                         }
                     }
                 }
-            }
+                EventType::PullRequest => {
+                    // Let's create the check run.
+                    let check_run_id = event.create_check_run(&github).await?;
+
+                    match handle_configs_pull_request(&github, &api_context.app, event.clone(), &company).await {
+                        Ok((conclusion, message)) => {
+                            event
+                                .update_check_run(&github, check_run_id, &message, conclusion)
+                                .await?;
+                        }
+                        Err(e) => {
+                            event
+                                .update_check_run(
+                                    &github,
+                                    check_run_id,
+                                    &event.get_error_string("planning configs on `pull_request`", e),
+                                    octorust::types::ChecksCreateRequestConclusion::Failure,
+                                )
+                                .await?;
+                        }
+                    }
+                }
+                _ => (),
+            },
             _ => {
                 // We can throw this out, log it and return early.
                 info!(
@@ -381,6 +398,71 @@ pub async fn handle_rfd_pull_request(
     ))
 }
 
+/// The most characters that GitHub allows in the summary of a check run.
+const CHECK_RUN_SUMMARY_LIMIT: usize = 65535;
+
+/// Handle a `pull_request` event for the configs repo. Plans the changes that merging the pull
+/// request would make to the users and groups in each external service, and reports the plan
+/// on the check run so that it can be reviewed before merging.
+pub async fn handle_configs_pull_request(
+    github: &octorust::Client,
+    api_context: &Context,
+    event: GitHubWebhook,
+    company: &Company,
+) -> Result<(octorust::types::ChecksCreateRequestConclusion, String)> {
+    // We only need a new plan when the contents of the pull request change.
+    if !["opened", "reopened", "synchronize"].contains(&event.action.as_str()) {
+        return Ok((
+            octorust::types::ChecksCreateRequestConclusion::Skipped,
+            format!("Ignoring pull_request hook due to unhandled action: {}", event.action),
+        ));
+    }
+
+    let sha = if event.pull_request.head.sha.is_empty() {
+        event.pull_request.head.id.to_string()
+    } else {
+        event.pull_request.head.sha.to_string()
+    };
+
+    // Get the configs as they are in the pull request.
+    let configs = get_configs_from_repo_at(github, company, &sha).await?;
+
+    let plan = ConfigsPlan::for_configs(&api_context.db, github, company, &configs.groups, &configs.users).await?;
+
+    // Keep the plan that is reviewed here, so that exactly this plan is applied once merged.
+    plan.store(&api_context.db, company.id, &sha).await?;
+
+    let mut rendered = plan.render();
+    let header = format!("Merging `{}` will make the following changes:\n\n```diff\n", sha);
+    let footer = "\n```";
+
+    if header.len() + rendered.len() + footer.len() > CHECK_RUN_SUMMARY_LIMIT {
+        let mut end = CHECK_RUN_SUMMARY_LIMIT - header.len() - footer.len();
+        while !rendered.is_char_boundary(end) {
+            end -= 1;
+        }
+        rendered.truncate(end);
+    }
+
+    Ok((
+        octorust::types::ChecksCreateRequestConclusion::Success,
+        format!("{}{}{}", header, rendered, footer),
+    ))
+}
+
+/// The revisions that a plan may have been reviewed for before they were pushed. These are the
+/// pushed commits themselves along with the revisions that the pushed revision was merged from.
+async fn reviewed_shas(github: &octorust::Client, company: &Company, event: &GitHubWebhook) -> Vec<String> {
+    let mut shas = event
+        .commits
+        .iter()
+        .map(|commit| commit.id.to_string())
+        .collect::<Vec<_>>();
+    shas.extend(reviewed_revisions(github, company, &event.after).await);
+
+    shas
+}
+
 /// Handle a `push` event for the configs repo.
 pub async fn handle_configs_push(
     github: &octorust::Client,
@@ -465,19 +547,48 @@ pub async fn handle_configs_push(
         a("[SUCCESS]: links shorturls");
     }
 
-    // Check if the groups.toml file changed.
-    // IMPORTANT: we need to sync the groups _before_ we sync the users in case we
-    // added a new group to GSuite.
-    if commit.file_changed("configs/groups.toml") {
-        sync_groups(&api_context.db, configs.groups, company).await?;
-        a("[SUCCESS]: groups");
-    }
+    // Changes to groups and users are only applied from the plan that was reviewed for this push.
+    // A push that was not reviewed is refused, and its groups and users are left as they are.
+    let groups_changed = commit.file_changed("configs/groups.toml");
+    let users_changed = commit.file_changed("configs/users.toml");
+    let reviewed = if groups_changed || users_changed {
+        let reviewed = ConfigsPlan::reviewed(
+            &api_context.db,
+            company.id,
+            &reviewed_shas(github, company, &event).await,
+        )
+        .await?;
+        if reviewed.is_none() {
+            a("[REFUSED]: groups and users, as no plan was reviewed for this push");
+        }
+
+        reviewed
+    } else {
+        None
+    };
 
-    // Check if the users.toml file changed.
-    if commit.file_changed("configs/users.toml") {
+    if let Some(reviewed) = reviewed {
         let config = api_context.app_config.read().unwrap().clone();
-        sync_users(&api_context.db, github, configs.users, company, &config).await?;
-        a("[SUCCESS]: users");
+
+        // Check if the groups.toml file changed.
+        // IMPORTANT: we need to sync the groups _before_ we sync the users in case we
+        // added a new group to GSuite.
+        if groups_changed {
+            let drift = sync_groups(&api_context.db, configs.groups, company, &config, &reviewed).await?;
+            if !drift.is_empty() {
+                a(&format!("[WARNING]: {}", drift));
+            }
+            a("[SUCCESS]: groups");
+        }
+
+        // Check if the users.toml file changed.
+        if users_changed {
+            let drift = sync_users(&api_context.db, github, configs.users, company, &config, &reviewed).await?;
+            if !drift.is_empty() {
+                a(&format!("[WARNING]: {}", drift));
+            }
+            a("[SUCCESS]: users");
+        }
     }
 
     // Check if the buildings.toml file changed.
@@ -1,9 +1,12 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use cio_api::{
+    app_config::RFDPdfStorageConfig,
     core::GitHubPullRequest,
-    features::Features,
-    rfd::{GitHubRFDReadmeLocation, GitHubRFDUpdate, NewRFD, RFDOutputError, RFDSearchIndex, RemoteRFD, RFD},
+    rfd::{
+        GitHubRFDReadmeLocation, GitHubRFDUpdate, LocalPDFStorage, NewRFD, NewRFDStateChange, RFDOutputError,
+        RFDReference, RFDSearchIndex, RFDState, RemoteRFD, S3Credentials, S3PDFStorage, RFD,
+    },
     shorturls::generate_shorturls_for_rfds,
     utils::{create_or_update_file_in_github_repo, decode_base64, get_file_content_from_repo},
 };
@@ -47,9 +50,11 @@ impl Default for RFDUpdater {
             Box::new(CreatePullRequest),
             Box::new(UpdatePullRequest),
             Box::new(ParseRFDLabels),
+            Box::new(UpdateReferences),
             Box::new(UpdateDiscussionUrl),                    // Stops on error
             Box::new(EnsureRFDWithPullRequestIsInValidState), // Stops on error
             Box::new(EnsureRFDOnDefaultIsInValidState),       // Stops on error
+            Box::new(RecordStateChange),
         ])
     }
 }
@@ -94,7 +99,9 @@ impl RFDUpdater {
         info!("Updating RFD {} on the {} branch", update.number, update.branch.branch);
 
         // Fetch the latest RFD information from GitHub
-        let RemoteRFD { rfd: new_rfd, location } = NewRFD::new_from_update(&api_context.company, update).await?;
+        let config = api_context.app_config.read().unwrap().rfd.clone();
+        let RemoteRFD { rfd: new_rfd, location } =
+            NewRFD::new_from_update(&api_context.company, &config, update).await?;
 
         info!(
             "Generated RFD {} from branch {} on GitHub",
@@ -319,7 +326,12 @@ impl RFDUpdateAction for UpdateSearch {
 pub struct UpdatePDFs;
 
 impl UpdatePDFs {
-    async fn upload(api_context: &Context, update: &GitHubRFDUpdate, rfd: &mut RFD) -> Result<()> {
+    async fn upload(
+        api_context: &Context,
+        config: &RFDPdfStorageConfig,
+        update: &GitHubRFDUpdate,
+        rfd: &mut RFD,
+    ) -> Result<()> {
         // Generate the PDFs for the RFD
         let pdf = match rfd.content()?.to_pdf(&rfd.title, &update.number, &update.branch).await {
             Ok(pdf) => pdf,
@@ -332,8 +344,8 @@ impl UpdatePDFs {
                         // error. We return early instead
                         return Ok(());
                     }
-                    RFDOutputError::Generic(inner) => {
-                        log::error!("Failed trying to generate PDF for RFD {}: {:?}", rfd.number, inner);
+                    RFDOutputError::ContentNotSupported(_) | RFDOutputError::Generic(_) => {
+                        log::error!("Failed trying to generate PDF for RFD {}: {:?}", rfd.number, err);
                         return Err(err.into());
                     }
                 }
@@ -341,7 +353,7 @@ impl UpdatePDFs {
         };
 
         // Upload the generate PDF
-        let upload = pdf.upload(&api_context.db, &api_context.company).await?;
+        let upload = pdf.upload(&api_context.db, &api_context.company, config).await?;
 
         // Store the PDF urls as needed to the RFD record
         if let Some(github_url) = upload.github_url {
@@ -352,11 +364,20 @@ impl UpdatePDFs {
             rfd.pdf_link_google_drive.replace_range(.., &google_drive_url);
         }
 
+        if let Some(local_url) = upload.local_url {
+            rfd.pdf_link_local.replace_range(.., &local_url);
+        }
+
+        if let Some(s3_url) = upload.s3_url {
+            rfd.pdf_link_s3.replace_range(.., &s3_url);
+        }
+
         Ok(())
     }
 
     async fn delete_old(
         api_context: &Context,
+        config: &RFDPdfStorageConfig,
         github: &octorust::Client,
         update: &GitHubRFDUpdate,
         old_rfd: &Option<&RFD>,
@@ -370,7 +391,7 @@ impl UpdatePDFs {
             let new_pdf_filename = rfd.get_pdf_filename();
 
             if old_pdf_filename != new_pdf_filename {
-                if Features::is_enabled("RFD_PDFS_IN_GITHUB") {
+                if config.github_enabled() {
                     let pdf_path = format!("/pdfs/{}", old_pdf_filename);
 
                     // First get the sha of the old pdf.
@@ -412,7 +433,7 @@ impl UpdatePDFs {
                     }
                 }
 
-                if Features::is_enabled("RFD_PDFS_IN_GOOGLE_DRIVE") {
+                if config.google_drive_enabled() {
                     // Initialize the Google Drive client.
                     let drive = api_context.company.authenticate_google_drive(&api_context.db).await?;
 
@@ -434,6 +455,26 @@ impl UpdatePDFs {
                         old_pdf_filename, new_pdf_filename
                     );
                 }
+
+                if let Some(local) = &config.local {
+                    LocalPDFStorage::new(local).delete_rfd_pdf(&old_pdf_filename).await?;
+
+                    info!(
+                        "[SUCCESS]: deleted old pdf file in local storage {} since the new name is {}",
+                        old_pdf_filename, new_pdf_filename
+                    );
+                }
+
+                if let Some(s3) = &config.s3 {
+                    S3PDFStorage::new(s3, S3Credentials::from_env()?)?
+                        .delete_rfd_pdf(&old_pdf_filename)
+                        .await?;
+
+                    info!(
+                        "[SUCCESS]: deleted old pdf file in S3 {} since the new name is {}",
+                        old_pdf_filename, new_pdf_filename
+                    );
+                }
             }
         }
 
@@ -456,8 +497,12 @@ impl RFDUpdateAction for UpdatePDFs {
             ..
         } = ctx;
 
-        Self::upload(api_context, update, rfd).await.map_err(into_continue)?;
-        Self::delete_old(api_context, github, update, old_rfd, rfd)
+        let config = api_context.app_config.read().unwrap().rfd.pdfs.clone();
+
+        Self::upload(api_context, &config, update, rfd)
+            .await
+            .map_err(into_continue)?;
+        Self::delete_old(api_context, &config, github, update, old_rfd, rfd)
             .await
             .map_err(into_continue)?;
 
@@ -527,7 +572,9 @@ impl RFDUpdateAction for CreatePullRequest {
         // handling an update on the default branch, and there are no previous pull requests for
         // for this branch. This includes Closed pull requests, therefore this action will not
         // re-open or create a new pull request for a branch that previously had an open PR
-        if update.branch.branch != update.branch.default_branch && rfd.state == "discussion" && pull_requests.is_empty()
+        if update.branch.branch != update.branch.default_branch
+            && rfd.get_state() == Some(RFDState::Discussion)
+            && pull_requests.is_empty()
         {
             let pull = github
                 .pulls()
@@ -635,14 +682,14 @@ impl RFDUpdateAction for UpdatePullRequest {
                     // Update the labels for the pull request.
                     let mut labels: Vec<String> = Default::default();
 
-                    if rfd.state == "discussion"
+                    if rfd.get_state() == Some(RFDState::Discussion)
                         && !pull_request
                             .labels
                             .iter()
                             .any(|label| label.name.ends_with("discussion"))
                     {
                         labels.push(":thought_balloon: discussion".to_string());
-                    } else if rfd.state == "ideation"
+                    } else if rfd.get_state() == Some(RFDState::Ideation)
                         && !pull_request.labels.iter().any(|label| label.name.ends_with("ideation"))
                     {
                         labels.push(":hatching_chick: ideation".to_string());
@@ -742,19 +789,61 @@ impl RFDUpdateAction for EnsureRFDWithPullRequestIsInValidState {
         ctx: &mut RFDUpdateActionContext,
         rfd: &mut RFD,
     ) -> Result<RFDUpdateActionResponse, RFDUpdateActionErr> {
-        let RFDUpdateActionContext { pull_requests, .. } = ctx;
+        let RFDUpdateActionContext {
+            github,
+            pull_requests,
+            update,
+            old_rfd,
+            ..
+        } = ctx;
 
         let mut requires_source_commit = false;
 
         // We only want to operate on open pull requests
-        let open_prs = pull_requests.iter().filter(|pr| pr.state == "open");
+        let open_prs = pull_requests
+            .iter()
+            .filter(|pr| pr.state == "open")
+            .collect::<Vec<&GitHubPullRequest>>();
+
+        // Before checking the state itself, ensure that the RFD did not skip over any of the states
+        // between its previous state and its new state. This applies however many pull requests are
+        // open for the branch. An illegal jump is reverted and the author is told why on each of the
+        // open pull requests
+        let previous_state = old_rfd.and_then(|old_rfd| old_rfd.get_state());
+
+        if let (Some(previous_state), Some(state)) = (previous_state, rfd.get_state()) {
+            if let Err(transition_err) = previous_state.validate_transition(state) {
+                warn!("RFD {} attempted an invalid transition: {}", rfd.number, transition_err);
+
+                rfd.update_state(previous_state).map_err(RFDUpdateActionErr::Stop)?;
+                requires_source_commit = true;
+
+                for pull_request in &open_prs {
+                    github
+                        .issues()
+                        .create_comment(
+                            &update.branch.owner,
+                            &update.branch.repo,
+                            pull_request.number,
+                            &octorust::types::PullsUpdateReviewRequest {
+                                body: format!(
+                                    "The state of RFD {} has been reverted to `{}`. {}.",
+                                    rfd.number, previous_state, transition_err
+                                ),
+                            },
+                        )
+                        .await
+                        .map_err(into_continue)?;
+                }
+            }
+        }
 
         // Explicitly we will only update a pull request if it is the only open pull request for the
         // branch that we are working on
-        match open_prs.count().cmp(&1) {
+        match open_prs.len().cmp(&1) {
             Ordering::Equal => {
                 // If there is a pull request open for this branch, then check to ensure that it is in one
-                // of three valid states:
+                // of the valid states:
                 //   * published  - A RFD may be in this state if it had previously been published and an
                 //                  an update is being made, Or the RFD may be in the process of being
                 //                  published
@@ -769,14 +858,27 @@ impl RFDUpdateAction for EnsureRFDWithPullRequestIsInValidState {
                 //                  initial thoughts on an idea
                 //   * abandoned  - A RFD may be in this state if it had previously been abandoned or is in
                 //                  the process of being abandoned
-                if rfd.state != "discussion"
-                    && rfd.state != "published"
-                    && rfd.state != "committed"
-                    && rfd.state != "ideation"
-                    && rfd.state != "abandoned"
-                {
-                    rfd.update_state("discussion").map_err(RFDUpdateActionErr::Stop)?;
-                    requires_source_commit = true;
+                let is_valid = rfd
+                    .get_state()
+                    .map(|state| state.is_valid_with_open_pull_request())
+                    .unwrap_or(false);
+
+                if !is_valid {
+                    // Moving the RFD in to discussion is held to the same transitions as a change
+                    // made by its authors. An RFD without a known state is always moved
+                    match rfd
+                        .get_state()
+                        .map(|state| state.validate_transition(RFDState::Discussion))
+                    {
+                        Some(Err(transition_err)) => {
+                            warn!("Unable to move RFD {} in to discussion: {}", rfd.number, transition_err);
+                        }
+                        _ => {
+                            rfd.update_state(RFDState::Discussion)
+                                .map_err(RFDUpdateActionErr::Stop)?;
+                            requires_source_commit = true;
+                        }
+                    }
                 }
             }
             Ordering::Greater => {
@@ -807,12 +909,13 @@ impl RFDUpdateAction for EnsureRFDOnDefaultIsInValidState {
 
         // If an RFD exists on the default branch then it should be in either the published or
         // abandoned state
-        if update.branch.branch == update.branch.default_branch
-            && rfd.state != "published"
-            && rfd.state != "committed"
-            && rfd.state != "abandoned"
-        {
-            log::warn!("RFD {} on the default branch is in an invalid state. It needs to be updated to either publisehd or abandoned", rfd.number);
+        let is_valid = rfd
+            .get_state()
+            .map(|state| state.is_valid_on_default_branch())
+            .unwrap_or(false);
+
+        if update.branch.branch == update.branch.default_branch && !is_valid {
+            log::warn!("RFD {} on the default branch is in an invalid state. It needs to be updated to either published, committed, or abandoned", rfd.number);
         }
 
         Ok(RFDUpdateActionResponse::default())
@@ -832,7 +935,79 @@ impl RFDUpdateAction for ParseRFDLabels {
         _ctx: &mut RFDUpdateActionContext,
         rfd: &mut RFD,
     ) -> Result<RFDUpdateActionResponse, RFDUpdateActionErr> {
-        rfd.labels = rfd.content().map_err(into_continue)?.get_labels();
+        rfd.labels = rfd.content().map_err(into_continue)?.metadata().labels;
+        Ok(RFDUpdateActionResponse::default())
+    }
+}
+
+pub struct UpdateReferences;
+
+#[async_trait]
+impl RFDUpdateAction for UpdateReferences {
+    async fn run(
+        &self,
+        ctx: &mut RFDUpdateActionContext,
+        rfd: &mut RFD,
+    ) -> Result<RFDUpdateActionResponse, RFDUpdateActionErr> {
+        let RFDUpdateActionContext {
+            api_context, update, ..
+        } = ctx;
+        let references = rfd.content().map_err(into_continue)?.references();
+
+        RFDReference::replace_for_rfd(&api_context.db, rfd.number, &references)
+            .await
+            .map_err(into_continue)?;
+        info!("Updated references of RFD {} on {}", rfd.number, update.branch.branch);
+
+        Ok(RFDUpdateActionResponse::default())
+    }
+}
+
+pub struct RecordStateChange;
+
+#[async_trait]
+impl RFDUpdateAction for RecordStateChange {
+    async fn run(
+        &self,
+        ctx: &mut RFDUpdateActionContext,
+        rfd: &mut RFD,
+    ) -> Result<RFDUpdateActionResponse, RFDUpdateActionErr> {
+        let RFDUpdateActionContext {
+            api_context,
+            update,
+            old_rfd,
+            ..
+        } = ctx;
+
+        let from_state = old_rfd.map(|old_rfd| old_rfd.state.clone()).unwrap_or_default();
+
+        if from_state == rfd.state {
+            return Ok(RFDUpdateActionResponse::default());
+        }
+
+        // Attribute the change to the most recent commit that touched the RFD
+        let commit = update
+            .branch
+            .get_latest_commit(&update.number)
+            .await
+            .map_err(into_continue)?;
+
+        NewRFDStateChange {
+            rfd_number: rfd.number,
+            from_state,
+            to_state: rfd.state.clone(),
+            changed_by: commit.author,
+            sha: commit.sha,
+        }
+        .create(&api_context.db)
+        .await
+        .map_err(into_continue)?;
+
+        info!(
+            "Recorded state change of RFD {} to {} on {}",
+            rfd.number, rfd.state, update.branch.branch
+        );
+
         Ok(RFDUpdateActionResponse::default())
     }
 }
@@ -1,10 +1,14 @@
 use anyhow::Result;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{Duration, Utc};
 use cio_api::{
-    rfd::{GitHubRFDRepo, NewRFD, RFDEntry, RFDIndexEntry, RFDs, RFD},
+    rfd::{
+        GitHubRFDRepo, NewRFDSnooze, RFDEntry, RFDIndexDiff, RFDIndexEntry, RFDSearchFilter, RFDSearchIndex,
+        RFDSearchKey, RFDSearchKeySigner, RFDSearchResults, RFDSnooze, RFDs, RFD,
+    },
     schema::rfds,
 };
-use diesel::{ExpressionMethods, QueryDsl};
+use diesel::QueryDsl;
 use log::{info, warn};
 
 use crate::{
@@ -12,8 +16,8 @@ use crate::{
     handlers_github::{
         rfd::{
             CopyImagesToGCP, CreatePullRequest, EnsureRFDOnDefaultIsInValidState,
-            EnsureRFDWithPullRequestIsInValidState, GenerateShortUrls, ParseRFDLabels, UpdateDiscussionUrl, UpdatePDFs,
-            UpdatePullRequest, UpdateSearch,
+            EnsureRFDWithPullRequestIsInValidState, GenerateShortUrls, ParseRFDLabels, RecordStateChange,
+            UpdateDiscussionUrl, UpdatePDFs, UpdatePullRequest, UpdateReferences, UpdateSearch,
         },
         RFDUpdater,
     },
@@ -49,21 +53,126 @@ pub async fn handle_rfd_index(ctx: &Context, offset: i32, limit: u32) -> Result<
 }
 
 pub async fn handle_rfd_view(ctx: &Context, num: i32) -> Result<Option<RFDEntry>> {
-    let mut rfd = rfds::dsl::rfds
-        .filter(rfds::dsl::number.eq(num))
-        .load_async::<RFD>(ctx.db.pool())
-        .await?;
+    RFDEntry::get_from_db(&ctx.db, num).await
+}
+
+pub async fn handle_rfd_search(
+    query: &str,
+    filter: &RFDSearchFilter,
+    limit: usize,
+    offset: usize,
+) -> Result<RFDSearchResults> {
+    let client = RFDSearchIndex::default_client()?;
+    RFDSearchIndex::search(&client, "rfd".to_string(), query, filter, limit, offset).await
+}
+
+pub fn handle_rfd_search_key(user: &str, filter: &RFDSearchFilter) -> Result<RFDSearchKey> {
+    let signer = RFDSearchKeySigner::from_env()?;
+    signer.sign(user, "rfd", filter, Utc::now() + Duration::hours(1))
+}
+
+/// Stop reporting an RFD as stale for the given number of days. Returns None if the RFD does not exist
+pub async fn handle_rfd_snooze(
+    ctx: &Context,
+    num: i32,
+    days: i64,
+    snoozed_by: String,
+    reason: String,
+) -> Result<Option<RFDSnooze>> {
+    if RFD::get_from_db(&ctx.db, num).await.is_none() {
+        return Ok(None);
+    }
+
+    let snooze = NewRFDSnooze {
+        rfd_number: num,
+        snoozed_until: Utc::now() + Duration::days(days),
+        snoozed_by,
+        reason,
+    }
+    .upsert(&ctx.db)
+    .await?;
+
+    info!(
+        "RFD {} snoozed until {} by {}",
+        num, snooze.snoozed_until, snooze.snoozed_by
+    );
+
+    Ok(Some(snooze))
+}
+
+pub async fn handle_rfd_unsnooze(ctx: &Context, num: i32) -> Result<()> {
+    RFDSnooze::delete(&ctx.db, num).await
+}
+
+/// Compare the search index against every RFD stored in the database, adding any sections that are
+/// missing from the index, replacing documents that lack the current RFD metadata (which backfills
+/// the metadata that searches filter on) and removing any documents that no longer belong to an RFD
+/// section. Only the RFDs that are out of sync are returned. When running in dry run mode the index
+/// is left as is
+pub async fn reindex_rfds(context: &Context, dry_run: bool) -> Result<Vec<RFDIndexDiff>> {
+    let client = RFDSearchIndex::default_client()?;
 
-    if !rfd.is_empty() {
-        let new_rfd: NewRFD = rfd.pop().unwrap().into();
-        Ok(Some(new_rfd.into()))
-    } else {
-        Ok(None)
+    if !dry_run {
+        RFDSearchIndex::configure_index(&client, "rfd".to_string()).await?;
     }
+
+    let index = client.index("rfd".to_string());
+    let rfds = RFDs::get_from_db(&context.db, context.company.id).await?;
+
+    let mut diffs = vec![];
+
+    for rfd in rfds {
+        let diff = match RFDSearchIndex::diff_rfd(&index, &rfd.number.into(), &rfd.content).await {
+            Ok(diff) => diff,
+            Err(err) => {
+                warn!(
+                    "Failed to compare RFD {} against the search index {:?}",
+                    rfd.number, err
+                );
+                continue;
+            }
+        };
+
+        if diff.is_empty() {
+            continue;
+        }
+
+        info!(
+            "RFD {} has {} sections missing from the search index, {} outdated documents and {} orphaned documents",
+            diff.rfd_number,
+            diff.missing.len(),
+            diff.outdated.len(),
+            diff.orphaned.len()
+        );
+
+        if !dry_run {
+            if let Err(err) = RFDSearchIndex::apply_diff(&index, &diff).await {
+                warn!("Failed to update search index for RFD {} {:?}", rfd.number, err);
+                continue;
+            }
+        }
+
+        diffs.push(diff);
+    }
+
+    info!("Found {} RFDs that are out of sync with the search index", diffs.len());
+
+    Ok(diffs)
 }
 
 // Sync the rfds with our database.
 pub async fn refresh_db_rfds(context: &Context) -> Result<()> {
+    // Searches filter on RFD metadata, make sure the index allows for filtering on those fields
+    // before any RFDs are indexed
+    match RFDSearchIndex::default_client() {
+        Ok(client) => {
+            if let Err(err) = RFDSearchIndex::configure_index(&client, "rfd".to_string()).await {
+                warn!("Failed to configure RFD search index {:?}", err);
+            }
+        }
+        Err(err) => warn!("Failed to create RFD search client {:?}", err),
+    }
+
     let repo = GitHubRFDRepo::new(&context.company).await?;
     let updates = repo.get_rfd_sync_updates().await?;
 
@@ -83,9 +192,11 @@ pub async fn refresh_db_rfds(context: &Context) -> Result<()> {
                     Box::new(CreatePullRequest),
                     Box::new(UpdatePullRequest),
                     Box::new(ParseRFDLabels),
+                    Box::new(UpdateReferences),
                     Box::new(UpdateDiscussionUrl),
                     Box::new(EnsureRFDWithPullRequestIsInValidState),
                     Box::new(EnsureRFDOnDefaultIsInValidState),
+                    Box::new(RecordStateChange),
                 ]);
 
                 updater.handle(&context, &[update]).await?;
@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use cio_api::{db::Database, idempotency_keys::IdempotencyKey};
+use dropshot::{RequestContext, ServerContext as DropshotServerContext, SharedExtractor};
+use dropshot_verify_request::idempotency::{IdempotencyKeyProvider, IdempotencyStore};
+use http::HeaderMap;
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+use crate::http::Headers;
+
+/// The database that delivery keys are recorded in. Extractors do not have access to the server
+/// context, so the store keeps its own pool
+static DB: OnceCell<Database> = OnceCell::const_new();
+
+/// Records delivery keys in the database, so that redeliveries are recognised across restarts and
+/// across instances of the server
+pub struct DatabaseStore;
+
+#[async_trait]
+impl IdempotencyStore for DatabaseStore {
+    async fn record(key: &str, ttl: Duration) -> Result<bool> {
+        let db = DB.get_or_init(Database::new).await;
+        IdempotencyKey::record(db, key, chrono::Duration::from_std(ttl)?).await
+    }
+
+    async fn forget(key: &str) -> Result<()> {
+        let db = DB.get_or_init(Database::new).await;
+        IdempotencyKey::forget(db, key).await
+    }
+}
+
+/// A key for a body that does not carry any identifiers of its own
+fn body_key(source: &str, body: &[u8]) -> String {
+    format!("{}:sha256:{}", source, hex::encode(Sha256::digest(body)))
+}
+
+/// GitHub sends a unique id with each delivery, which is kept when a delivery is redelivered
+pub struct GitHubDelivery;
+
+impl GitHubDelivery {
+    pub fn key_from_headers(headers: &HeaderMap) -> Option<String> {
+        headers
+            .get("X-GitHub-Delivery")
+            .and_then(|value| value.to_str().ok())
+            .filter(|delivery| !delivery.is_empty())
+            .map(|delivery| format!("github:{}", delivery))
+    }
+}
+
+#[async_trait]
+impl IdempotencyKeyProvider for GitHubDelivery {
+    type Store = DatabaseStore;
+
+    async fn key<Context: DropshotServerContext>(rqctx: &RequestContext<Context>, _: &[u8]) -> Result<Option<String>> {
+        let headers = Headers::from_request(rqctx).await?;
+        Ok(Self::key_from_headers(&headers.0))
+    }
+}
+
+/// DocuSign does not send a delivery id, so a delivery is identified by the envelope and the
+/// status change that it reports
+pub struct DocuSignDelivery;
+
+impl DocuSignDelivery {
+    pub fn key_from_body(body: &[u8]) -> Option<String> {
+        let envelope: serde_json::Value = serde_json::from_slice(body).ok()?;
+        let field = |name: &str| envelope.get(name).and_then(|value| value.as_str()).unwrap_or_default();
+
+        let envelope_id = field("envelopeId");
+        let changed_at = field("statusChangedDateTime");
+
+        if envelope_id.is_empty() || changed_at.is_empty() {
+            return Some(body_key("docusign", body));
+        }
+
+        Some(format!("docusign:{}:{}:{}", envelope_id, field("status"), changed_at))
+    }
+}
+
+#[async_trait]
+impl IdempotencyKeyProvider for DocuSignDelivery {
+    type Store = DatabaseStore;
+
+    async fn key<Context: DropshotServerContext>(_: &RequestContext<Context>, body: &[u8]) -> Result<Option<String>> {
+        Ok(Self::key_from_body(body))
+    }
+}
+
+/// Shippo does not send a delivery id, so a delivery is identified by the tracking number and the
+/// tracking status that it reports
+pub struct ShippoDelivery;
+
+impl ShippoDelivery {
+    pub fn key_from_body(body: &[u8]) -> Option<String> {
+        let event: serde_json::Value = serde_json::from_slice(body).ok()?;
+        let field = |pointer: &str| {
+            event
+                .pointer(pointer)
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+        };
+
+        let tracking_number = field("/data/tracking_number");
+        let changed_at = field("/data/tracking_status/status_date");
+
+        if tracking_number.is_empty() || changed_at.is_empty() {
+            return Some(body_key("shippo", body));
+        }
+
+        Some(format!(
+            "shippo:{}:{}:{}:{}",
+            field("/data/carrier"),
+            tracking_number,
+            field("/data/tracking_status/status"),
+            changed_at
+        ))
+    }
+}
+
+#[async_trait]
+impl IdempotencyKeyProvider for ShippoDelivery {
+    type Store = DatabaseStore;
+
+    async fn key<Context: DropshotServerContext>(_: &RequestContext<Context>, body: &[u8]) -> Result<Option<String>> {
+        Ok(Self::key_from_body(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dropshot_verify_request::idempotency::MemoryStore;
+    use http::HeaderValue;
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    async fn is_new(key: Option<String>) -> bool {
+        MemoryStore::record(&key.unwrap(), TTL).await.unwrap()
+    }
+
+    // Requires a migrated database. Run with CIO_DATABASE_URL set and --ignored
+    #[ignore]
+    #[tokio::test]
+    async fn test_database_store_rejects_redelivery() {
+        let key = format!("test:{}", uuid::Uuid::new_v4());
+
+        assert!(DatabaseStore::record(&key, TTL).await.unwrap());
+        assert!(!DatabaseStore::record(&key, TTL).await.unwrap());
+
+        // Forgotten keys are recorded again, as happens when an event could not be stored
+        DatabaseStore::forget(&key).await.unwrap();
+        assert!(DatabaseStore::record(&key, TTL).await.unwrap());
+        assert!(!DatabaseStore::record(&key, TTL).await.unwrap());
+
+        DatabaseStore::forget(&key).await.unwrap();
+    }
+
+    // Requires a migrated database. Run with CIO_DATABASE_URL set and --ignored
+    #[ignore]
+    #[tokio::test]
+    async fn test_database_store_forgets_expired_keys() {
+        let key = format!("test:{}", uuid::Uuid::new_v4());
+        let ttl = Duration::from_millis(10);
+
+        assert!(DatabaseStore::record(&key, ttl).await.unwrap());
+        assert!(!DatabaseStore::record(&key, ttl).await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(DatabaseStore::record(&key, ttl).await.unwrap());
+
+        DatabaseStore::forget(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_github_redelivery() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-GitHub-Delivery",
+            HeaderValue::from_static("72d3162e-cc78-11e3-81ab-4c9367dc0958"),
+        );
+
+        assert!(is_new(GitHubDelivery::key_from_headers(&headers)).await);
+        assert!(!is_new(GitHubDelivery::key_from_headers(&headers)).await);
+
+        headers.insert(
+            "X-GitHub-Delivery",
+            HeaderValue::from_static("7f0bd8de-cc78-11e3-8e1c-f4c4b8c0d54a"),
+        );
+        assert!(is_new(GitHubDelivery::key_from_headers(&headers)).await);
+
+        assert_eq!(None, GitHubDelivery::key_from_headers(&HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_docusign_redelivery() {
+        let sent = br#"{"envelopeId":"93be49ab","status":"sent","statusChangedDateTime":"2024-03-01T10:00:00Z"}"#;
+        let completed =
+            br#"{"envelopeId":"93be49ab","status":"completed","statusChangedDateTime":"2024-03-02T10:00:00Z"}"#;
+
+        assert!(is_new(DocuSignDelivery::key_from_body(sent)).await);
+        assert!(!is_new(DocuSignDelivery::key_from_body(sent)).await);
+        assert!(is_new(DocuSignDelivery::key_from_body(completed)).await);
+
+        // Envelopes without a status change time fall back to the contents of the body
+        let unchanged = br#"{"envelopeId":"93be49ab","status":"voided"}"#;
+        assert!(is_new(DocuSignDelivery::key_from_body(unchanged)).await);
+        assert!(!is_new(DocuSignDelivery::key_from_body(unchanged)).await);
+    }
+
+    #[tokio::test]
+    async fn test_shippo_redelivery() {
+        let transit = br#"{"event":"track_updated","test":false,"data":{"carrier":"usps","tracking_number":"9205590164917312751089","tracking_status":{"status":"TRANSIT","status_date":"2024-03-01T10:00:00Z"}}}"#;
+        let delivered = br#"{"event":"track_updated","test":false,"data":{"carrier":"usps","tracking_number":"9205590164917312751089","tracking_status":{"status":"DELIVERED","status_date":"2024-03-02T10:00:00Z"}}}"#;
+
+        assert!(is_new(ShippoDelivery::key_from_body(transit)).await);
+        assert!(!is_new(ShippoDelivery::key_from_body(transit)).await);
+        assert!(is_new(ShippoDelivery::key_from_body(delivered)).await);
+
+        // Events without a tracking status fall back to the contents of the body
+        let pending = br#"{"event":"track_updated","test":true,"data":{"carrier":"usps","tracking_number":""}}"#;
+        assert!(is_new(ShippoDelivery::key_from_body(pending)).await);
+        assert!(!is_new(ShippoDelivery::key_from_body(pending)).await);
+    }
+}
@@ -0,0 +1,475 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use cio_api::webhook_events::{NewWebhookEvent, WebhookEvent, WebhookEventFilter, WebhookEventReplay};
+use dropshot::RequestContext;
+use http::{header::HeaderName, HeaderMap, HeaderValue};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    context::ServerContext,
+    handlers,
+    handlers_slack::InteractiveEvent,
+    retry::{classify_error, ErrorClass, RetryPolicy},
+};
+
+/// How often the worker checks for events that are due
+const WORKER_TICK: Duration = Duration::from_secs(15);
+
+/// The most events that the worker takes on each tick
+const WORKER_BATCH: i64 = 50;
+
+/// How long an event stays claimed by a processor before it is assumed to have stopped
+const PROCESSING_LEASE_MINUTES: i64 = 15;
+
+/// Headers that carry credentials. They are not stored
+const REDACTED_HEADERS: &[&str] = &["authorization", "cookie", "proxy-authorization"];
+
+/// The vendor events that are received through the inbox, one for each webhook endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookKind {
+    AirtableApplicantsRecreatePiia,
+    AirtableApplicantsRequestBackgroundCheck,
+    AirtableApplicantsUpdate,
+    AirtableAssetsItemsPrintBarcodeLabel,
+    AirtableCertificatesRenew,
+    AirtableEmployeesPrintHomeAddressLabel,
+    AirtableShipmentsInboundCreate,
+    AirtableShipmentsOutboundCreate,
+    AirtableShipmentsOutboundReprintLabel,
+    AirtableShipmentsOutboundReprintReceipt,
+    AirtableShipmentsOutboundResendShipmentStatusEmailToRecipient,
+    AirtableShipmentsOutboundSchedulePickup,
+    AirtableSwagInventoryItemsPrintBarcodeLabels,
+    CheckrBackgroundUpdate,
+    DocuSignEnvelopeUpdate,
+    EasyPostTrackingUpdate,
+    GitHub,
+    ShipBob,
+    ShippoTrackingUpdate,
+    SlackCommands,
+    SlackInteractive,
+}
+
+impl WebhookKind {
+    pub const ALL: &'static [WebhookKind] = &[
+        WebhookKind::AirtableApplicantsRecreatePiia,
+        WebhookKind::AirtableApplicantsRequestBackgroundCheck,
+        WebhookKind::AirtableApplicantsUpdate,
+        WebhookKind::AirtableAssetsItemsPrintBarcodeLabel,
+        WebhookKind::AirtableCertificatesRenew,
+        WebhookKind::AirtableEmployeesPrintHomeAddressLabel,
+        WebhookKind::AirtableShipmentsInboundCreate,
+        WebhookKind::AirtableShipmentsOutboundCreate,
+        WebhookKind::AirtableShipmentsOutboundReprintLabel,
+        WebhookKind::AirtableShipmentsOutboundReprintReceipt,
+        WebhookKind::AirtableShipmentsOutboundResendShipmentStatusEmailToRecipient,
+        WebhookKind::AirtableShipmentsOutboundSchedulePickup,
+        WebhookKind::AirtableSwagInventoryItemsPrintBarcodeLabels,
+        WebhookKind::CheckrBackgroundUpdate,
+        WebhookKind::DocuSignEnvelopeUpdate,
+        WebhookKind::EasyPostTrackingUpdate,
+        WebhookKind::GitHub,
+        WebhookKind::ShipBob,
+        WebhookKind::ShippoTrackingUpdate,
+        WebhookKind::SlackCommands,
+        WebhookKind::SlackInteractive,
+    ];
+
+    /// The path of the endpoint that receives the event, which is what events are stored by
+    pub fn path(&self) -> &'static str {
+        match self {
+            WebhookKind::AirtableApplicantsRecreatePiia => "/airtable/applicants/recreate_piia",
+            WebhookKind::AirtableApplicantsRequestBackgroundCheck => "/airtable/applicants/request_background_check",
+            WebhookKind::AirtableApplicantsUpdate => "/airtable/applicants/update",
+            WebhookKind::AirtableAssetsItemsPrintBarcodeLabel => "/airtable/assets/items/print_barcode_label",
+            WebhookKind::AirtableCertificatesRenew => "/airtable/certificates/renew",
+            WebhookKind::AirtableEmployeesPrintHomeAddressLabel => "/airtable/employees/print_home_address_label",
+            WebhookKind::AirtableShipmentsInboundCreate => "/airtable/shipments/inbound/create",
+            WebhookKind::AirtableShipmentsOutboundCreate => "/airtable/shipments/outbound/create",
+            WebhookKind::AirtableShipmentsOutboundReprintLabel => "/airtable/shipments/outbound/reprint_label",
+            WebhookKind::AirtableShipmentsOutboundReprintReceipt => "/airtable/shipments/outbound/reprint_receipt",
+            WebhookKind::AirtableShipmentsOutboundResendShipmentStatusEmailToRecipient => {
+                "/airtable/shipments/outbound/resend_shipment_status_email_to_recipient"
+            }
+            WebhookKind::AirtableShipmentsOutboundSchedulePickup => "/airtable/shipments/outbound/schedule_pickup",
+            WebhookKind::AirtableSwagInventoryItemsPrintBarcodeLabels => {
+                "/airtable/swag/inventory/items/print_barcode_labels"
+            }
+            WebhookKind::CheckrBackgroundUpdate => "/checkr/background/update",
+            WebhookKind::DocuSignEnvelopeUpdate => "/docusign/envelope/update",
+            WebhookKind::EasyPostTrackingUpdate => "/easypost/tracking/update",
+            WebhookKind::GitHub => "/github",
+            WebhookKind::ShipBob => "/shipbob",
+            WebhookKind::ShippoTrackingUpdate => "/shippo/tracking/update",
+            WebhookKind::SlackCommands => "/slack/commands",
+            WebhookKind::SlackInteractive => "/slack/interactive",
+        }
+    }
+
+    /// The vendor that sends the event
+    pub fn source(&self) -> &'static str {
+        match self {
+            WebhookKind::AirtableApplicantsRecreatePiia
+            | WebhookKind::AirtableApplicantsRequestBackgroundCheck
+            | WebhookKind::AirtableApplicantsUpdate
+            | WebhookKind::AirtableAssetsItemsPrintBarcodeLabel
+            | WebhookKind::AirtableCertificatesRenew
+            | WebhookKind::AirtableEmployeesPrintHomeAddressLabel
+            | WebhookKind::AirtableShipmentsInboundCreate
+            | WebhookKind::AirtableShipmentsOutboundCreate
+            | WebhookKind::AirtableShipmentsOutboundReprintLabel
+            | WebhookKind::AirtableShipmentsOutboundReprintReceipt
+            | WebhookKind::AirtableShipmentsOutboundResendShipmentStatusEmailToRecipient
+            | WebhookKind::AirtableShipmentsOutboundSchedulePickup
+            | WebhookKind::AirtableSwagInventoryItemsPrintBarcodeLabels => "airtable",
+            WebhookKind::CheckrBackgroundUpdate => "checkr",
+            WebhookKind::DocuSignEnvelopeUpdate => "docusign",
+            WebhookKind::EasyPostTrackingUpdate => "easypost",
+            WebhookKind::GitHub => "github",
+            WebhookKind::ShipBob => "shipbob",
+            WebhookKind::ShippoTrackingUpdate => "shippo",
+            WebhookKind::SlackCommands | WebhookKind::SlackInteractive => "slack",
+        }
+    }
+
+    /// Slack sends its events as form data rather than JSON
+    pub fn form_encoded(&self) -> bool {
+        matches!(self, WebhookKind::SlackCommands | WebhookKind::SlackInteractive)
+    }
+
+    /// Slack waits on the response to its events, so they are only processed while Slack is
+    /// waiting. A failed Slack event can still be replayed by hand
+    pub fn retryable(&self) -> bool {
+        !matches!(self, WebhookKind::SlackCommands | WebhookKind::SlackInteractive)
+    }
+}
+
+impl fmt::Display for WebhookKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path())
+    }
+}
+
+impl FromStr for WebhookKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        WebhookKind::ALL
+            .iter()
+            .find(|kind| kind.path() == s)
+            .copied()
+            .ok_or_else(|| anyhow!("There is no webhook endpoint at {}", s))
+    }
+}
+
+/// Events that fail with a transient error are attempted again with this policy
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 5,
+        initial_backoff: Duration::from_secs(60),
+        max_backoff: Duration::from_secs(60 * 60),
+    }
+}
+
+/// The headers of a request as a JSON object, leaving out any credentials
+pub fn headers_to_json(headers: &HeaderMap) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+
+    for name in headers.keys() {
+        let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+            "[redacted]".to_string()
+        } else {
+            headers
+                .get_all(name)
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        map.insert(name.to_string(), serde_json::Value::String(value));
+    }
+
+    serde_json::Value::Object(map)
+}
+
+/// The headers that were stored with an event. Headers that are not valid are skipped
+pub fn headers_from_json(value: &serde_json::Value) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+
+    if let Some(map) = value.as_object() {
+        for (name, value) in map {
+            if let (Ok(name), Some(Ok(value))) = (HeaderName::from_str(name), value.as_str().map(HeaderValue::from_str))
+            {
+                headers.insert(name, value);
+            }
+        }
+    }
+
+    headers
+}
+
+/// Store the body of a request that was received by a webhook endpoint exactly as it was sent,
+/// before anything is done with it. The body is parsed when the event is dispatched
+pub async fn record(
+    rqctx: &RequestContext<ServerContext>,
+    kind: WebhookKind,
+    body: &[u8],
+    verified: bool,
+) -> Result<WebhookEvent> {
+    let body = std::str::from_utf8(body).map_err(|err| anyhow!("Webhook body for {} is not UTF-8: {}", kind, err))?;
+
+    let new_event = NewWebhookEvent::new(
+        kind.source(),
+        kind.path(),
+        headers_to_json(rqctx.request.headers()),
+        body.to_string(),
+        verified,
+    );
+
+    WebhookEvent::record(&rqctx.context().app.db, &new_event).await
+}
+
+/// Store the body of a request that was received by a webhook endpoint and start processing it in
+/// the background, so that the vendor does not wait on it
+pub async fn receive(
+    rqctx: &RequestContext<ServerContext>,
+    kind: WebhookKind,
+    body: &[u8],
+    verified: bool,
+) -> Result<()> {
+    let event = record(rqctx, kind, body, verified).await?;
+    let ctx = rqctx.context().clone();
+
+    tokio::spawn(async move {
+        if let Err(err) = process(&ctx, event.id).await {
+            warn!(
+                "Failed to process webhook event {} from {}: {:?}",
+                event.id, event.source, err
+            );
+        }
+    });
+
+    Ok(())
+}
+
+/// Make an attempt at processing an event, if it is due. Returns the output of the handler of
+/// the event, or None if the event was not due. Failed attempts are recorded on the event along
+/// with when it is due to be attempted again, if at all.
+pub async fn process(ctx: &ServerContext, id: i32) -> Result<Option<serde_json::Value>> {
+    let db = &ctx.app.db;
+    let lease = chrono::Duration::minutes(PROCESSING_LEASE_MINUTES);
+
+    let event = match WebhookEvent::claim(db, id, lease).await? {
+        Some(event) => event,
+        None => return Ok(None),
+    };
+
+    info!(
+        "Processing webhook event {} from {} at {} (attempt {})",
+        event.id, event.source, event.kind, event.attempts
+    );
+
+    match dispatch(ctx, &event).await {
+        Ok(output) => {
+            WebhookEvent::mark_processed(db, event.id).await?;
+            Ok(Some(output))
+        }
+        Err(err) => {
+            let policy = retry_policy();
+            let attempts = event.attempts.max(0) as u32;
+            let retryable = WebhookKind::from_str(&event.kind)
+                .map(|kind| kind.retryable())
+                .unwrap_or(false);
+
+            let next_attempt_at =
+                if retryable && classify_error(&err) == ErrorClass::Transient && attempts < policy.max_attempts {
+                    Some(Utc::now() + chrono::Duration::from_std(policy.backoff(attempts))?)
+                } else {
+                    None
+                };
+
+            WebhookEvent::mark_attempt_failed(db, event.id, &format!("{:?}", err), next_attempt_at).await?;
+
+            Err(err)
+        }
+    }
+}
+
+/// Parse the body of an event in the format that its endpoint receives
+fn parse<T>(event: &WebhookEvent) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let parsed = if WebhookKind::from_str(&event.kind)?.form_encoded() {
+        serde_urlencoded::from_str(&event.body).map_err(anyhow::Error::from)
+    } else {
+        serde_json::from_str(&event.body).map_err(anyhow::Error::from)
+    };
+
+    parsed.map_err(|err| anyhow!("Failed to parse webhook event {} for {}: {}", event.id, event.kind, err))
+}
+
+/// Hand an event to the handler for its endpoint
+async fn dispatch(ctx: &ServerContext, event: &WebhookEvent) -> Result<serde_json::Value> {
+    let kind = WebhookKind::from_str(&event.kind)?;
+    let headers = headers_from_json(&event.headers);
+
+    match kind {
+        WebhookKind::AirtableApplicantsRecreatePiia => {
+            handlers::listen_airtable_applicants_recreate_piia_webhooks(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableApplicantsRequestBackgroundCheck => {
+            handlers::handle_airtable_applicants_request_background_check(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableApplicantsUpdate => {
+            handlers::handle_airtable_applicants_update(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableAssetsItemsPrintBarcodeLabel => {
+            handlers::handle_airtable_assets_items_print_barcode_label(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableCertificatesRenew => {
+            handlers::handle_airtable_certificates_renew(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableEmployeesPrintHomeAddressLabel => {
+            handlers::handle_airtable_employees_print_home_address_label(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableShipmentsInboundCreate => {
+            handlers::handle_airtable_shipments_inbound_create(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableShipmentsOutboundCreate => {
+            handlers::handle_airtable_shipments_outbound_create(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableShipmentsOutboundReprintLabel => {
+            handlers::handle_airtable_shipments_outbound_reprint_label(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableShipmentsOutboundReprintReceipt => {
+            handlers::handle_airtable_shipments_outbound_reprint_receipt(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableShipmentsOutboundResendShipmentStatusEmailToRecipient => {
+            handlers::handle_airtable_shipments_outbound_resend_shipment_status_email_to_recipient(ctx, parse(event)?)
+                .await?
+        }
+        WebhookKind::AirtableShipmentsOutboundSchedulePickup => {
+            handlers::handle_airtable_shipments_outbound_schedule_pickup(ctx, parse(event)?).await?
+        }
+        WebhookKind::AirtableSwagInventoryItemsPrintBarcodeLabels => {
+            handlers::handle_airtable_swag_inventory_items_print_barcode_labels(ctx, parse(event)?).await?
+        }
+        WebhookKind::CheckrBackgroundUpdate => handlers::handle_checkr_background_update(ctx, parse(event)?).await?,
+        WebhookKind::DocuSignEnvelopeUpdate => handlers::handle_docusign_envelope_update(ctx, parse(event)?).await?,
+        WebhookKind::EasyPostTrackingUpdate => handlers::handle_easypost_tracking_update(ctx, parse(event)?).await?,
+        WebhookKind::GitHub => crate::handlers_github::handle_github(ctx, &headers, parse(event)?).await?,
+        WebhookKind::ShipBob => handlers::handle_shipbob(ctx, &headers, parse(event)?).await?,
+        WebhookKind::ShippoTrackingUpdate => handlers::handle_shippo_tracking_update(ctx, parse(event)?).await?,
+        WebhookKind::SlackCommands => return handlers::handle_slack_commands(ctx, parse(event)?).await,
+        WebhookKind::SlackInteractive => {
+            let interactive: InteractiveEvent = parse(event)?;
+            let response = handlers::handle_slack_interactive(ctx, interactive.payload).await?;
+            return Ok(serde_json::to_value(response)?);
+        }
+    }
+
+    Ok(serde_json::Value::Null)
+}
+
+/// Process events as they become due, which covers events that failed with a transient error and
+/// events whose processor stopped before finishing them
+pub async fn run_worker(ctx: ServerContext) {
+    let mut interval = tokio::time::interval(WORKER_TICK);
+
+    loop {
+        interval.tick().await;
+
+        let due = match WebhookEvent::due(&ctx.app.db, Utc::now(), WORKER_BATCH).await {
+            Ok(due) => due,
+            Err(err) => {
+                warn!("Failed to find webhook events that are due: {:?}", err);
+                continue;
+            }
+        };
+
+        for event in due {
+            if let Err(err) = process(&ctx, event.id).await {
+                warn!(
+                    "Failed to process webhook event {} from {}: {:?}",
+                    event.id, event.source, err
+                );
+            }
+        }
+    }
+}
+
+pub async fn handle_webhook_events_list(ctx: &ServerContext, filter: &WebhookEventFilter) -> Result<Vec<WebhookEvent>> {
+    WebhookEvent::list(&ctx.app.db, filter).await
+}
+
+/// Process an event again and return it with the outcome. Returns None if the event does not
+/// exist or is currently being processed
+pub async fn handle_webhook_event_replay(ctx: &ServerContext, id: i32) -> Result<Option<WebhookEvent>> {
+    let db = &ctx.app.db;
+
+    if WebhookEvent::replay(db, id).await?.is_none() {
+        return Ok(None);
+    }
+
+    // The outcome is recorded on the event
+    if let Err(err) = process(ctx, id).await {
+        warn!("Replay of webhook event {} failed: {:?}", id, err);
+    }
+
+    WebhookEvent::get(db, id).await
+}
+
+/// Put the events received within a time range back to be processed by the worker. Returns the
+/// ids of the events that will be processed again
+pub async fn handle_webhook_events_replay(ctx: &ServerContext, replay: &WebhookEventReplay) -> Result<Vec<i32>> {
+    let events = WebhookEvent::replay_range(&ctx.app.db, replay).await?;
+    info!("Replaying {} webhook events", events.len());
+
+    Ok(events.into_iter().map(|event| event.id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kinds_are_found_by_path() {
+        for kind in WebhookKind::ALL {
+            assert_eq!(*kind, WebhookKind::from_str(kind.path()).unwrap());
+        }
+
+        assert!(WebhookKind::from_str("/ping").is_err());
+        assert!(!WebhookKind::SlackCommands.retryable());
+        assert!(WebhookKind::GitHub.retryable());
+        assert!(WebhookKind::SlackInteractive.form_encoded());
+        assert!(!WebhookKind::GitHub.form_encoded());
+    }
+
+    #[test]
+    fn test_headers_round_trip_without_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-github-event", HeaderValue::from_static("push"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.append("accept", HeaderValue::from_static("text/html"));
+        headers.append("accept", HeaderValue::from_static("application/json"));
+
+        let json = headers_to_json(&headers);
+        assert_eq!(
+            serde_json::json!({
+                "x-github-event": "push",
+                "authorization": "[redacted]",
+                "accept": "text/html, application/json",
+            }),
+            json
+        );
+
+        let restored = headers_from_json(&json);
+        assert_eq!("push", restored.get("X-GitHub-Event").unwrap());
+        assert_eq!("[redacted]", restored.get("authorization").unwrap());
+    }
+}
@@ -1,11 +1,118 @@
+use std::collections::HashMap;
+
 use crate::context::Context;
 use anyhow::Result;
 
 pub async fn run_job_cmd(cmd: crate::core::SubCommand, context: Context) -> Result<()> {
+    // Jobs with a sandboxed context are run inside of the sandbox, and finish by printing the
+    // writes they would have made alongside the rest of their output
+    if let Some(sandbox) = context.sandbox.clone() {
+        let result = sandbox.scope(run_job(cmd, context)).await;
+        println!("{}", sandbox.summary());
+
+        return result;
+    }
+
+    run_job(cmd, context).await
+}
+
+async fn run_job(cmd: crate::core::SubCommand, context: Context) -> Result<()> {
+    // Jobs that have their own preview of what they would do print it when sandboxed
+    let dry_run = context.is_sandboxed();
+
     match cmd {
+        crate::core::SubCommand::CheckStaleRFDs(_) => {
+            let Context {
+                app_config,
+                db,
+                company,
+                ..
+            } = context;
+            let config = app_config.read().unwrap().rfd.stale.clone();
+            let report = cio_api::rfd::check_stale_rfds(&db, &company, &config, dry_run).await?;
+
+            if dry_run {
+                println!("{}", report);
+            }
+        }
+        crate::core::SubCommand::OffboardUsers(_) => {
+            let Context { db, company, .. } = context;
+            let records = cio_api::offboarding::run_offboardings(&db, &company, dry_run).await?;
+
+            if dry_run {
+                for record in &records {
+                    println!("{}", record);
+                }
+
+                if records.is_empty() {
+                    println!("No users are being offboarded");
+                }
+            }
+        }
+        crate::core::SubCommand::ProvisionUsers(_) => {
+            let Context {
+                app_config,
+                db,
+                company,
+                ..
+            } = context;
+            let config = app_config.read().unwrap().clone();
+            let due = cio_api::provisioning::run_due_steps(&db, &company, &config, dry_run).await?;
+
+            if dry_run {
+                for step in &due {
+                    println!("{} {} (due {})", step.username, step.step, step.due_on);
+                }
+
+                if due.is_empty() {
+                    println!("No provisioning steps are due");
+                }
+            }
+        }
+        crate::core::SubCommand::ReindexRFDs(_) => {
+            let diffs = crate::handlers_rfd::reindex_rfds(&context, dry_run).await?;
+
+            if dry_run {
+                for diff in &diffs {
+                    println!("{}", diff);
+                }
+
+                if diffs.is_empty() {
+                    println!("RFD search index is up to date");
+                }
+            }
+        }
+        crate::core::SubCommand::ReportDrift(_) => {
+            let Context {
+                app_config,
+                db,
+                company,
+                ..
+            } = context;
+            let config = app_config.read().unwrap().drift.clone();
+            let report = cio_api::drift::report_drift(&db, &company, &config, dry_run).await?;
+
+            if dry_run {
+                println!("{}", report);
+            }
+        }
         crate::core::SubCommand::SendRFDChangelog(_) => {
             let Context { db, company, .. } = context;
-            cio_api::rfd::send_rfd_changelog(&db, &company).await?;
+            let email = cio_api::rfd::send_rfd_changelog(&db, &company, dry_run).await?;
+
+            if dry_run {
+                match email {
+                    Some(email) => println!(
+                        "To: {}\nFrom: {}\nSubject: {}\n\n{}\n\n{}",
+                        email.to.join(", "),
+                        email.from,
+                        email.subject,
+                        email.text,
+                        email.html
+                    ),
+                    None => println!("No RFD changes to report"),
+                }
+            }
         }
         crate::core::SubCommand::SyncAnalytics(_) => {
             let Context { db, company, .. } = context;
@@ -171,3 +278,224 @@ pub async fn run_job_cmd(cmd: crate::core::SubCommand, context: Context) -> Resu
 
     Ok(())
 }
+
+/// A step of a job that is run as its own node of the job's saga. Running a job as steps records
+/// which step of the job failed, and lets a retry of the job resume from that step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobStep {
+    pub name: &'static str,
+    /// Reversible steps are undone when a later step of the job fails
+    pub reversible: bool,
+    /// Failures of steps that are not required are recorded, but do not fail the job
+    pub required: bool,
+}
+
+/// The name of the step of a configs sync that pins the revision of the configs repo that the
+/// rest of the steps are run against
+const CONFIGS_REVISION_STEP: &str = "revision";
+
+/// The name of the step of an offboarding run that pins the offboardings that the rest of the
+/// steps are run for
+const OFFBOARDINGS_STEP: &str = "offboardings";
+
+/// The name of the last step of an offboarding run, which completes the offboardings that have no
+/// steps left
+const OFFBOARDINGS_COMPLETE_STEP: &str = "complete";
+
+/// The jobs that are run as multiple steps
+pub const STEPPED_JOBS: &[&str] = &["offboard-users", "sync-configs"];
+
+/// The jobs that work across all companies at once, and so are only run for the company of the
+/// server rather than once per company
+pub const SHARED_JOBS: &[&str] = &["sync-companies", "sync-functions", "sync-mailing-lists"];
+
+/// The steps of a job, grouped into stages that are run one after another. The steps of a stage
+/// are run concurrently. Jobs that are not split into steps have no stages.
+pub fn job_stages(cmd: &crate::core::SubCommand) -> Vec<Vec<JobStep>> {
+    match cmd {
+        crate::core::SubCommand::SyncConfigs(_) => {
+            use cio_api::configs::ConfigsSyncStep;
+
+            let step = |step: ConfigsSyncStep, reversible: bool, required: bool| JobStep {
+                name: step.name(),
+                reversible,
+                required,
+            };
+
+            vec![
+                vec![JobStep {
+                    name: CONFIGS_REVISION_STEP,
+                    reversible: false,
+                    required: true,
+                }],
+                // The structure that users are synced against can be restored by syncing it from
+                // the configs of the last successful run
+                vec![step(ConfigsSyncStep::Buildings, true, true)],
+                vec![step(ConfigsSyncStep::Resources, true, true)],
+                vec![step(ConfigsSyncStep::Groups, true, true)],
+                // Syncing users provisions accounts in other services, which is not something
+                // that can be taken back
+                vec![step(ConfigsSyncStep::Users, false, true)],
+                vec![
+                    step(ConfigsSyncStep::Links, false, false),
+                    step(ConfigsSyncStep::Certificates, false, false),
+                    step(ConfigsSyncStep::Anniversaries, false, false),
+                ],
+            ]
+        }
+        crate::core::SubCommand::OffboardUsers(_) => {
+            let pinned = |name: &'static str| JobStep {
+                name,
+                reversible: false,
+                required: true,
+            };
+
+            // A departed user must never get their access back, so none of the steps are undone.
+            // A step that fails for one user is retried on the next run, and the steps that
+            // follow it still run for everyone else.
+            let mut stages = vec![vec![pinned(OFFBOARDINGS_STEP)]];
+            stages.extend(cio_api::offboarding::OffboardingStep::ALL.iter().map(|step| {
+                vec![JobStep {
+                    name: step.name(),
+                    reversible: false,
+                    required: false,
+                }]
+            }));
+            stages.push(vec![pinned(OFFBOARDINGS_COMPLETE_STEP)]);
+
+            stages
+        }
+        _ => vec![],
+    }
+}
+
+/// The outputs of the steps of a job that have already run, keyed by step name
+#[derive(Debug, Default, Clone)]
+pub struct JobStepInputs(pub HashMap<String, String>);
+
+impl JobStepInputs {
+    pub fn get(&self, step: &str) -> Result<&str> {
+        self.0
+            .get(step)
+            .map(|output| output.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing the output of step {}", step))
+    }
+}
+
+/// Run a single step of a job, returning the output of the step for the steps that follow it.
+pub async fn run_job_step(
+    cmd: crate::core::SubCommand,
+    step: &str,
+    context: Context,
+    inputs: &JobStepInputs,
+) -> Result<String> {
+    match cmd {
+        crate::core::SubCommand::SyncConfigs(_) => {
+            let Context {
+                app_config,
+                db,
+                company,
+                ..
+            } = context;
+            let github = company.authenticate_github()?;
+
+            if step == CONFIGS_REVISION_STEP {
+                return cio_api::configs::get_configs_revision(&github, &company).await;
+            }
+
+            let config = app_config.read().unwrap().clone();
+            let revision = inputs.get(CONFIGS_REVISION_STEP)?;
+            cio_api::configs::sync_configs_step(&db, &github, &company, &config, configs_sync_step(step)?, revision)
+                .await?;
+        }
+        crate::core::SubCommand::OffboardUsers(_) => {
+            let Context { db, company, .. } = context;
+
+            if step == OFFBOARDINGS_STEP {
+                let ids = cio_api::offboarding::offboardings_to_run(&db, &company).await?;
+                return Ok(ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(","));
+            }
+
+            let ids = offboarding_ids(inputs.get(OFFBOARDINGS_STEP)?)?;
+
+            if step == OFFBOARDINGS_COMPLETE_STEP {
+                cio_api::offboarding::complete_offboardings(&db, &company, &ids).await?;
+            } else {
+                let step = cio_api::offboarding::OffboardingStep::from_name(step)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown offboarding step {}", step))?;
+                cio_api::offboarding::run_offboarding_step(&db, &company, &ids, step).await?;
+            }
+        }
+        other => anyhow::bail!("Job {:?} does not have a step named {}", other, step),
+    }
+
+    Ok(String::new())
+}
+
+/// Revert a step of a job after a later step has failed.
+pub async fn undo_job_step(
+    cmd: crate::core::SubCommand,
+    step: &str,
+    context: Context,
+    inputs: &JobStepInputs,
+) -> Result<()> {
+    match cmd {
+        crate::core::SubCommand::SyncConfigs(_) => {
+            let Context {
+                app_config,
+                db,
+                company,
+                ..
+            } = context;
+
+            // Restore the step to the configs that were in place before this run
+            let revision = inputs.get(CONFIGS_REVISION_STEP)?;
+            let previous = cio_api::functions::FunctionStep::last_successful_output(
+                &db,
+                "sync-configs",
+                company.id,
+                CONFIGS_REVISION_STEP,
+            )
+            .await?;
+
+            match previous {
+                Some(previous) if previous != revision => {
+                    log::info!("Restoring {} to configs revision {}", step, previous);
+
+                    let github = company.authenticate_github()?;
+                    let config = app_config.read().unwrap().clone();
+                    cio_api::configs::sync_configs_step(
+                        &db,
+                        &github,
+                        &company,
+                        &config,
+                        configs_sync_step(step)?,
+                        &previous,
+                    )
+                    .await?;
+                }
+                Some(_) => log::info!(
+                    "{} was synced against the same configs as the last successful run",
+                    step
+                ),
+                None => log::warn!("There is no successful configs sync to restore {} from", step),
+            }
+        }
+        other => anyhow::bail!("Job {:?} does not have a step named {}", other, step),
+    }
+
+    Ok(())
+}
+
+fn configs_sync_step(step: &str) -> Result<cio_api::configs::ConfigsSyncStep> {
+    cio_api::configs::ConfigsSyncStep::from_name(step)
+        .ok_or_else(|| anyhow::anyhow!("Unknown configs sync step {}", step))
+}
+
+fn offboarding_ids(output: &str) -> Result<Vec<i32>> {
+    output
+        .split(',')
+        .filter(|id| !id.is_empty())
+        .map(|id| Ok(id.parse()?))
+        .collect()
+}
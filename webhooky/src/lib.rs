@@ -18,10 +18,14 @@ pub mod handlers_slack;
 // mod handlers_sendgrid;
 mod health;
 mod http;
+mod idempotency;
+mod inbox;
 mod job;
 mod mailing_lists;
 mod repos;
+mod retry;
 mod sagas;
+mod schedule;
 pub mod server;
 mod slack_commands;
 // mod tracking_numbers;
@@ -18,10 +18,14 @@ mod handlers_slack;
 // mod handlers_sendgrid;
 mod health;
 mod http;
+mod idempotency;
+mod inbox;
 mod job;
 mod mailing_lists;
 mod repos;
+mod retry;
 mod sagas;
+mod schedule;
 mod server;
 mod slack_commands;
 // mod tracking_numbers;
@@ -81,7 +85,11 @@ async fn tokio_main() -> Result<()> {
 
     let api = APIConfig::new()?;
 
-    let context = ServerContext::new(1, logger).await?;
+    let mut context = ServerContext::new(1, logger).await?;
+
+    if opts.dry_run {
+        context.app = context.app.sandboxed();
+    }
 
     if let Err(err) = run_main_cmd(opts.clone(), api, context).await {
         bail!("running cmd `{:?}` failed: {:?}", &opts.subcmd, err);
@@ -97,6 +105,10 @@ pub async fn run_main_cmd(opts: crate::core::Opts, api: APIConfig, context: Serv
 
     match opts.subcmd.clone() {
         crate::core::SubCommand::Server(s) => {
+            if opts.dry_run {
+                bail!("--dry-run is only supported when running a job");
+            }
+
             crate::server::server(s, api.api, context, opts.debug).await?;
         }
         crate::core::SubCommand::CreateServerSpec(spec) => {
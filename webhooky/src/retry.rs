@@ -0,0 +1,277 @@
+use std::{fmt, future::Future, time::Duration};
+
+use log::warn;
+use reqwest::StatusCode;
+
+/// Whether a failed attempt at a job is worth trying again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The failure came from a vendor being unavailable or rate limiting us, and is likely to go
+    /// away on its own
+    Transient,
+    /// The failure will happen again until something is changed, such as a bad config
+    Permanent,
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorClass::Transient => write!(f, "transient"),
+            ErrorClass::Permanent => write!(f, "permanent"),
+        }
+    }
+}
+
+fn classify_status(status: StatusCode) -> ErrorClass {
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::REQUEST_TIMEOUT || status.is_server_error() {
+        ErrorClass::Transient
+    } else {
+        ErrorClass::Permanent
+    }
+}
+
+/// Classify the error that a job failed with. Errors are classified by the type of any error in
+/// their chain, falling back to looking for the status of a rate limited or failed vendor request
+/// in the message of the error, as most of our API clients only report the status in their message.
+/// Anything else is treated as permanent.
+pub fn classify_error(err: &anyhow::Error) -> ErrorClass {
+    for cause in err.chain() {
+        if let Some(err) = cause.downcast_ref::<docusign::APIError>() {
+            return classify_status(err.status_code);
+        }
+
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = err.status() {
+                return classify_status(status);
+            }
+
+            if err.is_timeout() || err.is_connect() {
+                return ErrorClass::Transient;
+            }
+        }
+
+        if let Some(err) = cause.downcast_ref::<std::io::Error>() {
+            use std::io::ErrorKind::*;
+
+            if matches!(
+                err.kind(),
+                TimedOut | ConnectionReset | ConnectionAborted | ConnectionRefused | BrokenPipe | UnexpectedEof
+            ) {
+                return ErrorClass::Transient;
+            }
+        }
+    }
+
+    let message = format!("{:#}", err);
+
+    let transient_statuses = [
+        StatusCode::TOO_MANY_REQUESTS,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        StatusCode::BAD_GATEWAY,
+        StatusCode::SERVICE_UNAVAILABLE,
+        StatusCode::GATEWAY_TIMEOUT,
+    ];
+
+    if transient_statuses
+        .iter()
+        .any(|status| message.contains(&status.to_string()))
+        || message.to_lowercase().contains("rate limit")
+    {
+        ErrorClass::Transient
+    } else {
+        ErrorClass::Permanent
+    }
+}
+
+/// How many times a job is attempted, and how long to wait between attempts, when it fails with
+/// a transient error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The total number of attempts, including the first
+    pub max_attempts: u32,
+    /// The wait after the first failed attempt. The wait doubles after each failed attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// The policy for a job. The syncs against Google, Zoom and DocuSign are the most likely to hit
+    /// rate limits and outages, so they are given the most time to recover. Jobs that notify people
+    /// are never retried, as a failed run may already have sent some of its messages.
+    pub fn for_job(name: &str) -> Self {
+        match name {
+            "check-stale-rfds" | "send-rfd-changelog" => RetryPolicy {
+                max_attempts: 1,
+                initial_backoff: Duration::ZERO,
+                max_backoff: Duration::ZERO,
+            },
+            "sync-applications"
+            | "sync-configs"
+            | "sync-huddles"
+            | "sync-interviews"
+            | "sync-recorded-meetings"
+            | "sync-rfds" => RetryPolicy {
+                max_attempts: 5,
+                initial_backoff: Duration::from_secs(60),
+                max_backoff: Duration::from_secs(15 * 60),
+            },
+            _ => RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_secs(30),
+                max_backoff: Duration::from_secs(5 * 60),
+            },
+        }
+    }
+
+    /// How long to wait after the given attempt failed, counting attempts from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// A job that failed and is not going to be attempted again
+#[derive(Debug)]
+pub struct JobFailure {
+    pub error: anyhow::Error,
+    pub class: ErrorClass,
+    pub attempts: u32,
+}
+
+/// Run a job, attempting it again while it fails with transient errors until the policy runs out
+/// of attempts. `attempt` is given the number of the attempt, counting from 1.
+pub async fn run_with_retries<F, Fut, T>(name: &str, policy: &RetryPolicy, mut attempt: F) -> Result<T, JobFailure>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut attempts = 1;
+
+    loop {
+        match attempt(attempts).await {
+            Ok(value) => return Ok(value),
+            Err(error) => {
+                let class = classify_error(&error);
+                cio_api::metrics::record_job_attempt_failure(name, class);
+
+                if class == ErrorClass::Permanent || attempts >= policy.max_attempts {
+                    return Err(JobFailure { error, class, attempts });
+                }
+
+                let backoff = policy.backoff(attempts);
+                warn!(
+                    "Attempt {} of {} at `{}` failed with a {} error, trying again in {}s: {:?}",
+                    attempts,
+                    policy.max_attempts,
+                    name,
+                    class,
+                    backoff.as_secs(),
+                    error
+                );
+
+                tokio::time::sleep(backoff).await;
+                attempts += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn test_classify_error() {
+        let rate_limited = docusign::APIError {
+            status_code: StatusCode::TOO_MANY_REQUESTS,
+            body: String::new(),
+        };
+        assert_eq!(ErrorClass::Transient, classify_error(&anyhow::Error::new(rate_limited)));
+
+        let bad_request = docusign::APIError {
+            status_code: StatusCode::BAD_REQUEST,
+            body: "503 Service Unavailable".to_string(),
+        };
+        assert_eq!(ErrorClass::Permanent, classify_error(&anyhow::Error::new(bad_request)));
+
+        let reset = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset by peer");
+        assert_eq!(
+            ErrorClass::Transient,
+            classify_error(&anyhow::Error::new(reset).context("Failed to list users"))
+        );
+
+        assert_eq!(
+            ErrorClass::Transient,
+            classify_error(&anyhow!("code: 503 Service Unavailable, error: backend unavailable"))
+        );
+        assert_eq!(
+            ErrorClass::Transient,
+            classify_error(&anyhow!("User Rate Limit Exceeded").context("Failed to list calendar events"))
+        );
+        assert_eq!(
+            ErrorClass::Permanent,
+            classify_error(&anyhow!("missing field `github_org` in configs"))
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(100),
+        };
+
+        assert_eq!(Duration::from_secs(30), policy.backoff(1));
+        assert_eq!(Duration::from_secs(60), policy.backoff(2));
+        assert_eq!(Duration::from_secs(100), policy.backoff(3));
+        assert_eq!(Duration::from_secs(100), policy.backoff(40));
+    }
+
+    #[tokio::test]
+    async fn test_run_with_retries() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        };
+
+        // Transient errors are retried until an attempt succeeds
+        let calls = AtomicU32::new(0);
+        let result = run_with_retries("sync-test", &policy, |attempt| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 3 {
+                    Err(anyhow!("429 Too Many Requests"))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(3, result.unwrap());
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+
+        // Until the attempts run out
+        let failure = run_with_retries("sync-test", &policy, |_| async {
+            Err::<(), _>(anyhow!("502 Bad Gateway"))
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(3, failure.attempts);
+        assert_eq!(ErrorClass::Transient, failure.class);
+
+        // Permanent errors are not retried
+        let failure = run_with_retries("sync-test", &policy, |_| async {
+            Err::<(), _>(anyhow!("invalid config"))
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(1, failure.attempts);
+        assert_eq!(ErrorClass::Permanent, failure.class);
+    }
+}
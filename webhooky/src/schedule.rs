@@ -0,0 +1,312 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use cio_api::app_config::JobSchedule;
+use log::{info, warn};
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::context::ServerContext;
+
+/// How often the scheduler checks for jobs that are due
+const SCHEDULER_TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Parse a cron expression. Five field crontab expressions are run at the start of the minute,
+/// otherwise the expression must lead with a seconds field. Days of the week are best given by name,
+/// as numbered days start from Sunday as 1.
+pub fn parse_cron(expression: &str) -> Result<cron::Schedule> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+
+    cron::Schedule::from_str(&expression).map_err(|err| anyhow!("Invalid cron expression `{}`: {}", expression, err))
+}
+
+/// Parse an interval schedule of the form `@every <n><unit>`, where the unit is one of `s`, `m`,
+/// `h` or `d`, e.g. `@every 7h`.
+pub fn parse_interval(expression: &str) -> Result<Duration> {
+    let interval = expression
+        .trim()
+        .strip_prefix("@every")
+        .map(|interval| interval.trim())
+        .ok_or_else(|| anyhow!("Invalid interval `{}`: must start with @every", expression))?;
+
+    let split = interval.len() - interval.chars().last().map(|unit| unit.len_utf8()).unwrap_or(0);
+    let (count, unit) = interval.split_at(split);
+    let count = count
+        .parse::<i64>()
+        .map_err(|_| anyhow!("Invalid interval `{}`: expected a number of s, m, h or d", expression))?;
+
+    let interval = match unit {
+        "s" => Duration::seconds(count),
+        "m" => Duration::minutes(count),
+        "h" => Duration::hours(count),
+        "d" => Duration::days(count),
+        _ => bail!("Invalid interval `{}`: expected a number of s, m, h or d", expression),
+    };
+
+    if interval <= Duration::zero() {
+        bail!("Invalid interval `{}`: must be longer than zero", expression);
+    }
+
+    Ok(interval)
+}
+
+/// The next time that a job is due after a point in time, before any jitter is applied. Jobs on an
+/// `@every` interval are due one interval after the point in time.
+pub fn next_run(job: &JobSchedule, after: DateTime<Utc>) -> Result<Option<DateTime<Utc>>> {
+    if crate::core::into_job_command(&job.name).is_none() {
+        bail!("There is no job named {}", job.name);
+    }
+
+    if job.cron.trim().starts_with('@') {
+        return Ok(Some(after + parse_interval(&job.cron)?));
+    }
+
+    let schedule = parse_cron(&job.cron)?;
+
+    Ok(schedule
+        .after(&after.with_timezone(&job.timezone))
+        .next()
+        .map(|next| next.with_timezone(&Utc)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PlannedRun {
+    /// The schedule that the run was planned from
+    job: JobSchedule,
+    /// When the job will run. Jobs with an invalid schedule are never run
+    at: Option<DateTime<Utc>>,
+}
+
+/// Tracks when each scheduled job is next going to run.
+#[derive(Debug, Default)]
+pub struct SchedulePlanner {
+    planned: BTreeMap<String, PlannedRun>,
+}
+
+impl SchedulePlanner {
+    /// Bring the plan in line with the current schedule and take the jobs that are due to run.
+    /// Jobs that are new, or whose schedule has changed, are planned from now.
+    pub fn take_due<J>(&mut self, jobs: &[JobSchedule], now: DateTime<Utc>, mut jitter: J) -> Vec<String>
+    where
+        J: FnMut(u64) -> u64,
+    {
+        self.planned
+            .retain(|name, _| jobs.iter().any(|job| job.enabled && job.name == *name));
+
+        let mut due = vec![];
+
+        for job in jobs.iter().filter(|job| job.enabled) {
+            let plan = match self.planned.get(&job.name) {
+                Some(planned) if planned.job == *job => match planned.at {
+                    Some(at) if at <= now => {
+                        due.push(job.name.to_string());
+                        true
+                    }
+                    _ => false,
+                },
+                _ => true,
+            };
+
+            if plan {
+                let at = match next_run(job, now) {
+                    Ok(at) => at.map(|at| at + Duration::seconds(jitter(job.jitter_seconds) as i64)),
+                    Err(err) => {
+                        warn!("Not scheduling {}: {}", job.name, err);
+                        None
+                    }
+                };
+
+                self.planned
+                    .insert(job.name.to_string(), PlannedRun { job: job.clone(), at });
+            }
+        }
+
+        due
+    }
+
+    /// When a job is planned to run next, including its jitter
+    pub fn planned_at(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.planned.get(name).and_then(|planned| planned.at)
+    }
+}
+
+fn random_jitter(max_seconds: u64) -> u64 {
+    if max_seconds == 0 {
+        0
+    } else {
+        rand::thread_rng().gen_range(0..=max_seconds)
+    }
+}
+
+/// Run the jobs of the app config schedule as they become due. The schedule is read from the app
+/// config on every tick, so changes to it apply without restarting the server.
+pub async fn run_scheduler(server_context: ServerContext) {
+    let mut interval = tokio::time::interval(SCHEDULER_TICK);
+    let mut last_heartbeat = Utc::now();
+
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now();
+
+        if now - last_heartbeat >= Duration::minutes(1) {
+            crate::health::scheduler_health_check();
+            last_heartbeat = now;
+        }
+
+        let jobs = server_context.app.app_config.read().unwrap().schedule.jobs();
+        let due = server_context
+            .schedule
+            .lock()
+            .unwrap()
+            .take_due(&jobs, now, random_jitter);
+
+        for job in due {
+            info!("scheduled job `{}` is due", job);
+            tokio::spawn(crate::server::do_job(server_context.clone(), job));
+        }
+    }
+}
+
+/// The schedule of a job and when it is next going to run
+#[derive(Debug, Clone, PartialEq, JsonSchema, Serialize)]
+pub struct ScheduledJob {
+    pub name: String,
+    pub cron: String,
+    pub enabled: bool,
+    pub timezone: String,
+    pub jitter_seconds: u64,
+    /// When the job is next going to run. Only includes jitter if this server is running the
+    /// schedule
+    pub next_run: Option<DateTime<Utc>>,
+    /// Why the job can not be scheduled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub fn handle_schedule_view(server_context: &ServerContext) -> Vec<ScheduledJob> {
+    let jobs = server_context.app.app_config.read().unwrap().schedule.jobs();
+    let planner = server_context.schedule.lock().unwrap();
+    let now = Utc::now();
+
+    jobs.into_iter()
+        .map(|job| {
+            let (next_run, error) = match next_run(&job, now) {
+                Ok(_) if !job.enabled => (None, None),
+                Ok(next_run) => (planner.planned_at(&job.name).or(next_run), None),
+                Err(err) => (None, Some(err.to_string())),
+            };
+
+            ScheduledJob {
+                name: job.name,
+                cron: job.cron,
+                enabled: job.enabled,
+                timezone: job.timezone.to_string(),
+                jitter_seconds: job.jitter_seconds,
+                next_run,
+                error,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn job(name: &str, cron: &str) -> JobSchedule {
+        JobSchedule {
+            name: name.to_string(),
+            cron: cron.to_string(),
+            enabled: true,
+            jitter_seconds: 0,
+            timezone: chrono_tz::US::Pacific,
+        }
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(parse_cron("0 * * * *").is_ok());
+        assert!(parse_cron("30 0 * * * *").is_ok());
+        assert!(parse_cron("0 9 * * Tue").is_ok());
+        assert!(parse_cron("every hour").is_err());
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(Duration::hours(7), parse_interval("@every 7h").unwrap());
+        assert_eq!(Duration::minutes(30), parse_interval("@every 30m").unwrap());
+        assert_eq!(Duration::days(1), parse_interval(" @every 1d ").unwrap());
+        assert!(parse_interval("@every").is_err());
+        assert!(parse_interval("@every 0h").is_err());
+        assert!(parse_interval("@every 7w").is_err());
+        assert!(parse_interval("every 7h").is_err());
+    }
+
+    #[test]
+    fn test_next_run_in_timezone() {
+        // 2024-02-12 is a Monday, 16:30 UTC is 08:30 in US/Pacific
+        let now = Utc.with_ymd_and_hms(2024, 2, 12, 16, 30, 0).unwrap();
+
+        let next = next_run(&job("check-stale-rfds", "0 9 * * Tue"), now).unwrap();
+        assert_eq!(Some(Utc.with_ymd_and_hms(2024, 2, 13, 17, 0, 0).unwrap()), next);
+
+        let mut utc = job("check-stale-rfds", "0 9 * * Tue");
+        utc.timezone = chrono_tz::UTC;
+        let next = next_run(&utc, now).unwrap();
+        assert_eq!(Some(Utc.with_ymd_and_hms(2024, 2, 13, 9, 0, 0).unwrap()), next);
+
+        assert!(next_run(&job("not-a-job", "0 * * * *"), now).is_err());
+
+        // Intervals are counted from the point in time rather than from midnight
+        let next = next_run(&job("sync-applications", "@every 7h"), now).unwrap();
+        assert_eq!(Some(now + Duration::hours(7)), next);
+    }
+
+    #[test]
+    fn test_planner_takes_due_jobs() {
+        let mut planner = SchedulePlanner::default();
+        let start = Utc.with_ymd_and_hms(2024, 2, 12, 16, 30, 0).unwrap();
+        let jobs = vec![job("sync-configs", "0 * * * *"), job("sync-repos", "bad")];
+
+        // Nothing runs as soon as it is scheduled
+        assert!(planner.take_due(&jobs, start, |_| 0).is_empty());
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2024, 2, 12, 17, 0, 0).unwrap()),
+            planner.planned_at("sync-configs")
+        );
+        assert_eq!(None, planner.planned_at("sync-repos"));
+
+        let due = planner.take_due(&jobs, Utc.with_ymd_and_hms(2024, 2, 12, 17, 0, 0).unwrap(), |_| 0);
+        assert_eq!(vec!["sync-configs".to_string()], due);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2024, 2, 12, 18, 0, 0).unwrap()),
+            planner.planned_at("sync-configs")
+        );
+
+        // Changing the schedule replans the job, and jitter delays it
+        let mut jittered = job("sync-configs", "0 * * * *");
+        jittered.jitter_seconds = 60;
+        let now = Utc.with_ymd_and_hms(2024, 2, 12, 17, 10, 0).unwrap();
+        assert!(planner.take_due(&[jittered.clone()], now, |max| max / 2).is_empty());
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2024, 2, 12, 18, 0, 30).unwrap()),
+            planner.planned_at("sync-configs")
+        );
+        assert_eq!(None, planner.planned_at("sync-repos"));
+
+        // Disabled jobs are dropped from the plan
+        jittered.enabled = false;
+        assert!(planner.take_due(&[jittered], now, |_| 0).is_empty());
+        assert_eq!(None, planner.planned_at("sync-configs"));
+    }
+}
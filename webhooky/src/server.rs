@@ -1,25 +1,32 @@
 #![allow(clippy::type_complexity)]
-use std::{collections::HashMap, env, pin::Pin};
+use std::{collections::HashMap, env};
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use cio_api::{
     analytics::NewPageView,
-    functions::Function,
-    rfd::{RFDEntry, RFDIndexEntry},
+    functions::{DeadLetterFilter, Function, FunctionDeadLetter, FunctionDetails, FunctionFilter, FunctionSummary},
+    metrics::{EndpointPaths, RequestMetricsDrain},
+    offboarding::OffboardingRecord,
+    provisioning::ProvisioningStepRecord,
+    rfd::{
+        RFDEntry, RFDIndexEntry, RFDSearchFilter, RFDSearchKey, RFDSearchResults, RFDSnooze, RFDState, MAX_SNOOZE_DAYS,
+    },
     swag_store::Order,
+    webhook_events::{WebhookEvent, WebhookEventFilter, WebhookEventReplay},
 };
-use clokwerk::{AsyncScheduler, Job, TimeUnits};
 use docusign::DocuSign;
 use dropshot::{
     endpoint, ApiDescription, ConfigDropshot, ConfigLogging, ConfigLoggingLevel, HttpError, HttpResponseAccepted,
-    HttpResponseHeaders, HttpResponseOk, HttpServerStarter, OpenApiDefinition, PaginationOrder, PaginationParams, Path,
-    Query, RequestContext, ResultsPage, TypedBody, WhichPage,
+    HttpResponseDeleted, HttpResponseHeaders, HttpResponseOk, HttpServerStarter, OpenApiDefinition, PaginationOrder,
+    PaginationParams, Path, Query, RequestContext, ResultsPage, TypedBody, WhichPage,
 };
 use dropshot_verify_request::{
     bearer::{Bearer, BearerToken},
+    idempotency::Idempotent,
     query::{QueryToken, QueryTokenAudit},
     sig::{HmacVerifiedBody, HmacVerifiedBodyAudit},
+    RawBody,
 };
 use google_drive::Client as GoogleDrive;
 use gusto_api::Client as Gusto;
@@ -42,6 +49,8 @@ use crate::{
     github_types::GitHubWebhook,
     handlers_hiring::{ApplicantInfo, ApplicantUploadToken},
     handlers_slack::InteractiveEvent,
+    idempotency::{DocuSignDelivery, GitHubDelivery, ShippoDelivery},
+    inbox::WebhookKind,
 };
 
 pub struct APIConfig {
@@ -73,6 +82,7 @@ fn create_api() -> ApiDescription<ServerContext> {
      * allowing this metadata to live right alongside the handler function.
      */
     api.register(ping).unwrap();
+    api.register(metrics).unwrap();
     api.register(github_rate_limit).unwrap();
     api.register(listen_airtable_applicants_request_background_check_webhooks)
         .unwrap();
@@ -137,8 +147,31 @@ fn create_api() -> ApiDescription<ServerContext> {
     api.register(listen_store_order_create).unwrap();
     api.register(listen_rfd_index).unwrap();
     api.register(listen_rfd_view).unwrap();
+    api.register(listen_rfd_search).unwrap();
+    api.register(listen_rfd_search_key).unwrap();
+    api.register(listen_rfd_snooze).unwrap();
+    api.register(listen_rfd_unsnooze).unwrap();
     api.register(trigger_rfd_update_by_number).unwrap();
+    api.register(listen_functions_list).unwrap();
+    api.register(listen_function_view).unwrap();
+    api.register(listen_function_logs).unwrap();
+    api.register(trigger_function_cancel).unwrap();
+    api.register(trigger_function_retry).unwrap();
+    api.register(listen_dead_letters_list).unwrap();
+    api.register(listen_webhook_events_list).unwrap();
+    api.register(trigger_webhook_event_replay).unwrap();
+    api.register(trigger_webhook_events_replay).unwrap();
+    api.register(listen_schedule_view).unwrap();
+    api.register(listen_drift_view).unwrap();
+    api.register(listen_offboardings_list).unwrap();
+    api.register(listen_offboarding_view).unwrap();
+    api.register(listen_provisioning_steps_list).unwrap();
+    api.register(trigger_check_stale_rfds_create).unwrap();
     api.register(trigger_cleanup_create).unwrap();
+    api.register(trigger_offboard_users_create).unwrap();
+    api.register(trigger_provision_users_create).unwrap();
+    api.register(trigger_reindex_rfds_create).unwrap();
+    api.register(trigger_report_drift_create).unwrap();
 
     api.register(trigger_sync_analytics_create).unwrap();
     api.register(trigger_sync_api_tokens_create).unwrap();
@@ -204,6 +237,11 @@ pub async fn create_server(
     }
     let config_logging = ConfigLogging::StderrTerminal { level: log_level };
     let log = config_logging.to_logger("webhooky-server")?;
+
+    // Record the metrics of each request as dropshot logs its completion
+    let endpoints = EndpointPaths::from_openapi(&create_open_api(&api).json()?);
+    let log = slog::Logger::root(RequestMetricsDrain::new("webhooky", endpoints, log), slog::o!());
+
     /*
      * Set up the server.
      */
@@ -222,95 +260,15 @@ pub async fn server(
 ) -> Result<()> {
     let server = create_server(&s, api, server_context.clone(), debug).await?;
 
-    // This really only applied for when we are running with `do-cron` but we need the variable
-    // for the scheduler to be in the top level so we can run as async later based on the options.
-    let mut scheduler = AsyncScheduler::with_tz(chrono_tz::US::Pacific);
-
-    // Copy the Server struct so we can move it into our loop.
-    if s.do_cron {
-        /*
-         * Setup our cron jobs, with our timezone.
-         */
-        // scheduler
-        //     .every(1.day())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-analytics")});
-        scheduler
-            .every(23.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-api-tokens")});
-        scheduler
-            .every(7.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-applications")});
-        // scheduler
-        //     .every(2.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-asset-inventory")});
-        scheduler
-            .every(12.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-companies")});
-        scheduler
-            .every(1.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-configs")});
-        // scheduler
-        //     .every(6.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-finance")});
-        // scheduler
-        //     .every(12.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-functions")});
-        scheduler
-            .every(1.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-huddles")});
-        scheduler
-            .every(4.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-interviews")});
-        // scheduler
-        //     .every(12.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-journal-clubs")});
-        scheduler
-            .every(3.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-mailing-lists")});
-        // scheduler
-        //     .every(18.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-other")});
-        // scheduler.every(3.hours()).run(
-        //     enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-recorded-meetings")},
-        // );
-        scheduler
-            .every(16.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-repos")});
-        // scheduler
-        //     .every(14.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-rfds")});
-        scheduler
-            .every(30.minutes())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-salesforce")});
-        scheduler
-            .every(2.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-shipments")});
-        scheduler
-            .every(3.hours())
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-shorturls")});
-        // scheduler
-        //     .every(9.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-swag-inventory")});
-        // scheduler
-        //     .every(5.hours())
-        //     .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "sync-travel")});
-        scheduler
-            .every(1.minutes())
-            .run(|| async { crate::health::scheduler_health_check() });
-
-        // Run the RFD changelog.
-        scheduler
-            .every(clokwerk::Interval::Monday)
-            .at("8:00 am")
-            .run(enclose! { (server_context) move || create_do_job_fn(server_context.clone(), "send-rfd-changelog")});
-    }
-
     // For Cloud run & ctrl+c, shutdown gracefully.
     // "The main process inside the container will receive SIGTERM, and after a grace period,
     // SIGKILL."
     // Regsitering SIGKILL here will panic at runtime, so let's avoid that.
     let mut signals = Signals::new([SIGINT, SIGTERM])?;
 
+    // Process webhook events that are due to be attempted again.
+    tokio::spawn(crate::inbox::run_worker(server_context.clone()));
+
     tokio::spawn(enclose! { (server_context) async move {
         for sig in signals.forever() {
             let pid = std::process::id();
@@ -335,11 +293,8 @@ pub async fn server(
 
         info!("starting cron job scheduler...");
 
-        // Loop the scheduler.
-        loop {
-            scheduler.run_pending().await;
-            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
-        }
+        // Run the jobs from the schedule in the app config.
+        crate::schedule::run_scheduler(server_context).await;
     } else {
         server.await.unwrap();
     }
@@ -347,14 +302,10 @@ pub async fn server(
     Ok(())
 }
 
-pub fn create_do_job_fn(ctx: ServerContext, job: &str) -> Pin<Box<dyn std::future::Future<Output = ()> + Send>> {
-    Box::pin(do_job(ctx, job.to_string()))
-}
-
 pub async fn do_job(ctx: ServerContext, job: String) {
     info!("triggering cron job `{}`", job);
 
-    if let Err(err) = crate::handlers_cron::run_subcmd_job(&ctx, &job).await {
+    if let Err(err) = crate::handlers_cron::run_subcmd_job_for_companies(&ctx, &job).await {
         error!("Failed to spawn job: {:?}", err)
     }
 }
@@ -372,6 +323,18 @@ async fn ping(_rqctx: RequestContext<ServerContext>) -> Result<HttpResponseOk<St
     Ok(HttpResponseOk("pong".to_string()))
 }
 
+/** Metrics of the server in the Prometheus text format. */
+#[endpoint {
+    method = GET,
+    path = "/metrics",
+}]
+async fn metrics(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+) -> Result<http::Response<hyper::Body>, HttpError> {
+    cio_api::metrics::response(&rqctx.context().app.db).map_err(handle_anyhow_err_as_http_err)
+}
+
 #[derive(Deserialize, Serialize, Default, Clone, Debug, JsonSchema)]
 pub struct CounterResponse {
     #[serde(default)]
@@ -399,9 +362,16 @@ async fn listen_products_sold_count_requests(
 }]
 async fn listen_github_webhooks(
     rqctx: RequestContext<ServerContext>,
-    body: HmacVerifiedBody<crate::handlers_github::GitHubWebhookVerification, GitHubWebhook>,
+    body: Idempotent<
+        GitHubDelivery,
+        HmacVerifiedBody<crate::handlers_github::GitHubWebhookVerification, GitHubWebhook>,
+    >,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers_github::handle_github(&rqctx, body.into_inner()?)
+    let (body, delivery) = body.into_parts();
+    delivery.complete(body.parse()).await?;
+
+    delivery
+        .complete(crate::inbox::receive(&rqctx, WebhookKind::GitHub, body.as_bytes(), true).await)
         .await
         .map(accepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -461,12 +431,17 @@ pub struct GitHubRateLimit {
 async fn listen_airtable_employees_print_home_address_label_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_employees_print_home_address_label(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableEmployeesPrintHomeAddressLabel,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -479,12 +454,17 @@ async fn listen_airtable_employees_print_home_address_label_webhooks(
 async fn listen_airtable_certificates_renew_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_certificates_renew(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableCertificatesRenew,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -497,12 +477,17 @@ async fn listen_airtable_certificates_renew_webhooks(
 async fn listen_airtable_assets_items_print_barcode_label_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_assets_items_print_barcode_label(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableAssetsItemsPrintBarcodeLabel,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -515,12 +500,17 @@ async fn listen_airtable_assets_items_print_barcode_label_webhooks(
 async fn listen_airtable_swag_inventory_items_print_barcode_labels_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_swag_inventory_items_print_barcode_labels(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableSwagInventoryItemsPrintBarcodeLabels,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -533,12 +523,17 @@ async fn listen_airtable_swag_inventory_items_print_barcode_labels_webhooks(
 async fn listen_airtable_applicants_request_background_check_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_applicants_request_background_check(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableApplicantsRequestBackgroundCheck,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -552,12 +547,17 @@ async fn listen_airtable_applicants_request_background_check_webhooks(
 async fn listen_airtable_applicants_update_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_applicants_update(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableApplicantsUpdate,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -571,12 +571,17 @@ async fn listen_airtable_applicants_update_webhooks(
 async fn listen_airtable_applicants_recreate_piia_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::listen_airtable_applicants_recreate_piia_webhooks(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableApplicantsRecreatePiia,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -590,12 +595,17 @@ async fn listen_airtable_applicants_recreate_piia_webhooks(
 async fn listen_airtable_shipments_outbound_create_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_shipments_outbound_create(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableShipmentsOutboundCreate,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /// An Airtable row event.
@@ -617,12 +627,17 @@ pub struct AirtableRowEvent {
 async fn listen_airtable_shipments_outbound_reprint_label_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_shipments_outbound_reprint_label(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableShipmentsOutboundReprintLabel,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -635,12 +650,17 @@ async fn listen_airtable_shipments_outbound_reprint_label_webhooks(
 async fn listen_airtable_shipments_outbound_reprint_receipt_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_shipments_outbound_reprint_receipt(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableShipmentsOutboundReprintReceipt,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -653,11 +673,13 @@ async fn listen_airtable_shipments_outbound_reprint_receipt_webhooks(
 async fn listen_airtable_shipments_outbound_resend_shipment_status_email_to_recipient_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_shipments_outbound_resend_shipment_status_email_to_recipient(
+    crate::inbox::receive(
         &rqctx,
-        body_param.into_inner(),
+        WebhookKind::AirtableShipmentsOutboundResendShipmentStatusEmailToRecipient,
+        body_param.as_bytes(),
+        true,
     )
     .await
     .map(accepted)
@@ -674,12 +696,17 @@ async fn listen_airtable_shipments_outbound_resend_shipment_status_email_to_reci
 async fn listen_airtable_shipments_outbound_schedule_pickup_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_shipments_outbound_schedule_pickup(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableShipmentsOutboundSchedulePickup,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /// A SendGrid incoming email event.
@@ -1033,12 +1060,17 @@ async fn listen_application_files_upload_requests(
 async fn listen_airtable_shipments_inbound_create_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<AirtableToken>,
-    body_param: TypedBody<AirtableRowEvent>,
+    body_param: RawBody<AirtableRowEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_airtable_shipments_inbound_create(&rqctx, body_param.into_inner())
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::AirtableShipmentsInboundCreate,
+        body_param.as_bytes(),
+        true,
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 /**
@@ -1068,9 +1100,9 @@ async fn listen_store_order_create(
 }]
 async fn listen_easypost_tracking_update_webhooks(
     rqctx: RequestContext<ServerContext>,
-    body_param: TypedBody<EasyPostTrackingUpdateEvent>,
+    body_param: RawBody<EasyPostTrackingUpdateEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_easypost_tracking_update(&rqctx, body_param.into_inner())
+    crate::inbox::receive(&rqctx, WebhookKind::EasyPostTrackingUpdate, body_param.as_bytes(), true)
         .await
         .map(accepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1129,9 +1161,12 @@ pub struct EasyPostTrackingUpdateEvent {
 async fn listen_shippo_tracking_update_webhooks(
     rqctx: RequestContext<ServerContext>,
     _auth: QueryToken<ShippoToken>,
-    body_param: TypedBody<serde_json::Value>,
+    body_param: Idempotent<ShippoDelivery, RawBody<serde_json::Value>>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_shippo_tracking_update(&rqctx, body_param.into_inner())
+    let (body, delivery) = body_param.into_parts();
+
+    delivery
+        .complete(crate::inbox::receive(&rqctx, WebhookKind::ShippoTrackingUpdate, body.as_bytes(), true).await)
         .await
         .map(accepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1157,10 +1192,20 @@ async fn listen_checkr_background_update_webhooks(
     rqctx: RequestContext<ServerContext>,
     body: HmacVerifiedBodyAudit<crate::handlers_checkr::CheckrWebhookVerification, checkr::WebhookEvent>,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_checkr_background_update(&rqctx, body.into_inner()?)
-        .await
-        .map(accepted)
-        .map_err(handle_anyhow_err_as_http_err)
+    if !body.verified() {
+        cio_api::metrics::record_webhook_verification_failure("checkr");
+    }
+
+    body.parse()?;
+    crate::inbox::receive(
+        &rqctx,
+        WebhookKind::CheckrBackgroundUpdate,
+        body.as_bytes(),
+        body.verified(),
+    )
+    .await
+    .map(accepted)
+    .map_err(handle_anyhow_err_as_http_err)
 }
 
 #[derive(Debug, Clone, Default, JsonSchema, Deserialize, Serialize)]
@@ -1437,9 +1482,16 @@ async fn listen_auth_docusign_callback(
 }]
 async fn listen_docusign_envelope_update_webhooks(
     rqctx: RequestContext<ServerContext>,
-    body: HmacVerifiedBody<crate::handlers_docusign::DocusignWebhookVerification, docusign::Envelope>,
+    body: Idempotent<
+        DocuSignDelivery,
+        HmacVerifiedBody<crate::handlers_docusign::DocusignWebhookVerification, docusign::Envelope>,
+    >,
 ) -> Result<HttpResponseAccepted<String>, HttpError> {
-    crate::handlers::handle_docusign_envelope_update(&rqctx, body.into_inner()?)
+    let (body, delivery) = body.into_parts();
+    delivery.complete(body.parse()).await?;
+
+    delivery
+        .complete(crate::inbox::receive(&rqctx, WebhookKind::DocuSignEnvelopeUpdate, body.as_bytes(), true).await)
         .await
         .map(accepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1470,9 +1522,19 @@ async fn listen_slack_commands_webhooks(
     rqctx: RequestContext<ServerContext>,
     body: HmacVerifiedBodyAudit<crate::handlers_slack::SlackWebhookVerification, BotCommand>,
 ) -> Result<HttpResponseOk<serde_json::Value>, HttpError> {
-    crate::handlers::handle_slack_commands(&rqctx, body.into_inner()?)
+    if !body.verified() {
+        cio_api::metrics::record_webhook_verification_failure("slack");
+    }
+
+    body.parse()?;
+    let event = crate::inbox::record(&rqctx, WebhookKind::SlackCommands, body.as_bytes(), body.verified())
         .await
-        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)?;
+
+    // Slack shows the response to the command, so it is processed while Slack waits.
+    crate::inbox::process(rqctx.context(), event.id)
+        .await
+        .map(|output| HttpResponseOk(output.unwrap_or_default()))
         .map_err(handle_anyhow_err_as_http_err)
 }
 
@@ -1486,7 +1548,16 @@ async fn listen_slack_interactive_webhooks(
     rqctx: RequestContext<ServerContext>,
     body: HmacVerifiedBodyAudit<crate::handlers_slack::SlackWebhookVerification, InteractiveEvent>,
 ) -> Result<HttpResponseOk<String>, HttpError> {
-    crate::handlers::handle_slack_interactive(&rqctx, body.into_inner()?.payload)
+    if !body.verified() {
+        cio_api::metrics::record_webhook_verification_failure("slack");
+    }
+
+    body.parse()?;
+    let event = crate::inbox::record(&rqctx, WebhookKind::SlackInteractive, body.as_bytes(), body.verified())
+        .await
+        .map_err(handle_anyhow_err_as_http_err)?;
+
+    crate::inbox::process(rqctx.context(), event.id)
         .await
         .map(ok)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1499,10 +1570,10 @@ async fn listen_slack_interactive_webhooks(
 }]
 async fn listen_shipbob_webhooks(
     rqctx: RequestContext<ServerContext>,
-    _auth: QueryTokenAudit<InternalToken>,
-    body_param: TypedBody<serde_json::Value>,
+    auth: QueryTokenAudit<InternalToken>,
+    body_param: RawBody<serde_json::Value>,
 ) -> Result<HttpResponseOk<String>, HttpError> {
-    crate::handlers::handle_shipbob(&rqctx, body_param.into_inner())
+    crate::inbox::receive(&rqctx, WebhookKind::ShipBob, body_param.as_bytes(), auth.verified())
         .await
         .map(ok)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1571,6 +1642,567 @@ async fn listen_rfd_view(
     }
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RFDSearchParams {
+    /// The text to search for
+    pub q: String,
+    pub state: Option<RFDState>,
+    pub label: Option<String>,
+    /// Matches against either the name or the email of an author
+    pub author: Option<String>,
+    /// Maximum number of hits to return. Defaults to 20 and may not be more than 100
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Search the sections of all RFDs
+#[endpoint {
+    method = GET,
+    path = "/rfds/search",
+}]
+async fn listen_rfd_search(
+    _rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<RFDToken>,
+    query: Query<RFDSearchParams>,
+) -> Result<HttpResponseOk<RFDSearchResults>, HttpError> {
+    let RFDSearchParams {
+        q,
+        state,
+        label,
+        author,
+        limit,
+        offset,
+    } = query.into_inner();
+    let filter = RFDSearchFilter { state, label, author };
+    let limit = limit.unwrap_or(20).min(100) as usize;
+    let offset = offset.unwrap_or(0) as usize;
+
+    crate::handlers_rfd::handle_rfd_search(&q, &filter, limit, offset)
+        .await
+        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RFDSearchKeyParams {
+    /// The user that the caller authenticated and is requesting the key on behalf of
+    pub user: String,
+    pub state: Option<RFDState>,
+    pub label: Option<String>,
+    /// Matches against either the name or the email of an author
+    pub author: Option<String>,
+}
+
+/// Generate a short-lived key that allows a user to query the RFD search index directly. Any
+/// filters supplied are enforced on every search made with the key, and the key records the user
+/// that it was issued to
+#[endpoint {
+    method = GET,
+    path = "/rfds/search/key",
+}]
+async fn listen_rfd_search_key(
+    _rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<RFDToken>,
+    query: Query<RFDSearchKeyParams>,
+) -> Result<HttpResponseOk<RFDSearchKey>, HttpError> {
+    let RFDSearchKeyParams {
+        user,
+        state,
+        label,
+        author,
+    } = query.into_inner();
+
+    if user.trim().is_empty() {
+        return Err(HttpError::for_bad_request(
+            None,
+            "A search key must be requested for a user".to_string(),
+        ));
+    }
+
+    let filter = RFDSearchFilter { state, label, author };
+
+    crate::handlers_rfd::handle_rfd_search_key(user.trim(), &filter)
+        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RFDSnoozeRequest {
+    /// The number of days to stop reporting the RFD as stale for. Must be between 1 and 365
+    pub days: u32,
+    /// The person requesting the snooze
+    pub snoozed_by: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Stop reporting an RFD as stale for a number of days. Replaces any existing snooze of the RFD
+#[endpoint {
+    method = PUT,
+    path = "/rfd/{num}/snooze",
+}]
+async fn listen_rfd_snooze(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<RFDToken>,
+    path_params: Path<RFDPathParams>,
+    body: TypedBody<RFDSnoozeRequest>,
+) -> Result<HttpResponseOk<RFDSnooze>, HttpError> {
+    let RFDSnoozeRequest {
+        days,
+        snoozed_by,
+        reason,
+    } = body.into_inner();
+
+    if days == 0 || days > MAX_SNOOZE_DAYS {
+        return Err(HttpError::for_bad_request(
+            None,
+            format!("An RFD may be snoozed for between 1 and {} days", MAX_SNOOZE_DAYS),
+        ));
+    }
+
+    match crate::handlers_rfd::handle_rfd_snooze(
+        &rqctx.context().app,
+        path_params.into_inner().num,
+        days as i64,
+        snoozed_by,
+        reason,
+    )
+    .await
+    {
+        Ok(Some(snooze)) => Ok(HttpResponseOk(snooze)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+/// Resume reporting an RFD as stale
+#[endpoint {
+    method = DELETE,
+    path = "/rfd/{num}/snooze",
+}]
+async fn listen_rfd_unsnooze(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<RFDToken>,
+    path_params: Path<RFDPathParams>,
+) -> Result<HttpResponseDeleted, HttpError> {
+    crate::handlers_rfd::handle_rfd_unsnooze(&rqctx.context().app, path_params.into_inner().num)
+        .await
+        .map(|_| HttpResponseDeleted())
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/// List the runs of functions, most recent first
+#[endpoint {
+    method = GET,
+    path = "/functions",
+}]
+async fn listen_functions_list(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query: Query<FunctionFilter>,
+) -> Result<HttpResponseOk<Vec<FunctionSummary>>, HttpError> {
+    crate::handlers_cron::handle_functions_list(rqctx.context(), &query.into_inner())
+        .await
+        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct FunctionPathParams {
+    pub saga_id: uuid::Uuid,
+}
+
+/// Get the status, timings and logs of a function run
+#[endpoint {
+    method = GET,
+    path = "/functions/{saga_id}",
+}]
+async fn listen_function_view(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    path_params: Path<FunctionPathParams>,
+) -> Result<HttpResponseOk<FunctionDetails>, HttpError> {
+    let saga_id = path_params.into_inner().saga_id;
+
+    match crate::handlers_cron::handle_function_view(rqctx.context(), &saga_id.to_string()).await {
+        Ok(Some(function)) => Ok(HttpResponseOk(function)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct FunctionLogsParams {
+    /// Only return records written after this cursor. Used to follow the logs of a running function
+    pub after: Option<u64>,
+    /// Maximum number of records to return. Defaults to 100 and may not be more than 1000
+    pub limit: Option<usize>,
+}
+
+/// Get the tail of the logs of a function run
+#[endpoint {
+    method = GET,
+    path = "/functions/{saga_id}/logs",
+}]
+async fn listen_function_logs(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    path_params: Path<FunctionPathParams>,
+    query: Query<FunctionLogsParams>,
+) -> Result<HttpResponseOk<crate::handlers_cron::FunctionLogs>, HttpError> {
+    let saga_id = path_params.into_inner().saga_id;
+    let FunctionLogsParams { after, limit } = query.into_inner();
+    let limit = limit.unwrap_or(100).clamp(1, 1000);
+
+    match crate::handlers_cron::handle_function_logs(rqctx.context(), &saga_id, after, limit).await {
+        Ok(Some(logs)) => Ok(HttpResponseOk(logs)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+/// Cancel a function run that is in progress
+#[endpoint {
+    method = POST,
+    path = "/functions/{saga_id}/cancel",
+}]
+async fn trigger_function_cancel(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    path_params: Path<FunctionPathParams>,
+) -> Result<HttpResponseOk<FunctionDetails>, HttpError> {
+    let saga_id = path_params.into_inner().saga_id;
+
+    match crate::handlers_cron::handle_function_view(rqctx.context(), &saga_id.to_string()).await {
+        Ok(Some(function)) if function.summary.status != octorust::types::JobStatus::InProgress.to_string() => {
+            return Err(HttpError::for_bad_request(
+                None,
+                format!(
+                    "Function {} is {} and can not be cancelled",
+                    saga_id, function.summary.status
+                ),
+            ))
+        }
+        Ok(Some(_)) => (),
+        Ok(None) => return Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => return Err(handle_anyhow_err_as_http_err(err)),
+    }
+
+    match crate::handlers_cron::handle_function_cancel(rqctx.context(), &saga_id).await {
+        Ok(Some(function)) => Ok(HttpResponseOk(function)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+/// Run a function again for the same company as the run, resuming from the step that failed if
+/// it is run as multiple steps
+#[endpoint {
+    method = POST,
+    path = "/functions/{saga_id}/retry",
+}]
+async fn trigger_function_retry(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    path_params: Path<FunctionPathParams>,
+) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
+    let saga_id = path_params.into_inner().saga_id;
+
+    match crate::handlers_cron::handle_function_view(rqctx.context(), &saga_id.to_string()).await {
+        Ok(Some(function)) if function.summary.status == octorust::types::JobStatus::InProgress.to_string() => {
+            return Err(HttpError::for_bad_request(
+                None,
+                format!("Function {} is still in progress and can not be retried", saga_id),
+            ))
+        }
+        Ok(Some(_)) => (),
+        Ok(None) => return Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => return Err(handle_anyhow_err_as_http_err(err)),
+    }
+
+    match crate::handlers_cron::handle_function_retry(rqctx.context(), &saga_id).await {
+        Ok(Some(id)) => Ok(HttpResponseAccepted(id)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+/// List the function runs that failed for good, along with the last error of each
+#[endpoint {
+    method = GET,
+    path = "/dead-letters",
+}]
+async fn listen_dead_letters_list(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query: Query<DeadLetterFilter>,
+) -> Result<HttpResponseOk<Vec<FunctionDeadLetter>>, HttpError> {
+    crate::handlers_cron::handle_dead_letters_list(rqctx.context(), &query.into_inner())
+        .await
+        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/// List the webhook events that were received, most recently received first
+#[endpoint {
+    method = GET,
+    path = "/webhook-events",
+}]
+async fn listen_webhook_events_list(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query: Query<WebhookEventFilter>,
+) -> Result<HttpResponseOk<Vec<WebhookEvent>>, HttpError> {
+    crate::inbox::handle_webhook_events_list(rqctx.context(), &query.into_inner())
+        .await
+        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct WebhookEventPathParams {
+    pub id: i32,
+}
+
+/// Process a webhook event again and return the event with the outcome
+#[endpoint {
+    method = POST,
+    path = "/webhook-events/{id}/replay",
+}]
+async fn trigger_webhook_event_replay(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    path_params: Path<WebhookEventPathParams>,
+) -> Result<HttpResponseOk<WebhookEvent>, HttpError> {
+    match crate::inbox::handle_webhook_event_replay(rqctx.context(), path_params.into_inner().id).await {
+        Ok(Some(event)) => Ok(HttpResponseOk(event)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+/// Put the webhook events received within a time range back to be processed again. Returns the
+/// ids of the events that will be processed
+#[endpoint {
+    method = POST,
+    path = "/webhook-events/replay",
+}]
+async fn trigger_webhook_events_replay(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    body_param: TypedBody<WebhookEventReplay>,
+) -> Result<HttpResponseAccepted<Vec<i32>>, HttpError> {
+    let replay = body_param.into_inner();
+
+    if replay.received_after >= replay.received_before {
+        return Err(HttpError::for_bad_request(
+            None,
+            "received_after must be before received_before".to_string(),
+        ));
+    }
+
+    crate::inbox::handle_webhook_events_replay(rqctx.context(), &replay)
+        .await
+        .map(HttpResponseAccepted)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/// List the schedule of every job and when each job is next going to run
+#[endpoint {
+    method = GET,
+    path = "/schedule",
+}]
+async fn listen_schedule_view(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+) -> Result<HttpResponseOk<Vec<crate::schedule::ScheduledJob>>, HttpError> {
+    Ok(HttpResponseOk(crate::schedule::handle_schedule_view(rqctx.context())))
+}
+
+/// Get the latest report of the differences between the configs repo and the accounts in external
+/// services
+#[endpoint {
+    method = GET,
+    path = "/drift",
+}]
+async fn listen_drift_view(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+) -> Result<HttpResponseOk<cio_api::drift::DriftReport>, HttpError> {
+    let ctx = rqctx.context();
+
+    match cio_api::drift::DriftReport::latest(&ctx.app.db, ctx.app.company.id).await {
+        Ok(Some(report)) => Ok(HttpResponseOk(report)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+/// List the offboardings of users that were removed from the configs repo, along with the record
+/// of each of their steps
+#[endpoint {
+    method = GET,
+    path = "/offboardings",
+}]
+async fn listen_offboardings_list(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+) -> Result<HttpResponseOk<Vec<OffboardingRecord>>, HttpError> {
+    let ctx = rqctx.context();
+
+    OffboardingRecord::list(&ctx.app.db, ctx.app.company.id)
+        .await
+        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct OffboardingPathParams {
+    pub id: i32,
+}
+
+/// Get an offboarding along with the record of each of its steps
+#[endpoint {
+    method = GET,
+    path = "/offboardings/{id}",
+}]
+async fn listen_offboarding_view(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    path_params: Path<OffboardingPathParams>,
+) -> Result<HttpResponseOk<OffboardingRecord>, HttpError> {
+    let ctx = rqctx.context();
+
+    match OffboardingRecord::get(&ctx.app.db, ctx.app.company.id, path_params.into_inner().id).await {
+        Ok(Some(record)) => Ok(HttpResponseOk(record)),
+        Ok(None) => Err(HttpError::for_not_found(None, "".to_string())),
+        Err(err) => Err(handle_anyhow_err_as_http_err(err)),
+    }
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct ProvisioningStepParams {
+    /// Only include the steps of this user
+    pub username: Option<String>,
+}
+
+/// List the provisioning steps of new hires that have yet to run, in the order that they are due
+#[endpoint {
+    method = GET,
+    path = "/provisioning-steps",
+}]
+async fn listen_provisioning_steps_list(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query_args: Query<ProvisioningStepParams>,
+) -> Result<HttpResponseOk<Vec<ProvisioningStepRecord>>, HttpError> {
+    let ctx = rqctx.context();
+    let params = query_args.into_inner();
+
+    ProvisioningStepRecord::pending(&ctx.app.db, ctx.app.company.id, params.username.as_deref())
+        .await
+        .map(HttpResponseOk)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/// Selects the company that a job is run for
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RunJobParams {
+    /// Defaults to the company of the server
+    pub company_id: Option<i32>,
+}
+
+impl RunJobParams {
+    fn company_id(&self, ctx: &ServerContext) -> i32 {
+        self.company_id.unwrap_or(ctx.app.company.id)
+    }
+}
+
+/** Listen for triggering a function run of checking for stale RFDs. */
+#[endpoint {
+    method = POST,
+    path = "/run/check-stale-rfds",
+}]
+async fn trigger_check_stale_rfds_create(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
+) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "check-stale-rfds", company_id)
+        .await
+        .map(HttpResponseAccepted)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/** Listen for triggering a function run of reindexing RFDs. */
+#[endpoint {
+    method = POST,
+    path = "/run/reindex-rfds",
+}]
+async fn trigger_reindex_rfds_create(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
+) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "reindex-rfds", company_id)
+        .await
+        .map(HttpResponseAccepted)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/** Listen for triggering a function run of offboarding users removed from the configs. */
+#[endpoint {
+    method = POST,
+    path = "/run/offboard-users",
+}]
+async fn trigger_offboard_users_create(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
+) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "offboard-users", company_id)
+        .await
+        .map(HttpResponseAccepted)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/** Listen for triggering a function run of provisioning new hires. */
+#[endpoint {
+    method = POST,
+    path = "/run/provision-users",
+}]
+async fn trigger_provision_users_create(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
+) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "provision-users", company_id)
+        .await
+        .map(HttpResponseAccepted)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
+/** Listen for triggering a function run of reporting drift between the configs and external services. */
+#[endpoint {
+    method = POST,
+    path = "/run/report-drift",
+}]
+async fn trigger_report_drift_create(
+    rqctx: RequestContext<ServerContext>,
+    _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
+) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "report-drift", company_id)
+        .await
+        .map(HttpResponseAccepted)
+        .map_err(handle_anyhow_err_as_http_err)
+}
+
 /** Listen for triggering a function run of sync repos. */
 #[endpoint {
     method = POST,
@@ -1579,8 +2211,10 @@ async fn listen_rfd_view(
 async fn trigger_sync_repos_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-repos")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-repos", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1594,8 +2228,10 @@ async fn trigger_sync_repos_create(
 async fn trigger_sync_rfds_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-rfds")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-rfds", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1609,8 +2245,10 @@ async fn trigger_sync_rfds_create(
 async fn trigger_sync_travel_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-travel")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-travel", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1624,8 +2262,10 @@ async fn trigger_sync_travel_create(
 async fn trigger_sync_zoho_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-zoho")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-zoho", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1639,8 +2279,10 @@ async fn trigger_sync_zoho_create(
 async fn trigger_sync_functions_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-functions")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-functions", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1654,8 +2296,10 @@ async fn trigger_sync_functions_create(
 async fn trigger_sync_finance_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-finance")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-finance", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1669,8 +2313,10 @@ async fn trigger_sync_finance_create(
 async fn trigger_sync_salesforce_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-salesforce")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-salesforce", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1684,8 +2330,10 @@ async fn trigger_sync_salesforce_create(
 async fn trigger_sync_shipments_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-shipments")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-shipments", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1699,8 +2347,10 @@ async fn trigger_sync_shipments_create(
 async fn trigger_sync_shorturls_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-shorturls")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-shorturls", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1714,8 +2364,10 @@ async fn trigger_sync_shorturls_create(
 async fn trigger_sync_configs_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-configs")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-configs", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1729,8 +2381,10 @@ async fn trigger_sync_configs_create(
 async fn trigger_sync_recorded_meetings_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-recorded-meetings")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-recorded-meetings", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1744,8 +2398,10 @@ async fn trigger_sync_recorded_meetings_create(
 async fn trigger_sync_asset_inventory_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-asset-inventory")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-asset-inventory", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1759,8 +2415,10 @@ async fn trigger_sync_asset_inventory_create(
 async fn trigger_sync_swag_inventory_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-swag-inventory")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-swag-inventory", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1774,8 +2432,10 @@ async fn trigger_sync_swag_inventory_create(
 async fn trigger_sync_interviews_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-interviews")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-interviews", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1789,8 +2449,10 @@ async fn trigger_sync_interviews_create(
 async fn trigger_sync_applications_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-applications")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-applications", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1804,8 +2466,10 @@ async fn trigger_sync_applications_create(
 async fn trigger_sync_analytics_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-analytics")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-analytics", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1819,8 +2483,10 @@ async fn trigger_sync_analytics_create(
 async fn trigger_sync_companies_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-companies")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-companies", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1834,8 +2500,10 @@ async fn trigger_sync_companies_create(
 async fn trigger_sync_other_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-other")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-other", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1849,8 +2517,10 @@ async fn trigger_sync_other_create(
 async fn trigger_sync_huddles_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-huddles")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-huddles", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1864,8 +2534,10 @@ async fn trigger_sync_huddles_create(
 async fn trigger_sync_mailing_lists_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-mailing-lists")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-mailing-lists", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1879,8 +2551,10 @@ async fn trigger_sync_mailing_lists_create(
 async fn trigger_sync_journal_clubs_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-journal-clubs")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-journal-clubs", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)
@@ -1894,8 +2568,10 @@ async fn trigger_sync_journal_clubs_create(
 async fn trigger_sync_api_tokens_create(
     rqctx: RequestContext<ServerContext>,
     _auth: Bearer<InternalToken>,
+    query_args: Query<RunJobParams>,
 ) -> Result<HttpResponseAccepted<uuid::Uuid>, HttpError> {
-    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-api-tokens")
+    let company_id = query_args.into_inner().company_id(rqctx.context());
+    crate::handlers_cron::run_subcmd_job(rqctx.context(), "sync-api-tokens", company_id)
         .await
         .map(HttpResponseAccepted)
         .map_err(handle_anyhow_err_as_http_err)